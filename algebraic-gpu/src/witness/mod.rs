@@ -12,6 +12,15 @@ use std::str::FromStr;
 pub use witness_calculator::flat_array;
 pub use witness_calculator::WitnessCalculator;
 
+/// The kind of a witness generator file: a circom wasm, or the native witness generator
+/// `zkit compile --c` builds, which only the CPU prover runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WitnessGenerator {
+    #[default]
+    Wasm,
+    Native,
+}
+
 pub(crate) fn fnv(inp: &str) -> (u32, u32) {
     let mut hasher = FnvHasher::default();
     hasher.write(inp.as_bytes());
@@ -12,7 +12,9 @@ extern crate num_traits;
 extern crate rand;
 
 pub mod circom_circuit;
+pub mod r1cs_check;
 pub mod r1cs_file;
+pub mod r1cs_optimizer;
 pub mod reader;
 pub mod utils;
 pub mod witness;
@@ -0,0 +1,298 @@
+//! Check that a witness satisfies the constraints of an R1CS, and describe the unsatisfied
+//! ones with the signal names of the `.sym` file circom writes next to the `.r1cs`.
+use anyhow::{anyhow, bail, Result};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::bellman_ce::{
+    pairing::{bls12_381::Bls12, bn256::Bn256},
+    Field, PrimeField, ScalarEngine,
+};
+use crate::circom_circuit::R1CS;
+use crate::reader::{load_r1cs, load_witness_from_file};
+use crate::utils::repr_to_big;
+use crate::witness::{load_input_for_witness, WitnessCalculator};
+
+/// The signal names of the wires, from the lines `label,wire,component,name` of a `.sym`
+/// file. A wire may have several names, the signals circom merged into it, and the labels
+/// circom optimized out have the wire -1.
+#[derive(Debug, Default, Clone)]
+pub struct SymbolTable {
+    pub names: BTreeMap<usize, Vec<String>>,
+}
+
+impl SymbolTable {
+    pub fn from_file(filename: &str) -> Result<Self> {
+        Self::parse(&std::fs::read_to_string(filename)?)
+    }
+
+    pub fn parse(sym: &str) -> Result<Self> {
+        let mut names: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+        for (i, line) in sym.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let fields: Vec<&str> = line.splitn(4, ',').collect();
+            if fields.len() != 4 {
+                bail!("invalid sym line {}: {}", i + 1, line);
+            }
+            let wire: i64 = fields[1].trim().parse()?;
+            if wire >= 0 {
+                names
+                    .entry(wire as usize)
+                    .or_default()
+                    .push(fields[3].trim().to_string());
+            }
+        }
+        Ok(SymbolTable { names })
+    }
+
+    /// The first name of the wire, `one` for the constant wire and `w<wire>` without a name.
+    pub fn name(&self, wire: usize) -> String {
+        match self.names.get(&wire).and_then(|names| names.first()) {
+            Some(name) => name.clone(),
+            None if wire == 0 => "one".to_string(),
+            None => format!("w{}", wire),
+        }
+    }
+}
+
+/// A constraint A * B = C the witness does not satisfy, with the values of A, B and C.
+#[derive(Debug, Clone)]
+pub struct UnsatisfiedConstraint<E: ScalarEngine> {
+    pub index: usize,
+    pub a: E::Fr,
+    pub b: E::Fr,
+    pub c: E::Fr,
+}
+
+/// The constraints of `r1cs` the witness, indexed by wire, does not satisfy.
+pub fn check_constraints<E: ScalarEngine>(
+    r1cs: &R1CS<E>,
+    witness: &[E::Fr],
+) -> Result<Vec<UnsatisfiedConstraint<E>>> {
+    if witness.len() < r1cs.num_variables {
+        bail!(
+            "The witness has {} values, but the R1CS has {} wires",
+            witness.len(),
+            r1cs.num_variables
+        );
+    }
+    if witness[0] != E::Fr::one() {
+        bail!("The first wire of the witness must be 1");
+    }
+    let mut unsatisfied = vec![];
+    for (index, (a, b, c)) in r1cs.constraints.iter().enumerate() {
+        let (a, b, c) = (eval(a, witness), eval(b, witness), eval(c, witness));
+        let mut ab = a;
+        ab.mul_assign(&b);
+        if ab != c {
+            unsatisfied.push(UnsatisfiedConstraint { index, a, b, c });
+        }
+    }
+    Ok(unsatisfied)
+}
+
+/// A multi-line description of an unsatisfied constraint, each linear combination written
+/// as `coefficient * name (value)` terms.
+pub fn describe<E: ScalarEngine>(
+    r1cs: &R1CS<E>,
+    witness: &[E::Fr],
+    unsatisfied: &UnsatisfiedConstraint<E>,
+    symbols: &SymbolTable,
+) -> String {
+    let (a, b, c) = &r1cs.constraints[unsatisfied.index];
+    let mut ab = unsatisfied.a;
+    ab.mul_assign(&unsatisfied.b);
+    let lc = |lc: &Vec<(usize, E::Fr)>| {
+        if lc.is_empty() {
+            return "0".to_string();
+        }
+        lc.iter()
+            .map(|(wire, coeff)| {
+                format!(
+                    "{} * {} ({})",
+                    to_signed_string(coeff),
+                    symbols.name(*wire),
+                    to_signed_string(&witness[*wire])
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" + ")
+    };
+    format!(
+        "constraint {} is not satisfied: A * B = {}, C = {}\n    A = {} = {}\n    B = {} = {}\n    C = {} = {}",
+        unsatisfied.index,
+        to_signed_string(&ab),
+        to_signed_string(&unsatisfied.c),
+        lc(a),
+        to_signed_string(&unsatisfied.a),
+        lc(b),
+        to_signed_string(&unsatisfied.b),
+        lc(c),
+        to_signed_string(&unsatisfied.c),
+    )
+}
+
+/// Check the witness of `circuit_file`, a wtns or json file, or the one the wasm calculates
+/// from the input. The unsatisfied constraints are printed with the names of `sym_file`, by
+/// default the `.sym` next to the `.r1cs`, at most `max_reports` of them.
+pub fn r1cs_check(
+    curve_type: &str,
+    circuit_file: &str,
+    witness_file: Option<&str>,
+    wasm_file: Option<&str>,
+    input_file: Option<&str>,
+    sym_file: Option<&str>,
+    max_reports: usize,
+) -> Result<()> {
+    match curve_type {
+        "BN128" => check::<Bn256>(
+            circuit_file,
+            witness_file,
+            wasm_file,
+            input_file,
+            sym_file,
+            max_reports,
+        ),
+        "BLS12381" => check::<Bls12>(
+            circuit_file,
+            witness_file,
+            wasm_file,
+            input_file,
+            sym_file,
+            max_reports,
+        ),
+        _ => bail!("Unknown curve type: {}", curve_type),
+    }
+}
+
+fn check<E: ScalarEngine>(
+    circuit_file: &str,
+    witness_file: Option<&str>,
+    wasm_file: Option<&str>,
+    input_file: Option<&str>,
+    sym_file: Option<&str>,
+    max_reports: usize,
+) -> Result<()> {
+    let r1cs = load_r1cs::<E>(circuit_file);
+    let witness = match (witness_file, wasm_file, input_file) {
+        (Some(witness_file), _, _) => load_witness_from_file::<E>(witness_file),
+        (None, Some(wasm_file), Some(input_file)) => {
+            let mut wtns = WitnessCalculator::from_file(wasm_file)?;
+            wtns.calculate_witness(load_input_for_witness(input_file), false)?
+                .iter()
+                .map(|w| E::Fr::from_str(&w.to_string()))
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| anyhow!("the witness is not in the scalar field"))?
+        }
+        _ => bail!("check a witness file, or a wasm file with its input"),
+    };
+    let default_sym = Path::new(circuit_file).with_extension("sym");
+    let symbols = match sym_file {
+        Some(sym_file) => SymbolTable::from_file(sym_file)?,
+        None if default_sym.exists() => SymbolTable::from_file(&default_sym.to_string_lossy())?,
+        None => SymbolTable::default(),
+    };
+
+    let unsatisfied = check_constraints(&r1cs, &witness)?;
+    for u in unsatisfied.iter().take(max_reports) {
+        println!("{}", describe(&r1cs, &witness, u, &symbols));
+    }
+    if !r1cs.custom_gates_uses.is_empty() {
+        println!(
+            "{} uses of custom gates are not checked",
+            r1cs.custom_gates_uses.len()
+        );
+    }
+    if !unsatisfied.is_empty() {
+        bail!(
+            "{} of {} constraints are not satisfied",
+            unsatisfied.len(),
+            r1cs.constraints.len()
+        );
+    }
+    println!("All {} constraints are satisfied", r1cs.constraints.len());
+    Ok(())
+}
+
+fn eval<E: ScalarEngine>(lc: &[(usize, E::Fr)], witness: &[E::Fr]) -> E::Fr {
+    let mut sum = E::Fr::zero();
+    for (wire, coeff) in lc {
+        let mut term = witness[*wire];
+        term.mul_assign(coeff);
+        sum.add_assign(&term);
+    }
+    sum
+}
+
+/// The decimal of a field element, as a negative number when it is closer to the modulus.
+fn to_signed_string<F: PrimeField>(x: &F) -> String {
+    let positive = repr_to_big(x.into_repr());
+    let mut neg = *x;
+    neg.negate();
+    let negative = repr_to_big(neg.into_repr());
+    if negative.len() < positive.len() {
+        format!("-{}", negative)
+    } else {
+        positive
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CIRCUIT_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/multiplier.r1cs");
+    const WASM_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/multiplier.wasm");
+    const INPUT_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/multiplier.input.json");
+
+    #[test]
+    fn test_r1cs_check() {
+        let r1cs = load_r1cs::<Bn256>(CIRCUIT_FILE);
+        let mut wtns = WitnessCalculator::from_file(WASM_FILE).unwrap();
+        let mut witness: Vec<_> = wtns
+            .calculate_witness(load_input_for_witness(INPUT_FILE), false)
+            .unwrap()
+            .iter()
+            .map(|w| <Bn256 as ScalarEngine>::Fr::from_str(&w.to_string()).unwrap())
+            .collect();
+        assert!(check_constraints(&r1cs, &witness).unwrap().is_empty());
+        r1cs_check(
+            "BN128",
+            CIRCUIT_FILE,
+            None,
+            Some(WASM_FILE),
+            Some(INPUT_FILE),
+            None,
+            10,
+        )
+        .unwrap();
+
+        let last = r1cs.num_variables - 1;
+        witness[last].add_assign(&<Bn256 as ScalarEngine>::Fr::one());
+        let unsatisfied = check_constraints(&r1cs, &witness).unwrap();
+        assert!(!unsatisfied.is_empty());
+        let symbols =
+            SymbolTable::parse(&format!("1,-1,0,main.removed\n2,{},0,main.last", last)).unwrap();
+        assert_eq!(symbols.name(last), "main.last");
+        assert_eq!(symbols.name(0), "one");
+        assert_eq!(symbols.name(last + 1), format!("w{}", last + 1));
+        let description = describe(&r1cs, &witness, &unsatisfied[0], &symbols);
+        assert!(description.starts_with(&format!(
+            "constraint {} is not satisfied",
+            unsatisfied[0].index
+        )));
+        assert!(description.contains("main.last"));
+        assert!(!description.contains("main.removed"));
+
+        let wtns_file = "/tmp/r1cs_check.json";
+        let values: Vec<String> = witness.iter().map(|w| repr_to_big(w.into_repr())).collect();
+        std::fs::write(wtns_file, serde_json::to_string(&values).unwrap()).unwrap();
+        assert!(r1cs_check("BN128", CIRCUIT_FILE, Some(wtns_file), None, None, None, 10).is_err());
+        witness.pop();
+        assert!(check_constraints(&r1cs, &witness).is_err());
+        assert!(SymbolTable::parse("1,2,main.a").is_err());
+        assert_eq!(to_signed_string(&<Bn256 as ScalarEngine>::Fr::zero()), "0");
+    }
+}
@@ -2,11 +2,12 @@
 // Implement of https://github.com/iden3/r1csfile/blob/master/doc/r1cs_bin_format.md
 #![allow(unused_variables, dead_code, non_snake_case)]
 use crate::bellman_ce::{Field, PrimeField, PrimeFieldRepr, ScalarEngine};
-use crate::circom_circuit::{Constraint, CustomGates, CustomGatesUses};
-use byteorder::{LittleEndian, ReadBytesExt};
+use crate::circom_circuit::{CircuitJson, Constraint, CustomGates, CustomGatesUses, R1CS};
+use crate::utils::repr_to_big;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use std::{
     collections::BTreeMap,
-    io::{Error, ErrorKind, Read, Result, Seek, SeekFrom},
+    io::{Error, ErrorKind, Read, Result, Seek, SeekFrom, Write},
 };
 
 // R1CSFile's header
@@ -306,6 +307,223 @@ pub fn from_reader<R: Read + Seek, E: ScalarEngine>(mut reader: R) -> Result<R1C
     })
 }
 
+impl<E: ScalarEngine> R1CSFile<E> {
+    /// The file of an R1CS built or transformed in Rust. The R1CS does not keep the number of
+    /// private inputs, so it is 0, and the labels are the ones of `wire_mapping`.
+    pub fn from_r1cs(r1cs: &R1CS<E>, wire_mapping: &[usize]) -> Self {
+        let mut prime_size = vec![];
+        E::Fr::char()
+            .write_le(&mut prime_size)
+            .expect("write to a vec never fails");
+        let use_custom_gates = !r1cs.custom_gates.is_empty() || !r1cs.custom_gates_uses.is_empty();
+        R1CSFile {
+            version: 1,
+            header: Header {
+                field_size: (E::Fr::NUM_BITS + 7) / 8,
+                prime_size,
+                n_wires: r1cs.num_variables as u32,
+                n_pub_out: r1cs.num_outputs as u32,
+                n_pub_in: (r1cs.num_inputs - r1cs.num_outputs - 1) as u32,
+                n_prv_in: 0,
+                n_labels: wire_mapping.iter().max().map_or(0, |l| *l as u64 + 1),
+                n_constraints: r1cs.constraints.len() as u32,
+                use_custom_gates,
+            },
+            constraints: r1cs.constraints.clone(),
+            wire_mapping: wire_mapping.iter().map(|l| *l as u64).collect(),
+            custom_gates: r1cs.custom_gates.clone(),
+            custom_gates_uses: r1cs.custom_gates_uses.clone(),
+        }
+    }
+
+    /// The R1CS of the file, with the label of each wire.
+    pub fn into_r1cs(self) -> (R1CS<E>, Vec<usize>) {
+        let num_inputs = (1 + self.header.n_pub_in + self.header.n_pub_out) as usize;
+        let num_variables = self.header.n_wires as usize;
+        let num_aux = num_variables - num_inputs;
+        (
+            R1CS {
+                num_aux,
+                num_inputs,
+                num_variables,
+                num_outputs: self.header.n_pub_out as usize,
+                constraints: self.constraints,
+                custom_gates: self.custom_gates,
+                custom_gates_uses: self.custom_gates_uses,
+            },
+            self.wire_mapping.iter().map(|e| *e as usize).collect(),
+        )
+    }
+}
+
+fn write_field<W: Write, E: ScalarEngine>(mut writer: W, fr: &E::Fr) -> Result<()> {
+    fr.into_repr().write_le(&mut writer)
+}
+
+fn write_header<W: Write>(mut writer: W, header: &Header) -> Result<()> {
+    if header.prime_size.len() != header.field_size as usize {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "The prime does not have field_size bytes",
+        ));
+    }
+    writer.write_u32::<LittleEndian>(header.field_size)?;
+    writer.write_all(&header.prime_size)?;
+    writer.write_u32::<LittleEndian>(header.n_wires)?;
+    writer.write_u32::<LittleEndian>(header.n_pub_out)?;
+    writer.write_u32::<LittleEndian>(header.n_pub_in)?;
+    writer.write_u32::<LittleEndian>(header.n_prv_in)?;
+    writer.write_u64::<LittleEndian>(header.n_labels)?;
+    writer.write_u32::<LittleEndian>(header.n_constraints)
+}
+
+fn write_constraint_vec<W: Write, E: ScalarEngine>(
+    mut writer: W,
+    vec: &[(usize, E::Fr)],
+) -> Result<()> {
+    writer.write_u32::<LittleEndian>(vec.len() as u32)?;
+    for (wire, coeff) in vec {
+        writer.write_u32::<LittleEndian>(*wire as u32)?;
+        write_field::<&mut W, E>(&mut writer, coeff)?;
+    }
+    Ok(())
+}
+
+fn write_constraints<W: Write, E: ScalarEngine>(
+    mut writer: W,
+    constraints: &[Constraint<E>],
+) -> Result<()> {
+    for (a, b, c) in constraints {
+        write_constraint_vec::<&mut W, E>(&mut writer, a)?;
+        write_constraint_vec::<&mut W, E>(&mut writer, b)?;
+        write_constraint_vec::<&mut W, E>(&mut writer, c)?;
+    }
+    Ok(())
+}
+
+fn write_map<W: Write>(mut writer: W, wire_mapping: &[u64]) -> Result<()> {
+    for label in wire_mapping {
+        writer.write_u64::<LittleEndian>(*label)?;
+    }
+    Ok(())
+}
+
+fn write_custom_gates_list<W: Write, E: ScalarEngine>(
+    mut writer: W,
+    custom_gates: &[CustomGates<E>],
+) -> Result<()> {
+    writer.write_u32::<LittleEndian>(custom_gates.len() as u32)?;
+    for custom_gate in custom_gates {
+        writer.write_all(custom_gate.template_name.as_bytes())?;
+        writer.write_u8(0)?;
+        writer.write_u32::<LittleEndian>(custom_gate.parameters.len() as u32)?;
+        for parameter in custom_gate.parameters.iter() {
+            write_field::<&mut W, E>(&mut writer, parameter)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_custom_gates_uses_list<W: Write>(
+    mut writer: W,
+    custom_gates_uses: &[CustomGatesUses],
+) -> Result<()> {
+    writer.write_u32::<LittleEndian>(custom_gates_uses.len() as u32)?;
+    for c in custom_gates_uses {
+        writer.write_u32::<LittleEndian>(c.id as u32)?;
+        writer.write_u32::<LittleEndian>(c.signals.len() as u32)?;
+        for signal in c.signals.iter() {
+            writer.write_u64::<LittleEndian>(*signal)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_section<W: Write>(mut writer: W, section_type: u32, section: &[u8]) -> Result<()> {
+    writer.write_u32::<LittleEndian>(section_type)?;
+    writer.write_u64::<LittleEndian>(section.len() as u64)?;
+    writer.write_all(section)
+}
+
+/// Write the file in the order of the spec: the header, constraints and wire map sections,
+/// then the custom gates list and uses sections if the header uses custom gates.
+pub fn to_writer<W: Write, E: ScalarEngine>(mut writer: W, file: &R1CSFile<E>) -> Result<()> {
+    if file.header.n_constraints as usize != file.constraints.len() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "The header does not have the number of constraints",
+        ));
+    }
+    if file.header.n_wires as usize != file.wire_mapping.len() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "The wire map does not have a label for each wire",
+        ));
+    }
+    if !file.header.use_custom_gates
+        && !(file.custom_gates.is_empty() && file.custom_gates_uses.is_empty())
+    {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "The header does not use the custom gates",
+        ));
+    }
+
+    writer.write_all(&[0x72, 0x31, 0x63, 0x73])?;
+    writer.write_u32::<LittleEndian>(file.version)?;
+    let num_sections = if file.header.use_custom_gates { 5 } else { 3 };
+    writer.write_u32::<LittleEndian>(num_sections)?;
+
+    let mut section = vec![];
+    write_header(&mut section, &file.header)?;
+    write_section(&mut writer, HEADER_TYPE, &section)?;
+
+    section.clear();
+    write_constraints::<_, E>(&mut section, &file.constraints)?;
+    write_section(&mut writer, CONSTRAINT_TYPE, &section)?;
+
+    section.clear();
+    write_map(&mut section, &file.wire_mapping)?;
+    write_section(&mut writer, WIRE2LABEL_TYPE, &section)?;
+
+    if file.header.use_custom_gates {
+        section.clear();
+        write_custom_gates_list::<_, E>(&mut section, &file.custom_gates)?;
+        write_section(&mut writer, CUSTOM_GATES_LIST, &section)?;
+
+        section.clear();
+        write_custom_gates_uses_list(&mut section, &file.custom_gates_uses)?;
+        write_section(&mut writer, CUSTOM_GATES_USE, &section)?;
+    }
+    writer.flush()
+}
+
+/// The constraints in the format of circom `--json`, a map from wire to the decimal of the
+/// coefficient for each of A, B and C, with the sizes `reader::load_r1cs` needs to load it.
+pub fn to_json<E: ScalarEngine>(file: &R1CSFile<E>) -> CircuitJson {
+    let convert_constraint = |lc: &Vec<(usize, E::Fr)>| {
+        lc.iter()
+            .map(|(wire, coeff)| (wire.to_string(), repr_to_big(coeff.into_repr())))
+            .collect::<BTreeMap<_, _>>()
+    };
+    CircuitJson {
+        constraints: file
+            .constraints
+            .iter()
+            .map(|(a, b, c)| {
+                vec![
+                    convert_constraint(a),
+                    convert_constraint(b),
+                    convert_constraint(c),
+                ]
+            })
+            .collect(),
+        num_inputs: file.header.n_pub_in as usize,
+        num_outputs: file.header.n_pub_out as usize,
+        num_variables: file.header.n_wires as usize,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -393,6 +611,65 @@ mod tests {
 
         assert_eq!(file.wire_mapping.len(), 7);
         assert_eq!(file.wire_mapping[1], 3);
+
+        let mut written = vec![];
+        to_writer(&mut written, &file).unwrap();
+        assert_eq!(written, data);
+    }
+
+    #[test]
+    fn test_writer_round_trip() {
+        use crate::bellman_ce::Field;
+        use crate::reader::{load_r1cs, load_r1cs_from_bin, save_r1cs};
+        const CIRCUIT_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/multiplier.r1cs");
+
+        let data = std::fs::read(CIRCUIT_FILE).unwrap();
+        let mut file = from_reader::<_, Bn256>(Cursor::new(&data[..])).unwrap();
+        let mut two = <Bn256 as ScalarEngine>::Fr::one();
+        two.double();
+        file.header.use_custom_gates = true;
+        file.custom_gates.push(CustomGates {
+            template_name: "CMul".to_string(),
+            parameters: vec![two],
+        });
+        file.custom_gates_uses.push(CustomGatesUses {
+            id: 0,
+            signals: vec![1, 0x100000002],
+        });
+        let mut written = vec![];
+        to_writer(&mut written, &file).unwrap();
+        let read = from_reader::<_, Bn256>(Cursor::new(&written[..])).unwrap();
+        let mut rewritten = vec![];
+        to_writer(&mut rewritten, &read).unwrap();
+        assert_eq!(written, rewritten);
+        assert_eq!(read.header.prime_size, file.header.prime_size);
+        assert_eq!(read.header.n_labels, file.header.n_labels);
+        assert_eq!(read.constraints, file.constraints);
+        assert_eq!(read.wire_mapping, file.wire_mapping);
+        assert_eq!(read.custom_gates[0].template_name, "CMul");
+        assert_eq!(read.custom_gates[0].parameters, vec![two]);
+        assert_eq!(read.custom_gates_uses[0].signals, vec![1, 0x100000002]);
+
+        let (r1cs, wire_mapping) = load_r1cs_from_bin::<_, Bn256>(Cursor::new(&data[..]));
+        for filename in ["/tmp/r1cs_file.r1cs", "/tmp/r1cs_file.json"] {
+            save_r1cs(&r1cs, &wire_mapping, filename).unwrap();
+            let saved = load_r1cs::<Bn256>(filename);
+            assert_eq!(saved.num_inputs, r1cs.num_inputs);
+            assert_eq!(saved.num_outputs, r1cs.num_outputs);
+            assert_eq!(saved.num_variables, r1cs.num_variables);
+            assert_eq!(saved.constraints, r1cs.constraints);
+        }
+        let json = serde_json::to_value(to_json(&file)).unwrap();
+        assert_eq!(
+            json["constraints"].as_array().unwrap().len(),
+            file.constraints.len()
+        );
+
+        file.header.n_constraints += 1;
+        assert_eq!(
+            to_writer(&mut vec![], &file).err().unwrap().kind(),
+            ErrorKind::InvalidInput
+        );
     }
 
     #[test]
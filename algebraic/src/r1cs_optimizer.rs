@@ -0,0 +1,344 @@
+//! Simplify an R1CS before converting it to Plonk, e.g. by `recursion::r1cs2plonk`.
+//!
+//! The pass merges the repeated wires of the linear combinations and drops the zero terms,
+//! substitutes the linear constraints into the others, and removes the constraints of dead
+//! wires, the wires a constraint can always be satisfied by and no other constraint uses.
+//! The wires are never renumbered: the optimized R1CS keeps the wires, the public inputs and
+//! the custom gates of the original one, so its witness, and the wire mapping of the exec
+//! file of compressor12, are the ones of the original R1CS. The values of the removed wires
+//! are just left unconstrained.
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::bellman_ce::{Field, ScalarEngine};
+use crate::circom_circuit::{Constraint, R1CS};
+
+type Lc<E> = BTreeMap<usize, <E as ScalarEngine>::Fr>;
+
+/// The numbers of constraints the pass removed.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct OptimizationStats {
+    pub substituted: usize,
+    pub dead: usize,
+    pub trivial: usize,
+}
+
+pub fn optimize<E: ScalarEngine>(r1cs: &R1CS<E>) -> (R1CS<E>, OptimizationStats) {
+    let mut constraints: Vec<Option<(Lc<E>, Lc<E>, Lc<E>)>> = r1cs
+        .constraints
+        .iter()
+        .map(|(a, b, c)| Some((to_lc::<E>(a), to_lc::<E>(b), to_lc::<E>(c))))
+        .collect();
+
+    // the public inputs, and the signals of the custom gates, are kept as they are
+    let mut locked: BTreeSet<usize> = (0..r1cs.num_inputs).collect();
+    for u in r1cs.custom_gates_uses.iter() {
+        locked.extend(u.signals.iter().map(|s| *s as usize));
+    }
+
+    let mut uses: BTreeMap<usize, BTreeSet<usize>> = BTreeMap::new();
+    for (i, c) in constraints.iter().enumerate() {
+        for wire in wires::<E>(c.as_ref().unwrap()) {
+            uses.entry(wire).or_default().insert(i);
+        }
+    }
+
+    let mut stats = OptimizationStats::default();
+    let mut pending: BTreeSet<usize> = (0..constraints.len()).collect();
+    while let Some(i) = pending.pop_first() {
+        let constraint = match &constraints[i] {
+            Some(c) => c.clone(),
+            None => continue,
+        };
+        let linear = linear_form::<E>(&constraint);
+        let removed = match &linear {
+            Some(l) if l.keys().all(|w| *w == 0) => {
+                // a constant constraint, only remove the satisfied ones
+                if l.is_empty() {
+                    stats.trivial += 1;
+                    true
+                } else {
+                    false
+                }
+            }
+            Some(l) => {
+                let candidates: Vec<usize> = l
+                    .keys()
+                    .copied()
+                    .filter(|w| *w != 0 && !locked.contains(w))
+                    .collect();
+                let pivot = candidates
+                    .iter()
+                    .copied()
+                    .min_by_key(|w| uses.get(w).map_or(0, |u| u.len()));
+                match pivot {
+                    Some(p) if uses[&p].len() == 1 => {
+                        stats.dead += 1;
+                        true
+                    }
+                    Some(p) if worth_substituting(l, uses[&p].len() - 1) => {
+                        substitute::<E>(i, p, l, &mut constraints, &mut uses, &mut pending);
+                        stats.substituted += 1;
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            None => {
+                // the wire of C alone is set to satisfy A * B = C
+                let (a, b, c) = &constraint;
+                let dead = c.keys().any(|w| {
+                    *w != 0
+                        && !locked.contains(w)
+                        && !a.contains_key(w)
+                        && !b.contains_key(w)
+                        && uses[w].len() == 1
+                });
+                if dead {
+                    stats.dead += 1;
+                }
+                dead
+            }
+        };
+        if removed {
+            for wire in wires::<E>(&constraint) {
+                let u = uses.get_mut(&wire).unwrap();
+                u.remove(&i);
+                // the last constraint of a wire may now be dead
+                if u.len() == 1 {
+                    pending.extend(u.iter().copied());
+                }
+            }
+            constraints[i] = None;
+        }
+    }
+
+    let constraints: Vec<Constraint<E>> = constraints
+        .into_iter()
+        .flatten()
+        .map(|(a, b, c)| (from_lc::<E>(&a), from_lc::<E>(&b), from_lc::<E>(&c)))
+        .collect();
+    (
+        R1CS {
+            constraints,
+            ..r1cs.clone()
+        },
+        stats,
+    )
+}
+
+/// r1cs2plonk spends max(n - 3, 0) + 1 gates on a linear constraint of n wires, and
+/// substituting one of them adds up to n - 2 terms to each of the k other constraints of the
+/// wire, so a substitution pays off when k * (n - 2) <= max(n - 3, 0) + 1, i.e. when n <= 2
+/// or k <= 1.
+fn worth_substituting<F>(l: &BTreeMap<usize, F>, k: usize) -> bool {
+    let n = l.keys().filter(|w| **w != 0).count();
+    n <= 2 || k <= 1
+}
+
+/// Replace wire `p` by its expression from the linear constraint `l` of constraint `i` in the
+/// other constraints of `p`.
+fn substitute<E: ScalarEngine>(
+    i: usize,
+    p: usize,
+    l: &Lc<E>,
+    constraints: &mut [Option<(Lc<E>, Lc<E>, Lc<E>)>],
+    uses: &mut BTreeMap<usize, BTreeSet<usize>>,
+    pending: &mut BTreeSet<usize>,
+) {
+    // p = -(l - l[p] * p) / l[p]
+    let mut scale = l[&p].inverse().unwrap();
+    scale.negate();
+    let expr: Lc<E> = l
+        .iter()
+        .filter(|(w, _)| **w != p)
+        .map(|(w, v)| {
+            let mut v = *v;
+            v.mul_assign(&scale);
+            (*w, v)
+        })
+        .collect();
+
+    let targets: Vec<usize> = uses[&p].iter().copied().filter(|j| *j != i).collect();
+    for j in targets {
+        let c = constraints[j].as_mut().unwrap();
+        let before = wires::<E>(c);
+        for lc in [&mut c.0, &mut c.1, &mut c.2] {
+            if let Some(k) = lc.remove(&p) {
+                for (w, v) in expr.iter() {
+                    let mut term = *v;
+                    term.mul_assign(&k);
+                    add_term::<E>(lc, *w, term);
+                }
+            }
+        }
+        let after = wires::<E>(c);
+        for w in before.difference(&after) {
+            uses.get_mut(w).unwrap().remove(&j);
+        }
+        for w in after.difference(&before) {
+            uses.entry(*w).or_default().insert(j);
+        }
+        pending.insert(j);
+    }
+}
+
+/// The linear combination L = 0 of a constraint whose A or B is a constant.
+fn linear_form<E: ScalarEngine>((a, b, c): &(Lc<E>, Lc<E>, Lc<E>)) -> Option<Lc<E>> {
+    let constant = |lc: &Lc<E>| {
+        if lc.keys().all(|w| *w == 0) {
+            Some(lc.get(&0).copied().unwrap_or_else(E::Fr::zero))
+        } else {
+            None
+        }
+    };
+    let (k, other) = match (constant(a), constant(b)) {
+        (Some(k), _) => (k, b),
+        (None, Some(k)) => (k, a),
+        (None, None) => return None,
+    };
+    // k * other - c = 0
+    let mut l = Lc::<E>::new();
+    for (w, v) in other.iter() {
+        let mut v = *v;
+        v.mul_assign(&k);
+        add_term::<E>(&mut l, *w, v);
+    }
+    for (w, v) in c.iter() {
+        let mut v = *v;
+        v.negate();
+        add_term::<E>(&mut l, *w, v);
+    }
+    Some(l)
+}
+
+fn add_term<E: ScalarEngine>(lc: &mut Lc<E>, wire: usize, value: E::Fr) {
+    let sum = lc.entry(wire).or_insert_with(E::Fr::zero);
+    sum.add_assign(&value);
+    if sum.is_zero() {
+        lc.remove(&wire);
+    }
+}
+
+fn to_lc<E: ScalarEngine>(terms: &[(usize, E::Fr)]) -> Lc<E> {
+    let mut lc = Lc::<E>::new();
+    for (w, v) in terms {
+        add_term::<E>(&mut lc, *w, *v);
+    }
+    lc
+}
+
+fn from_lc<E: ScalarEngine>(lc: &Lc<E>) -> Vec<(usize, E::Fr)> {
+    lc.iter().map(|(w, v)| (*w, *v)).collect()
+}
+
+fn wires<E: ScalarEngine>((a, b, c): &(Lc<E>, Lc<E>, Lc<E>)) -> BTreeSet<usize> {
+    a.keys().chain(b.keys()).chain(c.keys()).copied().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bellman_ce::{pairing::bn256::Bn256, PrimeField};
+    use crate::circom_circuit::CustomGatesUses;
+    use crate::r1cs_check::check_constraints;
+    use crate::reader::load_r1cs;
+    use crate::witness::{load_input_for_witness, WitnessCalculator};
+
+    type Fr = <Bn256 as ScalarEngine>::Fr;
+
+    const CIRCUIT_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/multiplier.r1cs");
+    const WASM_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/multiplier.wasm");
+    const INPUT_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/multiplier.input.json");
+
+    fn fr(x: i64) -> Fr {
+        let mut v = Fr::from_str(&x.abs().to_string()).unwrap();
+        if x < 0 {
+            v.negate();
+        }
+        v
+    }
+
+    fn lc(terms: &[(usize, i64)]) -> Vec<(usize, Fr)> {
+        terms.iter().map(|(w, v)| (*w, fr(*v))).collect()
+    }
+
+    #[test]
+    fn test_optimize() {
+        // wires: one, out, a, b, t, u, dead, g
+        let r1cs = R1CS::<Bn256> {
+            num_inputs: 2,
+            num_aux: 6,
+            num_variables: 8,
+            num_outputs: 1,
+            constraints: vec![
+                // a * b = t
+                (lc(&[(2, 1)]), lc(&[(3, 1)]), lc(&[(4, 1)])),
+                // 2 * (t + t) = u, with a repeated wire
+                (lc(&[(0, 2)]), lc(&[(4, 1), (4, 1)]), lc(&[(5, 1)])),
+                // u * u = out
+                (lc(&[(5, 1)]), lc(&[(5, 1)]), lc(&[(1, 1)])),
+                // a * a = dead
+                (lc(&[(2, 1)]), lc(&[(2, 1)]), lc(&[(6, 1)])),
+                // 0 * a = 0
+                (lc(&[]), lc(&[(2, 1)]), lc(&[])),
+                // g + a = b, g is a custom gate signal so a is substituted
+                (lc(&[(0, 1)]), lc(&[(7, 1), (2, 1)]), lc(&[(3, 1)])),
+            ],
+            custom_gates: vec![],
+            custom_gates_uses: vec![CustomGatesUses {
+                id: 0,
+                signals: vec![7],
+            }],
+        };
+        // a = 3, b = 5, g = 2
+        let witness: Vec<Fr> = [1, 3600, 3, 5, 15, 60, 9, 2]
+            .iter()
+            .map(|x| fr(*x))
+            .collect();
+        assert!(check_constraints(&r1cs, &witness).unwrap().is_empty());
+
+        let (optimized, stats) = optimize(&r1cs);
+        assert_eq!(
+            stats,
+            OptimizationStats {
+                substituted: 2,
+                dead: 1,
+                trivial: 1,
+            }
+        );
+        assert_eq!(optimized.constraints.len(), 2);
+        assert_eq!(optimized.num_variables, r1cs.num_variables);
+        assert_eq!(optimized.custom_gates_uses.len(), 1);
+        let uses = |wire: usize| {
+            optimized
+                .constraints
+                .iter()
+                .any(|(a, b, c)| a.iter().chain(b).chain(c).any(|(w, _)| *w == wire))
+        };
+        assert!(!uses(2) && !uses(4) && !uses(6));
+        assert!(uses(1) && uses(7));
+        assert!(check_constraints(&optimized, &witness).unwrap().is_empty());
+
+        let mut wrong = witness;
+        wrong[1] = fr(3601);
+        assert!(!check_constraints(&optimized, &wrong).unwrap().is_empty());
+        wrong[1] = fr(3600);
+        wrong[7] = fr(3);
+        assert!(!check_constraints(&optimized, &wrong).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_optimize_circom() {
+        let r1cs = load_r1cs::<Bn256>(CIRCUIT_FILE);
+        let mut wtns = WitnessCalculator::from_file(WASM_FILE).unwrap();
+        let witness: Vec<Fr> = wtns
+            .calculate_witness(load_input_for_witness(INPUT_FILE), false)
+            .unwrap()
+            .iter()
+            .map(|w| Fr::from_str(&w.to_string()).unwrap())
+            .collect();
+        let (optimized, _) = optimize(&r1cs);
+        assert!(optimized.constraints.len() <= r1cs.constraints.len());
+        assert!(check_constraints(&optimized, &witness).unwrap().is_empty());
+    }
+}
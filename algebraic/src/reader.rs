@@ -3,7 +3,7 @@ use byteorder::{LittleEndian, ReadBytesExt};
 use itertools::Itertools;
 use std::collections::BTreeMap;
 use std::fs::{File, OpenOptions};
-use std::io::{BufReader, Read, Seek};
+use std::io::{BufReader, BufWriter, Read, Seek};
 use std::str;
 
 use crate::bellman_ce::{
@@ -58,16 +58,19 @@ pub fn load_witness_from_json_file<E: ScalarEngine>(filename: &str) -> Vec<E::Fr
         .read(true)
         .open(filename)
         .unwrap_or_else(|_| panic!("Unable to open {}.", filename));
-    load_witness_from_json::<E, BufReader<File>>(BufReader::new(reader))
+    load_witness_from_json::<E, BufReader<File>>(BufReader::new(reader)).expect("Unable to read.")
 }
 
 /// load witness from json by a reader
-fn load_witness_from_json<E: ScalarEngine, R: Read>(reader: R) -> Vec<E::Fr> {
-    let witness: Vec<String> = serde_json::from_reader(reader).expect("Unable to read.");
+pub fn load_witness_from_json<E: ScalarEngine, R: Read>(reader: R) -> Result<Vec<E::Fr>> {
+    let witness: Vec<String> = serde_json::from_reader(reader)?;
     witness
         .into_iter()
-        .map(|x| E::Fr::from_str(&x).unwrap())
-        .collect::<Vec<E::Fr>>()
+        .map(|x| match E::Fr::from_str(&x) {
+            Some(x) => Ok(x),
+            None => bail!("Invalid witness value {}", x),
+        })
+        .collect()
 }
 
 /// load witness from bin file by filename
@@ -117,7 +120,9 @@ pub fn load_witness_from_bin_reader<E: ScalarEngine, R: Read>(mut reader: R) ->
     }
     let mut prime = vec![0u8; field_size as usize];
     reader.read_exact(&mut prime)?;
-    if prime != hex!("010000f093f5e1439170b97948e833285d588181b64550b829a031e1724e6430") {
+    let mut modulus = Vec::with_capacity(field_size as usize);
+    E::Fr::char().write_le(&mut modulus)?;
+    if prime != modulus {
         bail!("invalid curve prime".to_string());
     }
     let witness_len = reader.read_u32::<LittleEndian>()?;
@@ -149,6 +154,22 @@ pub fn load_r1cs<E: ScalarEngine>(filename: &str) -> R1CS<E> {
     }
 }
 
+/// save r1cs with its wire mapping to a file by filename, as circom json if it ends with json
+pub fn save_r1cs<E: ScalarEngine>(
+    r1cs: &R1CS<E>,
+    wire_mapping: &[usize],
+    filename: &str,
+) -> Result<()> {
+    let file = crate::r1cs_file::R1CSFile::from_r1cs(r1cs, wire_mapping);
+    let writer = BufWriter::new(File::create(filename)?);
+    if filename.ends_with("json") {
+        serde_json::to_writer(writer, &crate::r1cs_file::to_json(&file))?;
+    } else {
+        crate::r1cs_file::to_writer(writer, &file)?;
+    }
+    Ok(())
+}
+
 /// load r1cs from json file by filename
 fn load_r1cs_from_json_file<E: ScalarEngine>(filename: &str) -> R1CS<E> {
     let reader = OpenOptions::new()
@@ -206,19 +227,5 @@ fn load_r1cs_from_bin_file<E: ScalarEngine>(filename: &str) -> (R1CS<E>, Vec<usi
 /// load r1cs from bin by a reader
 pub fn load_r1cs_from_bin<R: Read + Seek, E: ScalarEngine>(reader: R) -> (R1CS<E>, Vec<usize>) {
     let file = crate::r1cs_file::from_reader::<R, E>(reader).expect("Unable to read.");
-    let num_inputs = (1 + file.header.n_pub_in + file.header.n_pub_out) as usize;
-    let num_variables = file.header.n_wires as usize;
-    let num_aux = num_variables - num_inputs;
-    (
-        R1CS {
-            num_aux,
-            num_inputs,
-            num_variables,
-            num_outputs: file.header.n_pub_out as usize,
-            constraints: file.constraints,
-            custom_gates: file.custom_gates,
-            custom_gates_uses: file.custom_gates_uses,
-        },
-        file.wire_mapping.iter().map(|e| *e as usize).collect_vec(),
-    )
+    file.into_r1cs()
 }
@@ -1,15 +1,21 @@
 mod circom;
 pub(crate) mod memory;
+mod native;
+mod pool;
 pub(super) mod witness_calculator;
 
+use anyhow::Result;
 use fnv::FnvHasher;
+pub use native::NativeWitnessCalculator;
 use num_bigint::BigInt;
 use num_traits::{One, Zero};
+pub use pool::WitnessCalculatorPool;
 use serde_json::Value;
 use std::collections::HashMap;
 use std::hash::Hasher;
 use std::str::FromStr;
 pub use witness_calculator::flat_array;
+pub use witness_calculator::load_module;
 pub use witness_calculator::WitnessCalculator;
 
 pub(crate) fn fnv(inp: &str) -> (u32, u32) {
@@ -20,6 +26,31 @@ pub(crate) fn fnv(inp: &str) -> (u32, u32) {
     ((h >> 32) as u32, h as u32)
 }
 
+/// The kind of a witness generator file: a circom wasm, or the native witness generator
+/// `zkit compile --c` builds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WitnessGenerator {
+    #[default]
+    Wasm,
+    Native,
+}
+
+/// Calculate the witness by the `generator` of `circuit_file`, the compiled module of a wasm
+/// being cached.
+pub fn calculate_witness_from_file<I: IntoIterator<Item = (String, Vec<BigInt>)>>(
+    circuit_file: &str,
+    generator: WitnessGenerator,
+    inputs: I,
+    sanity_check: bool,
+) -> Result<Vec<BigInt>> {
+    match generator {
+        WitnessGenerator::Native => NativeWitnessCalculator::from_file(circuit_file)?
+            .calculate_witness(inputs, sanity_check),
+        WitnessGenerator::Wasm => WitnessCalculator::from_file_cached(circuit_file, None)?
+            .calculate_witness(inputs, sanity_check),
+    }
+}
+
 pub fn load_input_for_witness(input_file: &str) -> HashMap<String, Vec<BigInt>> {
     let inputs_str = std::fs::read_to_string(input_file)
         .unwrap_or_else(|_| panic!("Unable to load {}", input_file));
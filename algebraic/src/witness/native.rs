@@ -0,0 +1,198 @@
+//! Witness generation by the native witness generator of circom, the C++ program `zkit compile
+//! --c` writes and builds, with the API of the wasm `WitnessCalculator`.
+use crate::bellman_ce::ScalarEngine;
+use crate::witness::witness_calculator::write_wtns;
+use anyhow::{bail, Result};
+use byteorder::{LittleEndian, ReadBytesExt};
+use num_bigint::{BigInt, Sign};
+use serde_json::Value;
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, BufWriter, Read};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// the header of the last witness, to save the witness as the generator does
+struct WtnsHeader {
+    version: u32,
+    prime: BigInt,
+    n32: u32,
+}
+
+pub struct NativeWitnessCalculator {
+    binary: PathBuf,
+    header: Option<WtnsHeader>,
+}
+
+impl NativeWitnessCalculator {
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let binary = path.as_ref().to_path_buf();
+        if !binary.is_file() {
+            bail!(
+                "The native witness generator {} does not exist",
+                binary.display()
+            );
+        }
+        Ok(NativeWitnessCalculator {
+            binary,
+            header: None,
+        })
+    }
+
+    /// Run the generator on the input json file, it writes the wtns file itself.
+    pub fn calculate_witness_to_file(
+        &self,
+        input_file: impl AsRef<Path>,
+        wtns_file: impl AsRef<Path>,
+    ) -> Result<()> {
+        let output = Command::new(&self.binary)
+            .arg(input_file.as_ref())
+            .arg(wtns_file.as_ref())
+            .output()?;
+        if !output.status.success() {
+            bail!(
+                "{} failed with {}: {}",
+                self.binary.display(),
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(())
+    }
+
+    /// The generator always checks the asserts of the circuit, whatever `sanity_check` is.
+    pub fn calculate_witness<I: IntoIterator<Item = (String, Vec<BigInt>)>>(
+        &mut self,
+        inputs: I,
+        sanity_check: bool,
+    ) -> Result<Vec<BigInt>> {
+        let wtns = self.calculate_witness_bin(inputs, sanity_check)?;
+        let n32 = self.header.as_ref().map_or(1, |h| h.n32 as usize);
+        Ok(wtns
+            .chunks(n32)
+            .map(|w| BigInt::from_slice(Sign::Plus, w))
+            .collect())
+    }
+
+    pub fn calculate_witness_bin<I: IntoIterator<Item = (String, Vec<BigInt>)>>(
+        &mut self,
+        inputs: I,
+        _sanity_check: bool,
+    ) -> Result<Vec<u32>> {
+        let mut json = serde_json::Map::new();
+        for (name, values) in inputs {
+            let values = values.iter().map(|v| Value::String(v.to_string()));
+            json.insert(name, Value::Array(values.collect()));
+        }
+        let tmp = std::env::temp_dir().join(format!(
+            "native_witness_{}_{}",
+            std::process::id(),
+            rand::random::<u32>()
+        ));
+        let (input_file, wtns_file) = (tmp.with_extension("json"), tmp.with_extension("wtns"));
+        std::fs::write(&input_file, serde_json::to_string(&json)?)?;
+        let result = self
+            .calculate_witness_to_file(&input_file, &wtns_file)
+            .and_then(|_| read_wtns(BufReader::new(File::open(&wtns_file)?)));
+        let _ = std::fs::remove_file(&input_file);
+        let _ = std::fs::remove_file(&wtns_file);
+
+        let (header, wtns) = result?;
+        self.header = Some(header);
+        Ok(wtns)
+    }
+
+    pub fn save_witness_to_bin_file<E: ScalarEngine>(
+        &mut self,
+        filename: &str,
+        w: &[u32],
+    ) -> Result<()> {
+        let header = match &self.header {
+            Some(header) => header,
+            None => bail!("Calculate a witness before saving it"),
+        };
+        let writer = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(filename)?;
+        write_wtns(
+            BufWriter::new(writer),
+            header.version,
+            &header.prime,
+            header.n32,
+            w,
+        )
+    }
+}
+
+// read a wtns file of any field, each value as the little endian u32 of `write_wtns`.
+fn read_wtns<R: Read>(mut reader: R) -> Result<(WtnsHeader, Vec<u32>)> {
+    let mut wtns_header = [0u8; 4];
+    reader.read_exact(&mut wtns_header)?;
+    if wtns_header != [119, 116, 110, 115] {
+        bail!("Invalid file header");
+    }
+    let version = reader.read_u32::<LittleEndian>()?;
+    let num_sections = reader.read_u32::<LittleEndian>()?;
+    if num_sections != 2 {
+        bail!("invalid num sections");
+    }
+    if reader.read_u32::<LittleEndian>()? != 1 {
+        bail!("invalid section type");
+    }
+    let sec_size = reader.read_u64::<LittleEndian>()?;
+    let field_size = reader.read_u32::<LittleEndian>()?;
+    if field_size == 0 || field_size % 4 != 0 || sec_size != field_size as u64 + 8 {
+        bail!("invalid field byte size");
+    }
+    let mut prime = vec![0u8; field_size as usize];
+    reader.read_exact(&mut prime)?;
+    let witness_len = reader.read_u32::<LittleEndian>()?;
+    if reader.read_u32::<LittleEndian>()? != 2 {
+        bail!("invalid section type");
+    }
+    if reader.read_u64::<LittleEndian>()? != witness_len as u64 * field_size as u64 {
+        bail!("invalid witness section size");
+    }
+    let n32 = field_size / 4;
+    let mut wtns = vec![0u32; (witness_len * n32) as usize];
+    reader.read_u32_into::<LittleEndian>(&mut wtns)?;
+    let header = WtnsHeader {
+        version,
+        prime: BigInt::from_bytes_le(Sign::Plus, &prime),
+        n32,
+    };
+    Ok((header, wtns))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::witness::WitnessCalculator;
+
+    const WASM_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/test-vectors/mycircuit.wasm");
+
+    #[test]
+    fn test_read_wtns() {
+        let mut wtns = WitnessCalculator::from_file(WASM_FILE).unwrap();
+        let inputs = || {
+            vec![
+                ("a".to_string(), vec![BigInt::from(3)]),
+                ("b".to_string(), vec![BigInt::from(11)]),
+            ]
+        };
+        let w = wtns.calculate_witness_bin(inputs(), false).unwrap();
+        let mut buf = vec![];
+        wtns.save_witness_from_bin_writer::<crate::bellman_ce::pairing::bn256::Bn256, _>(
+            &mut buf, &w,
+        )
+        .unwrap();
+        let (header, read) = read_wtns(buf.as_slice()).unwrap();
+        assert_eq!(read, w);
+        assert_eq!(header.n32, 8);
+        assert_eq!(header.prime, wtns.memory.prime);
+        assert_eq!(header.version, wtns.circom_version);
+        assert!(read_wtns(&buf[..buf.len() - 1]).is_err());
+        assert!(NativeWitnessCalculator::from_file("/tmp/native_witness_missing").is_err());
+    }
+}
@@ -0,0 +1,115 @@
+//! A pool of witness calculators of one wasm, to calculate the witnesses of different inputs
+//! concurrently. The wasm is compiled once, and each calculator instantiates it in its own
+//! store.
+use crate::witness::witness_calculator::{load_module, WitnessCalculator};
+use anyhow::{anyhow, Result};
+use num_bigint::BigInt;
+use std::path::Path;
+use std::sync::Mutex;
+use wasmer::{Engine, Module, Store};
+
+pub struct WitnessCalculatorPool {
+    engine: Engine,
+    module: Module,
+    idle: Mutex<Vec<WitnessCalculator>>,
+}
+
+impl WitnessCalculatorPool {
+    /// The pool of the wasm of `path`, compiled with the cache of `load_module`.
+    pub fn from_file(path: impl AsRef<Path>, cache_dir: Option<&Path>) -> Result<Self> {
+        let store = Store::default();
+        let module = load_module(&store, path.as_ref(), cache_dir)?;
+        Ok(WitnessCalculatorPool {
+            engine: store.engine().clone(),
+            module,
+            idle: Mutex::new(vec![]),
+        })
+    }
+
+    /// Run `f` with an idle calculator, or a new one if all of them are busy. The calculator
+    /// goes back to the pool unless `f` fails, which may leave its instance in a bad state.
+    pub fn with_calculator<T>(
+        &self,
+        f: impl FnOnce(&mut WitnessCalculator) -> Result<T>,
+    ) -> Result<T> {
+        let idle = self.lock()?.pop();
+        let mut wtns = match idle {
+            Some(wtns) => wtns,
+            None => {
+                WitnessCalculator::from_store(Store::new(self.engine.clone()), self.module.clone())?
+            }
+        };
+        let result = f(&mut wtns)?;
+        self.lock()?.push(wtns);
+        Ok(result)
+    }
+
+    pub fn calculate_witness<I: IntoIterator<Item = (String, Vec<BigInt>)>>(
+        &self,
+        inputs: I,
+        sanity_check: bool,
+    ) -> Result<Vec<BigInt>> {
+        self.with_calculator(|wtns| wtns.calculate_witness(inputs, sanity_check))
+    }
+
+    pub fn calculate_witness_bin<I: IntoIterator<Item = (String, Vec<BigInt>)>>(
+        &self,
+        inputs: I,
+        sanity_check: bool,
+    ) -> Result<Vec<u32>> {
+        self.with_calculator(|wtns| wtns.calculate_witness_bin(inputs, sanity_check))
+    }
+
+    /// The number of instantiated calculators, the most ever used at the same time.
+    pub fn size(&self) -> Result<usize> {
+        Ok(self.lock()?.len())
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, Vec<WitnessCalculator>>> {
+        self.idle
+            .lock()
+            .map_err(|_| anyhow!("the witness calculator pool is poisoned"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WASM_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/test-vectors/mycircuit.wasm");
+
+    #[test]
+    fn test_witness_calculator_pool() {
+        let cache_dir = std::env::temp_dir().join("witness_calculator_pool");
+        std::fs::create_dir_all(&cache_dir).unwrap();
+        let pool = WitnessCalculatorPool::from_file(WASM_FILE, Some(cache_dir.as_path())).unwrap();
+        std::thread::scope(|s| {
+            let handles: Vec<_> = (1..=8u64)
+                .map(|a| {
+                    let pool = &pool;
+                    s.spawn(move || {
+                        let inputs = vec![
+                            ("a".to_string(), vec![BigInt::from(a)]),
+                            ("b".to_string(), vec![BigInt::from(a + 1)]),
+                        ];
+                        (a, pool.calculate_witness(inputs, false).unwrap())
+                    })
+                })
+                .collect();
+            for handle in handles {
+                let (a, witness) = handle.join().unwrap();
+                let expected: Vec<BigInt> = [1, a * (a + 1), a, a + 1]
+                    .into_iter()
+                    .map(BigInt::from)
+                    .collect();
+                assert_eq!(witness, expected);
+            }
+        });
+        let size = pool.size().unwrap();
+        assert!((1..=8).contains(&size));
+
+        let failed: Result<()> = pool.with_calculator(|_| Err(anyhow!("failed")));
+        assert!(failed.is_err());
+        assert_eq!(pool.size().unwrap(), size - 1);
+    }
+}
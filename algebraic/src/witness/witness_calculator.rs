@@ -10,8 +10,10 @@ use serde_json::Value;
 use std::str::FromStr;
 use wasmer::{imports, Function, Instance, Memory, MemoryType, Module, Store};
 
+use sha2::{Digest, Sha256};
 use std::fs::OpenOptions;
 use std::io::{BufWriter, Write};
+use std::path::Path;
 
 use byteorder::{LittleEndian, WriteBytesExt};
 
@@ -46,10 +48,63 @@ fn to_array32(s: &BigInt, size: usize) -> Vec<u32> {
     res
 }
 
+/// Compile the wasm, or load the module compiled by a previous call from `cache_dir`, by
+/// default the directory of the wasm. The cached module is keyed by the hash of the wasm and
+/// the wasmer version, and it is recompiled if the engine cannot load it.
+pub fn load_module(store: &Store, path: &Path, cache_dir: Option<&Path>) -> Result<Module> {
+    let wasm = std::fs::read(path)?;
+    let mut hasher = Sha256::new();
+    hasher.update(wasmer::VERSION.as_bytes());
+    hasher.update(&wasm);
+    let key = hex::encode(hasher.finalize());
+    let cache_dir = cache_dir
+        .or_else(|| path.parent())
+        .unwrap_or_else(|| Path::new("."));
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let cache_file = cache_dir.join(format!("{}.{}.wasmer", file_name, &key[..16]));
+
+    if cache_file.exists() {
+        // the module is deserialized without validation, so the cache must be as trusted as
+        // the wasm, like the default cache next to it.
+        match unsafe { Module::deserialize_from_file(store, &cache_file) } {
+            Ok(module) => return Ok(module),
+            Err(e) => log::warn!("Recompile {}: {}", cache_file.display(), e),
+        }
+    }
+    let module = Module::new(store, &wasm)?;
+    if let Err(e) = save_module(&module, &cache_file) {
+        log::warn!("Unable to cache {}: {}", cache_file.display(), e);
+    }
+    Ok(module)
+}
+
+// write to a temporary file first, so concurrent calls never load a partial module.
+fn save_module(module: &Module, cache_file: &Path) -> Result<()> {
+    let tmp_file = cache_file.with_extension(format!(
+        "{}.{}.tmp",
+        std::process::id(),
+        rand::random::<u32>()
+    ));
+    module.serialize_to_file(&tmp_file)?;
+    std::fs::rename(&tmp_file, cache_file)?;
+    Ok(())
+}
+
 impl WitnessCalculator {
-    pub fn from_file(path: impl AsRef<std::path::Path>) -> Result<Self> {
-        let mut store = Store::default();
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let store = Store::default();
         let module = Module::from_file(&store, path)?;
+        Self::from_store(store, module)
+    }
+
+    /// Like `from_file`, with the compiled module cached by `load_module`.
+    pub fn from_file_cached(path: impl AsRef<Path>, cache_dir: Option<&Path>) -> Result<Self> {
+        let store = Store::default();
+        let module = load_module(&store, path.as_ref(), cache_dir)?;
+        Self::from_store(store, module)
+    }
+
+    pub(crate) fn from_store(mut store: Store, module: Module) -> Result<Self> {
         let mut wtns = Self::from_module(&mut store, module)?;
         wtns.store = store;
         Ok(wtns)
@@ -200,58 +255,65 @@ impl WitnessCalculator {
 
     pub fn save_witness_from_bin_writer<E: ScalarEngine, W: Write>(
         &mut self,
-        mut writer: W,
+        writer: W,
         wtns: &Vec<u32>,
     ) -> Result<()> {
         let n32 = self.instance.get_field_num_len32(&mut self.store)?;
-        let wtns_header = [119, 116, 110, 115];
-        writer.write_all(&wtns_header)?;
-
-        let version = self.circom_version;
-        writer.write_u32::<LittleEndian>(version)?;
-        let num_section = 2u32;
-        writer.write_u32::<LittleEndian>(num_section)?;
-
-        // id section 1
-        let id_section = 1u32;
-        writer.write_u32::<LittleEndian>(id_section)?;
-
-        let sec_size: u64 = (n32 * 4 + 8) as u64;
-        writer.write_u64::<LittleEndian>(sec_size)?;
-
-        let field_size: u32 = n32 * 4;
-        writer.write_u32::<LittleEndian>(field_size)?;
-
-        // write prime
-        let (sign, prime_buf) = self.memory.prime.to_bytes_le();
-        if sign != Sign::Plus {
-            bail!(format!(
-                "Invalid prime: {}, must be positive",
-                self.memory.prime
-            ));
-        }
-        if prime_buf.len() as u32 != field_size {
-            bail!(format!(
-                "Invalid prime: {}, len must be of {}",
-                self.memory.prime,
-                prime_buf.len()
-            ));
-        }
-        writer.write_all(&prime_buf)?;
-
-        // write witness size
-        let wtns_size = wtns.len() as u32 / n32;
-        writer.write_u32::<LittleEndian>(wtns_size)?;
-        // sec type
-        writer.write_u32::<LittleEndian>(2)?;
-        // sec size
-        writer.write_u64::<LittleEndian>((wtns_size * field_size) as u64)?;
-
-        for w in wtns {
-            writer.write_u32::<LittleEndian>(*w)?;
-        }
-        Ok(())
+        write_wtns(writer, self.circom_version, &self.memory.prime, n32, wtns)
+    }
+}
+
+/// Write the witness in the wtns format, each value as `n32` little endian u32.
+pub(crate) fn write_wtns<W: Write>(
+    mut writer: W,
+    version: u32,
+    prime: &BigInt,
+    n32: u32,
+    wtns: &[u32],
+) -> Result<()> {
+    let wtns_header = [119, 116, 110, 115];
+    writer.write_all(&wtns_header)?;
+
+    writer.write_u32::<LittleEndian>(version)?;
+    let num_section = 2u32;
+    writer.write_u32::<LittleEndian>(num_section)?;
+
+    // id section 1
+    let id_section = 1u32;
+    writer.write_u32::<LittleEndian>(id_section)?;
+
+    let sec_size: u64 = (n32 * 4 + 8) as u64;
+    writer.write_u64::<LittleEndian>(sec_size)?;
+
+    let field_size: u32 = n32 * 4;
+    writer.write_u32::<LittleEndian>(field_size)?;
+
+    // write prime
+    let (sign, prime_buf) = prime.to_bytes_le();
+    if sign != Sign::Plus {
+        bail!(format!("Invalid prime: {}, must be positive", prime));
+    }
+    if prime_buf.len() as u32 != field_size {
+        bail!(format!(
+            "Invalid prime: {}, len must be of {}",
+            prime,
+            prime_buf.len()
+        ));
+    }
+    writer.write_all(&prime_buf)?;
+
+    // write witness size
+    let wtns_size = wtns.len() as u32 / n32;
+    writer.write_u32::<LittleEndian>(wtns_size)?;
+    // sec type
+    writer.write_u32::<LittleEndian>(2)?;
+    // sec size
+    writer.write_u64::<LittleEndian>((wtns_size * field_size) as u64)?;
+
+    for w in wtns {
+        writer.write_u32::<LittleEndian>(*w)?;
     }
+    Ok(())
 }
 
 #[allow(dead_code)]
@@ -444,6 +506,43 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_from_file_cached() {
+        let cache_dir = std::env::temp_dir().join("witness_calculator_cache");
+        let _ = std::fs::remove_dir_all(&cache_dir);
+        std::fs::create_dir_all(&cache_dir).unwrap();
+        let path = root_path("test-vectors/mycircuit.wasm");
+        let inputs = || {
+            vec![
+                ("a".to_string(), vec![BigInt::from(3)]),
+                ("b".to_string(), vec![BigInt::from(11)]),
+            ]
+        };
+        let expected = WitnessCalculator::from_file(&path)
+            .unwrap()
+            .calculate_witness(inputs(), false)
+            .unwrap();
+
+        for _ in 0..2 {
+            let mut wtns =
+                WitnessCalculator::from_file_cached(&path, Some(cache_dir.as_path())).unwrap();
+            assert_eq!(wtns.calculate_witness(inputs(), false).unwrap(), expected);
+            assert_eq!(std::fs::read_dir(&cache_dir).unwrap().count(), 1);
+        }
+
+        let cache_file = std::fs::read_dir(&cache_dir)
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        std::fs::write(&cache_file, b"invalid").unwrap();
+        let mut wtns =
+            WitnessCalculator::from_file_cached(&path, Some(cache_dir.as_path())).unwrap();
+        assert_eq!(wtns.calculate_witness(inputs(), false).unwrap(), expected);
+        assert_ne!(std::fs::read(&cache_file).unwrap(), b"invalid");
+    }
+
     // TODO: test complex samples
 
     fn run_test(case: TestCase) {
@@ -1,4 +1,4 @@
-use crate::errors::{bail, DslError, Result};
+use crate::errors::{bail, capture_reports, DslError, Result};
 use ansi_term::Colour;
 use compiler::compiler_interface;
 use compiler::compiler_interface::{Config, VCP};
@@ -21,35 +21,32 @@ pub struct CompilerConfig {
 }
 
 pub fn compile(config: CompilerConfig) -> Result<()> {
-    let circuit = match compiler_interface::run_compiler(
-        config.vcp,
-        Config {
-            debug_output: config.debug_output,
-            produce_input_log: config.produce_input_log,
-            wat_flag: config.wat_flag,
-        },
-        crate::CIRCOM_VERSION,
-    ) {
-        Ok(circuit) => circuit,
-        _ => {
-            bail!(DslError::CircomCompileError(
-                "compiler_interface::run_compiler error".to_string(),
-            ));
-        }
-    };
+    let circuit = capture_reports("compiler_interface::run_compiler", || {
+        compiler_interface::run_compiler(
+            config.vcp,
+            Config {
+                debug_output: config.debug_output,
+                produce_input_log: config.produce_input_log,
+                wat_flag: config.wat_flag,
+            },
+            crate::CIRCOM_VERSION,
+        )
+    })?;
 
     if config.c_flag {
-        match compiler_interface::write_c(
+        if compiler_interface::write_c(
             &circuit,
             &config.c_folder,
             &config.c_run_name,
             &config.c_file,
             &config.dat_file,
-        ) {
-            Ok(()) => (),
-
-            _ => log::error!("compiler_interface::write_c"),
-        };
+        )
+        .is_err()
+        {
+            bail!(DslError::CircomCompileError(
+                "compiler_interface::write_c error".to_string(),
+            ));
+        }
         log::trace!(
             "{} {} and {}",
             Colour::Green.paint("Written successfully:"),
@@ -69,6 +66,7 @@ pub fn compile(config: CompilerConfig) -> Result<()> {
             "fr.asm".to_string(),
             "Makefile".to_string()
         );
+        build_c(&config.c_folder, &config.c_run_name)?;
     }
 
     match (config.wat_flag, config.wasm_flag) {
@@ -127,6 +125,28 @@ pub fn compile(config: CompilerConfig) -> Result<()> {
     Ok(())
 }
 
+/// Build the native witness generator by the Makefile circom writes, which needs g++, nasm,
+/// GMP and nlohmann-json.
+fn build_c(c_folder: &str, c_run_name: &str) -> Result<()> {
+    let output = std::process::Command::new("make")
+        .current_dir(c_folder)
+        .output()?;
+    if !output.status.success() {
+        bail!(DslError::CircomCompileError(format!(
+            "make {}: {}",
+            c_folder,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    log::trace!(
+        "{} {}/{}",
+        Colour::Green.paint("Built successfully:"),
+        c_folder,
+        c_run_name
+    );
+    Ok(())
+}
+
 fn wat_to_wasm(wat_file: &str, wasm_file: &str) -> Result<()> {
     use std::fs::read_to_string;
     use std::fs::File;
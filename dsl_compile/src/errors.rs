@@ -1,3 +1,9 @@
+use codespan_reporting::diagnostic::Diagnostic;
+use codespan_reporting::term::{self, termcolor::NoColor};
+use program_structure::error_definition::Report;
+use program_structure::file_definition::FileLibrary;
+use std::io::Read;
+use std::sync::Mutex;
 use thiserror::Error;
 
 pub use anyhow::{bail, Result};
@@ -16,3 +22,79 @@ impl From<String> for DslError {
         DslError::Unknown(e)
     }
 }
+
+/// The reports of circom as it prints them, with their source locations, to propagate them
+/// in the error.
+pub(crate) fn reports_to_string(reports: &[Report], file_library: &FileLibrary) -> String {
+    let mut writer = NoColor::new(Vec::new());
+    let config = term::Config::default();
+    for report in reports {
+        let mut labels = report.get_primary().clone();
+        labels.extend(report.get_secondary().iter().cloned());
+        let diagnostic = if report.is_warning() {
+            Diagnostic::warning()
+        } else {
+            Diagnostic::error()
+        };
+        let diagnostic = diagnostic
+            .with_message(report.get_message())
+            .with_code(report.get_code().id())
+            .with_labels(labels)
+            .with_notes(report.get_notes().clone());
+        if term::emit(&mut writer, &config, file_library.to_storage(), &diagnostic).is_err() {
+            return reports
+                .iter()
+                .map(|r| r.get_message().clone())
+                .collect::<Vec<_>>()
+                .join("\n");
+        }
+    }
+    String::from_utf8_lossy(&writer.into_inner()).to_string()
+}
+
+/// Run `stage`, a circom stage that prints its reports to stderr instead of returning them,
+/// like `build_circuit`. The reports are captured and propagated in the error if it fails, or
+/// printed as circom does if it succeeds.
+pub(crate) fn capture_reports<T, E>(
+    name: &str,
+    stage: impl FnOnce() -> std::result::Result<T, E>,
+) -> Result<T> {
+    // stderr is redirected for the whole process, so one stage at a time
+    static STDERR: Mutex<()> = Mutex::new(());
+    let _lock = STDERR.lock().unwrap_or_else(|e| e.into_inner());
+    let mut redirect = gag::BufferRedirect::stderr()?;
+    let result = stage();
+    let mut reports = String::new();
+    redirect.read_to_string(&mut reports)?;
+    drop(redirect);
+
+    let reports = strip_colors(&reports);
+    match result {
+        Ok(value) => {
+            eprint!("{}", reports);
+            Ok(value)
+        }
+        Err(_) => bail!(DslError::CircomCompileError(format!(
+            "{} error\n{}",
+            name, reports
+        ))),
+    }
+}
+
+// circom prints its reports in color, drop the escape sequences like `\x1b[1;31m`
+fn strip_colors(reports: &str) -> String {
+    let mut result = String::with_capacity(reports.len());
+    let mut chars = reports.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
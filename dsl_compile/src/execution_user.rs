@@ -1,4 +1,4 @@
-use crate::errors::{bail, DslError, Result};
+use crate::errors::{bail, capture_reports, DslError, Result};
 use ansi_term::Colour;
 use compiler::hir::very_concrete_program::VCP;
 use constraint_writers::debug_writer::DebugWriter;
@@ -39,23 +39,19 @@ pub fn execute_project(program_archive: ProgramArchive, config: ExecutionConfig)
         // https://github.com/iden3/circom/commit/8f140c1dec7975b339bfe17c1f08d8081b913560
         flag_old_heuristics: false,
     };
-    match build_circuit(program_archive, build_config) {
-        Ok((exporter, vcp)) => {
-            if config.r1cs_flag {
-                generate_output_r1cs(&config.r1cs, exporter.as_ref())?;
-            }
-            if config.sym_flag {
-                generate_output_sym(&config.sym, exporter.as_ref())?;
-            }
-            if config.json_constraint_flag {
-                generate_json_constraints(&debug, exporter.as_ref())?;
-            }
-            Result::Ok(vcp)
-        }
-        Err(..) => bail!(DslError::CircomCompileError(
-            "execute_project error".to_string(),
-        )),
+    let (exporter, vcp) = capture_reports("execute_project", || {
+        build_circuit(program_archive, build_config)
+    })?;
+    if config.r1cs_flag {
+        generate_output_r1cs(&config.r1cs, exporter.as_ref())?;
     }
+    if config.sym_flag {
+        generate_output_sym(&config.sym, exporter.as_ref())?;
+    }
+    if config.json_constraint_flag {
+        generate_json_constraints(&debug, exporter.as_ref())?;
+    }
+    Result::Ok(vcp)
 }
 
 fn generate_output_r1cs(file: &str, exporter: &dyn ConstraintExporter) -> Result<()> {
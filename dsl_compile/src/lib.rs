@@ -1,19 +1,38 @@
 //! It's for: Domain Specific Language(DSL) compiler: now only include circom compile.
 
-use crate::errors::Result;
+use crate::errors::{bail, DslError, Result};
+use algebraic::bellman_ce::ScalarEngine;
+use algebraic::circom_circuit::R1CS;
+use algebraic::r1cs_check::SymbolTable;
+use std::io::Cursor;
 use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 mod compilation_user;
 mod errors;
 mod execution_user;
 mod input_user;
 mod parser_user;
+mod stats_user;
 mod type_analysis_user;
 
+pub use stats_user::{format_stats, StatsFormat, TemplateStats};
+
 /// Align with https://github.com/iden3/circom/blob/master/circom/Cargo.toml#L3
 const CIRCOM_VERSION: &str = "2.1.2";
 
-/// Compile circom circuits to r1cs, and generate witness
+/// Whether `circom_compiler` collects the statistics of each template.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Stats {
+    #[default]
+    Skip,
+    Collect,
+}
+
+/// Compile circom circuits to r1cs, and generate witness. With `c_flag`, the native witness
+/// generator is also written and built to `<output>/<name>_cpp/<name>`. With `Stats::Collect`,
+/// the statistics of each template are returned, see `format_stats`, otherwise they are empty.
+#[allow(clippy::too_many_arguments)]
 pub fn circom_compiler(
     input: String,
     prime: String,
@@ -22,7 +41,9 @@ pub fn circom_compiler(
     output: String,
     no_simplification: bool,
     reduced_simplification: bool,
-) -> Result<()> {
+    c_flag: bool,
+    stats: Stats,
+) -> Result<Vec<TemplateStats>> {
     use compilation_user::CompilerConfig;
     use execution_user::ExecutionConfig;
     let fullopt = !full_simplification.is_empty();
@@ -36,7 +57,8 @@ pub fn circom_compiler(
     let input = Path::new(&input);
     let output = Path::new(&output);
 
-    let user_input = input_user::Input::new(input, output, o_style, prime, link_directories)?;
+    let mut user_input = input_user::Input::new(input, output, o_style, prime, link_directories)?;
+    user_input.c_flag = c_flag;
     let mut program_archive = parser_user::parse_project(&user_input)?;
 
     type_analysis_user::analyse_project(&mut program_archive)?;
@@ -58,6 +80,12 @@ pub fn circom_compiler(
         prime: user_input.get_prime(),
     };
     let circuit = execution_user::execute_project(program_archive, config)?;
+    let stats = match stats {
+        Stats::Collect => {
+            stats_user::template_stats(&circuit, user_input.r1cs_file(), user_input.sym_file())?
+        }
+        Stats::Skip => vec![],
+    };
     let compilation_config = CompilerConfig {
         vcp: circuit,
         debug_output: user_input.print_ir_flag(),
@@ -75,5 +103,270 @@ pub fn circom_compiler(
         produce_input_log: user_input.main_inputs_flag(),
     };
     compilation_user::compile(compilation_config)?;
-    Result::Ok(())
+    Result::Ok(stats)
+}
+
+/// A circuit compiled in memory by `circom_compile_source`.
+pub struct CompiledCircuit<E: ScalarEngine> {
+    pub r1cs: R1CS<E>,
+    /// the label of each wire
+    pub wire_mapping: Vec<usize>,
+    pub wasm: Vec<u8>,
+    pub symbols: SymbolTable,
+}
+
+/// Compile the circom `source` with full simplification, and return the R1CS, the wasm and the
+/// symbols. circom writes them to a temporary directory, which is removed once they are read
+/// back. The source is compiled as the file `file_name` of `base_dir`: its includes are
+/// searched in `base_dir` and then in `link_directories`, and the reports of circom in the
+/// error are located in `base_dir/file_name`.
+pub fn circom_compile_source<E: ScalarEngine>(
+    source: &str,
+    file_name: &str,
+    base_dir: &Path,
+    prime: &str,
+    link_directories: Vec<String>,
+) -> Result<CompiledCircuit<E>> {
+    let name = Path::new(file_name);
+    let stem = match (name.parent(), name.file_stem()) {
+        (Some(parent), Some(stem)) if parent.as_os_str().is_empty() => {
+            stem.to_string_lossy().to_string()
+        }
+        _ => bail!("{} is not the name of a file", file_name),
+    };
+    // circom reads its input and writes its outputs as files, so compile in a temporary
+    // directory, unique to each call.
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let dir = std::env::temp_dir().join(format!(
+        "circom_compile_source_{}_{}",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    std::fs::create_dir_all(&dir)?;
+    let input = dir.join(file_name);
+    std::fs::write(&input, source)?;
+    // the canonical path first, it may contain the other one
+    let input_paths = [
+        std::fs::canonicalize(&input)?.to_string_lossy().to_string(),
+        input.to_string_lossy().to_string(),
+    ];
+    let mut include_directories = vec![base_dir.to_string_lossy().to_string()];
+    include_directories.extend(link_directories);
+    let result = compile_source_in(&dir, &input, &stem, prime, include_directories);
+    let _ = std::fs::remove_dir_all(&dir);
+
+    result.map_err(|e| {
+        if let Some(DslError::CircomCompileError(reports)) = e.downcast_ref::<DslError>() {
+            let virtual_path = base_dir.join(file_name).to_string_lossy().to_string();
+            let reports = input_paths.iter().fold(reports.clone(), |reports, path| {
+                reports.replace(path, &virtual_path)
+            });
+            return DslError::CircomCompileError(reports).into();
+        }
+        e
+    })
+}
+
+fn compile_source_in<E: ScalarEngine>(
+    dir: &Path,
+    input: &Path,
+    stem: &str,
+    prime: &str,
+    link_directories: Vec<String>,
+) -> Result<CompiledCircuit<E>> {
+    circom_compiler(
+        input.to_string_lossy().to_string(),
+        prime.to_lowercase(),
+        "full".to_string(),
+        link_directories,
+        dir.to_string_lossy().to_string(),
+        false,
+        false,
+        false,
+        Stats::Skip,
+    )?;
+
+    let r1cs = std::fs::read(dir.join(format!("{}.r1cs", stem)))?;
+    let (r1cs, wire_mapping) =
+        algebraic::r1cs_file::from_reader::<_, E>(Cursor::new(r1cs))?.into_r1cs();
+    let wasm = std::fs::read(
+        dir.join(format!("{}_js", stem))
+            .join(format!("{}.wasm", stem)),
+    )?;
+    let symbols = SymbolTable::from_file(&dir.join(format!("{}.sym", stem)).to_string_lossy())?;
+    Ok(CompiledCircuit {
+        r1cs,
+        wire_mapping,
+        wasm,
+        symbols,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use algebraic::bellman_ce::pairing::bn256::Bn256;
+    use algebraic::reader::load_witness_from_file;
+    use algebraic::witness::{load_input_for_witness, NativeWitnessCalculator, WitnessCalculator};
+
+    const CIRCOM_FILE: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../algebraic/test-vectors/mycircuit.circom"
+    );
+
+    #[test]
+    fn test_circom_compile_source() {
+        let source = std::fs::read_to_string(CIRCOM_FILE).unwrap();
+        let base_dir = Path::new(CIRCOM_FILE).parent().unwrap();
+        let compile = |source: &str| {
+            circom_compile_source::<Bn256>(source, "mycircuit.circom", base_dir, "BN128", vec![])
+        };
+        let circuit = compile(&source).unwrap();
+        assert_eq!(circuit.r1cs.constraints.len(), 1);
+        assert_eq!(circuit.r1cs.num_inputs, 2);
+        assert_eq!(circuit.r1cs.num_variables, 4);
+        assert_eq!(circuit.wire_mapping, vec![0, 1, 2, 3]);
+        assert_eq!(&circuit.wasm[..4], b"\0asm");
+        assert_eq!(circuit.symbols.name(1), "main.c");
+
+        let err = compile(&source.replace("a*b", "a*d"))
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(err.contains("analyse_project error"));
+        assert!(err.contains(&format!("{}:", CIRCOM_FILE)));
+        assert!(!err.contains("circom_compile_source_"));
+
+        assert!(circom_compile_source::<Bn256>(
+            &source,
+            "test-vectors/mycircuit.circom",
+            base_dir,
+            "BN128",
+            vec![]
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_circom_compile_source_include() {
+        let base_dir = std::env::temp_dir().join("dsl_compile_source_include");
+        std::fs::create_dir_all(&base_dir).unwrap();
+        let template = std::fs::read_to_string(CIRCOM_FILE).unwrap();
+        let template = template.replace("component main = Multiplier();", "");
+        std::fs::write(base_dir.join("multiplier.circom"), template).unwrap();
+
+        let source = "pragma circom 2.0.1;\ninclude \"multiplier.circom\";\n\ncomponent main = Multiplier();\n";
+        let compile = |source: &str| {
+            circom_compile_source::<Bn256>(source, "verifier.circom", &base_dir, "BN128", vec![])
+        };
+        let circuit = compile(source).unwrap();
+        assert_eq!(circuit.r1cs.constraints.len(), 1);
+        assert_eq!(circuit.symbols.name(1), "main.c");
+
+        let err = compile(&source.replace("Multiplier()", "Adder()"))
+            .err()
+            .unwrap()
+            .to_string();
+        let virtual_path = base_dir.join("verifier.circom");
+        assert!(err.contains(&format!("{}:", virtual_path.to_string_lossy())));
+    }
+
+    #[test]
+    fn test_circom_compile_source_execution_error() {
+        let base_dir = Path::new(CIRCOM_FILE).parent().unwrap();
+        let source = std::fs::read_to_string(CIRCOM_FILE).unwrap();
+        let virtual_path = base_dir.join("failing.circom");
+        for (from, to, report) in [
+            (
+                "c <== a*b;",
+                "c <== a*b*a;",
+                "Non quadratic constraints are not allowed",
+            ),
+            (
+                "c <== a*b;",
+                "c <== a*b;\n    assert(1 == 2);",
+                "False assert reached",
+            ),
+        ] {
+            let err = circom_compile_source::<Bn256>(
+                &source.replace(from, to),
+                "failing.circom",
+                base_dir,
+                "BN128",
+                vec![],
+            )
+            .err()
+            .unwrap()
+            .to_string();
+            assert!(err.contains("execute_project error"), "{}", err);
+            assert!(err.contains(report), "{}", err);
+            assert!(err.contains(&format!("{}:", virtual_path.to_string_lossy())));
+            assert!(!err.contains('\x1b'));
+        }
+    }
+
+    #[test]
+    #[ignore = "needs g++, nasm, GMP and nlohmann-json to build the native witness generator"]
+    fn test_native_witness_parity() {
+        let output = std::env::temp_dir().join("dsl_compile_native");
+        std::fs::create_dir_all(&output).unwrap();
+        let output = output.to_str().unwrap().to_string();
+        let stats = circom_compiler(
+            CIRCOM_FILE.to_string(),
+            "bn128".to_string(),
+            "full".to_string(),
+            vec![],
+            output.clone(),
+            false,
+            false,
+            true,
+            Stats::Collect,
+        )
+        .unwrap();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].template, "Multiplier");
+        assert_eq!(stats[0].constraints, 1);
+        let wasm_file = format!("{}/mycircuit_js/mycircuit.wasm", output);
+        let binary = format!("{}/mycircuit_cpp/mycircuit", output);
+
+        let mut wasm = WitnessCalculator::from_file(&wasm_file).unwrap();
+        let mut native = NativeWitnessCalculator::from_file(&binary).unwrap();
+        for (a, b) in [
+            ("3", "11"),
+            (
+                "21888242871839275222246405745257275088548364400416034343698204186575796149939",
+                "11",
+            ),
+        ] {
+            let input_file = format!("{}/input.json", output);
+            std::fs::write(&input_file, format!(r#"{{"a": "{}", "b": "{}"}}"#, a, b)).unwrap();
+            let inputs = || load_input_for_witness(&input_file);
+            assert_eq!(
+                native.calculate_witness(inputs(), false).unwrap(),
+                wasm.calculate_witness(inputs(), false).unwrap()
+            );
+
+            let w = wasm.calculate_witness_bin(inputs(), false).unwrap();
+            assert_eq!(native.calculate_witness_bin(inputs(), false).unwrap(), w);
+            let (wasm_wtns, native_wtns) = (
+                format!("{}/wasm.wtns", output),
+                format!("{}/native.wtns", output),
+            );
+            wasm.save_witness_to_bin_file::<Bn256>(&wasm_wtns, &w)
+                .unwrap();
+            native
+                .calculate_witness_to_file(&input_file, &native_wtns)
+                .unwrap();
+            assert_eq!(
+                load_witness_from_file::<Bn256>(&native_wtns),
+                load_witness_from_file::<Bn256>(&wasm_wtns)
+            );
+        }
+
+        let input_file = format!("{}/input.json", output);
+        std::fs::write(&input_file, r#"{"a": "3"}"#).unwrap();
+        assert!(native
+            .calculate_witness(load_input_for_witness(&input_file), false)
+            .is_err());
+    }
 }
@@ -1,4 +1,4 @@
-use crate::errors::{bail, DslError, Result};
+use crate::errors::{bail, reports_to_string, DslError, Result};
 use crate::input_user::Input;
 use crate::CIRCOM_VERSION;
 use program_structure::error_definition::Report;
@@ -12,12 +12,10 @@ pub fn parse_project(input_info: &Input) -> Result<ProgramArchive> {
         input_info.link_libraries.clone(),
     );
     match result_program_archive {
-        Err((file_library, report_collection)) => {
-            Report::print_reports(&report_collection, &file_library);
-            bail!(DslError::CircomCompileError(
-                "parser::run_parser error".to_string(),
-            ))
-        }
+        Err((file_library, report_collection)) => bail!(DslError::CircomCompileError(format!(
+            "parser::run_parser error\n{}",
+            reports_to_string(&report_collection, &file_library)
+        ))),
         Ok((program_archive, warnings)) => {
             Report::print_reports(&warnings, &program_archive.file_library);
             Ok(program_archive)
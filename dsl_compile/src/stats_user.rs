@@ -0,0 +1,307 @@
+//! Statistics per template of a compiled circuit. The components and their signals come from
+//! the VCP, the wires and constraints from the simplified r1cs and sym files the
+//! ConstraintExporter writes, whatever the prime.
+use crate::errors::{bail, DslError, Result};
+use byteorder::{LittleEndian, ReadBytesExt};
+use compiler::compiler_interface::VCP;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{Cursor, Read, Seek, SeekFrom};
+use std::str::FromStr;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TemplateStats {
+    pub template: String,
+    pub instances: usize,
+    pub signals: usize,
+    pub wires: usize,
+    pub constraints: usize,
+}
+
+/// How `format_stats` prints the statistics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatsFormat {
+    Table,
+    Json,
+}
+
+impl FromStr for StatsFormat {
+    type Err = DslError;
+
+    fn from_str(format: &str) -> std::result::Result<Self, Self::Err> {
+        match format {
+            "table" => Ok(StatsFormat::Table),
+            "json" => Ok(StatsFormat::Json),
+            _ => Err(DslError::Unknown(format!(
+                "unknown stats format {}",
+                format
+            ))),
+        }
+    }
+}
+
+// a component of the circuit, by its path in the sym file, like `main.hash[2]`
+#[derive(Debug, Clone)]
+struct Component {
+    template: String,
+    signals: usize,
+}
+
+pub fn template_stats(vcp: &VCP, r1cs_file: &str, sym_file: &str) -> Result<Vec<TemplateStats>> {
+    let components = components(vcp);
+    let sym = std::fs::read_to_string(sym_file)?;
+    let constraints = read_constraint_wires(&std::fs::read(r1cs_file)?)?;
+    collect_stats(&components, &sym, &constraints)
+}
+
+/// The statistics as a table, sorted by constraints, or as json.
+pub fn format_stats(stats: &[TemplateStats], format: StatsFormat) -> Result<String> {
+    match format {
+        StatsFormat::Table => {
+            let total = stats.iter().fold(
+                TemplateStats {
+                    template: "total".to_string(),
+                    ..Default::default()
+                },
+                |mut total, s| {
+                    total.instances += s.instances;
+                    total.signals += s.signals;
+                    total.wires += s.wires;
+                    total.constraints += s.constraints;
+                    total
+                },
+            );
+            let width = stats
+                .iter()
+                .map(|s| s.template.len())
+                .chain(["template".len()])
+                .max()
+                .unwrap_or_default();
+            let mut table = format!(
+                "{:<width$} {:>10} {:>12} {:>12} {:>12}\n",
+                "template", "instances", "signals", "wires", "constraints"
+            );
+            for s in stats.iter().chain([&total]) {
+                table.push_str(&format!(
+                    "{:<width$} {:>10} {:>12} {:>12} {:>12}\n",
+                    s.template, s.instances, s.signals, s.wires, s.constraints
+                ));
+            }
+            Ok(table)
+        }
+        StatsFormat::Json => {
+            let stats: Vec<_> = stats
+                .iter()
+                .map(|s| {
+                    serde_json::json!({
+                        "template": s.template,
+                        "instances": s.instances,
+                        "signals": s.signals,
+                        "wires": s.wires,
+                        "constraints": s.constraints,
+                    })
+                })
+                .collect();
+            Ok(serde_json::to_string_pretty(&stats)?)
+        }
+    }
+}
+
+// walk the components from main, the path of a subcomponent is the one circom writes in the
+// sym file.
+fn components(vcp: &VCP) -> HashMap<String, Component> {
+    let templates: HashMap<_, _> = vcp.templates.iter().map(|t| (t.template_id, t)).collect();
+    let mut components = HashMap::new();
+    let mut stack = vec![("main".to_string(), vcp.main_id)];
+    while let Some((path, id)) = stack.pop() {
+        let template = templates[&id];
+        for trigger in template.triggers.iter() {
+            let mut sub = format!("{}.{}", path, trigger.component_name);
+            for i in trigger.indexed_with.iter() {
+                sub.push_str(&format!("[{}]", i));
+            }
+            stack.push((sub, trigger.template_id));
+        }
+        components.insert(
+            path,
+            Component {
+                template: template.template_name.clone(),
+                signals: template.number_of_inputs
+                    + template.number_of_outputs
+                    + template.number_of_intermediates,
+            },
+        );
+    }
+    components
+}
+
+// A wire belongs to the component of its first signal in the sym file. The simplification
+// merges the constraints of components, so a constraint is counted in the component of its
+// last wire, the one it was usually generated for.
+fn collect_stats(
+    components: &HashMap<String, Component>,
+    sym: &str,
+    constraints: &[Vec<u32>],
+) -> Result<Vec<TemplateStats>> {
+    let mut stats: BTreeMap<&str, TemplateStats> = BTreeMap::new();
+    for component in components.values() {
+        let s = stats.entry(component.template.as_str()).or_default();
+        s.instances += 1;
+        s.signals += component.signals;
+    }
+
+    let mut wire_templates: HashMap<u32, &str> = HashMap::new();
+    for (i, line) in sym.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = line.splitn(4, ',').collect();
+        if fields.len() != 4 {
+            bail!(DslError::Unknown(format!(
+                "invalid sym line {}: {}",
+                i + 1,
+                line
+            )));
+        }
+        let wire: i64 = fields[1]
+            .trim()
+            .parse()
+            .map_err(|_| DslError::Unknown(format!("invalid sym line {}: {}", i + 1, line)))?;
+        let path = fields[3]
+            .trim()
+            .rsplit_once('.')
+            .map_or("", |(path, _)| path);
+        if wire < 0 || wire_templates.contains_key(&(wire as u32)) {
+            continue;
+        }
+        if let Some(component) = components.get(path) {
+            wire_templates.insert(wire as u32, component.template.as_str());
+            stats.entry(component.template.as_str()).or_default().wires += 1;
+        }
+    }
+
+    for wires in constraints.iter() {
+        let template = wires
+            .iter()
+            .filter(|w| **w != 0)
+            .max()
+            .and_then(|w| wire_templates.get(w));
+        if let Some(template) = template {
+            stats.entry(*template).or_default().constraints += 1;
+        }
+    }
+
+    let mut stats: Vec<TemplateStats> = stats
+        .into_iter()
+        .map(|(template, s)| TemplateStats {
+            template: template.to_string(),
+            ..s
+        })
+        .collect();
+    stats.sort_by(|a, b| b.constraints.cmp(&a.constraints));
+    Ok(stats)
+}
+
+// the wires of each constraint, skipping the coefficients, so it reads the r1cs of any prime
+fn read_constraint_wires(r1cs: &[u8]) -> Result<Vec<Vec<u32>>> {
+    let mut reader = Cursor::new(r1cs);
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if magic != *b"r1cs" {
+        bail!(DslError::Unknown("Invalid magic number".to_string()));
+    }
+    let _version = reader.read_u32::<LittleEndian>()?;
+    let num_sections = reader.read_u32::<LittleEndian>()?;
+    let mut section_offsets = HashMap::new();
+    for _ in 0..num_sections {
+        let section_type = reader.read_u32::<LittleEndian>()?;
+        let section_size = reader.read_u64::<LittleEndian>()?;
+        section_offsets.insert(section_type, reader.position());
+        reader.seek(SeekFrom::Current(section_size as i64))?;
+    }
+    let (header, constraints) = match (section_offsets.get(&1), section_offsets.get(&2)) {
+        (Some(header), Some(constraints)) => (*header, *constraints),
+        _ => bail!(DslError::Unknown(
+            "The r1cs has no header or constraints".to_string()
+        )),
+    };
+
+    reader.set_position(header);
+    let field_size = reader.read_u32::<LittleEndian>()? as i64;
+    // skip the prime, the numbers of wires, outputs, inputs, private inputs and labels
+    reader.seek(SeekFrom::Current(field_size + 4 * 4 + 8))?;
+    let n_constraints = reader.read_u32::<LittleEndian>()?;
+
+    reader.set_position(constraints);
+    let mut result = Vec::with_capacity(n_constraints as usize);
+    for _ in 0..n_constraints {
+        let mut wires = vec![];
+        for _ in 0..3 {
+            let n = reader.read_u32::<LittleEndian>()?;
+            for _ in 0..n {
+                wires.push(reader.read_u32::<LittleEndian>()?);
+                reader.seek(SeekFrom::Current(field_size))?;
+            }
+        }
+        result.push(wires);
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CIRCUIT_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/multiplier.r1cs");
+
+    #[test]
+    fn test_collect_stats() {
+        let constraints = read_constraint_wires(&std::fs::read(CIRCUIT_FILE).unwrap()).unwrap();
+        assert_eq!(constraints, vec![vec![2, 3, 1]]);
+        assert!(read_constraint_wires(b"r1c").is_err());
+
+        let component = |template: &str, signals| Component {
+            template: template.to_string(),
+            signals,
+        };
+        let components = HashMap::from([
+            ("main".to_string(), component("Main", 3)),
+            ("main.mul[0]".to_string(), component("Multiplier", 3)),
+            ("main.mul[1]".to_string(), component("Multiplier", 3)),
+        ]);
+        let sym = "1,1,0,main.out\n2,2,0,main.in\n3,-1,1,main.mul[0].a\n4,3,1,main.mul[0].c\n\
+                   5,3,2,main.mul[1].a\n6,4,2,main.mul[1].c\n";
+        let constraints = vec![vec![2, 2, 3], vec![3, 2, 4], vec![0, 2, 1]];
+        let stats = collect_stats(&components, sym, &constraints).unwrap();
+        assert_eq!(
+            stats,
+            vec![
+                TemplateStats {
+                    template: "Multiplier".to_string(),
+                    instances: 2,
+                    signals: 6,
+                    wires: 2,
+                    constraints: 2,
+                },
+                TemplateStats {
+                    template: "Main".to_string(),
+                    instances: 1,
+                    signals: 3,
+                    wires: 2,
+                    constraints: 1,
+                },
+            ]
+        );
+
+        let table = format_stats(&stats, StatsFormat::Table).unwrap();
+        assert_eq!(table.lines().count(), 4);
+        assert!(table.lines().last().unwrap().starts_with("total"));
+        assert!(table.lines().last().unwrap().ends_with(" 3"));
+        let json: serde_json::Value =
+            serde_json::from_str(&format_stats(&stats, StatsFormat::Json).unwrap()).unwrap();
+        assert_eq!(json[0]["template"], "Multiplier");
+        assert_eq!(json[1]["constraints"], 1);
+        assert_eq!("json".parse::<StatsFormat>().unwrap(), StatsFormat::Json);
+        assert!("csv".parse::<StatsFormat>().is_err());
+        assert!(collect_stats(&components, "1,2,main.a", &constraints).is_err());
+    }
+}
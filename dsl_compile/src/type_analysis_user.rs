@@ -1,4 +1,4 @@
-use crate::errors::{bail, DslError, Result};
+use crate::errors::{bail, reports_to_string, DslError, Result};
 use program_structure::error_definition::Report;
 use program_structure::program_archive::ProgramArchive;
 use type_analysis::check_types::check_types;
@@ -6,12 +6,10 @@ use type_analysis::check_types::check_types;
 pub fn analyse_project(program_archive: &mut ProgramArchive) -> Result<()> {
     let analysis_result = check_types(program_archive);
     match analysis_result {
-        Err(errs) => {
-            Report::print_reports(&errs, program_archive.get_file_library());
-            bail!(DslError::CircomCompileError(String::from(
-                "analyse_project error",
-            )))
-        }
+        Err(errs) => bail!(DslError::CircomCompileError(format!(
+            "analyse_project error\n{}",
+            reports_to_string(&errs, program_archive.get_file_library())
+        ))),
         Ok(warns) => {
             Report::print_reports(&warns, program_archive.get_file_library());
             Ok(())
@@ -0,0 +1,1378 @@
+//! Aggregation of groth16 proofs of one verifying key, see SnarkPack, Gailly, Maller and
+//! Nitulescu, https://eprint.iacr.org/2021/529.
+//!
+//! The n proofs, padded to a power of two by repeating the last one, are committed in GT with
+//! the keys v = h^(a^i), h^(b^i) and w = g^(a^(n+i)), g^(b^(n+i)) of an SRS of two secrets a
+//! and b. With a random r, the n groth16 equations become one, the pairing product of A and
+//! B^(r^i) against e(alpha, beta)^(sum r^i) e(sum r^i IC(x_i), gamma) e(sum r^i C_i, delta).
+//! The prover shows that the committed A, B and C give these inner products with log n rounds
+//! of GIPA, each one halving the vectors and the keys with a challenge x, and ends with KZG
+//! openings of the folded keys, which are polynomials of the challenges evaluated at a and b.
+//!
+//! The transcript hashes the proof with keccak256 in the order of `AggregateProof::to_words`,
+//! a field element as 32 big endian bytes. The verifier checks the GT equations of the last
+//! round and the groth16 equation together, raised to the powers of a last challenge rho,
+//! with one multi pairing.
+//!
+//! The SRS is read from the powers of tau of two phase 1 ceremonies, as SnarkPack takes a
+//! from the one of Filecoin and b from the one of Zcash, so nobody knows a or b.
+use crate::bellman_ce::{
+    bls12_381::{self, Bls12},
+    bn256::{self, Bn256},
+    groth16::{Proof, VerifyingKey},
+    pairing::{CurveAffine, CurveProjective, Engine},
+};
+use crate::json_utils::{render_scalar_to_str, Parser, G1, G2};
+use crate::mpc::read_ptau_powers;
+use crate::zkey::ZKeyEngine;
+use crate::{Field, PrimeField, PrimeFieldRepr};
+use algebraic::utils::repr_to_big;
+use anyhow::{anyhow, bail, Result};
+use num_bigint::BigUint;
+use num_traits::Num;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+use tiny_keccak::{Hasher, Keccak};
+
+const PROTOCOL: &str = "snarkpack";
+
+/// The curves of the aggregation, an element of GT is kept as its 12 coefficients over Fq,
+/// c0.c0.c0, c0.c0.c1, c0.c1.c0, ..., c1.c2.c1.
+pub trait AggregateEngine: ZKeyEngine {
+    fn to_fqk(c: &[Self::Fq]) -> Self::Fqk;
+    fn from_fqk(e: &Self::Fqk) -> Vec<Self::Fq>;
+}
+
+impl AggregateEngine for Bn256 {
+    fn to_fqk(c: &[Self::Fq]) -> Self::Fqk {
+        let fq2 = |i: usize| bn256::Fq2 {
+            c0: c[2 * i],
+            c1: c[2 * i + 1],
+        };
+        let fq6 = |i: usize| bn256::Fq6 {
+            c0: fq2(3 * i),
+            c1: fq2(3 * i + 1),
+            c2: fq2(3 * i + 2),
+        };
+        bn256::Fq12 {
+            c0: fq6(0),
+            c1: fq6(1),
+        }
+    }
+    fn from_fqk(e: &Self::Fqk) -> Vec<Self::Fq> {
+        [e.c0.c0, e.c0.c1, e.c0.c2, e.c1.c0, e.c1.c1, e.c1.c2]
+            .iter()
+            .flat_map(|c| [c.c0, c.c1])
+            .collect()
+    }
+}
+
+impl AggregateEngine for Bls12 {
+    fn to_fqk(c: &[Self::Fq]) -> Self::Fqk {
+        let fq2 = |i: usize| bls12_381::Fq2 {
+            c0: c[2 * i],
+            c1: c[2 * i + 1],
+        };
+        let fq6 = |i: usize| bls12_381::Fq6 {
+            c0: fq2(3 * i),
+            c1: fq2(3 * i + 1),
+            c2: fq2(3 * i + 2),
+        };
+        bls12_381::Fq12 {
+            c0: fq6(0),
+            c1: fq6(1),
+        }
+    }
+    fn from_fqk(e: &Self::Fqk) -> Vec<Self::Fq> {
+        [e.c0.c0, e.c0.c1, e.c0.c2, e.c1.c0, e.c1.c1, e.c1.c2]
+            .iter()
+            .flat_map(|c| [c.c0, c.c1])
+            .collect()
+    }
+}
+
+/// The SRS of the aggregation, g^(a^i) and g^(b^i) for i < 2n, h^(a^i) and h^(b^i) for
+/// i < n, of the secrets a and b of two phase 1 ceremonies.
+pub struct AggregationKey<E: Engine> {
+    pub g_a: Vec<E::G1Affine>,
+    pub g_b: Vec<E::G1Affine>,
+    pub h_a: Vec<E::G2Affine>,
+    pub h_b: Vec<E::G2Affine>,
+}
+
+/// The part of the SRS the verifier needs.
+pub struct AggregationVerifyingKey<E: Engine> {
+    pub g: E::G1Affine,
+    pub h: E::G2Affine,
+    pub g_a: E::G1Affine,
+    pub g_b: E::G1Affine,
+    pub h_a: E::G2Affine,
+    pub h_b: E::G2Affine,
+}
+
+impl<E: ZKeyEngine> AggregationKey<E> {
+    /// A key for up to `max_proofs` proofs, rounded up to a power of two, from the ptau of
+    /// a and the ptau of b, which must come from independent ceremonies.
+    pub fn from_ptau(max_proofs: usize, ptau_a: &str, ptau_b: &str) -> Result<Self> {
+        let n = padded_len(max_proofs);
+        let (g_a, h_a) = read_ptau_powers::<E>(ptau_a, 2 * n, n)?;
+        let (g_b, h_b) = read_ptau_powers::<E>(ptau_b, 2 * n, n)?;
+        let (g, h) = (E::G1Affine::one(), E::G2Affine::one());
+        if g_a[0] != g || g_b[0] != g || h_a[0] != h || h_b[0] != h {
+            bail!("The ptau files are not over the generators of the curve");
+        }
+        if g_a[1] == g_b[1] {
+            bail!("The two ptau files are of the same tau");
+        }
+        // the powers in G1 and in G2 are of the same secret
+        for (g_s, h_s) in [(&g_a, &h_a), (&g_b, &h_b)] {
+            if E::pairing(g_s[1], h) != E::pairing(g, h_s[1]) {
+                bail!("The powers of tau in G1 and in G2 of a ptau differ");
+            }
+        }
+        Ok(Self { g_a, g_b, h_a, h_b })
+    }
+}
+
+impl<E: Engine> AggregationKey<E> {
+    pub fn max_proofs(&self) -> usize {
+        self.h_a.len()
+    }
+
+    pub fn verifying_key(&self) -> AggregationVerifyingKey<E> {
+        AggregationVerifyingKey {
+            g: self.g_a[0],
+            h: self.h_a[0],
+            g_a: self.g_a[1],
+            g_b: self.g_b[1],
+            h_a: self.h_a[1],
+            h_b: self.h_b[1],
+        }
+    }
+}
+
+/// The messages of a GIPA round, `_l` goes with x and `_r` with x^-1 in the folded
+/// commitments, the pairs are the commitments with the keys of a and b.
+pub struct GipaRound<E: Engine> {
+    pub tab_l: [E::Fqk; 2],
+    pub tab_r: [E::Fqk; 2],
+    pub zab_l: E::Fqk,
+    pub zab_r: E::Fqk,
+    pub tc_l: [E::Fqk; 2],
+    pub tc_r: [E::Fqk; 2],
+    pub zc_l: E::G1Affine,
+    pub zc_r: E::G1Affine,
+}
+
+pub struct AggregateProof<E: Engine> {
+    /// the commitments to A and B, and to C
+    pub com_ab: [E::Fqk; 2],
+    pub com_c: [E::Fqk; 2],
+    /// prod e(A_i, B_i)^(r^i) and sum r^i C_i
+    pub ip_ab: E::Fqk,
+    pub agg_c: E::G1Affine,
+    pub rounds: Vec<GipaRound<E>>,
+    /// A, B, C and the keys after the last round
+    pub a: E::G1Affine,
+    pub b: E::G2Affine,
+    pub c: E::G1Affine,
+    pub v: [E::G2Affine; 2],
+    pub w: [E::G1Affine; 2],
+    /// the KZG openings of v and w
+    pub v_opening: [E::G2Affine; 2],
+    pub w_opening: [E::G1Affine; 2],
+}
+
+// the number of proofs after the padding
+fn padded_len(n: usize) -> usize {
+    n.next_power_of_two().max(2)
+}
+
+fn pad<T: Clone>(items: &[T], n: usize) -> Vec<T> {
+    items
+        .iter()
+        .chain(std::iter::repeat(&items[items.len() - 1]))
+        .take(n)
+        .cloned()
+        .collect()
+}
+
+fn scalar_powers<F: PrimeField>(s: F, count: usize) -> Vec<F> {
+    let mut powers = Vec::with_capacity(count);
+    let mut acc = F::one();
+    for _ in 0..count {
+        powers.push(acc);
+        acc.mul_assign(&s);
+    }
+    powers
+}
+
+pub(crate) fn multiexp<G: CurveAffine>(bases: &[G], scalars: &[G::Scalar]) -> G::Projective {
+    bases
+        .par_iter()
+        .zip(scalars.par_iter())
+        .map(|(p, s)| p.mul(*s))
+        .reduce(G::Projective::zero, |mut a, b| {
+            a.add_assign(&b);
+            a
+        })
+}
+
+fn scale<G: CurveAffine>(points: &[G], scalars: &[G::Scalar]) -> Vec<G> {
+    points
+        .par_iter()
+        .zip(scalars.par_iter())
+        .map(|(p, s)| p.mul(*s).into_affine())
+        .collect()
+}
+
+// left + x * right
+fn fold<G: CurveAffine>(left: &[G], right: &[G], x: G::Scalar) -> Vec<G> {
+    left.par_iter()
+        .zip(right.par_iter())
+        .map(|(l, r)| {
+            let mut p = r.mul(x);
+            p.add_assign_mixed(l);
+            p.into_affine()
+        })
+        .collect()
+}
+
+fn fold_scalars<F: PrimeField>(left: &[F], right: &[F], x: F) -> Vec<F> {
+    left.iter()
+        .zip(right.iter())
+        .map(|(l, r)| {
+            let mut s = *r;
+            s.mul_assign(&x);
+            s.add_assign(l);
+            s
+        })
+        .collect()
+}
+
+/// prod e(g1[i], g2[i])
+fn pairing_product<E: Engine>(g1: &[E::G1Affine], g2: &[E::G2Affine]) -> E::Fqk {
+    let f = g1
+        .par_chunks(64)
+        .zip(g2.par_chunks(64))
+        .map(|(g1, g2)| {
+            let prepared: Vec<_> = g1
+                .iter()
+                .zip(g2.iter())
+                .map(|(p, q)| (p.prepare(), q.prepare()))
+                .collect();
+            let terms: Vec<_> = prepared.iter().map(|(p, q)| (p, q)).collect();
+            E::miller_loop(terms.iter())
+        })
+        .reduce(E::Fqk::one, |mut a, b| {
+            a.mul_assign(&b);
+            a
+        });
+    E::final_exponentiation(&f).unwrap()
+}
+
+// prod e(a_i, v_i) e(w_i, b_i)
+fn commit_ab<E: Engine>(
+    a: &[E::G1Affine],
+    b: &[E::G2Affine],
+    v: &[E::G2Affine],
+    w: &[E::G1Affine],
+) -> E::Fqk {
+    pairing_product::<E>(&[a, w].concat(), &[v, b].concat())
+}
+
+// the coefficients of prod_j (1 + factors[j] X^(n / 2^(j+1))) with n = 2^factors.len(), the
+// coefficient of X^i is the product of the factors of the rounds that took i from the right
+fn folding_poly<F: PrimeField>(factors: &[F]) -> Vec<F> {
+    let n = 1 << factors.len();
+    let mut coefs = vec![F::one(); n];
+    for (j, f) in factors.iter().enumerate() {
+        let m = n >> (j + 1);
+        for (i, c) in coefs.iter_mut().enumerate() {
+            if i & m != 0 {
+                c.mul_assign(f);
+            }
+        }
+    }
+    coefs
+}
+
+fn folding_eval<F: PrimeField>(factors: &[F], z: F) -> F {
+    let n = 1u64 << factors.len();
+    let mut acc = F::one();
+    for (j, f) in factors.iter().enumerate() {
+        let mut t = z.pow([n >> (j + 1)]);
+        t.mul_assign(f);
+        t.add_assign(&F::one());
+        acc.mul_assign(&t);
+    }
+    acc
+}
+
+// the factors x_j r^(-n/2^(j+1)) of the folded w, over g^(a^n)
+fn w_factors<F: PrimeField>(challenges: &[F], r_inv: F) -> Vec<F> {
+    let n = 1u64 << challenges.len();
+    challenges
+        .iter()
+        .enumerate()
+        .map(|(j, x)| {
+            let mut f = r_inv.pow([n >> (j + 1)]);
+            f.mul_assign(x);
+            f
+        })
+        .collect()
+}
+
+// (f(X) - f(z)) / (X - z)
+fn quotient<F: PrimeField>(coefs: &[F], z: F) -> Vec<F> {
+    let mut q = vec![F::zero(); coefs.len() - 1];
+    let mut acc = F::zero();
+    for k in (1..coefs.len()).rev() {
+        acc.mul_assign(&z);
+        acc.add_assign(&coefs[k]);
+        q[k - 1] = acc;
+    }
+    q
+}
+
+fn inverses<F: PrimeField>(s: &[F]) -> Vec<F> {
+    s.iter().map(|s| s.inverse().unwrap()).collect()
+}
+
+fn g1_words<E: AggregateEngine>(p: &E::G1Affine) -> Vec<E::Fq> {
+    if p.is_zero() {
+        return vec![E::Fq::zero(); 2];
+    }
+    let (x, y) = p.into_xy_unchecked();
+    vec![x, y]
+}
+
+// x.c1, x.c0, y.c1, y.c0 as the pairing precompile of ethereum takes them
+fn g2_words<E: AggregateEngine>(p: &E::G2Affine) -> Vec<E::Fq> {
+    if p.is_zero() {
+        return vec![E::Fq::zero(); 4];
+    }
+    let (x, y) = p.into_xy_unchecked();
+    let (x, y) = (E::from_fqe(&x), E::from_fqe(&y));
+    vec![x.1, x.0, y.1, y.0]
+}
+
+fn commitment_words<E: AggregateEngine>(com_ab: &[E::Fqk; 2], com_c: &[E::Fqk; 2]) -> Vec<E::Fq> {
+    com_ab
+        .iter()
+        .chain(com_c.iter())
+        .flat_map(E::from_fqk)
+        .collect()
+}
+
+fn inner_product_words<E: AggregateEngine>(ip_ab: &E::Fqk, agg_c: &E::G1Affine) -> Vec<E::Fq> {
+    let mut words = E::from_fqk(ip_ab);
+    words.extend(g1_words::<E>(agg_c));
+    words
+}
+
+fn final_words<E: AggregateEngine>(
+    a: &E::G1Affine,
+    b: &E::G2Affine,
+    c: &E::G1Affine,
+    v: &[E::G2Affine; 2],
+    w: &[E::G1Affine; 2],
+) -> Vec<E::Fq> {
+    let mut words = vec![];
+    for p in [a, c, &w[0], &w[1]] {
+        words.extend(g1_words::<E>(p));
+    }
+    for p in [b, &v[0], &v[1]] {
+        words.extend(g2_words::<E>(p));
+    }
+    words
+}
+
+fn opening_words<E: AggregateEngine>(
+    v_opening: &[E::G2Affine; 2],
+    w_opening: &[E::G1Affine; 2],
+) -> Vec<E::Fq> {
+    let mut words = vec![];
+    for p in v_opening {
+        words.extend(g2_words::<E>(p));
+    }
+    for p in w_opening {
+        words.extend(g1_words::<E>(p));
+    }
+    words
+}
+
+impl<E: AggregateEngine> GipaRound<E> {
+    fn to_words(&self) -> Vec<E::Fq> {
+        let mut words: Vec<_> = [
+            &self.tab_l[0],
+            &self.tab_l[1],
+            &self.tab_r[0],
+            &self.tab_r[1],
+            &self.zab_l,
+            &self.zab_r,
+            &self.tc_l[0],
+            &self.tc_l[1],
+            &self.tc_r[0],
+            &self.tc_r[1],
+        ]
+        .into_iter()
+        .flat_map(E::from_fqk)
+        .collect();
+        words.extend(g1_words::<E>(&self.zc_l));
+        words.extend(g1_words::<E>(&self.zc_r));
+        words
+    }
+}
+
+impl<E: AggregateEngine> AggregateProof<E> {
+    /// The proof as the words of the transcript, in their order.
+    pub fn to_words(&self) -> Vec<E::Fq> {
+        let mut words = commitment_words::<E>(&self.com_ab, &self.com_c);
+        words.extend(inner_product_words::<E>(&self.ip_ab, &self.agg_c));
+        for round in &self.rounds {
+            words.extend(round.to_words());
+        }
+        words.extend(final_words::<E>(
+            &self.a, &self.b, &self.c, &self.v, &self.w,
+        ));
+        words.extend(opening_words::<E>(&self.v_opening, &self.w_opening));
+        words
+    }
+}
+
+/// Fiat-Shamir with keccak256, a challenge is the hash of the previous one and of the
+/// messages since, reduced mod r. The first one also hashes n and the public inputs.
+struct Transcript<E: Engine> {
+    buf: Vec<u8>,
+    _marker: PhantomData<E>,
+}
+
+impl<E: AggregateEngine> Transcript<E> {
+    fn new(n: usize, inputs: &[Vec<E::Fr>]) -> Self {
+        let mut buf = vec![0u8; 24];
+        buf.extend((n as u64).to_be_bytes());
+        for x in inputs.iter().flatten() {
+            x.into_repr().write_be(&mut buf).unwrap();
+        }
+        Self {
+            buf,
+            _marker: PhantomData,
+        }
+    }
+
+    fn append(&mut self, words: &[E::Fq]) {
+        for w in words {
+            w.into_repr().write_be(&mut self.buf).unwrap();
+        }
+    }
+
+    fn challenge(&mut self) -> Option<E::Fr> {
+        let mut hasher = Keccak::v256();
+        hasher.update(&self.buf);
+        let mut hash = [0u8; 32];
+        hasher.finalize(&mut hash);
+        let modulus = BigUint::from_str_radix(&repr_to_big(E::Fr::char()), 10).unwrap();
+        let c = BigUint::from_bytes_be(&hash) % modulus;
+        let c = E::Fr::from_str(&c.to_str_radix(10)).unwrap();
+        self.buf.clear();
+        c.into_repr().write_be(&mut self.buf).unwrap();
+        if c.is_zero() {
+            None
+        } else {
+            Some(c)
+        }
+    }
+}
+
+/// Aggregates the proofs of one verifying key, `proofs` are the public inputs and the proof of
+/// each statement.
+pub fn aggregate_proofs<E: AggregateEngine>(
+    key: &AggregationKey<E>,
+    proofs: &[(Vec<E::Fr>, Proof<E>)],
+) -> Result<AggregateProof<E>> {
+    if proofs.is_empty() {
+        bail!("no proof to aggregate");
+    }
+    let n = padded_len(proofs.len());
+    if n > key.max_proofs() {
+        bail!(
+            "the aggregation key is for {} proofs, not {}",
+            key.max_proofs(),
+            proofs.len()
+        );
+    }
+    let proofs = pad(proofs, n);
+    let inputs: Vec<_> = proofs.iter().map(|(x, _)| x.clone()).collect();
+    let mut a: Vec<_> = proofs.iter().map(|(_, p)| p.a).collect();
+    let mut b: Vec<_> = proofs.iter().map(|(_, p)| p.b).collect();
+    let mut c: Vec<_> = proofs.iter().map(|(_, p)| p.c).collect();
+    let mut v = [key.h_a[..n].to_vec(), key.h_b[..n].to_vec()];
+    let mut w = [key.g_a[n..2 * n].to_vec(), key.g_b[n..2 * n].to_vec()];
+    let zero_challenge = || anyhow!("zero challenge, aggregate again");
+
+    let com_ab = [
+        commit_ab::<E>(&a, &b, &v[0], &w[0]),
+        commit_ab::<E>(&a, &b, &v[1], &w[1]),
+    ];
+    let com_c = [
+        pairing_product::<E>(&c, &v[0]),
+        pairing_product::<E>(&c, &v[1]),
+    ];
+    let mut transcript = Transcript::<E>::new(n, &inputs);
+    transcript.append(&commitment_words::<E>(&com_ab, &com_c));
+    let r = transcript.challenge().ok_or_else(zero_challenge)?;
+
+    // B_i^(r^i) with the key w_i^(r^-i) has the same commitment
+    let r_inv = r.inverse().unwrap();
+    let mut r_powers = scalar_powers(r, n);
+    b = scale(&b, &r_powers);
+    let r_inv_powers = scalar_powers(r_inv, n);
+    for w in w.iter_mut() {
+        *w = scale(w, &r_inv_powers);
+    }
+    let ip_ab = pairing_product::<E>(&a, &b);
+    let agg_c = multiexp(&c, &r_powers).into_affine();
+    transcript.append(&inner_product_words::<E>(&ip_ab, &agg_c));
+
+    let mut rounds = vec![];
+    let mut challenges = vec![];
+    while a.len() > 1 {
+        let m = a.len() / 2;
+        let (a_l, a_r) = a.split_at(m);
+        let (b_l, b_r) = b.split_at(m);
+        let (c_l, c_r) = c.split_at(m);
+        let (r_l, r_r) = r_powers.split_at(m);
+        let round = GipaRound::<E> {
+            tab_l: [
+                commit_ab::<E>(a_r, b_l, &v[0][..m], &w[0][m..]),
+                commit_ab::<E>(a_r, b_l, &v[1][..m], &w[1][m..]),
+            ],
+            tab_r: [
+                commit_ab::<E>(a_l, b_r, &v[0][m..], &w[0][..m]),
+                commit_ab::<E>(a_l, b_r, &v[1][m..], &w[1][..m]),
+            ],
+            zab_l: pairing_product::<E>(a_r, b_l),
+            zab_r: pairing_product::<E>(a_l, b_r),
+            tc_l: [
+                pairing_product::<E>(c_r, &v[0][..m]),
+                pairing_product::<E>(c_r, &v[1][..m]),
+            ],
+            tc_r: [
+                pairing_product::<E>(c_l, &v[0][m..]),
+                pairing_product::<E>(c_l, &v[1][m..]),
+            ],
+            zc_l: multiexp(c_r, r_l).into_affine(),
+            zc_r: multiexp(c_l, r_r).into_affine(),
+        };
+        transcript.append(&round.to_words());
+        let x = transcript.challenge().ok_or_else(zero_challenge)?;
+        let x_inv = x.inverse().unwrap();
+
+        a = fold(a_l, a_r, x);
+        b = fold(b_l, b_r, x_inv);
+        c = fold(c_l, c_r, x);
+        r_powers = fold_scalars(r_l, r_r, x_inv);
+        for v in v.iter_mut() {
+            *v = fold(&v[..m], &v[m..], x_inv);
+        }
+        for w in w.iter_mut() {
+            *w = fold(&w[..m], &w[m..], x);
+        }
+        rounds.push(round);
+        challenges.push(x);
+    }
+    let v = [v[0][0], v[1][0]];
+    let w = [w[0][0], w[1][0]];
+    transcript.append(&final_words::<E>(&a[0], &b[0], &c[0], &v, &w));
+    let z = transcript.challenge().ok_or_else(zero_challenge)?;
+
+    // v is h^f_v(a), h^f_v(b) and w is g^g_w(a), g^g_w(b)
+    let f_v = folding_poly(&inverses(&challenges));
+    let g_w: Vec<_> = vec![E::Fr::zero(); n]
+        .into_iter()
+        .chain(folding_poly(&w_factors(&challenges, r_inv)))
+        .collect();
+    let q_v = quotient(&f_v, z);
+    let q_w = quotient(&g_w, z);
+    let v_opening = [
+        multiexp(&key.h_a[..q_v.len()], &q_v).into_affine(),
+        multiexp(&key.h_b[..q_v.len()], &q_v).into_affine(),
+    ];
+    let w_opening = [
+        multiexp(&key.g_a[..q_w.len()], &q_w).into_affine(),
+        multiexp(&key.g_b[..q_w.len()], &q_w).into_affine(),
+    ];
+
+    Ok(AggregateProof {
+        com_ab,
+        com_c,
+        ip_ab,
+        agg_c,
+        rounds,
+        a: a[0],
+        b: b[0],
+        c: c[0],
+        v,
+        w,
+        v_opening,
+        w_opening,
+    })
+}
+
+/// Verifies the aggregate proof of the statements of `public_inputs`.
+pub fn verify_aggregate_proof<E: AggregateEngine>(
+    vk: &VerifyingKey<E>,
+    key: &AggregationVerifyingKey<E>,
+    public_inputs: &[Vec<E::Fr>],
+    proof: &AggregateProof<E>,
+) -> bool {
+    if public_inputs.is_empty()
+        || public_inputs
+            .iter()
+            .any(|inputs| inputs.len() + 1 != vk.ic.len())
+    {
+        return false;
+    }
+    let n = padded_len(public_inputs.len());
+    if proof.rounds.len() != n.trailing_zeros() as usize {
+        return false;
+    }
+    let inputs = pad(public_inputs, n);
+    check_aggregate_proof(vk, key, &inputs, proof).unwrap_or(false)
+}
+
+fn check_aggregate_proof<E: AggregateEngine>(
+    vk: &VerifyingKey<E>,
+    key: &AggregationVerifyingKey<E>,
+    inputs: &[Vec<E::Fr>],
+    proof: &AggregateProof<E>,
+) -> Option<bool> {
+    let n = inputs.len();
+    let mut transcript = Transcript::<E>::new(n, inputs);
+    transcript.append(&commitment_words::<E>(&proof.com_ab, &proof.com_c));
+    let r = transcript.challenge()?;
+    transcript.append(&inner_product_words::<E>(&proof.ip_ab, &proof.agg_c));
+    let mut challenges = vec![];
+    for round in &proof.rounds {
+        transcript.append(&round.to_words());
+        challenges.push(transcript.challenge()?);
+    }
+    transcript.append(&final_words::<E>(
+        &proof.a, &proof.b, &proof.c, &proof.v, &proof.w,
+    ));
+    let z = transcript.challenge()?;
+    transcript.append(&opening_words::<E>(&proof.v_opening, &proof.w_opening));
+    let rho = transcript.challenge()?;
+    let rho_powers = scalar_powers(rho, 7);
+    let challenges_inv = inverses(&challenges);
+    let r_inv = r.inverse().unwrap();
+
+    // the C folded with the r^i folded is sum r^i C_i folded
+    let mut zc = proof.agg_c.into_projective();
+    for (round, (x, x_inv)) in proof
+        .rounds
+        .iter()
+        .zip(challenges.iter().zip(challenges_inv.iter()))
+    {
+        zc.add_assign(&round.zc_l.mul(*x));
+        zc.add_assign(&round.zc_r.mul(*x_inv));
+    }
+    let r_final = folding_eval(&challenges_inv, r);
+    if zc.into_affine() != proof.c.mul(r_final).into_affine() {
+        return Some(false);
+    }
+
+    // the KZG openings of v at a and b, e(g, v - f_v(z) h) = e(g^a / g^z, pi), and of w,
+    // e(w - g_w(z) g, h) = e(pi, h^a / h^z), together with the powers of rho
+    let f_v_z = folding_eval(&challenges_inv, z);
+    let mut g_w_z = folding_eval(&w_factors(&challenges, r_inv), z);
+    g_w_z.mul_assign(&z.pow([n as u64]));
+    let mut g_h = {
+        let mut s = rho;
+        s.add_assign(&E::Fr::one());
+        s.mul_assign(&f_v_z);
+        s.negate();
+        key.g.mul(s)
+    };
+    for (i, (w, pi)) in proof.w.iter().zip(proof.w_opening.iter()).enumerate() {
+        let mut t = w.into_projective();
+        t.sub_assign(&key.g.mul(g_w_z));
+        t.add_assign(&pi.mul(z));
+        t.mul_assign(rho_powers[i + 2]);
+        g_h.add_assign(&t);
+    }
+    let g_z_minus = |g_s: &E::G1Affine, s: E::Fr| {
+        let mut p = key.g.mul(z);
+        p.sub_assign(&g_s.into_projective());
+        p.mul_assign(s);
+        p.into_affine()
+    };
+    let neg = |p: &E::G1Affine, s: E::Fr| {
+        let mut p = p.mul(s);
+        p.negate();
+        p.into_affine()
+    };
+    let openings = pairing_product::<E>(
+        &[
+            key.g,
+            key.g.mul(rho).into_affine(),
+            g_h.into_affine(),
+            g_z_minus(&key.g_a, E::Fr::one()),
+            g_z_minus(&key.g_b, rho),
+            neg(&proof.w_opening[0], rho_powers[2]),
+            neg(&proof.w_opening[1], rho_powers[3]),
+        ],
+        &[
+            proof.v[0],
+            proof.v[1],
+            key.h,
+            proof.v_opening[0],
+            proof.v_opening[1],
+            key.h_a,
+            key.h_b,
+        ],
+    );
+    if openings != E::Fqk::one() {
+        return Some(false);
+    }
+
+    // T, U, Z, T_c, U_c folded with the messages of the rounds against the pairings of the
+    // last round, and the groth16 equation of ip_ab, to the powers rho^1 ... rho^6
+    let times = |s: &E::Fr, x: &E::Fr| {
+        let mut s = *s;
+        s.mul_assign(x);
+        s
+    };
+    let mut ip_ab_exp = rho_powers[3];
+    ip_ab_exp.add_assign(&rho_powers[6]);
+    let mut terms = vec![
+        (proof.com_ab[0], rho_powers[1]),
+        (proof.com_ab[1], rho_powers[2]),
+        (proof.ip_ab, ip_ab_exp),
+        (proof.com_c[0], rho_powers[4]),
+        (proof.com_c[1], rho_powers[5]),
+    ];
+    for (round, (x, x_inv)) in proof
+        .rounds
+        .iter()
+        .zip(challenges.iter().zip(challenges_inv.iter()))
+    {
+        terms.extend([
+            (round.tab_l[0], times(&rho_powers[1], x)),
+            (round.tab_l[1], times(&rho_powers[2], x)),
+            (round.tab_r[0], times(&rho_powers[1], x_inv)),
+            (round.tab_r[1], times(&rho_powers[2], x_inv)),
+            (round.zab_l, times(&rho_powers[3], x)),
+            (round.zab_r, times(&rho_powers[3], x_inv)),
+            (round.tc_l[0], times(&rho_powers[4], x)),
+            (round.tc_l[1], times(&rho_powers[5], x)),
+            (round.tc_r[0], times(&rho_powers[4], x_inv)),
+            (round.tc_r[1], times(&rho_powers[5], x_inv)),
+        ]);
+    }
+    let folded =
+        terms
+            .par_iter()
+            .map(|(f, e)| f.pow(e.into_repr()))
+            .reduce(E::Fqk::one, |mut a, b| {
+                a.mul_assign(&b);
+                a
+            });
+
+    // sum r^i IC(x_i) = sum_j (sum_i r^i x_ij) IC_j, with x_i0 = 1
+    let r_powers = scalar_powers(r, n);
+    let mut ic_coefs = vec![E::Fr::zero(); vk.ic.len()];
+    for (x, r) in inputs.iter().zip(r_powers.iter()) {
+        for (coef, x) in ic_coefs
+            .iter_mut()
+            .zip(std::iter::once(&E::Fr::one()).chain(x))
+        {
+            coef.add_assign(&times(x, r));
+        }
+    }
+    let combination =
+        |points: &[E::G1Affine], scalars: &[E::Fr]| multiexp(points, scalars).into_affine();
+    let pairings = pairing_product::<E>(
+        &[
+            combination(&[proof.a, proof.c], &[rho_powers[1], rho_powers[4]]),
+            combination(&[proof.a, proof.c], &[rho_powers[2], rho_powers[5]]),
+            combination(
+                &[proof.a, proof.w[0], proof.w[1]],
+                &[rho_powers[3], rho_powers[1], rho_powers[2]],
+            ),
+            vk.alpha_g1
+                .mul(times(&ic_coefs[0], &rho_powers[6]))
+                .into_affine(),
+            combination(&vk.ic, &ic_coefs)
+                .mul(rho_powers[6])
+                .into_affine(),
+            proof.agg_c.mul(rho_powers[6]).into_affine(),
+        ],
+        &[
+            proof.v[0],
+            proof.v[1],
+            proof.b,
+            vk.beta_g2,
+            vk.gamma_g2,
+            vk.delta_g2,
+        ],
+    );
+    Some(folded == pairings)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AggregationKeyFile {
+    pub protocol: String,
+    pub curve: String,
+    pub g_a: Vec<G1>,
+    pub g_b: Vec<G1>,
+    pub h_a: Vec<G2>,
+    pub h_b: Vec<G2>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GipaRoundFile {
+    pub tab_l: [Vec<String>; 2],
+    pub tab_r: [Vec<String>; 2],
+    pub zab_l: Vec<String>,
+    pub zab_r: Vec<String>,
+    pub tc_l: [Vec<String>; 2],
+    pub tc_r: [Vec<String>; 2],
+    pub zc_l: G1,
+    pub zc_r: G1,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AggregateProofFile {
+    pub protocol: String,
+    pub curve: String,
+    pub com_ab: [Vec<String>; 2],
+    pub com_c: [Vec<String>; 2],
+    pub ip_ab: Vec<String>,
+    pub agg_c: G1,
+    pub rounds: Vec<GipaRoundFile>,
+    pub a: G1,
+    pub b: G2,
+    pub c: G1,
+    pub v: [G2; 2],
+    pub w: [G1; 2],
+    pub v_opening: [G2; 2],
+    pub w_opening: [G1; 2],
+}
+
+fn gt_json<E: AggregateEngine>(f: &E::Fqk) -> Vec<String> {
+    E::from_fqk(f)
+        .iter()
+        .map(|c| render_scalar_to_str(c, false))
+        .collect()
+}
+
+fn to_fq<E: AggregateEngine>(s: &str) -> Result<E::Fq> {
+    E::Fq::from_str(s).ok_or_else(|| anyhow!("invalid field element: {}", s))
+}
+
+// The points are (de)serialized by `Parser`, as the single proofs. It writes the point at
+// infinity with the coordinates it is kept with, x = 0 and y = 1, which are not on the curves.
+// The points of a proof are checked to be in the group.
+fn to_g1<E: AggregateEngine>(p: &G1, checked: bool) -> Result<E::G1Affine> {
+    // Parser panics on an invalid number
+    let (x, y) = (to_fq::<E>(&p.x)?, to_fq::<E>(&p.y)?);
+    if x.is_zero() && (y.is_zero() || y == E::Fq::one()) {
+        return Ok(E::G1Affine::zero());
+    }
+    let point = E::to_g1(&p.x, &p.y);
+    if checked {
+        Ok(E::G1Affine::from_xy_checked(x, y)?)
+    } else {
+        Ok(point)
+    }
+}
+
+fn to_g2<E: AggregateEngine>(p: &G2, checked: bool) -> Result<E::G2Affine> {
+    let c =
+        p.x.iter()
+            .chain(p.y.iter())
+            .map(|s| to_fq::<E>(s))
+            .collect::<Result<Vec<_>>>()?;
+    if c[0].is_zero()
+        && c[1].is_zero()
+        && c[3].is_zero()
+        && (c[2].is_zero() || c[2] == E::Fq::one())
+    {
+        return Ok(E::G2Affine::zero());
+    }
+    let point = E::to_g2(&p.x[0], &p.x[1], &p.y[0], &p.y[1]);
+    if checked {
+        let (x, y) = point.into_xy_unchecked();
+        Ok(E::G2Affine::from_xy_checked(x, y)?)
+    } else {
+        Ok(point)
+    }
+}
+
+fn to_gt<E: AggregateEngine>(f: &[String]) -> Result<E::Fqk> {
+    if f.len() != 12 {
+        bail!("an element of GT has 12 coefficients, not {}", f.len());
+    }
+    let c = f
+        .iter()
+        .map(|s| to_fq::<E>(s))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(E::to_fqk(&c))
+}
+
+pub fn serialize_aggregation_key<E: AggregateEngine>(
+    key: &AggregationKey<E>,
+    curve_type: &str,
+) -> Result<String> {
+    let key_file = AggregationKeyFile {
+        protocol: PROTOCOL.to_string(),
+        curve: curve_type.to_string(),
+        g_a: key.g_a.iter().map(|p| E::parse_g1_json(p, false)).collect(),
+        g_b: key.g_b.iter().map(|p| E::parse_g1_json(p, false)).collect(),
+        h_a: key.h_a.iter().map(|p| E::parse_g2_json(p, false)).collect(),
+        h_b: key.h_b.iter().map(|p| E::parse_g2_json(p, false)).collect(),
+    };
+    Ok(serde_json::to_string(&key_file)?)
+}
+
+pub fn to_aggregation_key<E: AggregateEngine>(s: &str) -> Result<AggregationKey<E>> {
+    let key_file: AggregationKeyFile = serde_json::from_str(s)?;
+    let n = key_file.h_a.len();
+    if n < 2
+        || !n.is_power_of_two()
+        || key_file.h_b.len() != n
+        || key_file.g_a.len() != 2 * n
+        || key_file.g_b.len() != 2 * n
+    {
+        bail!("invalid aggregation key");
+    }
+    Ok(AggregationKey {
+        g_a: key_file
+            .g_a
+            .iter()
+            .map(|p| to_g1::<E>(p, false))
+            .collect::<Result<_>>()?,
+        g_b: key_file
+            .g_b
+            .iter()
+            .map(|p| to_g1::<E>(p, false))
+            .collect::<Result<_>>()?,
+        h_a: key_file
+            .h_a
+            .iter()
+            .map(|p| to_g2::<E>(p, false))
+            .collect::<Result<_>>()?,
+        h_b: key_file
+            .h_b
+            .iter()
+            .map(|p| to_g2::<E>(p, false))
+            .collect::<Result<_>>()?,
+    })
+}
+
+pub fn serialize_aggregate_proof<E: AggregateEngine>(
+    proof: &AggregateProof<E>,
+    curve_type: &str,
+) -> Result<String> {
+    let pair = |f: &[E::Fqk; 2]| [gt_json::<E>(&f[0]), gt_json::<E>(&f[1])];
+    let proof_file = AggregateProofFile {
+        protocol: PROTOCOL.to_string(),
+        curve: curve_type.to_string(),
+        com_ab: pair(&proof.com_ab),
+        com_c: pair(&proof.com_c),
+        ip_ab: gt_json::<E>(&proof.ip_ab),
+        agg_c: E::parse_g1_json(&proof.agg_c, false),
+        rounds: proof
+            .rounds
+            .iter()
+            .map(|round| GipaRoundFile {
+                tab_l: pair(&round.tab_l),
+                tab_r: pair(&round.tab_r),
+                zab_l: gt_json::<E>(&round.zab_l),
+                zab_r: gt_json::<E>(&round.zab_r),
+                tc_l: pair(&round.tc_l),
+                tc_r: pair(&round.tc_r),
+                zc_l: E::parse_g1_json(&round.zc_l, false),
+                zc_r: E::parse_g1_json(&round.zc_r, false),
+            })
+            .collect(),
+        a: E::parse_g1_json(&proof.a, false),
+        b: E::parse_g2_json(&proof.b, false),
+        c: E::parse_g1_json(&proof.c, false),
+        v: [
+            E::parse_g2_json(&proof.v[0], false),
+            E::parse_g2_json(&proof.v[1], false),
+        ],
+        w: [
+            E::parse_g1_json(&proof.w[0], false),
+            E::parse_g1_json(&proof.w[1], false),
+        ],
+        v_opening: [
+            E::parse_g2_json(&proof.v_opening[0], false),
+            E::parse_g2_json(&proof.v_opening[1], false),
+        ],
+        w_opening: [
+            E::parse_g1_json(&proof.w_opening[0], false),
+            E::parse_g1_json(&proof.w_opening[1], false),
+        ],
+    };
+    Ok(serde_json::to_string(&proof_file)?)
+}
+
+pub fn to_aggregate_proof<E: AggregateEngine>(s: &str) -> Result<AggregateProof<E>> {
+    let proof_file: AggregateProofFile = serde_json::from_str(s)?;
+    let pair = |f: &[Vec<String>; 2]| -> Result<[E::Fqk; 2]> {
+        Ok([to_gt::<E>(&f[0])?, to_gt::<E>(&f[1])?])
+    };
+    let g1 = |p: &G1| to_g1::<E>(p, true);
+    let g2 = |p: &G2| to_g2::<E>(p, true);
+    Ok(AggregateProof {
+        com_ab: pair(&proof_file.com_ab)?,
+        com_c: pair(&proof_file.com_c)?,
+        ip_ab: to_gt::<E>(&proof_file.ip_ab)?,
+        agg_c: g1(&proof_file.agg_c)?,
+        rounds: proof_file
+            .rounds
+            .iter()
+            .map(|round| {
+                Ok(GipaRound {
+                    tab_l: pair(&round.tab_l)?,
+                    tab_r: pair(&round.tab_r)?,
+                    zab_l: to_gt::<E>(&round.zab_l)?,
+                    zab_r: to_gt::<E>(&round.zab_r)?,
+                    tc_l: pair(&round.tc_l)?,
+                    tc_r: pair(&round.tc_r)?,
+                    zc_l: g1(&round.zc_l)?,
+                    zc_r: g1(&round.zc_r)?,
+                })
+            })
+            .collect::<Result<_>>()?,
+        a: g1(&proof_file.a)?,
+        b: g2(&proof_file.b)?,
+        c: g1(&proof_file.c)?,
+        v: [g2(&proof_file.v[0])?, g2(&proof_file.v[1])?],
+        w: [g1(&proof_file.w[0])?, g1(&proof_file.w[1])?],
+        v_opening: [g2(&proof_file.v_opening[0])?, g2(&proof_file.v_opening[1])?],
+        w_opening: [g1(&proof_file.w_opening[0])?, g1(&proof_file.w_opening[1])?],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{
+        create_circuit_add_witness, generate_aggregate_verifier, groth16_aggregate,
+        groth16_aggregate_setup, groth16_aggregate_verify, groth16_prove, groth16_setup,
+        WitnessGenerator,
+    };
+    use crate::calldata::tests::run_forge_test;
+    use crate::groth16::Groth16;
+    use crate::mpc::tests::write_ptau;
+    use algebraic::circom_circuit::CircomCircuit;
+    use algebraic::reader::load_r1cs;
+    use algebraic::witness::{load_input_for_witness, WitnessCalculator};
+    use rand_old::Rand;
+
+    const CIRCUIT_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/multiplier.r1cs");
+    const WASM_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/multiplier.wasm");
+    const INPUT_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/multiplier.input.json");
+    const CIRCUIT_FILE_BLS12: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/test-vectors/mycircuit_bls12381.r1cs"
+    );
+    const WASM_FILE_BLS12: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/test-vectors/mycircuit_bls12381.wasm"
+    );
+
+    /// The ptau of a and the ptau of b, of random secrets, for `max_proofs` proofs.
+    fn write_ptaus<E: AggregateEngine>(prefix: &str, max_proofs: usize) -> (String, String) {
+        let rng = &mut rand_old::thread_rng();
+        let power = (2 * padded_len(max_proofs)).trailing_zeros();
+        let mut ptau = |name: &str| {
+            let ptau_file = format!("{}_{}.ptau", prefix, name);
+            let (tau, alpha, beta) = (E::Fr::rand(rng), E::Fr::rand(rng), E::Fr::rand(rng));
+            write_ptau::<E>(&ptau_file, power, &tau, &alpha, &beta);
+            ptau_file
+        };
+        (ptau("a"), ptau("b"))
+    }
+
+    fn check_aggregate<E: AggregateEngine>(curve_type: &str, circuit_file: &str, wasm_file: &str) {
+        let mut rng = rand_old::thread_rng();
+        let circuit = CircomCircuit::<E> {
+            r1cs: load_r1cs(circuit_file),
+            witness: None,
+            wire_mapping: None,
+            aux_offset: 0,
+        };
+        let (pk, vk) = Groth16::circuit_specific_setup(circuit.clone(), &mut rng).unwrap();
+        let mut wtns = WitnessCalculator::from_file(wasm_file).unwrap();
+        let w = wtns
+            .calculate_witness(load_input_for_witness(INPUT_FILE), false)
+            .unwrap();
+        let circuit = create_circuit_add_witness(circuit, w);
+        let inputs = circuit.get_public_inputs().unwrap();
+        let mut proofs: Vec<_> = (0..3)
+            .map(|_| {
+                let proof = Groth16::prove(&pk, circuit.clone(), &mut rng).unwrap();
+                (inputs.clone(), proof)
+            })
+            .collect();
+        let prefix = format!("/tmp/aggregate_{}", curve_type);
+        let (ptau_a, ptau_b) = write_ptaus::<E>(&prefix, 4);
+        let key = AggregationKey::<E>::from_ptau(4, &ptau_a, &ptau_b).unwrap();
+        assert!(AggregationKey::<E>::from_ptau(4, &ptau_a, &ptau_a).is_err());
+        assert!(AggregationKey::<E>::from_ptau(8, &ptau_a, &ptau_b).is_err());
+        let public_inputs: Vec<_> = proofs.iter().map(|(x, _)| x.clone()).collect();
+
+        // 3 proofs are padded to 4, 2 rounds
+        let proof = aggregate_proofs(&key, &proofs).unwrap();
+        assert_eq!(proof.rounds.len(), 2);
+        assert_eq!(proof.to_words().len(), 94 + 124 * 2);
+        assert!(verify_aggregate_proof(
+            &vk,
+            &key.verifying_key(),
+            &public_inputs,
+            &proof
+        ));
+        let json = serialize_aggregate_proof(&proof, "").unwrap();
+        let proof = to_aggregate_proof::<E>(&json).unwrap();
+        assert!(verify_aggregate_proof(
+            &vk,
+            &key.verifying_key(),
+            &public_inputs,
+            &proof
+        ));
+
+        // the points are encoded as in the single proofs, the point at infinity included
+        let json: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let b = E::parse_g2_json(&proof.b, false);
+        assert_eq!(json["b"]["x"][0], b.x[0]);
+        assert_eq!(json["b"]["y"][1], b.y[1]);
+        let zero = E::parse_g1_json(&E::G1Affine::zero(), false);
+        assert!(to_g1::<E>(&zero, true).unwrap().is_zero());
+        let zero = E::parse_g2_json(&E::G2Affine::zero(), false);
+        assert!(to_g2::<E>(&zero, true).unwrap().is_zero());
+        assert!(to_g1::<E>(
+            &G1 {
+                x: "1".to_string(),
+                y: "x".to_string()
+            },
+            true
+        )
+        .is_err());
+
+        // a wrong public input, a missing statement, another key
+        let mut wrong_inputs = public_inputs.clone();
+        wrong_inputs[2][0].add_assign(&E::Fr::one());
+        assert!(!verify_aggregate_proof(
+            &vk,
+            &key.verifying_key(),
+            &wrong_inputs,
+            &proof
+        ));
+        assert!(!verify_aggregate_proof(
+            &vk,
+            &key.verifying_key(),
+            &public_inputs[..2],
+            &proof
+        ));
+        let (ptau_c, _) = write_ptaus::<E>(&format!("{}_other", prefix), 4);
+        let other_key = AggregationKey::<E>::from_ptau(4, &ptau_c, &ptau_b).unwrap();
+        assert!(!verify_aggregate_proof(
+            &vk,
+            &other_key.verifying_key(),
+            &public_inputs,
+            &proof
+        ));
+
+        // the aggregate of two valid proofs with their C swapped
+        let c = proofs[0].1.c;
+        proofs[0].1.c = proofs[1].1.c;
+        proofs[1].1.c = c;
+        let proof = aggregate_proofs(&key, &proofs).unwrap();
+        assert!(!verify_aggregate_proof(
+            &vk,
+            &key.verifying_key(),
+            &public_inputs,
+            &proof
+        ));
+
+        assert!(aggregate_proofs(&key, &proofs[..0]).is_err());
+        let many: Vec<_> = (0..5).map(|_| proofs[2].clone()).collect();
+        assert!(aggregate_proofs(&key, &many).is_err());
+    }
+
+    #[test]
+    fn test_aggregate_bn128() {
+        check_aggregate::<Bn256>("BN128", CIRCUIT_FILE, WASM_FILE);
+    }
+
+    #[test]
+    fn test_aggregate_bls12381() {
+        check_aggregate::<Bls12>("BLS12381", CIRCUIT_FILE_BLS12, WASM_FILE_BLS12);
+    }
+
+    /// The public inputs and proofs of `count` proofs of the multiplier on BN128.
+    fn prove_files(prefix: &str, pk_file: &str, count: usize) -> (Vec<String>, Vec<String>) {
+        let mut public_input_files = vec![];
+        let mut proof_files = vec![];
+        for i in 0..count {
+            let public_input_file = format!("{}.public{}.json", prefix, i);
+            let proof_file = format!("{}.proof{}.json", prefix, i);
+            groth16_prove(
+                "BN128",
+                CIRCUIT_FILE,
+                WASM_FILE,
+                WitnessGenerator::Wasm,
+                pk_file,
+                INPUT_FILE,
+                &public_input_file,
+                &proof_file,
+                false,
+            )
+            .unwrap();
+            public_input_files.push(public_input_file);
+            proof_files.push(proof_file);
+        }
+        (public_input_files, proof_files)
+    }
+
+    #[test]
+    fn test_aggregate_files() {
+        let pk_file = "/tmp/aggregate.pk";
+        let vk_file = "/tmp/aggregate.vk.json";
+        let key_file = "/tmp/aggregate.key.json";
+        let aggregate_proof_file = "/tmp/aggregate.proof.json";
+        let (ptau_a, ptau_b) = write_ptaus::<Bn256>("/tmp/aggregate", 2);
+        groth16_setup("BN128", CIRCUIT_FILE, pk_file, vk_file, false).unwrap();
+        assert!(groth16_aggregate_setup("BN128", 2, &ptau_b, &ptau_b, key_file).is_err());
+        groth16_aggregate_setup("BN128", 2, &ptau_a, &ptau_b, key_file).unwrap();
+        let (public_input_files, proof_files) = prove_files("/tmp/aggregate", pk_file, 2);
+        groth16_aggregate(
+            "BN128",
+            key_file,
+            &public_input_files,
+            &proof_files,
+            aggregate_proof_file,
+        )
+        .unwrap();
+        groth16_aggregate_verify(
+            "BN128",
+            key_file,
+            vk_file,
+            &public_input_files,
+            aggregate_proof_file,
+        )
+        .unwrap();
+        let sol_file = "/tmp/aggregate_verifier.sol";
+        generate_aggregate_verifier(key_file, vk_file, sol_file).unwrap();
+        let sol = std::fs::read_to_string(sol_file).unwrap();
+        assert!(sol.contains("contract AggregateVerifier") && !sol.contains("<%"));
+        let key: AggregationKeyFile =
+            serde_json::from_str(&std::fs::read_to_string(key_file).unwrap()).unwrap();
+        assert!(sol.contains(&format!("ak.h_b = Pairing.G2Point({});", key.h_b[1])));
+
+        std::fs::write(&public_input_files[1], "[\"1\"]").unwrap();
+        assert!(groth16_aggregate_verify(
+            "BN128",
+            key_file,
+            vk_file,
+            &public_input_files,
+            aggregate_proof_file,
+        )
+        .is_err());
+        assert!(groth16_aggregate(
+            "BN128",
+            key_file,
+            &public_input_files[..1],
+            &proof_files,
+            aggregate_proof_file,
+        )
+        .is_err());
+    }
+
+    // The contract of generate_aggregate_verifier accepts the aggregate of 3 proofs, padded to
+    // 4, with the words of to_words, and rejects it with a wrong public input.
+    #[test]
+    #[ignore = "needs forge of Foundry"]
+    fn test_aggregate_verifier_contract() {
+        let pk_file = "/tmp/aggregate_contract.pk";
+        let vk_file = "/tmp/aggregate_contract.vk.json";
+        let key_file = "/tmp/aggregate_contract.key.json";
+        let aggregate_proof_file = "/tmp/aggregate_contract.proof.json";
+        let sol_file = "/tmp/aggregate_contract.sol";
+        let (ptau_a, ptau_b) = write_ptaus::<Bn256>("/tmp/aggregate_contract", 4);
+        groth16_setup("BN128", CIRCUIT_FILE, pk_file, vk_file, false).unwrap();
+        groth16_aggregate_setup("BN128", 4, &ptau_a, &ptau_b, key_file).unwrap();
+        let (public_input_files, proof_files) = prove_files("/tmp/aggregate_contract", pk_file, 3);
+        groth16_aggregate(
+            "BN128",
+            key_file,
+            &public_input_files,
+            &proof_files,
+            aggregate_proof_file,
+        )
+        .unwrap();
+        generate_aggregate_verifier(key_file, vk_file, sol_file).unwrap();
+
+        let proof =
+            to_aggregate_proof::<Bn256>(&std::fs::read_to_string(aggregate_proof_file).unwrap())
+                .unwrap();
+        let words: Vec<String> = proof
+            .to_words()
+            .iter()
+            .map(|w| render_scalar_to_str(w, false))
+            .collect();
+        let mut inputs: Vec<String> = vec![];
+        for file in &public_input_files {
+            let input: Vec<String> =
+                serde_json::from_str(&std::fs::read_to_string(file).unwrap()).unwrap();
+            inputs.extend(input);
+        }
+        let assign = |name: &str, values: &[String]| -> String {
+            let mut sol = format!(
+                "        uint256[] memory {} = new uint256[]({});\n",
+                name,
+                values.len()
+            );
+            for (i, v) in values.iter().enumerate() {
+                sol.push_str(&format!("        {}[{}] = {};\n", name, i, v));
+            }
+            sol
+        };
+        let test = format!(
+            r#"// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.16;
+
+import "../src/AggregateVerifier.sol";
+
+contract AggregateVerifierTest {{
+    AggregateVerifier verifier = new AggregateVerifier();
+
+    function testVerifyAggregate() public view {{
+{}{}        require(verifier.verifyAggregate(input, proof), "valid proof rejected");
+        input[0] = input[0] + 1;
+        require(!verifier.verifyAggregate(input, proof), "wrong input accepted");
+    }}
+}}
+"#,
+            assign("input", &inputs),
+            assign("proof", &words)
+        );
+        run_forge_test(
+            "aggregate_verifier",
+            &[
+                (
+                    "src/AggregateVerifier.sol",
+                    std::fs::read_to_string(sol_file).unwrap(),
+                ),
+                ("test/AggregateVerifier.t.sol", test),
+            ],
+        );
+    }
+}
@@ -6,16 +6,38 @@ use crate::bellman_ce::{
         bn256::{Bn256, Fr},
     },
 };
-use crate::{groth16::Groth16, json_utils::*, template::CONTRACT_TEMPLATE};
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+use crate::{
+    aggregation::{self, AggregateEngine, AggregationKey, AggregationKeyFile},
+    batch, mpc,
+    template::{AGGREGATE_CONTRACT_TEMPLATE, RUST_VERIFIER_TEMPLATE},
+    zkey::{is_zkey, ZKey, ZKeyEngine},
+};
+use crate::{
+    calldata::{Abi, Calldata},
+    groth16::Groth16,
+    json_utils::*,
+    template::{
+        ASSEMBLY_CONTRACT_TEMPLATE, CONTRACT_TEMPLATE, WASM_CARGO_TEMPLATE, WASM_LIB_TEMPLATE,
+        WASM_PROOF_JSON,
+    },
+};
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+pub use algebraic::witness::WitnessGenerator;
 #[cfg(not(any(feature = "cuda", feature = "opencl")))]
 use algebraic::{
     bellman_ce::Engine,
-    circom_circuit::CircomCircuit,
-    reader::load_r1cs,
-    witness::{load_input_for_witness, WitnessCalculator},
+    circom_circuit::{CircomCircuit, R1CS},
+    reader::{load_r1cs, load_witness_from_bin_reader},
+    witness::{
+        calculate_witness_from_file, load_input_for_witness, NativeWitnessCalculator,
+        WitnessCalculator,
+    },
     Field, PrimeField,
 };
 #[cfg(any(feature = "cuda", feature = "opencl"))]
+pub use algebraic_gpu::witness::WitnessGenerator;
+#[cfg(any(feature = "cuda", feature = "opencl"))]
 use algebraic_gpu::{
     circom_circuit::CircomCircuit,
     reader::load_r1cs,
@@ -29,7 +51,8 @@ use bellperson::{gpu, groth16::*};
 use blstrs::{Bls12, Scalar};
 #[cfg(any(feature = "cuda", feature = "opencl"))]
 use group::WnafGroup;
-use num_traits::Zero;
+use num_bigint::BigUint;
+use num_traits::{Num, Zero};
 #[cfg(any(feature = "cuda", feature = "opencl"))]
 use pairing::{Engine, MultiMillerLoop};
 #[cfg(any(feature = "cuda", feature = "opencl"))]
@@ -149,20 +172,17 @@ pub fn groth16_prove(
     curve_type: &str,
     circuit_file: &str,
     wtns_file: &str,
+    generator: WitnessGenerator,
     pk_file: &str,
     input_file: &str,
     public_input_file: &str,
     proof_file: &str,
     to_hex: bool,
 ) -> Result<()> {
-    let mut rng = rand::thread_rng();
-
-    let mut wtns = WitnessCalculator::from_file(wtns_file)?;
     let inputs = load_input_for_witness(input_file);
-    let w = wtns.calculate_witness(inputs, false)?;
+    let w = calculate_witness_from_file(wtns_file, generator, inputs, false)?;
     match curve_type {
         "BN128" => {
-            let pk: Parameters<Bn256> = read_pk_from_file(pk_file, false)?;
             let w = w
                 .iter()
                 .map(|wi| {
@@ -174,14 +194,13 @@ pub fn groth16_prove(
                 })
                 .collect::<Vec<_>>();
             let circuit = create_circuit_from_file::<Bn256>(circuit_file, Some(w));
-            let proof = Groth16::prove(&pk, circuit.clone(), &mut rng)?;
+            let proof = prove_with_pk_file(pk_file, circuit.clone())?;
             let proof_json = serialize_proof(&proof, curve_type, to_hex)?;
             std::fs::write(proof_file, proof_json)?;
             let input_json = circuit.get_public_inputs_json();
             std::fs::write(public_input_file, input_json)?;
         }
         "BLS12381" => {
-            let pk: Parameters<Bls12> = read_pk_from_file(pk_file, false)?;
             let w = w
                 .iter()
                 .map(|wi| {
@@ -193,7 +212,7 @@ pub fn groth16_prove(
                 })
                 .collect::<Vec<_>>();
             let circuit = create_circuit_from_file::<Bls12>(circuit_file, Some(w));
-            let proof = Groth16::prove(&pk, circuit.clone(), &mut rng)?;
+            let proof = prove_with_pk_file(pk_file, circuit.clone())?;
             let proof_json = serialize_proof(&proof, curve_type, to_hex)?;
             std::fs::write(proof_file, proof_json)?;
             let input_json = circuit.get_public_inputs_json();
@@ -206,6 +225,325 @@ pub fn groth16_prove(
     Ok(())
 }
 
+/// Prove with a witness computed elsewhere, e.g. by `calculate_witness` or circom's C++ witness
+/// generator, instead of running the wasm. `witness_file` is in circom's wtns format.
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+pub fn groth16_prove_with_witness(
+    curve_type: &str,
+    circuit_file: &str,
+    witness_file: &str,
+    pk_file: &str,
+    public_input_file: &str,
+    proof_file: &str,
+    to_hex: bool,
+) -> Result<()> {
+    match curve_type {
+        "BN128" => prove_with_witness::<Bn256>(
+            curve_type,
+            circuit_file,
+            witness_file,
+            pk_file,
+            public_input_file,
+            proof_file,
+            to_hex,
+        ),
+        "BLS12381" => prove_with_witness::<Bls12>(
+            curve_type,
+            circuit_file,
+            witness_file,
+            pk_file,
+            public_input_file,
+            proof_file,
+            to_hex,
+        ),
+        _ => {
+            bail!(format!("Unknown curve type: {}", curve_type))
+        }
+    }
+}
+
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+fn prove_with_witness<E: ZKeyEngine>(
+    curve_type: &str,
+    circuit_file: &str,
+    witness_file: &str,
+    pk_file: &str,
+    public_input_file: &str,
+    proof_file: &str,
+    to_hex: bool,
+) -> Result<()> {
+    let file =
+        std::fs::File::open(witness_file).map_err(|e| anyhow!("Open {}, {:?}", witness_file, e))?;
+    let w = load_witness_from_bin_reader::<E, _>(std::io::BufReader::new(file))?;
+    let mut circuit = create_circuit_from_file::<E>(circuit_file, None);
+    check_witness(&circuit.r1cs, &w)?;
+    circuit.witness = Some(w);
+    let proof = prove_with_pk_file(pk_file, circuit.clone())?;
+    let proof_json = serialize_proof(&proof, curve_type, to_hex)?;
+    std::fs::write(proof_file, proof_json)?;
+    let input_json = circuit.get_public_inputs_json();
+    std::fs::write(public_input_file, input_json)?;
+    Ok(())
+}
+
+/// A witness read from a file must have one value per wire of the R1CS, the first being 1.
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+fn check_witness<E: Engine>(r1cs: &R1CS<E>, witness: &[E::Fr]) -> Result<()> {
+    if witness.len() != r1cs.num_variables {
+        bail!(
+            "The witness has {} values, but the R1CS has {} wires",
+            witness.len(),
+            r1cs.num_variables
+        );
+    }
+    if witness.first() != Some(&E::Fr::one()) {
+        bail!("The first wire of the witness must be 1");
+    }
+    Ok(())
+}
+
+/// Calculate the witness of `input_file` by the `generator` of `wasm_file`, and save it to the
+/// wtns file `output`.
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+pub fn calculate_witness(
+    wasm_file: &str,
+    generator: WitnessGenerator,
+    input_file: &str,
+    output: &str,
+) -> Result<()> {
+    if generator == WitnessGenerator::Native {
+        return NativeWitnessCalculator::from_file(wasm_file)?
+            .calculate_witness_to_file(input_file, output);
+    }
+    let mut wtns = WitnessCalculator::from_file(wasm_file)?;
+    let inputs = load_input_for_witness(input_file);
+    let w = wtns.calculate_witness_bin(inputs, false)?;
+    wtns.save_witness_to_bin_file::<Bn256>(output, &w)
+}
+
+/// Check a snarkjs zkey against `circuit_file`, write it to `pk_file` as the proving key of
+/// groth16_prove, and export its verifying key.
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+pub fn groth16_import_zkey(
+    curve_type: &str,
+    zkey_file: &str,
+    circuit_file: &str,
+    pk_file: &str,
+    vk_file: &str,
+    to_hex: bool,
+) -> Result<()> {
+    match curve_type {
+        "BN128" => import_zkey::<Bn256>(
+            curve_type,
+            zkey_file,
+            circuit_file,
+            pk_file,
+            vk_file,
+            to_hex,
+        ),
+        "BLS12381" => import_zkey::<Bls12>(
+            curve_type,
+            zkey_file,
+            circuit_file,
+            pk_file,
+            vk_file,
+            to_hex,
+        ),
+        _ => {
+            bail!(format!("Unknown curve type: {}", curve_type))
+        }
+    }
+}
+
+#[cfg(any(feature = "cuda", feature = "opencl"))]
+pub fn groth16_import_zkey(
+    _curve_type: &str,
+    _zkey_file: &str,
+    _circuit_file: &str,
+    _pk_file: &str,
+    _vk_file: &str,
+    _to_hex: bool,
+) -> Result<()> {
+    bail!("zkey import is not supported by the GPU prover")
+}
+
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+fn import_zkey<E: ZKeyEngine>(
+    curve_type: &str,
+    zkey_file: &str,
+    circuit_file: &str,
+    pk_file: &str,
+    vk_file: &str,
+    to_hex: bool,
+) -> Result<()> {
+    let zkey = ZKey::<E>::read(zkey_file, true)?;
+    zkey.check_r1cs(&load_r1cs::<E>(circuit_file))?;
+    zkey.write(pk_file)?;
+    let vk_json = serialize_vk(&zkey.vk, curve_type, to_hex)?;
+    std::fs::write(vk_file, vk_json)?;
+    Ok(())
+}
+
+/// Start the phase 2 ceremony of `circuit_file` on the phase 1 transcript `ptau_file`, a ptau
+/// of snarkjs prepared for phase 2. The contributions are not compatible with snarkjs, see
+/// `mpc`.
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+pub fn groth16_zkey_new(
+    curve_type: &str,
+    circuit_file: &str,
+    ptau_file: &str,
+    zkey_file: &str,
+) -> Result<()> {
+    fn zkey_new<E: ZKeyEngine>(circuit_file: &str, ptau_file: &str, zkey_file: &str) -> Result<()> {
+        mpc::new_zkey(&load_r1cs::<E>(circuit_file), ptau_file)?.write(zkey_file)
+    }
+    match curve_type {
+        "BN128" => zkey_new::<Bn256>(circuit_file, ptau_file, zkey_file),
+        "BLS12381" => zkey_new::<Bls12>(circuit_file, ptau_file, zkey_file),
+        _ => {
+            bail!(format!("Unknown curve type: {}", curve_type))
+        }
+    }
+}
+
+/// Contribute to the ceremony with the system randomness and `entropy`.
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+pub fn groth16_contribute(
+    curve_type: &str,
+    zkey_file: &str,
+    zkey_out_file: &str,
+    name: &str,
+    entropy: &str,
+) -> Result<()> {
+    fn contribute<E: ZKeyEngine>(
+        zkey_file: &str,
+        zkey_out_file: &str,
+        name: &str,
+        entropy: &str,
+    ) -> Result<()> {
+        let mut zkey = ZKey::<E>::read(zkey_file, true)?;
+        let hash = mpc::contribute(&mut zkey, entropy.as_bytes(), name)?;
+        zkey.write(zkey_out_file)?;
+        println!("Contribution hash: {}", hex::encode(hash));
+        Ok(())
+    }
+    match curve_type {
+        "BN128" => contribute::<Bn256>(zkey_file, zkey_out_file, name, entropy),
+        "BLS12381" => contribute::<Bls12>(zkey_file, zkey_out_file, name, entropy),
+        _ => {
+            bail!(format!("Unknown curve type: {}", curve_type))
+        }
+    }
+}
+
+/// Close the ceremony with a contribution derived from the public `beacon_hash`, in hex,
+/// hashed 2^`iterations_exp` times.
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+pub fn groth16_beacon(
+    curve_type: &str,
+    zkey_file: &str,
+    zkey_out_file: &str,
+    name: &str,
+    beacon_hash: &str,
+    iterations_exp: u32,
+) -> Result<()> {
+    fn beacon<E: ZKeyEngine>(
+        zkey_file: &str,
+        zkey_out_file: &str,
+        name: &str,
+        beacon_hash: &[u8],
+        iterations_exp: u32,
+    ) -> Result<()> {
+        let mut zkey = ZKey::<E>::read(zkey_file, true)?;
+        let hash = mpc::beacon(&mut zkey, beacon_hash, iterations_exp, name)?;
+        zkey.write(zkey_out_file)?;
+        println!("Contribution hash: {}", hex::encode(hash));
+        Ok(())
+    }
+    let beacon_hash = hex::decode(beacon_hash.trim_start_matches("0x"))
+        .map_err(|e| anyhow!("Invalid beacon hash {}: {}", beacon_hash, e))?;
+    match curve_type {
+        "BN128" => beacon::<Bn256>(zkey_file, zkey_out_file, name, &beacon_hash, iterations_exp),
+        "BLS12381" => beacon::<Bls12>(zkey_file, zkey_out_file, name, &beacon_hash, iterations_exp),
+        _ => {
+            bail!(format!("Unknown curve type: {}", curve_type))
+        }
+    }
+}
+
+/// Check that `zkey_file` comes from `circuit_file` and `ptau_file` through its contributions.
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+pub fn groth16_verify_contributions(
+    curve_type: &str,
+    circuit_file: &str,
+    ptau_file: &str,
+    zkey_file: &str,
+) -> Result<()> {
+    fn verify<E: ZKeyEngine>(circuit_file: &str, ptau_file: &str, zkey_file: &str) -> Result<()> {
+        let zkey = ZKey::<E>::read(zkey_file, true)?;
+        let mpc = mpc::verify_contributions(&load_r1cs::<E>(circuit_file), ptau_file, &zkey)?;
+        for (i, c) in mpc.contributions.iter().enumerate() {
+            println!(
+                "contribution #{} {}: {}",
+                i + 1,
+                c.name,
+                hex::encode(c.hash()?)
+            );
+        }
+        Ok(())
+    }
+    match curve_type {
+        "BN128" => verify::<Bn256>(circuit_file, ptau_file, zkey_file),
+        "BLS12381" => verify::<Bls12>(circuit_file, ptau_file, zkey_file),
+        _ => {
+            bail!(format!("Unknown curve type: {}", curve_type))
+        }
+    }
+}
+
+#[cfg(any(feature = "cuda", feature = "opencl"))]
+pub fn groth16_zkey_new(
+    _curve_type: &str,
+    _circuit_file: &str,
+    _ptau_file: &str,
+    _zkey_file: &str,
+) -> Result<()> {
+    bail!("the ceremony is not supported by the GPU prover")
+}
+
+#[cfg(any(feature = "cuda", feature = "opencl"))]
+pub fn groth16_contribute(
+    _curve_type: &str,
+    _zkey_file: &str,
+    _zkey_out_file: &str,
+    _name: &str,
+    _entropy: &str,
+) -> Result<()> {
+    bail!("the ceremony is not supported by the GPU prover")
+}
+
+#[cfg(any(feature = "cuda", feature = "opencl"))]
+pub fn groth16_beacon(
+    _curve_type: &str,
+    _zkey_file: &str,
+    _zkey_out_file: &str,
+    _name: &str,
+    _beacon_hash: &str,
+    _iterations_exp: u32,
+) -> Result<()> {
+    bail!("the ceremony is not supported by the GPU prover")
+}
+
+#[cfg(any(feature = "cuda", feature = "opencl"))]
+pub fn groth16_verify_contributions(
+    _curve_type: &str,
+    _circuit_file: &str,
+    _ptau_file: &str,
+    _zkey_file: &str,
+) -> Result<()> {
+    bail!("the ceremony is not supported by the GPU prover")
+}
+
 #[cfg(not(any(feature = "cuda", feature = "opencl")))]
 #[allow(clippy::too_many_arguments)]
 pub fn groth16_prove_inplace<E: Engine + crate::json_utils::Parser>(
@@ -237,12 +575,16 @@ pub fn groth16_prove(
     curve_type: &str,
     circuit_file: &str,
     wtns_file: &str,
+    generator: WitnessGenerator,
     pk_file: &str,
     input_file: &str,
     public_input_file: &str,
     proof_file: &str,
     to_hex: bool,
 ) -> Result<()> {
+    if generator == WitnessGenerator::Native {
+        bail!("the native witness generator is not supported by the GPU prover");
+    }
     let mut rng = rand::thread_rng();
 
     let mut wtns = WitnessCalculator::from_file(wtns_file)?;
@@ -302,6 +644,35 @@ pub fn groth16_prove_inplace(
     Ok(())
 }
 
+#[cfg(any(feature = "cuda", feature = "opencl"))]
+pub fn groth16_prove_with_witness(
+    _curve_type: &str,
+    _circuit_file: &str,
+    _witness_file: &str,
+    _pk_file: &str,
+    _public_input_file: &str,
+    _proof_file: &str,
+    _to_hex: bool,
+) -> Result<()> {
+    bail!("proving from a witness file is not supported by the GPU prover")
+}
+
+#[cfg(any(feature = "cuda", feature = "opencl"))]
+pub fn calculate_witness(
+    wasm_file: &str,
+    generator: WitnessGenerator,
+    input_file: &str,
+    output: &str,
+) -> Result<()> {
+    if generator == WitnessGenerator::Native {
+        bail!("the native witness generator is not supported by the GPU prover");
+    }
+    let mut wtns = WitnessCalculator::from_file(wasm_file)?;
+    let inputs = load_input_for_witness(input_file);
+    let w = wtns.calculate_witness_bin(inputs, false)?;
+    wtns.save_witness_to_bin_file::<Scalar>(output, &w)
+}
+
 #[cfg(not(any(feature = "cuda", feature = "opencl")))]
 pub fn groth16_verify(
     curve_type: &str,
@@ -362,6 +733,222 @@ pub fn groth16_verify_inplace<E: Engine + crate::json_utils::Parser>(
     Ok(())
 }
 
+/// Verify the proofs of one verifying key together, `public_input_files[i]` is the public
+/// input of `proof_files[i]`. The error names the invalid proofs.
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+pub fn groth16_batch_verify(
+    curve_type: &str,
+    vk_file: &str,
+    public_input_files: &[String],
+    proof_files: &[String],
+) -> Result<()> {
+    if public_input_files.len() != proof_files.len() {
+        bail!(
+            "{} public inputs for {} proofs",
+            public_input_files.len(),
+            proof_files.len()
+        );
+    }
+    match curve_type {
+        "BN128" => batch_verify::<Bn256>(vk_file, public_input_files, proof_files),
+        "BLS12381" => batch_verify::<Bls12>(vk_file, public_input_files, proof_files),
+        _ => {
+            bail!(format!("Unknown curve type: {}", curve_type))
+        }
+    }
+}
+
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+fn batch_verify<E: Engine + crate::json_utils::Parser>(
+    vk_file: &str,
+    public_input_files: &[String],
+    proof_files: &[String],
+) -> Result<()> {
+    let vk = read_vk_from_file::<E>(vk_file)?;
+    let proofs = read_proofs_from_files::<E>(public_input_files, proof_files)?;
+    let invalid = batch::find_invalid_proofs(&vk, &proofs, &mut rand::thread_rng());
+    if !invalid.is_empty() {
+        let files: Vec<&str> = invalid.iter().map(|i| proof_files[*i].as_str()).collect();
+        bail!("verify failed: {}", files.join(", "));
+    }
+    Ok(())
+}
+
+#[cfg(any(feature = "cuda", feature = "opencl"))]
+pub fn groth16_batch_verify(
+    _curve_type: &str,
+    _vk_file: &str,
+    _public_input_files: &[String],
+    _proof_files: &[String],
+) -> Result<()> {
+    bail!("batch verification is not supported by the GPU prover")
+}
+
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+pub fn groth16_aggregate_setup(
+    curve_type: &str,
+    max_proofs: usize,
+    ptau_a: &str,
+    ptau_b: &str,
+    key_file: &str,
+) -> Result<()> {
+    match curve_type {
+        "BN128" => aggregate_setup::<Bn256>(curve_type, max_proofs, ptau_a, ptau_b, key_file),
+        "BLS12381" => aggregate_setup::<Bls12>(curve_type, max_proofs, ptau_a, ptau_b, key_file),
+        _ => {
+            bail!(format!("Unknown curve type: {}", curve_type))
+        }
+    }
+}
+
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+fn aggregate_setup<E: AggregateEngine>(
+    curve_type: &str,
+    max_proofs: usize,
+    ptau_a: &str,
+    ptau_b: &str,
+    key_file: &str,
+) -> Result<()> {
+    if max_proofs == 0 {
+        bail!("an aggregation key is for at least one proof");
+    }
+    let key = AggregationKey::<E>::from_ptau(max_proofs, ptau_a, ptau_b)?;
+    std::fs::write(
+        key_file,
+        aggregation::serialize_aggregation_key(&key, curve_type)?,
+    )?;
+    Ok(())
+}
+
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+pub fn groth16_aggregate(
+    curve_type: &str,
+    key_file: &str,
+    public_input_files: &[String],
+    proof_files: &[String],
+    aggregate_proof_file: &str,
+) -> Result<()> {
+    if public_input_files.len() != proof_files.len() {
+        bail!(
+            "{} public inputs for {} proofs",
+            public_input_files.len(),
+            proof_files.len()
+        );
+    }
+    match curve_type {
+        "BN128" => aggregate::<Bn256>(
+            curve_type,
+            key_file,
+            public_input_files,
+            proof_files,
+            aggregate_proof_file,
+        ),
+        "BLS12381" => aggregate::<Bls12>(
+            curve_type,
+            key_file,
+            public_input_files,
+            proof_files,
+            aggregate_proof_file,
+        ),
+        _ => {
+            bail!(format!("Unknown curve type: {}", curve_type))
+        }
+    }
+}
+
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+fn aggregate<E: AggregateEngine>(
+    curve_type: &str,
+    key_file: &str,
+    public_input_files: &[String],
+    proof_files: &[String],
+    aggregate_proof_file: &str,
+) -> Result<()> {
+    let key = read_aggregation_key_from_file::<E>(key_file)?;
+    let proofs = read_proofs_from_files::<E>(public_input_files, proof_files)?;
+    let proof = aggregation::aggregate_proofs(&key, &proofs)?;
+    std::fs::write(
+        aggregate_proof_file,
+        aggregation::serialize_aggregate_proof(&proof, curve_type)?,
+    )?;
+    Ok(())
+}
+
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+pub fn groth16_aggregate_verify(
+    curve_type: &str,
+    key_file: &str,
+    vk_file: &str,
+    public_input_files: &[String],
+    aggregate_proof_file: &str,
+) -> Result<()> {
+    match curve_type {
+        "BN128" => {
+            aggregate_verify::<Bn256>(key_file, vk_file, public_input_files, aggregate_proof_file)
+        }
+        "BLS12381" => {
+            aggregate_verify::<Bls12>(key_file, vk_file, public_input_files, aggregate_proof_file)
+        }
+        _ => {
+            bail!(format!("Unknown curve type: {}", curve_type))
+        }
+    }
+}
+
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+fn aggregate_verify<E: AggregateEngine>(
+    key_file: &str,
+    vk_file: &str,
+    public_input_files: &[String],
+    aggregate_proof_file: &str,
+) -> Result<()> {
+    let key = read_aggregation_key_from_file::<E>(key_file)?;
+    let vk = read_vk_from_file::<E>(vk_file)?;
+    let public_inputs = public_input_files
+        .iter()
+        .map(|input| read_public_input_from_file::<E::Fr>(input))
+        .collect::<Result<Vec<_>>>()?;
+    let json_data = std::fs::read_to_string(aggregate_proof_file)?;
+    let proof = aggregation::to_aggregate_proof::<E>(&json_data)?;
+    if !aggregation::verify_aggregate_proof(&vk, &key.verifying_key(), &public_inputs, &proof) {
+        bail!("verify failed");
+    }
+    Ok(())
+}
+
+#[cfg(any(feature = "cuda", feature = "opencl"))]
+pub fn groth16_aggregate_setup(
+    _curve_type: &str,
+    _max_proofs: usize,
+    _ptau_a: &str,
+    _ptau_b: &str,
+    _key_file: &str,
+) -> Result<()> {
+    bail!("proof aggregation is not supported by the GPU prover")
+}
+
+#[cfg(any(feature = "cuda", feature = "opencl"))]
+pub fn groth16_aggregate(
+    _curve_type: &str,
+    _key_file: &str,
+    _public_input_files: &[String],
+    _proof_files: &[String],
+    _aggregate_proof_file: &str,
+) -> Result<()> {
+    bail!("proof aggregation is not supported by the GPU prover")
+}
+
+#[cfg(any(feature = "cuda", feature = "opencl"))]
+pub fn groth16_aggregate_verify(
+    _curve_type: &str,
+    _key_file: &str,
+    _vk_file: &str,
+    _public_input_files: &[String],
+    _aggregate_proof_file: &str,
+) -> Result<()> {
+    bail!("proof aggregation is not supported by the GPU prover")
+}
+
 #[cfg(any(feature = "cuda", feature = "opencl"))]
 pub fn groth16_verify(
     curve_type: &str,
@@ -502,6 +1089,324 @@ pub fn generate_verifier(vk_file_path: &str, sol_file_path: &str) -> Result<()>
     Ok(())
 }
 
+/// Generate the gas optimized verifier of `ASSEMBLY_CONTRACT_TEMPLATE`, whose
+/// `verifyProof(uint[2] a, uint[2][2] b, uint[2] c, uint[n] input)` is the one of snarkjs.
+pub fn generate_assembly_verifier(vk_file_path: &str, sol_file_path: &str) -> Result<()> {
+    let json_data = std::fs::read_to_string(vk_file_path)?;
+    let vk_file: VerifyingKeyFile = serde_json::from_str(&json_data)?;
+    if vk_file.curve != "BN128" {
+        bail!(
+            "the Solidity verifier is only for BN128, not {}",
+            vk_file.curve
+        );
+    }
+    if vk_file.ic.is_empty() {
+        bail!("the verification key has no IC");
+    }
+    let input_count = vk_file.ic.len() - 1;
+
+    let mut vk_constants = vec![
+        ("alphax".to_string(), &vk_file.alpha_g1.x),
+        ("alphay".to_string(), &vk_file.alpha_g1.y),
+    ];
+    // the precompile takes c1 before c0
+    for (name, g2) in [
+        ("beta", &vk_file.beta_g2),
+        ("gamma", &vk_file.gamma_g2),
+        ("delta", &vk_file.delta_g2),
+    ] {
+        vk_constants.push((format!("{}x1", name), &g2.x[1]));
+        vk_constants.push((format!("{}x2", name), &g2.x[0]));
+        vk_constants.push((format!("{}y1", name), &g2.y[1]));
+        vk_constants.push((format!("{}y2", name), &g2.y[0]));
+    }
+    for (i, g1) in vk_file.ic.iter().enumerate() {
+        vk_constants.push((format!("IC{}x", i), &g1.x));
+        vk_constants.push((format!("IC{}y", i), &g1.y));
+    }
+    let vk_constants = vk_constants
+        .iter()
+        .map(|(name, v)| format!("    uint256 constant {} = {};", name, v))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let g1_mul_acc = (1..=input_count)
+        .map(|i| {
+            format!(
+                "                g1_mulAccC(_pVk, IC{}x, IC{}y, calldataload(add(pubSignals, {})))",
+                i,
+                i,
+                (i - 1) * 32
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let check_fields = (0..input_count)
+        .map(|i| {
+            format!(
+                "            checkField(calldataload(add(_pubSignals, {})))",
+                i * 32
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    // a zero length array is not a valid Solidity type
+    let (pub_signals_argument, pub_signals) = if input_count > 0 {
+        (
+            format!(",\n        uint[{}] calldata _pubSignals", input_count),
+            "_pubSignals",
+        )
+    } else {
+        (String::new(), "0")
+    };
+
+    let template_text = ASSEMBLY_CONTRACT_TEMPLATE
+        .replace("<%vk_constants%>", &vk_constants)
+        .replace("<%g1_mul_acc%>", &g1_mul_acc)
+        .replace("<%check_fields%>", &check_fields)
+        .replace("<%pub_signals_argument%>", &pub_signals_argument)
+        .replace("<%pub_signals%>", pub_signals);
+
+    match std::fs::write(sol_file_path, template_text) {
+        Ok(()) => println!("Generate solidity verifier successfully!"),
+        Err(e) => {
+            bail!("write sol file failed, {:?}", e)
+        }
+    }
+    Ok(())
+}
+
+/// Generate a `no_std` Rust module verifying groth16 proofs of the key, see
+/// `RUST_VERIFIER_TEMPLATE`.
+pub fn generate_rust_verifier(vk_file_path: &str, rs_file_path: &str) -> Result<()> {
+    let (_, verifier) = render_rust_verifier(vk_file_path)?;
+    std::fs::write(rs_file_path, verifier)?;
+    println!("Generate rust verifier successfully!");
+    Ok(())
+}
+
+/// Generate a wasm-bindgen package in `package_dir`, whose `verify(proof_json,
+/// public_input_json)` runs the module of `generate_rust_verifier`.
+pub fn generate_wasm_verifier(vk_file_path: &str, package_dir: &str) -> Result<()> {
+    let (curve, verifier) = render_rust_verifier(vk_file_path)?;
+    let (ark_curve_crate, _, _, _) = ark_curve(&curve)?;
+    let src_dir = std::path::Path::new(package_dir).join("src");
+    std::fs::create_dir_all(&src_dir)?;
+    std::fs::write(
+        std::path::Path::new(package_dir).join("Cargo.toml"),
+        WASM_CARGO_TEMPLATE.replace("<%ark_curve_crate%>", ark_curve_crate),
+    )?;
+    std::fs::write(
+        src_dir.join("lib.rs"),
+        WASM_LIB_TEMPLATE.replace("<%curve%>", &curve),
+    )?;
+    std::fs::write(src_dir.join("proof_json.rs"), WASM_PROOF_JSON)?;
+    std::fs::write(src_dir.join("verifier.rs"), verifier)?;
+    println!("Generate wasm verifier successfully!");
+    Ok(())
+}
+
+/// The arkworks crate, module and pairing of a curve, and the bytes of its base field.
+fn ark_curve(curve_type: &str) -> Result<(&'static str, &'static str, &'static str, usize)> {
+    match curve_type {
+        "BN128" => Ok(("ark-bn254", "ark_bn254", "Bn254", 32)),
+        "BLS12381" => Ok(("ark-bls12-381", "ark_bls12_381", "Bls12_381", 48)),
+        _ => bail!(format!("Unknown curve type: {}", curve_type)),
+    }
+}
+
+/// The curve of the key and the source of its `RUST_VERIFIER_TEMPLATE` module.
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+fn render_rust_verifier(vk_file_path: &str) -> Result<(String, String)> {
+    let json_data = std::fs::read_to_string(vk_file_path)?;
+    let vk_file: VerifyingKeyFile = serde_json::from_str(&json_data)?;
+    if vk_file.ic.is_empty() {
+        bail!("the verification key has no IC");
+    }
+    let (ark_curve_crate, ark_curve_module, ark_pairing, fq_bytes) = ark_curve(&vk_file.curve)?;
+    let constants = match vk_file.curve.as_str() {
+        "BN128" => rust_vk_constants(&to_verification_key::<Bn256>(&json_data), fq_bytes)?,
+        "BLS12381" => rust_vk_constants(&to_verification_key::<Bls12>(&json_data), fq_bytes)?,
+        _ => {
+            bail!(format!("Unknown curve type: {}", vk_file.curve))
+        }
+    };
+    let verifier = RUST_VERIFIER_TEMPLATE
+        .replace("<%curve%>", &vk_file.curve)
+        .replace("<%ark_curve_crate%>", ark_curve_crate)
+        .replace("<%ark_curve_module%>", ark_curve_module)
+        .replace("<%ark_curve%>", ark_pairing)
+        .replace("<%fq_bytes%>", &fq_bytes.to_string())
+        .replace("<%num_inputs%>", &(vk_file.ic.len() - 1).to_string())
+        .replace("<%vk_constants%>", &constants);
+    Ok((vk_file.curve, verifier))
+}
+
+#[cfg(any(feature = "cuda", feature = "opencl"))]
+fn render_rust_verifier(_vk_file_path: &str) -> Result<(String, String)> {
+    bail!("the Rust verifier is not supported by the GPU prover")
+}
+
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+fn rust_vk_constants<P: Parser>(vk: &VerifyingKey<P>, fq_bytes: usize) -> Result<String> {
+    let bytes = |value: String| -> Result<String> {
+        let value = BigUint::from_str_radix(value.trim_start_matches("0x"), 16)?.to_bytes_be();
+        if value.len() > fq_bytes {
+            bail!("a coordinate does not fit in {} bytes", fq_bytes);
+        }
+        let mut padded = vec![0u8; fq_bytes - value.len()];
+        padded.extend(value);
+        let padded: Vec<String> = padded.iter().map(|b| format!("0x{:02x}", b)).collect();
+        Ok(format!("[{}]", padded.join(", ")))
+    };
+    let g1 = |p: &P::G1Affine, indent: &str| -> Result<String> {
+        let (x, y) = P::parse_g1(p, true);
+        Ok(format!(
+            "[\n{i}    {},\n{i}    {},\n{i}]",
+            bytes(x)?,
+            bytes(y)?,
+            i = indent
+        ))
+    };
+    let g2 = |p: &P::G2Affine| -> Result<String> {
+        let (x0, x1, y0, y1) = P::parse_g2(p, true);
+        Ok(format!(
+            "[\n    {},\n    {},\n    {},\n    {},\n]",
+            bytes(x0)?,
+            bytes(x1)?,
+            bytes(y0)?,
+            bytes(y1)?
+        ))
+    };
+    let mut constants = format!("pub const ALPHA_G1: G1Bytes = {};\n", g1(&vk.alpha_g1, "")?);
+    constants.push_str(&format!(
+        "pub const BETA_G2: G2Bytes = {};\n",
+        g2(&vk.beta_g2)?
+    ));
+    constants.push_str(&format!(
+        "pub const GAMMA_G2: G2Bytes = {};\n",
+        g2(&vk.gamma_g2)?
+    ));
+    constants.push_str(&format!(
+        "pub const DELTA_G2: G2Bytes = {};\n",
+        g2(&vk.delta_g2)?
+    ));
+    constants.push_str("pub const IC: [G1Bytes; NUM_INPUTS + 1] = [\n");
+    for p in vk.ic.iter() {
+        constants.push_str(&format!("    {},\n", g1(p, "    ")?));
+    }
+    constants.push_str("];");
+    Ok(constants)
+}
+
+/// Export a BN128 proof as the arguments of `verifyTx` of the contract of `generate_verifier`,
+/// or of `verifyProof` of the one of `generate_assembly_verifier` if `assembly`: the ABI
+/// encoded calldata, the Solidity declarations of the proof and input, and a Foundry test
+/// calling the contract imported from `verifier_path`.
+pub fn groth16_calldata(
+    vk_file: &str,
+    public_input_file: &str,
+    proof_file: &str,
+    calldata_file: &str,
+    solidity_file: &str,
+    test_file: &str,
+    verifier_path: &str,
+    assembly: bool,
+) -> Result<()> {
+    let vk_file: VerifyingKeyFile = serde_json::from_str(&std::fs::read_to_string(vk_file)?)?;
+    let proof: ProofFile = serde_json::from_str(&std::fs::read_to_string(proof_file)?)?;
+    let inputs: Vec<String> = serde_json::from_str(&std::fs::read_to_string(public_input_file)?)?;
+    if vk_file.curve != proof.curve {
+        bail!(
+            "the proof is on {}, but the verification key on {}",
+            proof.curve,
+            vk_file.curve
+        );
+    }
+    if inputs.len() + 1 != vk_file.ic.len() {
+        bail!(
+            "the verification key takes {} public inputs, but {} are given",
+            vk_file.ic.len().saturating_sub(1),
+            inputs.len()
+        );
+    }
+    let abi = if assembly {
+        Abi::VerifyProof
+    } else {
+        Abi::VerifyTx
+    };
+    let calldata = Calldata::new(&proof, &inputs, abi)?;
+    std::fs::write(calldata_file, format!("0x{}\n", calldata.encode()))?;
+    std::fs::write(solidity_file, calldata.to_solidity(""))?;
+    std::fs::write(test_file, calldata.to_foundry_test(verifier_path))?;
+    Ok(())
+}
+
+// The verifier of aggregate proofs on BN128, see AGGREGATE_CONTRACT_TEMPLATE for its cost.
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+pub fn generate_aggregate_verifier(
+    key_file_path: &str,
+    vk_file_path: &str,
+    sol_file_path: &str,
+) -> Result<()> {
+    let vk_file: VerifyingKeyFile = serde_json::from_str(&std::fs::read_to_string(vk_file_path)?)?;
+    let key_file: AggregationKeyFile =
+        serde_json::from_str(&std::fs::read_to_string(key_file_path)?)?;
+    if vk_file.curve != "BN128" || key_file.curve != "BN128" {
+        bail!("the Solidity aggregate verifier is only for BN128");
+    }
+    if key_file.g_a.len() < 2 || key_file.h_a.len() < 2 {
+        bail!("invalid aggregation key");
+    }
+
+    let gamma_abc_pts: Vec<String> = vk_file
+        .ic
+        .iter()
+        .enumerate()
+        .map(|(i, g1)| format!("vk.gamma_abc[{}] = Pairing.G1Point({});", i, g1))
+        .collect();
+    let mut template_text = String::from(AGGREGATE_CONTRACT_TEMPLATE);
+    for (placeholder, value) in [
+        ("<%vk_alpha%>", vk_file.alpha_g1.to_string()),
+        ("<%vk_beta%>", vk_file.beta_g2.to_string()),
+        ("<%vk_gamma%>", vk_file.gamma_g2.to_string()),
+        ("<%vk_delta%>", vk_file.delta_g2.to_string()),
+        ("<%vk_gamma_abc_length%>", vk_file.ic.len().to_string()),
+        ("<%vk_gamma_abc_pts%>", gamma_abc_pts.join("\n        ")),
+        ("<%ak_g%>", key_file.g_a[0].to_string()),
+        ("<%ak_h%>", key_file.h_a[0].to_string()),
+        ("<%ak_g_a%>", key_file.g_a[1].to_string()),
+        ("<%ak_g_b%>", key_file.g_b[1].to_string()),
+        ("<%ak_h_a%>", key_file.h_a[1].to_string()),
+        ("<%ak_h_b%>", key_file.h_b[1].to_string()),
+    ] {
+        template_text = template_text.replace(placeholder, &value);
+    }
+
+    let re = Regex::new(r"(?P<v>0[xX][0-9a-fA-F]{64})").unwrap();
+    template_text = re.replace_all(&template_text, "uint256($v)").to_string();
+
+    match std::fs::write(
+        sol_file_path,
+        format!("{}{}", solidity_pairing_lib(false), template_text),
+    ) {
+        Ok(()) => println!("Generate solidity aggregate verifier successfully!"),
+        Err(e) => {
+            bail!("write sol file failed, {:?}", e)
+        }
+    }
+    Ok(())
+}
+
+#[cfg(any(feature = "cuda", feature = "opencl"))]
+pub fn generate_aggregate_verifier(
+    _key_file_path: &str,
+    _vk_file_path: &str,
+    _sol_file_path: &str,
+) -> Result<()> {
+    bail!("proof aggregation is not supported by the GPU prover")
+}
+
 #[cfg(not(any(feature = "cuda", feature = "opencl")))]
 fn create_circuit_from_file<E: Engine>(
     circuit_file: &str,
@@ -578,6 +1483,24 @@ pub fn read_pk_from_file<E: Engine>(file_path: &str, checked: bool) -> Result<Pa
     Ok(Parameters::<E>::read(&mut reader, checked)?)
 }
 
+/// Prove `circuit` with the bellman parameters `pk_file`, or with a snarkjs zkey, which is
+/// checked against the R1CS and proved over the domain of snarkjs by `ZKey::prove`.
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+fn prove_with_pk_file<E: ZKeyEngine>(pk_file: &str, circuit: CircomCircuit<E>) -> Result<Proof<E>> {
+    let mut rng = rand::thread_rng();
+    if !is_zkey(pk_file)? {
+        let pk: Parameters<E> = read_pk_from_file(pk_file, false)?;
+        return Groth16::prove(&pk, circuit, &mut rng);
+    }
+    let zkey = ZKey::<E>::read(pk_file, false)?;
+    zkey.check_r1cs(&circuit.r1cs)?;
+    let witness = circuit
+        .witness
+        .as_ref()
+        .ok_or_else(|| anyhow!("The circuit has no witness"))?;
+    zkey.prove(witness, &mut rng)
+}
+
 #[cfg(any(feature = "cuda", feature = "opencl"))]
 fn read_pk_from_file<E: Engine>(file_path: &str, checked: bool) -> Result<Parameters<E>>
 where
@@ -613,6 +1536,31 @@ fn read_proof_from_file<P: Parser>(file_path: &str) -> Result<Proof<P>> {
     Ok(to_proof::<P>(&json_data))
 }
 
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+fn read_proofs_from_files<E: Engine + Parser>(
+    public_input_files: &[String],
+    proof_files: &[String],
+) -> Result<Vec<(Vec<E::Fr>, Proof<E>)>> {
+    public_input_files
+        .iter()
+        .zip(proof_files.iter())
+        .map(|(input, proof)| {
+            Ok((
+                read_public_input_from_file::<E::Fr>(input)?,
+                read_proof_from_file::<E>(proof)?,
+            ))
+        })
+        .collect()
+}
+
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+fn read_aggregation_key_from_file<E: AggregateEngine>(
+    file_path: &str,
+) -> Result<AggregationKey<E>> {
+    let json_data = std::fs::read_to_string(file_path)?;
+    aggregation::to_aggregation_key::<E>(&json_data)
+}
+
 fn write_pk_vk_to_files<P: Parser>(
     curve_type: &str,
     pk: Parameters<P>,
@@ -794,3 +1742,427 @@ library Pairing {
         .join("\n")
     }
 }
+
+#[cfg(test)]
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+mod tests {
+    use super::*;
+    use crate::calldata::tests::run_forge_test;
+    use crate::proof_json::{parse_number, to_be_bytes};
+    use algebraic::reader::load_witness_from_bin_file;
+
+    const CIRCUIT_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/multiplier.r1cs");
+    const WASM_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/multiplier.wasm");
+    const INPUT_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/multiplier.input.json");
+    const CIRCUIT_FILE_BLS12: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/test-vectors/mycircuit_bls12381.r1cs"
+    );
+    const WASM_FILE_BLS12: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/test-vectors/mycircuit_bls12381.wasm"
+    );
+    const RUST_VERIFIER_BN128: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/test-vectors/rust_verifier_bn128"
+    );
+    const RUST_VERIFIER_BLS12: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/test-vectors/rust_verifier_bls12381"
+    );
+
+    fn check_prove_with_witness<E: ZKeyEngine>(
+        curve_type: &str,
+        circuit_file: &str,
+        wasm_file: &str,
+    ) {
+        let prefix = format!("/tmp/prove_with_witness_{}", curve_type);
+        let pk_file = format!("{}.pk", prefix);
+        let vk_file = format!("{}.vk.json", prefix);
+        let witness_file = format!("{}.wtns", prefix);
+        let public_input_file = format!("{}.public.json", prefix);
+        let proof_file = format!("{}.proof.json", prefix);
+        groth16_setup(curve_type, circuit_file, &pk_file, &vk_file, false).unwrap();
+        calculate_witness(wasm_file, WitnessGenerator::Wasm, INPUT_FILE, &witness_file).unwrap();
+        groth16_prove_with_witness(
+            curve_type,
+            circuit_file,
+            &witness_file,
+            &pk_file,
+            &public_input_file,
+            &proof_file,
+            false,
+        )
+        .unwrap();
+        groth16_verify(curve_type, &vk_file, &public_input_file, &proof_file).unwrap();
+
+        for bad_witness_file in [format!("{}.missing.wtns", prefix), vk_file.clone()] {
+            assert!(groth16_prove_with_witness(
+                curve_type,
+                circuit_file,
+                &bad_witness_file,
+                &pk_file,
+                &public_input_file,
+                &proof_file,
+                false,
+            )
+            .is_err());
+        }
+
+        let r1cs = load_r1cs::<E>(circuit_file);
+        let mut w = load_witness_from_bin_file::<E>(&witness_file);
+        assert!(check_witness(&r1cs, &w).is_ok());
+        w[0] = E::Fr::zero();
+        assert!(check_witness(&r1cs, &w).is_err());
+        w.pop();
+        assert!(check_witness(&r1cs, &w).is_err());
+    }
+
+    fn check_rust_verifier<E: ZKeyEngine>(curve_type: &str, circuit_file: &str) {
+        let prefix = format!("/tmp/rust_verifier_{}", curve_type);
+        let pk_file = format!("{}.pk", prefix);
+        let vk_file = format!("{}.vk.json", prefix);
+        let rs_file = format!("{}.rs", prefix);
+        let package_dir = format!("{}_wasm", prefix);
+        groth16_setup(curve_type, circuit_file, &pk_file, &vk_file, true).unwrap();
+        generate_rust_verifier(&vk_file, &rs_file).unwrap();
+        generate_wasm_verifier(&vk_file, &package_dir).unwrap();
+
+        let verifier = std::fs::read_to_string(&rs_file).unwrap();
+        assert!(!verifier.contains("<%"));
+        let vk = read_vk_from_file::<E>(&vk_file).unwrap();
+        assert!(verifier.contains(&format!(
+            "pub const NUM_INPUTS: usize = {};",
+            vk.ic.len() - 1
+        )));
+
+        let package = std::path::Path::new(&package_dir);
+        assert_eq!(
+            std::fs::read_to_string(package.join("src/verifier.rs")).unwrap(),
+            verifier
+        );
+        assert_eq!(
+            std::fs::read_to_string(package.join("src/proof_json.rs")).unwrap(),
+            WASM_PROOF_JSON
+        );
+        let lib = std::fs::read_to_string(package.join("src/lib.rs")).unwrap();
+        assert!(lib.contains(&format!("const CURVE: &str = \"{}\";", curve_type)));
+        let (ark_curve_crate, _, _, _) = ark_curve(curve_type).unwrap();
+        let cargo = std::fs::read_to_string(package.join("Cargo.toml")).unwrap();
+        assert!(cargo.contains(ark_curve_crate));
+    }
+
+    /// The proof of `prefix.proof.json` and a copy with a and c swapped, each with the public
+    /// input of `prefix.public.json` and with a changed one, as json, and whether
+    /// `groth16_verify` accepts them. The proof is simulated with the trapdoor of the key.
+    fn verifier_cases(curve_type: &str, prefix: &str) -> Vec<(String, String, bool)> {
+        let vk_file = format!("{}.vk.json", prefix);
+        let public_input_file = format!("/tmp/verifier_cases_{}.public.json", curve_type);
+        let proof_file = format!("/tmp/verifier_cases_{}.proof.json", curve_type);
+        let read_proof = || -> ProofFile {
+            let json = std::fs::read_to_string(format!("{}.proof.json", prefix)).unwrap();
+            serde_json::from_str(&json).unwrap()
+        };
+        let mut swapped = read_proof();
+        std::mem::swap(&mut swapped.a, &mut swapped.c);
+        let inputs: Vec<String> = serde_json::from_str(
+            &std::fs::read_to_string(format!("{}.public.json", prefix)).unwrap(),
+        )
+        .unwrap();
+        let mut other_inputs = inputs.clone();
+        other_inputs[0] = (BigUint::from_str_radix(&inputs[0], 10).unwrap() + 1u32).to_string();
+
+        let mut cases = vec![];
+        for proof in [read_proof(), swapped] {
+            for inputs in [&inputs, &other_inputs] {
+                let proof_json = serde_json::to_string(&proof).unwrap();
+                let input_json = serde_json::to_string(inputs).unwrap();
+                std::fs::write(&proof_file, &proof_json).unwrap();
+                std::fs::write(&public_input_file, &input_json).unwrap();
+                let valid =
+                    groth16_verify(curve_type, &vk_file, &public_input_file, &proof_file).is_ok();
+                cases.push((proof_json, input_json, valid));
+            }
+        }
+        assert_eq!(cases.iter().filter(|(_, _, valid)| *valid).count(), 1);
+        cases
+    }
+
+    /// `prefix.rs` is compiled into the tests as `crate::rust_verifier_*`. It must be what
+    /// `generate_rust_verifier` renders from `prefix.vk.json`, and accept and reject the proofs
+    /// `groth16_verify` does.
+    fn check_compiled_rust_verifier(
+        curve_type: &str,
+        prefix: &str,
+        verify: impl Fn(&[u8], &[[u8; 32]]) -> Option<bool>,
+    ) {
+        let vk_file = format!("{}.vk.json", prefix);
+        let rs_file = format!("/tmp/compiled_rust_verifier_{}.rs", curve_type);
+        generate_rust_verifier(&vk_file, &rs_file).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&rs_file).unwrap(),
+            std::fs::read_to_string(format!("{}.rs", prefix)).unwrap()
+        );
+
+        let (_, _, _, fq_bytes) = ark_curve(curve_type).unwrap();
+        let be_bytes = |value: &str, len: usize| -> Vec<u8> {
+            let value = parse_number(value).unwrap().to_bytes_be();
+            let mut padded = vec![0u8; len - value.len()];
+            padded.extend(value);
+            padded
+        };
+        for (proof_json, input_json, valid) in verifier_cases(curve_type, prefix) {
+            let proof: ProofFile = serde_json::from_str(&proof_json).unwrap();
+            let inputs: Vec<String> = serde_json::from_str(&input_json).unwrap();
+            let mut proof_bytes = vec![];
+            for w in proof.words() {
+                proof_bytes.extend(be_bytes(w, fq_bytes));
+            }
+            let input_bytes: Vec<[u8; 32]> = inputs
+                .iter()
+                .map(|x| to_be_bytes::<32>(x).unwrap())
+                .collect();
+            assert_eq!(verify(&proof_bytes, &input_bytes), Some(valid));
+        }
+    }
+
+    /// Builds the package of `generate_wasm_verifier` for `prefix.vk.json` with cargo, as a
+    /// host library, and runs a test of it asserting that `verify` agrees with
+    /// `groth16_verify`.
+    fn check_wasm_verifier(curve_type: &str, prefix: &str) {
+        let package_dir = format!("/tmp/wasm_verifier_{}", curve_type);
+        let _ = std::fs::remove_dir_all(&package_dir);
+        generate_wasm_verifier(&format!("{}.vk.json", prefix), &package_dir).unwrap();
+
+        let mut test = String::from("#[test]\nfn test_verify() {\n");
+        for (proof_json, input_json, valid) in verifier_cases(curve_type, prefix) {
+            test.push_str(&format!(
+                "    assert_eq!(groth16_verifier::verify({:?}, {:?}).ok(), Some({}));\n",
+                proof_json, input_json, valid
+            ));
+        }
+        test.push_str("}\n");
+        let tests_dir = std::path::Path::new(&package_dir).join("tests");
+        std::fs::create_dir_all(&tests_dir).unwrap();
+        std::fs::write(tests_dir.join("verify.rs"), test).unwrap();
+
+        let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+        let status = std::process::Command::new(cargo)
+            .args([
+                "test",
+                "--manifest-path",
+                &format!("{}/Cargo.toml", package_dir),
+            ])
+            .status()
+            .unwrap();
+        assert!(status.success(), "the tests of {} failed", package_dir);
+    }
+
+    #[test]
+    fn test_rust_verifier_bn128() {
+        check_rust_verifier::<Bn256>("BN128", CIRCUIT_FILE);
+        check_compiled_rust_verifier("BN128", RUST_VERIFIER_BN128, |proof, inputs| {
+            crate::rust_verifier_bn128::verify(proof, inputs).ok()
+        });
+    }
+
+    #[test]
+    fn test_rust_verifier_bls12381() {
+        check_rust_verifier::<Bls12>("BLS12381", CIRCUIT_FILE_BLS12);
+        check_compiled_rust_verifier("BLS12381", RUST_VERIFIER_BLS12, |proof, inputs| {
+            crate::rust_verifier_bls12381::verify(proof, inputs).ok()
+        });
+    }
+
+    #[test]
+    #[ignore = "builds the wasm package with cargo, which fetches its dependencies"]
+    fn test_wasm_verifier_bn128() {
+        check_wasm_verifier("BN128", RUST_VERIFIER_BN128);
+    }
+
+    #[test]
+    #[ignore = "builds the wasm package with cargo, which fetches its dependencies"]
+    fn test_wasm_verifier_bls12381() {
+        check_wasm_verifier("BLS12381", RUST_VERIFIER_BLS12);
+    }
+
+    /// A proof of the multiplier on BN128 with the prefix, as its key, proof.json and public
+    /// input json.
+    fn prove_bn128(prefix: &str) -> (String, String, String) {
+        let pk_file = format!("{}.pk", prefix);
+        let vk_file = format!("{}.vk.json", prefix);
+        let public_input_file = format!("{}.public.json", prefix);
+        let proof_file = format!("{}.proof.json", prefix);
+        groth16_setup("BN128", CIRCUIT_FILE, &pk_file, &vk_file, false).unwrap();
+        groth16_prove(
+            "BN128",
+            CIRCUIT_FILE,
+            WASM_FILE,
+            WitnessGenerator::Wasm,
+            &pk_file,
+            INPUT_FILE,
+            &public_input_file,
+            &proof_file,
+            true,
+        )
+        .unwrap();
+        (vk_file, public_input_file, proof_file)
+    }
+
+    #[test]
+    fn test_groth16_calldata() {
+        let (vk_file, public_input_file, proof_file) = prove_bn128("/tmp/calldata");
+        let calldata_file = "/tmp/calldata.txt";
+        let solidity_file = "/tmp/calldata.sol";
+        let test_file = "/tmp/Verifier.t.sol";
+        let inputs: Vec<String> =
+            serde_json::from_str(&std::fs::read_to_string(&public_input_file).unwrap()).unwrap();
+        let proof: ProofFile =
+            serde_json::from_str(&std::fs::read_to_string(&proof_file).unwrap()).unwrap();
+        for assembly in [false, true] {
+            groth16_calldata(
+                &vk_file,
+                &public_input_file,
+                &proof_file,
+                calldata_file,
+                solidity_file,
+                test_file,
+                "verifier.sol",
+                assembly,
+            )
+            .unwrap();
+            let calldata = std::fs::read_to_string(calldata_file).unwrap();
+            assert_eq!(calldata.trim().len(), 2 + 8 + 64 * (8 + inputs.len()));
+            let test = std::fs::read_to_string(test_file).unwrap();
+            assert!(test.contains(&format!("hex\"{}\"", &calldata.trim()[2..])));
+            // the first word of b is b.x[0] for verifyTx and b.x[1] for verifyProof
+            let b = &calldata.trim()[2 + 8 + 64 * 2..][..64];
+            let x = &proof.b.x[assembly as usize];
+            assert_eq!(
+                parse_number(&format!("0x{}", b)).unwrap(),
+                parse_number(x).unwrap()
+            );
+        }
+
+        std::fs::write(&public_input_file, "[]").unwrap();
+        assert!(groth16_calldata(
+            &vk_file,
+            &public_input_file,
+            &proof_file,
+            calldata_file,
+            solidity_file,
+            test_file,
+            "verifier.sol",
+            false,
+        )
+        .is_err());
+    }
+
+    // The contracts of generate_verifier and generate_assembly_verifier accept the calldata of
+    // groth16_calldata for a real proof, and reject it with a wrong public input.
+    #[test]
+    #[ignore = "needs forge of Foundry"]
+    fn test_verifier_contracts() {
+        let (vk_file, public_input_file, proof_file) = prove_bn128("/tmp/verifier_contracts");
+        let proof: ProofFile =
+            serde_json::from_str(&std::fs::read_to_string(&proof_file).unwrap()).unwrap();
+        let inputs: Vec<String> =
+            serde_json::from_str(&std::fs::read_to_string(&public_input_file).unwrap()).unwrap();
+        let mut wrong_inputs = inputs.clone();
+        wrong_inputs[0] = (parse_number(&inputs[0]).unwrap() + 1u32).to_string();
+        let sol_file = "/tmp/verifier_contracts.sol";
+        for (name, assembly) in [("verify_tx", false), ("verify_proof", true)] {
+            if assembly {
+                generate_assembly_verifier(&vk_file, sol_file).unwrap();
+            } else {
+                generate_verifier(&vk_file, sol_file).unwrap();
+            }
+            let abi = if assembly {
+                Abi::VerifyProof
+            } else {
+                Abi::VerifyTx
+            };
+            let calldata = Calldata::new(&proof, &inputs, abi).unwrap();
+            let wrong_calldata = Calldata::new(&proof, &wrong_inputs, abi).unwrap();
+            let test = format!(
+                r#"// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.16;
+
+import "../src/Verifier.sol";
+
+contract VerifierTest {{
+    function test_{name}() public {{
+        Verifier verifier = new Verifier();
+        (bool success, bytes memory result) = address(verifier).staticcall(hex"{valid}");
+        require(success && abi.decode(result, (bool)), "valid proof rejected");
+        (success, result) = address(verifier).staticcall(hex"{wrong}");
+        require(success && !abi.decode(result, (bool)), "wrong input accepted");
+    }}
+}}
+"#,
+                name = calldata.function(),
+                valid = calldata.encode(),
+                wrong = wrong_calldata.encode(),
+            );
+            run_forge_test(
+                name,
+                &[
+                    (
+                        "src/Verifier.sol",
+                        std::fs::read_to_string(sol_file).unwrap(),
+                    ),
+                    ("test/Verifier.t.sol", test),
+                ],
+            );
+        }
+    }
+
+    #[test]
+    fn test_generate_assembly_verifier() {
+        let pk_file = "/tmp/assembly.pk";
+        let vk_file = "/tmp/assembly.vk.json";
+        let sol_file = "/tmp/assembly_verifier.sol";
+        groth16_setup("BN128", CIRCUIT_FILE, pk_file, vk_file, false).unwrap();
+        generate_assembly_verifier(vk_file, sol_file).unwrap();
+
+        let load_vk = || -> VerifyingKeyFile {
+            serde_json::from_str(&std::fs::read_to_string(vk_file).unwrap()).unwrap()
+        };
+        let vk = load_vk();
+        let sol = std::fs::read_to_string(sol_file).unwrap();
+        let input_count = vk.ic.len() - 1;
+        assert!(!sol.contains("<%"));
+        assert!(sol.contains(&format!("uint[{}] calldata _pubSignals", input_count)));
+        assert!(sol.contains(&format!("uint256 constant betax1 = {};", vk.beta_g2.x[1])));
+        assert!(sol.contains(&format!("uint256 constant deltay2 = {};", vk.delta_g2.y[0])));
+        assert!(sol.contains(&format!(
+            "uint256 constant IC{}y = {};",
+            input_count, vk.ic[input_count].y
+        )));
+        assert_eq!(sol.matches("g1_mulAccC(_pVk").count(), input_count);
+        assert_eq!(sol.matches("checkField(calldataload").count(), input_count);
+
+        let mut vk_bls12 = load_vk();
+        let mut vk_without_inputs = vk;
+        vk_without_inputs.ic.truncate(1);
+        std::fs::write(vk_file, serde_json::to_string(&vk_without_inputs).unwrap()).unwrap();
+        generate_assembly_verifier(vk_file, sol_file).unwrap();
+        let sol = std::fs::read_to_string(sol_file).unwrap();
+        assert!(!sol.contains("_pubSignals"));
+        assert!(sol.contains("checkPairing(_pA, _pB, _pC, 0, pMem)"));
+
+        vk_bls12.curve = "BLS12381".to_string();
+        std::fs::write(vk_file, serde_json::to_string(&vk_bls12).unwrap()).unwrap();
+        assert!(generate_assembly_verifier(vk_file, sol_file).is_err());
+    }
+
+    #[test]
+    fn test_prove_with_witness_bn128() {
+        check_prove_with_witness::<Bn256>("BN128", CIRCUIT_FILE, WASM_FILE);
+    }
+
+    #[test]
+    fn test_prove_with_witness_bls12381() {
+        check_prove_with_witness::<Bls12>("BLS12381", CIRCUIT_FILE_BLS12, WASM_FILE_BLS12);
+    }
+}
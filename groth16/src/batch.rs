@@ -0,0 +1,229 @@
+//! Batch verification of groth16 proofs of one verifying key.
+//!
+//! Each proof checks e(A, B) = e(alpha, beta) e(IC(x), gamma) e(C, delta). Scaling the i-th
+//! check by a random r_i and multiplying them gives one multi pairing of n + 2 pairs:
+//! prod e(r_i A_i, B_i) e(-sum r_i IC(x_i), gamma) e(-sum r_i C_i, delta) = e(alpha, beta)^sum r_i,
+//! which an invalid proof fails except with probability 2^-128. When the batch fails, it is
+//! split in halves until the invalid proofs are found.
+use crate::bellman_ce::{
+    groth16::{Proof, VerifyingKey},
+    pairing::{CurveAffine, CurveProjective, Engine},
+};
+use crate::{Field, PrimeField, PrimeFieldRepr};
+use rand_old::Rng;
+use rayon::prelude::*;
+
+// r_i of 128 bits
+fn random_scalar<F: PrimeField, R: Rng>(rng: &mut R) -> F {
+    let mut repr = F::Repr::default();
+    repr.as_mut()[0] = rng.gen();
+    repr.as_mut()[1] = rng.gen();
+    F::from_repr(repr).unwrap()
+}
+
+/// Checks all the proofs with one multi pairing. `proofs` are the public inputs and the
+/// proof of each statement.
+pub fn verify_batch<E: Engine, R: Rng>(
+    vk: &VerifyingKey<E>,
+    proofs: &[(Vec<E::Fr>, Proof<E>)],
+    rng: &mut R,
+) -> bool {
+    if proofs.is_empty() {
+        return true;
+    }
+    if proofs
+        .iter()
+        .any(|(inputs, _)| inputs.len() + 1 != vk.ic.len())
+    {
+        return false;
+    }
+    let r: Vec<E::Fr> = (0..proofs.len()).map(|_| random_scalar(rng)).collect();
+
+    // sum r_i IC(x_i) = sum_j (sum_i r_i x_ij) IC_j, with x_i0 = 1
+    let mut r_sum = E::Fr::zero();
+    let mut ic_coefs = vec![E::Fr::zero(); vk.ic.len()];
+    for ((inputs, _), r) in proofs.iter().zip(r.iter()) {
+        r_sum.add_assign(r);
+        for (coef, x) in ic_coefs
+            .iter_mut()
+            .zip(std::iter::once(&E::Fr::one()).chain(inputs))
+        {
+            let mut t = *x;
+            t.mul_assign(r);
+            coef.add_assign(&t);
+        }
+    }
+    let sum = |a: E::G1, b: E::G1| {
+        let mut a = a;
+        a.add_assign(&b);
+        a
+    };
+    let mut acc_ic = vk
+        .ic
+        .par_iter()
+        .zip(ic_coefs.par_iter())
+        .map(|(p, c)| p.mul(*c))
+        .reduce(E::G1::zero, sum);
+    let mut acc_c = proofs
+        .par_iter()
+        .zip(r.par_iter())
+        .map(|((_, proof), r)| proof.c.mul(*r))
+        .reduce(E::G1::zero, sum);
+    acc_ic.negate();
+    acc_c.negate();
+
+    let mut pairs: Vec<_> = proofs
+        .par_iter()
+        .zip(r.par_iter())
+        .map(|((_, proof), r)| (proof.a.mul(*r).into_affine().prepare(), proof.b.prepare()))
+        .collect();
+    pairs.push((acc_ic.into_affine().prepare(), vk.gamma_g2.prepare()));
+    pairs.push((acc_c.into_affine().prepare(), vk.delta_g2.prepare()));
+    let terms: Vec<_> = pairs.iter().map(|(a, b)| (a, b)).collect();
+
+    let expected = E::pairing(vk.alpha_g1, vk.beta_g2).pow(r_sum.into_repr());
+    E::final_exponentiation(&E::miller_loop(terms.iter())) == Some(expected)
+}
+
+/// The indexes of the invalid proofs, the batch is split in halves while it fails.
+pub fn find_invalid_proofs<E: Engine, R: Rng>(
+    vk: &VerifyingKey<E>,
+    proofs: &[(Vec<E::Fr>, Proof<E>)],
+    rng: &mut R,
+) -> Vec<usize> {
+    let mut invalid = vec![];
+    bisect(vk, proofs, 0, rng, &mut invalid);
+    invalid
+}
+
+fn bisect<E: Engine, R: Rng>(
+    vk: &VerifyingKey<E>,
+    proofs: &[(Vec<E::Fr>, Proof<E>)],
+    offset: usize,
+    rng: &mut R,
+    invalid: &mut Vec<usize>,
+) {
+    if verify_batch(vk, proofs, rng) {
+        return;
+    }
+    if proofs.len() == 1 {
+        invalid.push(offset);
+        return;
+    }
+    let mid = proofs.len() / 2;
+    bisect(vk, &proofs[..mid], offset, rng, invalid);
+    bisect(vk, &proofs[mid..], offset + mid, rng, invalid);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{
+        create_circuit_add_witness, groth16_batch_verify, groth16_prove, groth16_setup,
+        WitnessGenerator,
+    };
+    use crate::bellman_ce::{bls12_381::Bls12, bn256::Bn256};
+    use crate::groth16::Groth16;
+    use algebraic::circom_circuit::CircomCircuit;
+    use algebraic::reader::load_r1cs;
+    use algebraic::witness::{load_input_for_witness, WitnessCalculator};
+
+    const CIRCUIT_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/multiplier.r1cs");
+    const WASM_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/multiplier.wasm");
+    const INPUT_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/multiplier.input.json");
+    const CIRCUIT_FILE_BLS12: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/test-vectors/mycircuit_bls12381.r1cs"
+    );
+    const WASM_FILE_BLS12: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/test-vectors/mycircuit_bls12381.wasm"
+    );
+
+    fn check_batch<E: Engine>(circuit_file: &str, wasm_file: &str) {
+        let mut rng = rand_old::thread_rng();
+        let circuit = CircomCircuit::<E> {
+            r1cs: load_r1cs(circuit_file),
+            witness: None,
+            wire_mapping: None,
+            aux_offset: 0,
+        };
+        let (pk, vk) = Groth16::circuit_specific_setup(circuit.clone(), &mut rng).unwrap();
+        let mut wtns = WitnessCalculator::from_file(wasm_file).unwrap();
+        let w = wtns
+            .calculate_witness(load_input_for_witness(INPUT_FILE), false)
+            .unwrap();
+        let circuit = create_circuit_add_witness(circuit, w);
+        let inputs = circuit.get_public_inputs().unwrap();
+        let mut proofs: Vec<_> = (0..6)
+            .map(|_| {
+                let proof = Groth16::prove(&pk, circuit.clone(), &mut rng).unwrap();
+                (inputs.clone(), proof)
+            })
+            .collect();
+        assert!(verify_batch(&vk, &proofs, &mut rng));
+        assert!(find_invalid_proofs(&vk, &proofs, &mut rng).is_empty());
+        assert!(verify_batch(&vk, &proofs[..0], &mut rng));
+
+        // a wrong public input, and two valid proofs with their C swapped
+        proofs[1].0[0].add_assign(&E::Fr::one());
+        let c = proofs[3].1.c;
+        proofs[3].1.c = proofs[4].1.c;
+        proofs[4].1.c = c;
+        assert!(!verify_batch(&vk, &proofs, &mut rng));
+        assert_eq!(find_invalid_proofs(&vk, &proofs, &mut rng), vec![1, 3, 4]);
+        proofs[5].0.pop();
+        assert_eq!(
+            find_invalid_proofs(&vk, &proofs, &mut rng),
+            vec![1, 3, 4, 5]
+        );
+    }
+
+    #[test]
+    fn test_batch_verify_bn128() {
+        check_batch::<Bn256>(CIRCUIT_FILE, WASM_FILE);
+    }
+
+    #[test]
+    fn test_batch_verify_bls12381() {
+        check_batch::<Bls12>(CIRCUIT_FILE_BLS12, WASM_FILE_BLS12);
+    }
+
+    #[test]
+    fn test_batch_verify_files() {
+        let pk_file = "/tmp/batch_verify.pk";
+        let vk_file = "/tmp/batch_verify.vk.json";
+        groth16_setup("BN128", CIRCUIT_FILE, pk_file, vk_file, false).unwrap();
+        let mut public_input_files = vec![];
+        let mut proof_files = vec![];
+        for i in 0..3 {
+            let public_input_file = format!("/tmp/batch_verify.public{}.json", i);
+            let proof_file = format!("/tmp/batch_verify.proof{}.json", i);
+            groth16_prove(
+                "BN128",
+                CIRCUIT_FILE,
+                WASM_FILE,
+                WitnessGenerator::Wasm,
+                pk_file,
+                INPUT_FILE,
+                &public_input_file,
+                &proof_file,
+                false,
+            )
+            .unwrap();
+            public_input_files.push(public_input_file);
+            proof_files.push(proof_file);
+        }
+        groth16_batch_verify("BN128", vk_file, &public_input_files, &proof_files).unwrap();
+
+        // the public input of another statement
+        std::fs::write(&public_input_files[2], "[\"1\"]").unwrap();
+        let err = groth16_batch_verify("BN128", vk_file, &public_input_files, &proof_files)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains(&proof_files[2]) && !err.contains(&proof_files[1]));
+        assert!(
+            groth16_batch_verify("BN128", vk_file, &public_input_files[..2], &proof_files).is_err()
+        );
+    }
+}
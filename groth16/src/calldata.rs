@@ -0,0 +1,405 @@
+//! Arguments of `verifyTx` of the contract written by `api::generate_verifier`, and of
+//! `verifyProof` of the one written by `api::generate_assembly_verifier`.
+//!
+//! `verifyTx(Proof memory proof, uint[n] memory input)` only takes static types, so its ABI
+//! encoding is the selector followed by one word per value, in the order
+//! a.x, a.y, b.x[0], b.x[1], b.y[0], b.y[1], c.x, c.y, input[0], ..., input[n - 1].
+//! The coordinates of b keep the order of proof.json, the Pairing library swaps them for the
+//! pairing precompile. Without public inputs, `verifyTx` only takes the proof.
+//!
+//! `verifyProof(uint[2] a, uint[2][2] b, uint[2] c, uint[n] input)`, the ABI of snarkjs, has
+//! the same words but takes b in the order of the precompile,
+//! b.x[1], b.x[0], b.y[1], b.y[0].
+use crate::json_utils::ProofFile;
+use crate::proof_json::parse_number;
+use anyhow::{bail, Result};
+use num_bigint::BigUint;
+use num_traits::Num;
+use tiny_keccak::{Hasher, Keccak};
+
+/// The scalar field of BN128, `verifyTx` rejects inputs out of it.
+const SNARK_SCALAR_FIELD: &str =
+    "21888242871839275222246405745257275088548364400416034343698204186575808495617";
+
+/// The function of the Solidity verifier the calldata is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Abi {
+    /// `verifyTx` of `api::generate_verifier`
+    VerifyTx,
+    /// `verifyProof` of `api::generate_assembly_verifier`
+    VerifyProof,
+}
+
+/// The proof and public inputs of a `verifyTx` or `verifyProof` call. b is in the order of
+/// proof.json.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Calldata {
+    pub abi: Abi,
+    pub a: [BigUint; 2],
+    pub b: [BigUint; 4],
+    pub c: [BigUint; 2],
+    pub inputs: Vec<BigUint>,
+}
+
+impl Calldata {
+    pub fn new(proof: &ProofFile, public_inputs: &[String], abi: Abi) -> Result<Self> {
+        if proof.curve != "BN128" {
+            bail!(
+                "the Solidity verifier is only for BN128, not {}",
+                proof.curve
+            );
+        }
+        let modulus = BigUint::from_str_radix(SNARK_SCALAR_FIELD, 10)?;
+        let inputs = public_inputs
+            .iter()
+            .map(|s| parse_word(s))
+            .collect::<Result<Vec<_>>>()?;
+        if let Some(i) = inputs.iter().position(|x| *x >= modulus) {
+            bail!("public input {} is not in the scalar field", i);
+        }
+        let [ax, ay, bx0, bx1, by0, by1, cx, cy] = proof.words();
+        Ok(Calldata {
+            abi,
+            a: [parse_word(ax)?, parse_word(ay)?],
+            b: [
+                parse_word(bx0)?,
+                parse_word(bx1)?,
+                parse_word(by0)?,
+                parse_word(by1)?,
+            ],
+            c: [parse_word(cx)?, parse_word(cy)?],
+            inputs,
+        })
+    }
+
+    /// The words of the call after the selector.
+    pub fn words(&self) -> Vec<BigUint> {
+        let mut words = vec![];
+        words.extend_from_slice(&self.a);
+        words.extend(self.b_words());
+        words.extend_from_slice(&self.c);
+        words.extend_from_slice(&self.inputs);
+        words
+    }
+
+    /// b in the order of the ABI.
+    fn b_words(&self) -> [BigUint; 4] {
+        let [bx0, bx1, by0, by1] = self.b.clone();
+        match self.abi {
+            Abi::VerifyTx => [bx0, bx1, by0, by1],
+            Abi::VerifyProof => [bx1, bx0, by1, by0],
+        }
+    }
+
+    /// The name of the function in Solidity.
+    pub fn function(&self) -> &'static str {
+        match self.abi {
+            Abi::VerifyTx => "verifyTx",
+            Abi::VerifyProof => "verifyProof",
+        }
+    }
+
+    pub fn signature(&self) -> String {
+        let proof = match self.abi {
+            Abi::VerifyTx => "((uint256,uint256),(uint256[2],uint256[2]),(uint256,uint256))",
+            Abi::VerifyProof => "uint256[2],uint256[2][2],uint256[2]",
+        };
+        if self.inputs.is_empty() {
+            format!("{}({})", self.function(), proof)
+        } else {
+            format!(
+                "{}({},uint256[{}])",
+                self.function(),
+                proof,
+                self.inputs.len()
+            )
+        }
+    }
+
+    /// The ABI encoded call, as hex without the 0x prefix.
+    pub fn encode(&self) -> String {
+        let mut hex: String = selector(&self.signature())
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        for w in self.words() {
+            hex.push_str(&format!("{:0>64}", w.to_str_radix(16)));
+        }
+        hex
+    }
+
+    /// Solidity statements declaring the proof, `proof` for `verifyTx` and `a`, `b` and `c`
+    /// for `verifyProof`, and, if there are public inputs, `input`.
+    pub fn to_solidity(&self, indent: &str) -> String {
+        let [ax, ay] = &self.a;
+        let [b0, b1, b2, b3] = &self.b_words();
+        let [cx, cy] = &self.c;
+        let mut sol = match self.abi {
+            Abi::VerifyTx => format!(
+                "{i}Verifier.Proof memory proof = Verifier.Proof(\n\
+                 {i}    Pairing.G1Point({}, {}),\n\
+                 {i}    Pairing.G2Point([uint256({}), {}], [uint256({}), {}]),\n\
+                 {i}    Pairing.G1Point({}, {})\n\
+                 {i});\n",
+                ax,
+                ay,
+                b0,
+                b1,
+                b2,
+                b3,
+                cx,
+                cy,
+                i = indent
+            ),
+            Abi::VerifyProof => format!(
+                "{i}uint256[2] memory a = [uint256({}), {}];\n\
+                 {i}uint256[2][2] memory b = [[uint256({}), {}], [uint256({}), {}]];\n\
+                 {i}uint256[2] memory c = [uint256({}), {}];\n",
+                ax,
+                ay,
+                b0,
+                b1,
+                b2,
+                b3,
+                cx,
+                cy,
+                i = indent
+            ),
+        };
+        if let Some((first, rest)) = self.inputs.split_first() {
+            let mut values = format!("uint256({})", first);
+            for x in rest {
+                values.push_str(&format!(", {}", x));
+            }
+            sol.push_str(&format!(
+                "{}uint256[{}] memory input = [{}];\n",
+                indent,
+                self.inputs.len(),
+                values
+            ));
+        }
+        sol
+    }
+
+    /// A Foundry test of the verifier imported from `verifier_path`. It verifies the proof
+    /// through `verifyTx` or `verifyProof`, with the raw calldata, and checks that a changed
+    /// input is rejected.
+    pub fn to_foundry_test(&self, verifier_path: &str) -> String {
+        let indent = "        ";
+        let proof = match self.abi {
+            Abi::VerifyTx => "proof",
+            Abi::VerifyProof => "a, b, c",
+        };
+        let args = if self.inputs.is_empty() {
+            proof.to_string()
+        } else {
+            format!("{}, input", proof)
+        };
+        let function = self.function();
+        let mut name = function.to_string();
+        name[..1].make_ascii_uppercase();
+        let mut test = format!(
+            r#"// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.16;
+
+import "forge-std/Test.sol";
+import "{verifier_path}";
+
+contract VerifierTest is Test {{
+    Verifier verifier;
+
+    function setUp() public {{
+        verifier = new Verifier();
+    }}
+
+    function test{name}() public {{
+{declarations}        assertTrue(verifier.{function}({args}));
+    }}
+
+    function test{name}Calldata() public {{
+        (bool success, bytes memory result) = address(verifier).staticcall(
+            hex"{calldata}"
+        );
+        assertTrue(success);
+        assertTrue(abi.decode(result, (bool)));
+    }}
+"#,
+            verifier_path = verifier_path,
+            name = name,
+            declarations = self.to_solidity(indent),
+            function = function,
+            args = args,
+            calldata = self.encode(),
+        );
+        if !self.inputs.is_empty() {
+            test.push_str(&format!(
+                r#"
+    function testRejectWrongInput() public {{
+{declarations}        input[0] = (input[0] + 1) % {modulus};
+        assertFalse(verifier.{function}({args}));
+    }}
+"#,
+                declarations = self.to_solidity(indent),
+                modulus = SNARK_SCALAR_FIELD,
+                function = function,
+                args = args,
+            ));
+        }
+        test.push_str("}\n");
+        test
+    }
+}
+
+/// The first 4 bytes of the keccak256 of a function signature.
+pub fn selector(signature: &str) -> [u8; 4] {
+    let mut hasher = Keccak::v256();
+    hasher.update(signature.as_bytes());
+    let mut hash = [0u8; 32];
+    hasher.finalize(&mut hash);
+    [hash[0], hash[1], hash[2], hash[3]]
+}
+
+/// A decimal or 0x prefixed hex value of proof.json, or of the public input json.
+fn parse_word(s: &str) -> Result<BigUint> {
+    match parse_number(s) {
+        Ok(w) if w.bits() <= 256 => Ok(w),
+        _ => bail!("invalid uint256: {}", s),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::json_utils::{G1, G2};
+
+    // The aggregate verifier is above the contract size limit of EIP-170 and needs more gas
+    // than a block has.
+    const FOUNDRY_TOML: &str = r#"[profile.default]
+src = "src"
+test = "test"
+out = "out"
+libs = []
+optimizer = true
+optimizer_runs = 1
+gas_limit = "18446744073709551615"
+code_size_limit = 1000000
+"#;
+
+    /// Runs `forge test` in a Foundry project of `files`, the paths relative to its root,
+    /// e.g. a verifier in src and a test contract of it in test.
+    pub(crate) fn run_forge_test(name: &str, files: &[(&str, String)]) {
+        let root = format!("/tmp/forge_{}", name);
+        let _ = std::fs::remove_dir_all(&root);
+        for dir in ["src", "test"] {
+            std::fs::create_dir_all(format!("{}/{}", root, dir)).unwrap();
+        }
+        std::fs::write(format!("{}/foundry.toml", root), FOUNDRY_TOML).unwrap();
+        for (path, content) in files {
+            std::fs::write(format!("{}/{}", root, path), content).unwrap();
+        }
+        let status = std::process::Command::new("forge")
+            .args(["test", "--root", &root])
+            .status()
+            .expect("forge is not installed");
+        assert!(status.success(), "forge test failed in {}", root);
+    }
+
+    fn proof_file(curve: &str) -> ProofFile {
+        let g1 = |x: &str, y: &str| G1 {
+            x: x.to_string(),
+            y: y.to_string(),
+        };
+        ProofFile {
+            a: g1("1", "2"),
+            b: G2 {
+                x: ["3".to_string(), "0x04".to_string()],
+                y: ["5".to_string(), "6".to_string()],
+            },
+            c: g1("7", "0x8"),
+            protocol: "groth16".to_string(),
+            curve: curve.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_selector() {
+        assert_eq!(
+            selector("transfer(address,uint256)"),
+            [0xa9, 0x05, 0x9c, 0xbb]
+        );
+    }
+
+    #[test]
+    fn test_calldata() {
+        let inputs = vec!["9".to_string(), "0xa".to_string()];
+        let calldata = Calldata::new(&proof_file("BN128"), &inputs, Abi::VerifyTx).unwrap();
+        let words: Vec<_> = (1u32..=10).map(BigUint::from).collect();
+        assert_eq!(calldata.words(), words);
+        assert_eq!(
+            calldata.signature(),
+            "verifyTx(((uint256,uint256),(uint256[2],uint256[2]),(uint256,uint256)),uint256[2])"
+        );
+        let encoded = calldata.encode();
+        assert_eq!(encoded.len(), 8 + 64 * 10);
+        assert!(encoded.ends_with(&format!("{:064x}", 10)));
+        assert!(calldata
+            .to_solidity("")
+            .contains("Pairing.G2Point([uint256(3), 4], [uint256(5), 6])"));
+        assert!(calldata
+            .to_solidity("")
+            .contains("uint256[2] memory input = [uint256(9), 10];"));
+        let test = calldata.to_foundry_test("../verifier.sol");
+        assert!(test.contains("import \"../verifier.sol\";"));
+        assert!(test.contains(&format!("hex\"{}\"", encoded)));
+        assert!(test.contains("testRejectWrongInput"));
+
+        let calldata = Calldata::new(&proof_file("BN128"), &[], Abi::VerifyTx).unwrap();
+        assert!(calldata.signature().ends_with("(uint256,uint256)))"));
+        assert!(!calldata.to_solidity("").contains("input"));
+        assert!(!calldata
+            .to_foundry_test("verifier.sol")
+            .contains("testRejectWrongInput"));
+
+        let new = |inputs: &[String]| Calldata::new(&proof_file("BN128"), inputs, Abi::VerifyTx);
+        assert!(Calldata::new(&proof_file("BLS12381"), &inputs, Abi::VerifyTx).is_err());
+        assert!(new(&[SNARK_SCALAR_FIELD.to_string()]).is_err());
+        assert!(new(&["0xg".to_string()]).is_err());
+        let too_big = format!("0x1{}", "0".repeat(64));
+        assert!(new(&[too_big]).is_err());
+    }
+
+    #[test]
+    fn test_verify_proof_calldata() {
+        let inputs = vec!["9".to_string(), "0xa".to_string()];
+        let calldata = Calldata::new(&proof_file("BN128"), &inputs, Abi::VerifyProof).unwrap();
+        let words: Vec<_> = [1u32, 2, 4, 3, 6, 5, 7, 8, 9, 10]
+            .into_iter()
+            .map(BigUint::from)
+            .collect();
+        assert_eq!(calldata.words(), words);
+        assert_eq!(
+            calldata.signature(),
+            "verifyProof(uint256[2],uint256[2][2],uint256[2],uint256[2])"
+        );
+        // the selector of the snarkjs verifiers of 2 public inputs
+        let encoded = calldata.encode();
+        assert!(encoded.starts_with("f5c9d69e"));
+        assert_eq!(encoded.len(), 8 + 64 * 10);
+        let sol = calldata.to_solidity("");
+        assert!(sol.contains("uint256[2][2] memory b = [[uint256(4), 3], [uint256(6), 5]];"));
+        assert!(sol.contains("uint256[2] memory input = [uint256(9), 10];"));
+        let test = calldata.to_foundry_test("verifier.sol");
+        assert!(test.contains("function testVerifyProof()"));
+        assert!(test.contains("verifier.verifyProof(a, b, c, input)"));
+        assert!(test.contains(&format!("hex\"{}\"", encoded)));
+
+        let calldata = Calldata::new(&proof_file("BN128"), &[], Abi::VerifyProof).unwrap();
+        assert_eq!(
+            calldata.signature(),
+            "verifyProof(uint256[2],uint256[2][2],uint256[2])"
+        );
+        assert!(calldata
+            .to_foundry_test("verifier.sol")
+            .contains("verifier.verifyProof(a, b, c)"));
+    }
+}
@@ -27,17 +27,8 @@ use pairing::MultiMillerLoop;
 use serde::{Deserialize, Serialize};
 use serde_json::to_string;
 use std::fmt;
-#[derive(Debug, Serialize, Deserialize)]
-pub struct G1 {
-    pub x: String,
-    pub y: String,
-}
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct G2 {
-    pub x: [String; 2],
-    pub y: [String; 2],
-}
+pub use crate::proof_json::{ProofFile, G1, G2};
 
 impl fmt::Display for G1 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -77,20 +68,6 @@ pub struct VerifyingKeyFile {
     pub ic: Vec<G1>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct ProofFile {
-    #[serde(rename = "pi_a")]
-    pub a: G1,
-    #[serde(rename = "pi_b")]
-    pub b: G2,
-    #[serde(rename = "pi_c")]
-    pub c: G1,
-    #[serde(rename = "protocol")]
-    pub protocol: String,
-    #[serde(rename = "curve")]
-    pub curve: String,
-}
-
 #[cfg(not(any(feature = "cuda", feature = "opencl")))]
 pub trait Parser: franklin_crypto::bellman::pairing::Engine {
     fn parse_g1(e: &Self::G1Affine, to_hex: bool) -> (String, String);
@@ -390,13 +367,11 @@ pub fn to_proof<P: Parser>(s: &str) -> Proof<P> {
     let proof: ProofFile =
         serde_json::from_str(s).expect("Error during deserialization of the JSON data");
 
-    let convert_g1 = |point: &G1| P::to_g1(&point.x, &point.y);
-    let convert_g2 = |point: &G2| P::to_g2(&point.x[0], &point.x[1], &point.y[0], &point.y[1]);
-
+    let [ax, ay, bx0, bx1, by0, by1, cx, cy] = proof.words();
     Proof {
-        a: convert_g1(&proof.a),
-        b: convert_g2(&proof.b),
-        c: convert_g1(&proof.c),
+        a: P::to_g1(ax, ay),
+        b: P::to_g2(bx0, bx1, by0, by1),
+        c: P::to_g1(cx, cy),
     }
 }
 
@@ -1,7 +1,27 @@
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+pub mod aggregation;
 pub mod api;
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+pub mod batch;
+pub mod calldata;
 pub mod groth16;
 pub mod json_utils;
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+pub mod mpc;
+pub mod proof_json;
 mod template;
+#[cfg(not(any(feature = "cuda", feature = "opencl")))]
+pub mod zkey;
+
+// The verifiers of generate_rust_verifier for the keys of test-vectors/rust_verifier_*.vk.json
+#[cfg(all(test, not(any(feature = "cuda", feature = "opencl"))))]
+#[rustfmt::skip]
+#[path = "../test-vectors/rust_verifier_bn128.rs"]
+mod rust_verifier_bn128;
+#[cfg(all(test, not(any(feature = "cuda", feature = "opencl"))))]
+#[rustfmt::skip]
+#[path = "../test-vectors/rust_verifier_bls12381.rs"]
+mod rust_verifier_bls12381;
 
 #[cfg(not(any(feature = "cuda", feature = "opencl")))]
 mod non_gpu_specific {
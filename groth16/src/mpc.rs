@@ -0,0 +1,827 @@
+//! The phase 2 ceremony of groth16 on zkeys, see snarkjs/src/zkey_new.js, zkey_contribute.js,
+//! zkey_beacon.js and zkey_verify_frominit.js.
+//!
+//! `new_zkey` builds the initial key of a R1CS from a phase 1 transcript prepared for phase 2,
+//! the `.ptau` of snarkjs, with gamma and delta set to the generators. Each contribution
+//! multiplies delta by a secret x and divides C and H by it, and records a proof of knowledge
+//! of x chained to the previous contributions, so the key is safe as long as one contributor
+//! drops its x. The beacon is a last contribution whose x comes from a public random value.
+//!
+//! The ceremony is not compatible with the one of snarkjs. The contributions are kept in the
+//! section 10 of the zkey with the layout of snarkjs, and the final key proves with snarkjs,
+//! but the transcript hashes the points as they are encoded in the zkey and the hash to G2
+//! samples with the ChaCha of rand. So `snarkjs zkey verify` rejects a chain of zkit,
+//! `groth16_verify_contributions` rejects a chain of snarkjs, and the contributors of one
+//! ceremony must all use zkit, or all use snarkjs.
+use crate::bellman_ce::{
+    groth16::VerifyingKey,
+    pairing::{CurveAffine, CurveProjective, Engine},
+};
+use crate::zkey::{
+    domain_size, n8, prime_bytes, r1cs_coefs, read_g1, read_g1s, read_g2, read_sections,
+    seek_section, write_g1, write_g2, ZKey, ZKeyEngine,
+};
+use crate::{Field, PrimeField};
+use algebraic::circom_circuit::R1CS;
+use anyhow::{anyhow, bail, Result};
+use blake2::{Blake2b, Digest};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use rand_old::{ChaChaRng, Rand, Rng, SeedableRng};
+use rayon::prelude::*;
+use sha2::Sha256;
+use std::collections::BTreeMap;
+use std::io::{BufReader, Read, Seek, Write};
+
+const PTAU_HEADER: u32 = 1;
+const TAU_G1: u32 = 2;
+const TAU_G2: u32 = 3;
+const ALPHA_TAU_G1: u32 = 4;
+const BETA_TAU_G1: u32 = 5;
+const BETA_G2: u32 = 6;
+const LAGRANGE_G1: u32 = 12;
+const LAGRANGE_G2: u32 = 13;
+const LAGRANGE_ALPHA_G1: u32 = 14;
+const LAGRANGE_BETA_G1: u32 = 15;
+
+const CONTRIBUTION: u32 = 0;
+const BEACON: u32 = 1;
+
+pub struct Contribution<E: ZKeyEngine> {
+    pub delta_after: E::G1Affine,
+    /// the proof of knowledge of x: a random s, s * x, and the hash to G2 of the transcript
+    /// times x
+    pub g1_s: E::G1Affine,
+    pub g1_sx: E::G1Affine,
+    pub g2_spx: E::G2Affine,
+    pub transcript: [u8; 64],
+    /// 0 for a contribution, 1 for the beacon
+    pub contribution_type: u32,
+    pub name: String,
+    /// the beacon hash and the log2 of its iterations
+    pub beacon: Option<(Vec<u8>, u32)>,
+}
+
+/// The section 10 of a zkey.
+pub struct MpcParams<E: ZKeyEngine> {
+    /// the hash of the initial key
+    pub cs_hash: [u8; 64],
+    pub contributions: Vec<Contribution<E>>,
+}
+
+fn blake2b(hasher: Blake2b) -> [u8; 64] {
+    let mut hash = [0u8; 64];
+    hash.copy_from_slice(&hasher.finalize());
+    hash
+}
+
+// the seed of snarkjs, the first 8 words of `hash` big endian
+fn chacha(hash: &[u8]) -> ChaChaRng {
+    let seed: Vec<u32> = hash[..32]
+        .chunks(4)
+        .map(|w| u32::from_be_bytes([w[0], w[1], w[2], w[3]]))
+        .collect();
+    ChaChaRng::from_seed(&seed)
+}
+
+fn hash_to_g2<E: ZKeyEngine>(transcript: &[u8; 64]) -> E::G2Affine {
+    E::G2::rand(&mut chacha(transcript)).into_affine()
+}
+
+// x, s and s * x
+fn sample_key<E: ZKeyEngine, R: Rng>(rng: &mut R) -> (E::Fr, E::G1Affine, E::G1Affine) {
+    let x = E::Fr::rand(rng);
+    let g1_s = E::G1::rand(rng).into_affine();
+    let g1_sx = g1_s.mul(x).into_affine();
+    (x, g1_s, g1_sx)
+}
+
+fn beacon_rng(beacon_hash: &[u8], iterations_exp: u32) -> Result<ChaChaRng> {
+    if !(10..=63).contains(&iterations_exp) {
+        bail!(
+            "The beacon iterations exponent must be between 10 and 63, got {}",
+            iterations_exp
+        );
+    }
+    if beacon_hash.is_empty() || beacon_hash.len() > 255 {
+        bail!("The beacon hash must have 1 to 255 bytes");
+    }
+    let mut hash = beacon_hash.to_vec();
+    for _ in 0..(1u64 << iterations_exp) {
+        hash = Sha256::digest(&hash).to_vec();
+    }
+    Ok(chacha(&hash))
+}
+
+// e(a1, b2) == e(b1, a2)
+fn same_ratio<E: Engine>(g1: (E::G1Affine, E::G1Affine), g2: (E::G2Affine, E::G2Affine)) -> bool {
+    E::pairing(g1.0, g2.1) == E::pairing(g1.1, g2.0)
+}
+
+// sum_i r_i * points_i for random r_i of 64 bits
+fn random_combination<G: CurveAffine>(points: &[G], r: &[u64]) -> G {
+    points
+        .par_iter()
+        .zip(r.par_iter())
+        .map(|(p, r)| p.mul(<G::Scalar as PrimeField>::Repr::from(*r)))
+        .reduce(G::Projective::zero, |mut a, b| {
+            a.add_assign(&b);
+            a
+        })
+        .into_affine()
+}
+
+fn scale<G: CurveAffine>(points: &mut [G], s: G::Scalar) {
+    points
+        .par_iter_mut()
+        .for_each(|p| *p = p.mul(s).into_affine());
+}
+
+fn take<'a>(params: &mut &'a [u8], len: usize) -> Result<&'a [u8]> {
+    if params.len() < len {
+        bail!("The contribution parameters are truncated");
+    }
+    let (value, rest) = params.split_at(len);
+    *params = rest;
+    Ok(value)
+}
+
+impl<E: ZKeyEngine> Contribution<E> {
+    fn read<R: Read>(reader: &mut R) -> Result<Self> {
+        let delta_after = read_g1::<E, _>(reader, true)?;
+        let g1_s = read_g1::<E, _>(reader, true)?;
+        let g1_sx = read_g1::<E, _>(reader, true)?;
+        let g2_spx = read_g2::<E, _>(reader, true)?;
+        let mut transcript = [0u8; 64];
+        reader.read_exact(&mut transcript)?;
+        let contribution_type = reader.read_u32::<LittleEndian>()?;
+        let mut params = vec![0u8; reader.read_u32::<LittleEndian>()? as usize];
+        reader.read_exact(&mut params)?;
+
+        let mut name = String::new();
+        let mut beacon_hash = None;
+        let mut iterations_exp = None;
+        let mut params = params.as_slice();
+        while !params.is_empty() {
+            let tag = take(&mut params, 1)?[0];
+            match tag {
+                1 | 3 => {
+                    let len = take(&mut params, 1)?[0] as usize;
+                    let value = take(&mut params, len)?;
+                    if tag == 1 {
+                        name = String::from_utf8_lossy(value).into_owned();
+                    } else {
+                        beacon_hash = Some(value.to_vec());
+                    }
+                }
+                2 => iterations_exp = Some(take(&mut params, 1)?[0] as u32),
+                _ => bail!("Unknown contribution parameter {}", tag),
+            }
+        }
+        Ok(Contribution {
+            delta_after,
+            g1_s,
+            g1_sx,
+            g2_spx,
+            transcript,
+            contribution_type,
+            name,
+            beacon: beacon_hash.zip(iterations_exp),
+        })
+    }
+
+    fn write<W: Write>(&self, writer: &mut W) -> Result<()> {
+        self.write_pub_key(writer)?;
+        writer.write_u32::<LittleEndian>(self.contribution_type)?;
+        let mut params = vec![];
+        if !self.name.is_empty() {
+            let mut end = self.name.len().min(64);
+            while !self.name.is_char_boundary(end) {
+                end -= 1;
+            }
+            params.extend_from_slice(&[1, end as u8]);
+            params.extend_from_slice(&self.name.as_bytes()[..end]);
+        }
+        if let Some((hash, iterations_exp)) = self.beacon.as_ref() {
+            params.extend_from_slice(&[2, *iterations_exp as u8, 3, hash.len() as u8]);
+            params.extend_from_slice(hash);
+        }
+        writer.write_u32::<LittleEndian>(params.len() as u32)?;
+        writer.write_all(&params)?;
+        Ok(())
+    }
+
+    fn write_pub_key<W: Write>(&self, writer: &mut W) -> Result<()> {
+        write_g1::<E, _>(writer, &self.delta_after)?;
+        write_g1::<E, _>(writer, &self.g1_s)?;
+        write_g1::<E, _>(writer, &self.g1_sx)?;
+        write_g2::<E, _>(writer, &self.g2_spx)?;
+        writer.write_all(&self.transcript)?;
+        Ok(())
+    }
+
+    /// The hash of the public key of the contribution, which the contributor publishes.
+    pub fn hash(&self) -> Result<[u8; 64]> {
+        let mut buf = vec![];
+        self.write_pub_key(&mut buf)?;
+        Ok(blake2b(Blake2b::new().chain(&buf)))
+    }
+}
+
+impl<E: ZKeyEngine> MpcParams<E> {
+    pub fn read(mut data: &[u8]) -> Result<Self> {
+        let reader = &mut data;
+        let mut cs_hash = [0u8; 64];
+        reader
+            .read_exact(&mut cs_hash)
+            .map_err(|_| anyhow!("The zkey has no contributions section"))?;
+        let n = reader.read_u32::<LittleEndian>()?;
+        let contributions = (0..n)
+            .map(|_| Contribution::read(reader))
+            .collect::<Result<Vec<_>>>()?;
+        if !reader.is_empty() {
+            bail!("The contributions section has trailing bytes");
+        }
+        Ok(MpcParams {
+            cs_hash,
+            contributions,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut buf = self.cs_hash.to_vec();
+        buf.write_u32::<LittleEndian>(self.contributions.len() as u32)?;
+        for c in self.contributions.iter() {
+            c.write(&mut buf)?;
+        }
+        Ok(buf)
+    }
+
+    // blake2b(cs_hash, the previous contributions, s, s * x)
+    fn transcript(
+        &self,
+        previous: usize,
+        g1_s: &E::G1Affine,
+        g1_sx: &E::G1Affine,
+    ) -> Result<[u8; 64]> {
+        let mut buf = self.cs_hash.to_vec();
+        for c in self.contributions[..previous].iter() {
+            c.write_pub_key(&mut buf)?;
+        }
+        write_g1::<E, _>(&mut buf, g1_s)?;
+        write_g1::<E, _>(&mut buf, g1_sx)?;
+        Ok(blake2b(Blake2b::new().chain(&buf)))
+    }
+}
+
+struct HashWriter(Blake2b);
+
+impl Write for HashWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+// the power of the ptau, whose prime must be the one of the curve
+fn read_ptau_power<E: ZKeyEngine, R: Read + Seek>(
+    reader: &mut R,
+    sections: &BTreeMap<u32, (u64, u64)>,
+) -> Result<u32> {
+    seek_section(reader, sections, PTAU_HEADER, "ptau")?;
+    let n8q = reader.read_u32::<LittleEndian>()? as usize;
+    let mut q = vec![0u8; n8q];
+    reader.read_exact(&mut q)?;
+    if q != prime_bytes::<E::Fq>()? {
+        bail!("The prime of the ptau is not the one of the curve");
+    }
+    Ok(reader.read_u32::<LittleEndian>()?)
+}
+
+/// tau^i of the phase 1 transcript `ptau_file`, in G1 for i < `g1_count` and in G2 for
+/// i < `g2_count`. The points are not checked, `snarkjs powersoftau verify` does it.
+pub(crate) fn read_ptau_powers<E: ZKeyEngine>(
+    ptau_file: &str,
+    g1_count: usize,
+    g2_count: usize,
+) -> Result<(Vec<E::G1Affine>, Vec<E::G2Affine>)> {
+    let file =
+        std::fs::File::open(ptau_file).map_err(|e| anyhow!("Open {}, {:?}", ptau_file, e))?;
+    let mut reader = BufReader::new(file);
+    let sections = read_sections(&mut reader, b"ptau", ptau_file)?;
+    let power = read_ptau_power::<E, _>(&mut reader, &sections)?;
+    let g1_size = 2 * n8::<E::Fq>() as u64;
+    let g1_len = seek_section(&mut reader, &sections, TAU_G1, "ptau")? / g1_size;
+    let g2_len = sections
+        .get(&TAU_G2)
+        .map_or(0, |(_, size)| size / (2 * g1_size));
+    if (g1_len as usize) < g1_count || (g2_len as usize) < g2_count {
+        bail!(
+            "{} of power {} has {} powers of tau in G1 and {} in G2, {} and {} are needed",
+            ptau_file,
+            power,
+            g1_len,
+            g2_len,
+            g1_count,
+            g2_count
+        );
+    }
+    let g1 = read_g1s::<E, _>(&mut reader, g1_count, false)?;
+    seek_section(&mut reader, &sections, TAU_G2, "ptau")?;
+    let g2 = (0..g2_count)
+        .map(|_| read_g2::<E, _>(&mut reader, false))
+        .collect::<Result<Vec<_>>>()?;
+    Ok((g1, g2))
+}
+
+/// The initial key of `r1cs` from the prepared phase 1 transcript `ptau_file`. The points
+/// of the ptau are not checked, `snarkjs powersoftau verify` does it.
+pub fn new_zkey<E: ZKeyEngine>(r1cs: &R1CS<E>, ptau_file: &str) -> Result<ZKey<E>> {
+    let file =
+        std::fs::File::open(ptau_file).map_err(|e| anyhow!("Open {}, {:?}", ptau_file, e))?;
+    let mut reader = BufReader::new(file);
+    let sections = read_sections(&mut reader, b"ptau", ptau_file)?;
+    for section in [
+        LAGRANGE_G1,
+        LAGRANGE_G2,
+        LAGRANGE_ALPHA_G1,
+        LAGRANGE_BETA_G1,
+    ] {
+        if !sections.contains_key(&section) {
+            bail!(
+                "{} is not prepared for phase 2, section {} is missing",
+                ptau_file,
+                section
+            );
+        }
+    }
+    let g1_size = 2 * n8::<E::Fq>() as u64;
+    let g2_size = 2 * g1_size;
+    // moves to the point `offset` of `section`, which must have `count` points from there
+    let seek = |reader: &mut BufReader<std::fs::File>,
+                section: u32,
+                offset: usize,
+                count: usize,
+                point_size: u64|
+     -> Result<()> {
+        let size = seek_section(reader, &sections, section, "ptau")?;
+        if (offset + count) as u64 * point_size > size {
+            bail!("ptau section {} is too short", section);
+        }
+        reader.seek_relative((offset as u64 * point_size) as i64)?;
+        Ok(())
+    };
+
+    let power = read_ptau_power::<E, _>(&mut reader, &sections)?;
+    let n = domain_size(r1cs);
+    if n > 1 << power {
+        bail!(
+            "The circuit needs a ptau of power {}, {} is of power {}",
+            n.trailing_zeros(),
+            ptau_file,
+            power
+        );
+    }
+
+    seek(&mut reader, ALPHA_TAU_G1, 0, 1, g1_size)?;
+    let alpha_g1 = read_g1::<E, _>(&mut reader, false)?;
+    seek(&mut reader, BETA_TAU_G1, 0, 1, g1_size)?;
+    let beta_g1 = read_g1::<E, _>(&mut reader, false)?;
+    seek(&mut reader, BETA_G2, 0, 1, g2_size)?;
+    let beta_g2 = read_g2::<E, _>(&mut reader, false)?;
+    // the Lagrange basis of size 2^p starts at the point 2^p - 1
+    seek(&mut reader, LAGRANGE_G1, n - 1, n, g1_size)?;
+    let l_tau = read_g1s::<E, _>(&mut reader, n, false)?;
+    seek(&mut reader, LAGRANGE_ALPHA_G1, n - 1, n, g1_size)?;
+    let l_alpha = read_g1s::<E, _>(&mut reader, n, false)?;
+    seek(&mut reader, LAGRANGE_BETA_G1, n - 1, n, g1_size)?;
+    let l_beta = read_g1s::<E, _>(&mut reader, n, false)?;
+    seek(&mut reader, LAGRANGE_G2, n - 1, n, g2_size)?;
+    let l_tau_g2 = (0..n)
+        .map(|_| read_g2::<E, _>(&mut reader, false))
+        .collect::<Result<Vec<_>>>()?;
+    // the odd points of the domain of size 2n
+    seek(&mut reader, LAGRANGE_G1, 2 * n - 1, 2 * n, g1_size)?;
+    let h = read_g1s::<E, _>(&mut reader, 2 * n, false)?
+        .into_iter()
+        .skip(1)
+        .step_by(2)
+        .collect();
+
+    // A and B over tau, and IC and C over beta * A + alpha * B + C
+    let coefs = r1cs_coefs(r1cs);
+    let mut terms = vec![vec![]; r1cs.num_variables];
+    for coef in coefs.iter() {
+        terms[coef.signal as usize].push((coef.matrix, coef.constraint as usize, coef.value));
+    }
+    for (i, (_, _, c)) in r1cs.constraints.iter().enumerate() {
+        for (s, v) in c.iter() {
+            terms[*s].push((2, i, *v));
+        }
+    }
+    let points: Vec<_> = terms
+        .par_iter()
+        .map(|terms| {
+            let (mut a, mut b_g1, mut b_g2, mut ic) =
+                (E::G1::zero(), E::G1::zero(), E::G2::zero(), E::G1::zero());
+            for (matrix, c, v) in terms.iter() {
+                match *matrix {
+                    0 => {
+                        a.add_assign(&l_tau[*c].mul(*v));
+                        ic.add_assign(&l_beta[*c].mul(*v));
+                    }
+                    1 => {
+                        b_g1.add_assign(&l_tau[*c].mul(*v));
+                        b_g2.add_assign(&l_tau_g2[*c].mul(*v));
+                        ic.add_assign(&l_alpha[*c].mul(*v));
+                    }
+                    _ => ic.add_assign(&l_tau[*c].mul(*v)),
+                }
+            }
+            (
+                a.into_affine(),
+                b_g1.into_affine(),
+                b_g2.into_affine(),
+                ic.into_affine(),
+            )
+        })
+        .collect();
+    let mut ic: Vec<_> = points.iter().map(|p| p.3).collect();
+    let c = ic.split_off(r1cs.num_inputs);
+
+    let mut zkey = ZKey {
+        n_vars: r1cs.num_variables,
+        n_public: r1cs.num_inputs - 1,
+        domain_size: n,
+        vk: VerifyingKey {
+            alpha_g1,
+            beta_g1,
+            beta_g2,
+            gamma_g2: E::G2Affine::one(),
+            delta_g1: E::G1Affine::one(),
+            delta_g2: E::G2Affine::one(),
+            ic,
+        },
+        coefs,
+        a: points.iter().map(|p| p.0).collect(),
+        b_g1: points.iter().map(|p| p.1).collect(),
+        b_g2: points.iter().map(|p| p.2).collect(),
+        c,
+        h,
+        contributions: vec![],
+    };
+    zkey.check_r1cs(r1cs)?;
+
+    let mut hasher = HashWriter(Blake2b::new());
+    zkey.write_to(&mut hasher)?;
+    zkey.contributions = MpcParams::<E> {
+        cs_hash: blake2b(hasher.0),
+        contributions: vec![],
+    }
+    .to_bytes()?;
+    Ok(zkey)
+}
+
+fn add_contribution<E: ZKeyEngine, R: Rng>(
+    zkey: &mut ZKey<E>,
+    rng: &mut R,
+    name: &str,
+    beacon: Option<(Vec<u8>, u32)>,
+) -> Result<[u8; 64]> {
+    let mut mpc = MpcParams::<E>::read(&zkey.contributions)?;
+    let (x, g1_s, g1_sx) = sample_key::<E, _>(rng);
+    let x_inv = x
+        .inverse()
+        .ok_or_else(|| anyhow!("The contributed secret is zero"))?;
+    let transcript = mpc.transcript(mpc.contributions.len(), &g1_s, &g1_sx)?;
+    let g2_spx = hash_to_g2::<E>(&transcript).mul(x).into_affine();
+
+    zkey.vk.delta_g1 = zkey.vk.delta_g1.mul(x).into_affine();
+    zkey.vk.delta_g2 = zkey.vk.delta_g2.mul(x).into_affine();
+    scale(&mut zkey.c, x_inv);
+    scale(&mut zkey.h, x_inv);
+
+    let contribution = Contribution {
+        delta_after: zkey.vk.delta_g1,
+        g1_s,
+        g1_sx,
+        g2_spx,
+        transcript,
+        contribution_type: if beacon.is_some() {
+            BEACON
+        } else {
+            CONTRIBUTION
+        },
+        name: name.to_string(),
+        beacon,
+    };
+    let hash = contribution.hash()?;
+    mpc.contributions.push(contribution);
+    zkey.contributions = mpc.to_bytes()?;
+    Ok(hash)
+}
+
+/// Multiplies delta by a secret drawn from the system randomness and `entropy`, and records
+/// the contribution. Returns the hash of the contribution.
+pub fn contribute<E: ZKeyEngine>(
+    zkey: &mut ZKey<E>,
+    entropy: &[u8],
+    name: &str,
+) -> Result<[u8; 64]> {
+    let mut random = [0u8; 64];
+    rand_old::thread_rng().fill_bytes(&mut random);
+    let seed = blake2b(Blake2b::new().chain(&random).chain(entropy));
+    add_contribution(zkey, &mut chacha(&seed), name, None)
+}
+
+/// The last contribution, with a secret anyone can derive from `beacon_hash` hashed
+/// 2^`iterations_exp` times with sha256.
+pub fn beacon<E: ZKeyEngine>(
+    zkey: &mut ZKey<E>,
+    beacon_hash: &[u8],
+    iterations_exp: u32,
+    name: &str,
+) -> Result<[u8; 64]> {
+    let mut rng = beacon_rng(beacon_hash, iterations_exp)?;
+    add_contribution(
+        zkey,
+        &mut rng,
+        name,
+        Some((beacon_hash.to_vec(), iterations_exp)),
+    )
+}
+
+/// Checks that `zkey` comes from the initial key of `r1cs` and `ptau_file` through its
+/// contributions, and returns them.
+pub fn verify_contributions<E: ZKeyEngine>(
+    r1cs: &R1CS<E>,
+    ptau_file: &str,
+    zkey: &ZKey<E>,
+) -> Result<MpcParams<E>> {
+    zkey.check_r1cs(r1cs)?;
+    let init = new_zkey(r1cs, ptau_file)?;
+    let mpc = MpcParams::<E>::read(&zkey.contributions)?;
+    if mpc.cs_hash != MpcParams::<E>::read(&init.contributions)?.cs_hash {
+        bail!("The zkey does not start from the R1CS and the ptau, the circuit hash differs");
+    }
+    let vk = &zkey.vk;
+    if vk.alpha_g1 != init.vk.alpha_g1
+        || vk.beta_g1 != init.vk.beta_g1
+        || vk.beta_g2 != init.vk.beta_g2
+        || vk.gamma_g2 != init.vk.gamma_g2
+        || vk.ic != init.vk.ic
+    {
+        bail!("alpha, beta, gamma or IC of the zkey differ from the initial key");
+    }
+    if zkey.a != init.a || zkey.b_g1 != init.b_g1 || zkey.b_g2 != init.b_g2 {
+        bail!("A or B of the zkey differ from the initial key");
+    }
+
+    let mut delta_before = E::G1Affine::one();
+    for (i, c) in mpc.contributions.iter().enumerate() {
+        let id = i + 1;
+        if c.delta_after.is_zero() || c.g1_s.is_zero() || c.g1_sx.is_zero() || c.g2_spx.is_zero() {
+            bail!("Contribution {}: a point of its key is zero", id);
+        }
+        if mpc.transcript(i, &c.g1_s, &c.g1_sx)? != c.transcript {
+            bail!(
+                "Contribution {}: the transcript does not follow the previous contributions",
+                id
+            );
+        }
+        let g2_sp = hash_to_g2::<E>(&c.transcript);
+        if !same_ratio::<E>((c.g1_s, c.g1_sx), (g2_sp, c.g2_spx)) {
+            bail!(
+                "Contribution {}: invalid proof of knowledge of the secret",
+                id
+            );
+        }
+        if !same_ratio::<E>((delta_before, c.delta_after), (g2_sp, c.g2_spx)) {
+            bail!("Contribution {}: delta is not multiplied by the secret", id);
+        }
+        match (c.contribution_type, c.beacon.as_ref()) {
+            (CONTRIBUTION, _) => {}
+            (BEACON, Some((hash, iterations_exp))) => {
+                let (_, g1_s, g1_sx) = sample_key::<E, _>(&mut beacon_rng(hash, *iterations_exp)?);
+                if g1_s != c.g1_s || g1_sx != c.g1_sx {
+                    bail!(
+                        "Contribution {}: the secret does not come from the beacon",
+                        id
+                    );
+                }
+            }
+            (t, _) => bail!("Contribution {}: invalid type {}", id, t),
+        }
+        delta_before = c.delta_after;
+    }
+
+    if vk.delta_g1 != delta_before {
+        bail!("delta of the zkey is not the one of the last contribution");
+    }
+    if !same_ratio::<E>(
+        (E::G1Affine::one(), vk.delta_g1),
+        (E::G2Affine::one(), vk.delta_g2),
+    ) {
+        bail!("delta in G1 and in G2 differ");
+    }
+    // C and H are the ones of the initial key over delta
+    let mut rng = rand_old::thread_rng();
+    for (name, points, init_points) in [("C", &zkey.c, &init.c), ("H", &zkey.h, &init.h)] {
+        let r: Vec<u64> = (0..points.len()).map(|_| rng.gen()).collect();
+        if !same_ratio::<E>(
+            (
+                random_combination(points, &r),
+                random_combination(init_points, &r),
+            ),
+            (E::G2Affine::one(), vk.delta_g2),
+        ) {
+            bail!(
+                "{} of the zkey is not the one of the initial key over delta",
+                name
+            );
+        }
+    }
+    Ok(mpc)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::api::{groth16_import_zkey, groth16_prove, groth16_verify, WitnessGenerator};
+    use crate::bellman_ce::{bls12_381::Bls12, bn256::Bn256};
+    use crate::zkey::tests::{
+        lagrange, zkey_for_setup, CIRCUIT_FILE, CIRCUIT_FILE_BLS12, INPUT_FILE, WASM_FILE,
+    };
+    use algebraic::reader::load_r1cs;
+
+    /// A prepared ptau of the toxic waste `tau`, `alpha` and `beta`, with only the points
+    /// `new_zkey` and `read_ptau_powers` read.
+    pub(crate) fn write_ptau<E: ZKeyEngine>(
+        file_path: &str,
+        power: u32,
+        tau: &E::Fr,
+        alpha: &E::Fr,
+        beta: &E::Fr,
+    ) {
+        let g1 = |s: E::Fr| E::G1Affine::one().mul(s).into_affine();
+        let g2 = |s: E::Fr| E::G2Affine::one().mul(s).into_affine();
+        let lagrange_g1 = |factor: &E::Fr, powers: u32| {
+            let mut buf = vec![];
+            for p in 0..=powers {
+                for j in 0..1 << p {
+                    let mut l = lagrange(1 << p, j, tau);
+                    l.mul_assign(factor);
+                    write_g1::<E, _>(&mut buf, &g1(l)).unwrap();
+                }
+            }
+            buf
+        };
+        let one = E::Fr::one();
+
+        let mut sections: Vec<(u32, Vec<u8>)> = vec![];
+        let mut buf = vec![];
+        buf.write_u32::<LittleEndian>(n8::<E::Fq>() as u32).unwrap();
+        buf.write_all(&prime_bytes::<E::Fq>().unwrap()).unwrap();
+        buf.write_u32::<LittleEndian>(power).unwrap();
+        buf.write_u32::<LittleEndian>(power).unwrap();
+        sections.push((PTAU_HEADER, buf));
+        let mut buf = vec![];
+        for i in 0..(2 << power) - 1 {
+            write_g1::<E, _>(&mut buf, &g1(tau.pow([i as u64]))).unwrap();
+        }
+        sections.push((TAU_G1, buf));
+        let mut buf = vec![];
+        for i in 0..1 << power {
+            write_g2::<E, _>(&mut buf, &g2(tau.pow([i as u64]))).unwrap();
+        }
+        sections.push((TAU_G2, buf));
+        let mut buf = vec![];
+        write_g1::<E, _>(&mut buf, &g1(*alpha)).unwrap();
+        sections.push((ALPHA_TAU_G1, buf));
+        let mut buf = vec![];
+        write_g1::<E, _>(&mut buf, &g1(*beta)).unwrap();
+        sections.push((BETA_TAU_G1, buf));
+        let mut buf = vec![];
+        write_g2::<E, _>(&mut buf, &g2(*beta)).unwrap();
+        sections.push((BETA_G2, buf));
+        sections.push((LAGRANGE_G1, lagrange_g1(&one, power + 1)));
+        let mut buf = vec![];
+        for p in 0..=power {
+            for j in 0..1 << p {
+                write_g2::<E, _>(&mut buf, &g2(lagrange(1 << p, j, tau))).unwrap();
+            }
+        }
+        sections.push((LAGRANGE_G2, buf));
+        sections.push((LAGRANGE_ALPHA_G1, lagrange_g1(alpha, power)));
+        sections.push((LAGRANGE_BETA_G1, lagrange_g1(beta, power)));
+
+        let mut file = std::fs::File::create(file_path).unwrap();
+        file.write_all(b"ptau").unwrap();
+        file.write_u32::<LittleEndian>(1).unwrap();
+        file.write_u32::<LittleEndian>(sections.len() as u32)
+            .unwrap();
+        for (section_type, data) in sections.iter() {
+            file.write_u32::<LittleEndian>(*section_type).unwrap();
+            file.write_u64::<LittleEndian>(data.len() as u64).unwrap();
+            file.write_all(data).unwrap();
+        }
+    }
+
+    fn check_ceremony<E: ZKeyEngine>(circuit_file: &str, ptau_file: &str, zkey_file: &str) {
+        let r1cs = load_r1cs::<E>(circuit_file);
+        let rng = &mut rand_old::thread_rng();
+        let (tau, alpha, beta) = (E::Fr::rand(rng), E::Fr::rand(rng), E::Fr::rand(rng));
+        let power = domain_size(&r1cs).trailing_zeros();
+        write_ptau::<E>(ptau_file, power, &tau, &alpha, &beta);
+
+        // the key snarkjs makes for the toxic waste, with gamma = delta = 1
+        let zkey = new_zkey(&r1cs, ptau_file).unwrap();
+        let one = E::Fr::one();
+        let expected = zkey_for_setup(&r1cs, alpha, beta, one, one, tau);
+        assert!(zkey.vk == expected.vk);
+        assert_eq!(zkey.coefs, expected.coefs);
+        assert_eq!(zkey.a, expected.a);
+        assert_eq!(zkey.b_g1, expected.b_g1);
+        assert_eq!(zkey.b_g2, expected.b_g2);
+        assert_eq!(zkey.c, expected.c);
+        assert_eq!(zkey.h, expected.h);
+        let mpc = verify_contributions(&r1cs, ptau_file, &zkey).unwrap();
+        assert!(mpc.contributions.is_empty());
+
+        let mut zkey = zkey;
+        let hash = contribute(&mut zkey, b"some entropy", "first").unwrap();
+        contribute(&mut zkey, b"", "second").unwrap();
+        beacon(&mut zkey, &[0xab; 32], 10, "beacon").unwrap();
+        zkey.write(zkey_file).unwrap();
+        let zkey = ZKey::<E>::read(zkey_file, true).unwrap();
+        let mpc = verify_contributions(&r1cs, ptau_file, &zkey).unwrap();
+        assert_eq!(mpc.contributions.len(), 3);
+        assert_eq!(mpc.contributions[0].hash().unwrap(), hash);
+        assert_eq!(mpc.contributions[1].name, "second");
+        assert_eq!(mpc.contributions[2].contribution_type, BEACON);
+        assert_eq!(mpc.contributions[2].beacon, Some((vec![0xab; 32], 10)));
+
+        let mut wrong = ZKey::<E>::read(zkey_file, true).unwrap();
+        wrong.c[0] = E::G1Affine::one();
+        assert!(verify_contributions(&r1cs, ptau_file, &wrong).is_err());
+        let mut wrong = ZKey::<E>::read(zkey_file, true).unwrap();
+        wrong.vk.delta_g1 = E::G1Affine::one();
+        assert!(verify_contributions(&r1cs, ptau_file, &wrong).is_err());
+        // the beacon of another hash
+        let mut wrong = ZKey::<E>::read(zkey_file, true).unwrap();
+        let mut mpc = MpcParams::<E>::read(&wrong.contributions).unwrap();
+        mpc.contributions[2].beacon = Some((vec![0xcd; 32], 10));
+        wrong.contributions = mpc.to_bytes().unwrap();
+        assert!(verify_contributions(&r1cs, ptau_file, &wrong).is_err());
+    }
+
+    #[test]
+    fn test_ceremony_bn128() {
+        check_ceremony::<Bn256>(
+            CIRCUIT_FILE,
+            "/tmp/multiplier.ptau",
+            "/tmp/multiplier_mpc.zkey",
+        );
+
+        groth16_import_zkey(
+            "BN128",
+            "/tmp/multiplier_mpc.zkey",
+            CIRCUIT_FILE,
+            "/tmp/multiplier_mpc.pk",
+            "/tmp/multiplier_mpc.vk.json",
+            false,
+        )
+        .unwrap();
+        groth16_prove(
+            "BN128",
+            CIRCUIT_FILE,
+            WASM_FILE,
+            WitnessGenerator::Wasm,
+            "/tmp/multiplier_mpc.zkey",
+            INPUT_FILE,
+            "/tmp/multiplier_mpc.public.json",
+            "/tmp/multiplier_mpc.proof.json",
+            false,
+        )
+        .unwrap();
+        groth16_verify(
+            "BN128",
+            "/tmp/multiplier_mpc.vk.json",
+            "/tmp/multiplier_mpc.public.json",
+            "/tmp/multiplier_mpc.proof.json",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_ceremony_bls12381() {
+        check_ceremony::<Bls12>(
+            CIRCUIT_FILE_BLS12,
+            "/tmp/mycircuit_bls12381.ptau",
+            "/tmp/mycircuit_bls12381_mpc.zkey",
+        );
+    }
+}
@@ -0,0 +1,69 @@
+//! The proof.json of `groth16_prove` and the decoding of its numbers. The file is also the
+//! src/proof_json.rs of the package of `api::generate_wasm_verifier`, so it only depends on
+//! serde, num-bigint and num-traits.
+use num_bigint::BigUint;
+use num_traits::Num;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct G1 {
+    pub x: String,
+    pub y: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct G2 {
+    pub x: [String; 2],
+    pub y: [String; 2],
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProofFile {
+    #[serde(rename = "pi_a")]
+    pub a: G1,
+    #[serde(rename = "pi_b")]
+    pub b: G2,
+    #[serde(rename = "pi_c")]
+    pub c: G1,
+    #[serde(rename = "protocol")]
+    pub protocol: String,
+    #[serde(rename = "curve")]
+    pub curve: String,
+}
+
+impl ProofFile {
+    /// The coordinates as a.x, a.y, b.x[0], b.x[1], b.y[0], b.y[1], c.x, c.y, the order of
+    /// `to_proof`, of the calldata of the Solidity verifier and of the Rust verifier.
+    pub fn words(&self) -> [&str; 8] {
+        [
+            &self.a.x,
+            &self.a.y,
+            &self.b.x[0],
+            &self.b.x[1],
+            &self.b.y[0],
+            &self.b.y[1],
+            &self.c.x,
+            &self.c.y,
+        ]
+    }
+}
+
+/// A decimal or 0x prefixed hex number of proof.json, or of the public input json.
+pub fn parse_number(s: &str) -> Result<BigUint, String> {
+    match s.strip_prefix("0x") {
+        Some(hex) => BigUint::from_str_radix(hex, 16),
+        None => BigUint::from_str_radix(s, 10),
+    }
+    .map_err(|_| format!("invalid number: {}", s))
+}
+
+/// A decimal or 0x prefixed hex number as `N` big-endian bytes.
+pub fn to_be_bytes<const N: usize>(s: &str) -> Result<[u8; N], String> {
+    let be = parse_number(s)?.to_bytes_be();
+    if be.len() > N {
+        return Err(format!("{} does not fit in {} bytes", s, N));
+    }
+    let mut bytes = [0u8; N];
+    bytes[N - be.len()..].copy_from_slice(&be);
+    Ok(bytes)
+}
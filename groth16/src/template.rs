@@ -52,3 +52,1016 @@ contract Verifier {
     }
 }
 "#;
+
+// A gas optimized verifier on BN128 with the ABI of the snarkjs verifiers. The key is
+// hardcoded as constants, and the proof and inputs are read from calldata by inline assembly
+// calling the precompiles 0x06 (add), 0x07 (mul) and 0x08 (pairing). The G2 coordinates are
+// in the order of the precompile, b is [[x.c1, x.c0], [y.c1, y.c0]].
+pub const ASSEMBLY_CONTRACT_TEMPLATE: &str = r#"// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.16;
+
+contract Verifier {
+    // Scalar field size
+    uint256 constant r = 21888242871839275222246405745257275088548364400416034343698204186575808495617;
+    // Base field size
+    uint256 constant q = 21888242871839275222246405745257275088696311157297823662689037894645226208583;
+
+    // Verification key
+<%vk_constants%>
+
+    // Memory layout
+    uint16 constant pVk = 0;
+    uint16 constant pPairing = 128;
+    uint16 constant pLastMem = 896;
+
+    function verifyProof(
+        uint[2] calldata _pA,
+        uint[2][2] calldata _pB,
+        uint[2] calldata _pC<%pub_signals_argument%>
+    ) public view returns (bool) {
+        assembly {
+            function checkField(v) {
+                if iszero(lt(v, r)) {
+                    mstore(0, 0)
+                    return(0, 0x20)
+                }
+            }
+
+            // pR += (x, y) * s
+            function g1_mulAccC(pR, x, y, s) {
+                let success
+                let mIn := mload(0x40)
+                mstore(mIn, x)
+                mstore(add(mIn, 32), y)
+                mstore(add(mIn, 64), s)
+
+                success := staticcall(sub(gas(), 2000), 7, mIn, 96, mIn, 64)
+                if iszero(success) {
+                    mstore(0, 0)
+                    return(0, 0x20)
+                }
+
+                mstore(add(mIn, 64), mload(pR))
+                mstore(add(mIn, 96), mload(add(pR, 32)))
+
+                success := staticcall(sub(gas(), 2000), 6, mIn, 128, pR, 64)
+                if iszero(success) {
+                    mstore(0, 0)
+                    return(0, 0x20)
+                }
+            }
+
+            // e(-A, B) * e(alpha, beta) * e(vk_x, gamma) * e(C, delta) == 1
+            function checkPairing(pA, pB, pC, pubSignals, pMem) -> isOk {
+                let _pPairing := add(pMem, pPairing)
+                let _pVk := add(pMem, pVk)
+
+                mstore(_pVk, IC0x)
+                mstore(add(_pVk, 32), IC0y)
+
+                // Compute the linear combination vk_x
+<%g1_mul_acc%>
+
+                // -A
+                mstore(_pPairing, calldataload(pA))
+                mstore(add(_pPairing, 32), mod(sub(q, calldataload(add(pA, 32))), q))
+
+                // B
+                mstore(add(_pPairing, 64), calldataload(pB))
+                mstore(add(_pPairing, 96), calldataload(add(pB, 32)))
+                mstore(add(_pPairing, 128), calldataload(add(pB, 64)))
+                mstore(add(_pPairing, 160), calldataload(add(pB, 96)))
+
+                // alpha
+                mstore(add(_pPairing, 192), alphax)
+                mstore(add(_pPairing, 224), alphay)
+
+                // beta
+                mstore(add(_pPairing, 256), betax1)
+                mstore(add(_pPairing, 288), betax2)
+                mstore(add(_pPairing, 320), betay1)
+                mstore(add(_pPairing, 352), betay2)
+
+                // vk_x
+                mstore(add(_pPairing, 384), mload(add(pMem, pVk)))
+                mstore(add(_pPairing, 416), mload(add(pMem, add(pVk, 32))))
+
+                // gamma
+                mstore(add(_pPairing, 448), gammax1)
+                mstore(add(_pPairing, 480), gammax2)
+                mstore(add(_pPairing, 512), gammay1)
+                mstore(add(_pPairing, 544), gammay2)
+
+                // C
+                mstore(add(_pPairing, 576), calldataload(pC))
+                mstore(add(_pPairing, 608), calldataload(add(pC, 32)))
+
+                // delta
+                mstore(add(_pPairing, 640), deltax1)
+                mstore(add(_pPairing, 672), deltax2)
+                mstore(add(_pPairing, 704), deltay1)
+                mstore(add(_pPairing, 736), deltay2)
+
+                let success := staticcall(sub(gas(), 2000), 8, _pPairing, 768, _pPairing, 0x20)
+
+                isOk := and(success, mload(_pPairing))
+            }
+
+            let pMem := mload(0x40)
+            mstore(0x40, add(pMem, pLastMem))
+
+            // Validate that all the inputs are in the scalar field
+<%check_fields%>
+
+            let isValid := checkPairing(_pA, _pB, _pC, <%pub_signals%>, pMem)
+
+            mstore(0, isValid)
+            return(0, 0x20)
+        }
+    }
+}
+"#;
+
+// The verifier of `aggregation::AggregateProof` on BN128. The precompiles only check that a
+// pairing product is one, while the aggregation compares elements of GT, so library Gt does
+// the arithmetic of Fq12, the Miller loop and the final exponentiation in the EVM. It costs
+// far more gas than a groth16 verifier and suits eth_call or chains with a high gas limit.
+pub const AGGREGATE_CONTRACT_TEMPLATE: &str = r#"
+// Fq12 = Fq6[w] / (w^2 - v), Fq6 = Fq2[v] / (v^3 - xi), Fq2 = Fq[u] / (u^2 + 1), xi = 9 + u.
+// An element is 12 words c0.c0.c0, c0.c0.c1, c0.c1.c0, ..., c1.c2.c1, the word 6 * (k % 2) +
+// 2 * (k / 2) is the coefficient of w^k. The functions write their result to the last
+// argument, which may be one of the inputs, and give back the memory of their temporaries.
+library Gt {
+    uint256 constant Q = 21888242871839275222246405745257275088696311157297823662689037894645226208583;
+    uint256 constant R = 21888242871839275222246405745257275088548364400416034343698204186575808495617;
+    uint256 constant U = 4965661367192848881;
+    uint256 constant ATE_LOOP_COUNT = 29793968203157093288;
+
+    function freeMemory() internal pure returns (uint256 p) {
+        assembly { p := mload(0x40) }
+    }
+    function restoreMemory(uint256 p) internal pure {
+        assembly { mstore(0x40, p) }
+    }
+    function expMod(uint256 base, uint256 e, uint256 m) internal view returns (uint256 r) {
+        bool success;
+        assembly {
+            let p := mload(0x40)
+            mstore(p, 0x20)
+            mstore(add(p, 0x20), 0x20)
+            mstore(add(p, 0x40), 0x20)
+            mstore(add(p, 0x60), base)
+            mstore(add(p, 0x80), e)
+            mstore(add(p, 0xa0), m)
+            success := staticcall(gas(), 5, p, 0xc0, p, 0x20)
+            r := mload(p)
+        }
+        require(success);
+    }
+    function neg(uint256 a) internal pure returns (uint256) {
+        return a == 0 ? 0 : Q - a;
+    }
+
+    function f2add(uint256 a0, uint256 a1, uint256 b0, uint256 b1) internal pure returns (uint256, uint256) {
+        return (addmod(a0, b0, Q), addmod(a1, b1, Q));
+    }
+    function f2sub(uint256 a0, uint256 a1, uint256 b0, uint256 b1) internal pure returns (uint256, uint256) {
+        return (addmod(a0, Q - b0, Q), addmod(a1, Q - b1, Q));
+    }
+    function f2mul(uint256 a0, uint256 a1, uint256 b0, uint256 b1) internal pure returns (uint256, uint256) {
+        return (
+            addmod(mulmod(a0, b0, Q), Q - mulmod(a1, b1, Q), Q),
+            addmod(mulmod(a0, b1, Q), mulmod(a1, b0, Q), Q)
+        );
+    }
+    function f2mulXi(uint256 a0, uint256 a1) internal pure returns (uint256, uint256) {
+        return (addmod(mulmod(a0, 9, Q), Q - a1, Q), addmod(a0, mulmod(a1, 9, Q), Q));
+    }
+    function f2inverse(uint256 a0, uint256 a1) internal view returns (uint256, uint256) {
+        uint256 t = expMod(addmod(mulmod(a0, a0, Q), mulmod(a1, a1, Q), Q), Q - 2, Q);
+        return (mulmod(a0, t, Q), mulmod(neg(a1), t, Q));
+    }
+
+    // r[k..k + 6] = a[i..i + 6] * b[j..j + 6], r is not a nor b
+    function fq6Mul(uint256[12] memory a, uint256 i, uint256[12] memory b, uint256 j, uint256[12] memory r, uint256 k) internal pure {
+        uint256 x0;
+        uint256 x1;
+        uint256 y0;
+        uint256 y1;
+        // c0 = a0 b0 + xi (a1 b2 + a2 b1)
+        (x0, x1) = f2mul(a[i + 2], a[i + 3], b[j + 4], b[j + 5]);
+        (y0, y1) = f2mul(a[i + 4], a[i + 5], b[j + 2], b[j + 3]);
+        (x0, x1) = f2mulXi(addmod(x0, y0, Q), addmod(x1, y1, Q));
+        (y0, y1) = f2mul(a[i], a[i + 1], b[j], b[j + 1]);
+        (r[k], r[k + 1]) = f2add(x0, x1, y0, y1);
+        // c1 = a0 b1 + a1 b0 + xi a2 b2
+        (x0, x1) = f2mul(a[i + 4], a[i + 5], b[j + 4], b[j + 5]);
+        (x0, x1) = f2mulXi(x0, x1);
+        (y0, y1) = f2mul(a[i], a[i + 1], b[j + 2], b[j + 3]);
+        (x0, x1) = f2add(x0, x1, y0, y1);
+        (y0, y1) = f2mul(a[i + 2], a[i + 3], b[j], b[j + 1]);
+        (r[k + 2], r[k + 3]) = f2add(x0, x1, y0, y1);
+        // c2 = a0 b2 + a2 b0 + a1 b1
+        (x0, x1) = f2mul(a[i], a[i + 1], b[j + 4], b[j + 5]);
+        (y0, y1) = f2mul(a[i + 4], a[i + 5], b[j], b[j + 1]);
+        (x0, x1) = f2add(x0, x1, y0, y1);
+        (y0, y1) = f2mul(a[i + 2], a[i + 3], b[j + 2], b[j + 3]);
+        (r[k + 4], r[k + 5]) = f2add(x0, x1, y0, y1);
+    }
+    // r[k..k + 6] = 1 / a[i..i + 6], r is not a
+    function fq6Inverse(uint256[12] memory a, uint256 i, uint256[12] memory r, uint256 k) internal view {
+        uint256 x0;
+        uint256 x1;
+        uint256 y0;
+        uint256 y1;
+        // t0 = a0^2 - xi a1 a2, t1 = xi a2^2 - a0 a1, t2 = a1^2 - a0 a2
+        (x0, x1) = f2mul(a[i], a[i + 1], a[i], a[i + 1]);
+        (y0, y1) = f2mul(a[i + 2], a[i + 3], a[i + 4], a[i + 5]);
+        (y0, y1) = f2mulXi(y0, y1);
+        (r[k], r[k + 1]) = f2sub(x0, x1, y0, y1);
+        (x0, x1) = f2mul(a[i + 4], a[i + 5], a[i + 4], a[i + 5]);
+        (x0, x1) = f2mulXi(x0, x1);
+        (y0, y1) = f2mul(a[i], a[i + 1], a[i + 2], a[i + 3]);
+        (r[k + 2], r[k + 3]) = f2sub(x0, x1, y0, y1);
+        (x0, x1) = f2mul(a[i + 2], a[i + 3], a[i + 2], a[i + 3]);
+        (y0, y1) = f2mul(a[i], a[i + 1], a[i + 4], a[i + 5]);
+        (r[k + 4], r[k + 5]) = f2sub(x0, x1, y0, y1);
+        // 1 / (a0 t0 + xi (a2 t1 + a1 t2))
+        (x0, x1) = f2mul(a[i + 4], a[i + 5], r[k + 2], r[k + 3]);
+        (y0, y1) = f2mul(a[i + 2], a[i + 3], r[k + 4], r[k + 5]);
+        (x0, x1) = f2mulXi(addmod(x0, y0, Q), addmod(x1, y1, Q));
+        (y0, y1) = f2mul(a[i], a[i + 1], r[k], r[k + 1]);
+        (x0, x1) = f2inverse(addmod(x0, y0, Q), addmod(x1, y1, Q));
+        (r[k], r[k + 1]) = f2mul(r[k], r[k + 1], x0, x1);
+        (r[k + 2], r[k + 3]) = f2mul(r[k + 2], r[k + 3], x0, x1);
+        (r[k + 4], r[k + 5]) = f2mul(r[k + 4], r[k + 5], x0, x1);
+    }
+
+    function one() internal pure returns (uint256[12] memory r) {
+        r[0] = 1;
+    }
+    function copy(uint256[12] memory a, uint256[12] memory r) internal pure {
+        for (uint256 i = 0; i < 12; i++) {
+            r[i] = a[i];
+        }
+    }
+    function equal(uint256[12] memory a, uint256[12] memory b) internal pure returns (bool) {
+        for (uint256 i = 0; i < 12; i++) {
+            if (a[i] != b[i]) {
+                return false;
+            }
+        }
+        return true;
+    }
+    // (a0 + a1 w) (b0 + b1 w) = a0 b0 + a1 b1 v + (a0 b1 + a1 b0) w
+    function mul(uint256[12] memory a, uint256[12] memory b, uint256[12] memory r) internal pure {
+        uint256 fmp = freeMemory();
+        uint256[12] memory t;
+        uint256[12] memory s;
+        fq6Mul(a, 0, b, 0, t, 0);
+        fq6Mul(a, 6, b, 6, t, 6);
+        fq6Mul(a, 0, b, 6, s, 0);
+        fq6Mul(a, 6, b, 0, s, 6);
+        (r[0], r[1]) = f2mulXi(t[10], t[11]);
+        (r[0], r[1]) = f2add(r[0], r[1], t[0], t[1]);
+        for (uint256 i = 2; i < 6; i++) {
+            r[i] = addmod(t[i], t[i + 4], Q);
+        }
+        for (uint256 i = 0; i < 6; i++) {
+            r[i + 6] = addmod(s[i], s[i + 6], Q);
+        }
+        restoreMemory(fmp);
+    }
+    function conjugate(uint256[12] memory a, uint256[12] memory r) internal pure {
+        for (uint256 i = 0; i < 6; i++) {
+            r[i] = a[i];
+            r[i + 6] = neg(a[i + 6]);
+        }
+    }
+    // 1 / (a0 + a1 w) = (a0 - a1 w) / (a0^2 - a1^2 v)
+    function inverse(uint256[12] memory a, uint256[12] memory r) internal view {
+        uint256 fmp = freeMemory();
+        uint256[12] memory t;
+        uint256[12] memory s;
+        fq6Mul(a, 0, a, 0, t, 0);
+        fq6Mul(a, 6, a, 6, t, 6);
+        (s[0], s[1]) = f2mulXi(t[10], t[11]);
+        (s[0], s[1]) = f2sub(t[0], t[1], s[0], s[1]);
+        for (uint256 i = 2; i < 6; i++) {
+            s[i] = addmod(t[i], Q - t[i + 4], Q);
+        }
+        fq6Inverse(s, 0, t, 0);
+        fq6Mul(a, 0, t, 0, s, 0);
+        fq6Mul(a, 6, t, 0, s, 6);
+        conjugate(s, r);
+        restoreMemory(fmp);
+    }
+    // xi^(k (p^power - 1) / 6) for k < 6
+    function gammas(uint256 power) internal pure returns (uint256[12] memory) {
+        if (power == 1) {
+            return [uint256(1), 0,
+                8376118865763821496583973867626364092589906065868298776909617916018768340080,
+                16469823323077808223889137241176536799009286646108169935659301613961712198316,
+                21575463638280843010398324269430826099269044274347216827212613867836435027261,
+                10307601595873709700152284273816112264069230130616436755625194854815875713954,
+                2821565182194536844548159561693502659359617185244120367078079554186484126554,
+                3505843767911556378687030309984248845540243509899259641013678093033130930403,
+                2581911344467009335267311115468803099551665605076196740867805258568234346338,
+                19937756971775647987995932169929341994314640652964949448313374472400716661030,
+                685108087231508774477564247770172212460312782337200605669322048753928464687,
+                8447204650696766136447902020341177575205426561248465145919723016860428151883];
+        }
+        if (power == 2) {
+            return [uint256(1), 0,
+                21888242871839275220042445260109153167277707414472061641714758635765020556617, 0,
+                21888242871839275220042445260109153167277707414472061641714758635765020556616, 0,
+                21888242871839275222246405745257275088696311157297823662689037894645226208582, 0,
+                2203960485148121921418603742825762020974279258880205651966, 0,
+                2203960485148121921418603742825762020974279258880205651967, 0];
+        }
+        require(power == 3);
+        return [uint256(1), 0,
+            11697423496358154304825782922584725312912383441159505038794027105778954184319,
+            303847389135065887422783454877609941456349188919719272345083954437860409601,
+            3772000881919853776433695186713858239009073593817195771773381919316419345261,
+            2236595495967245188281701248203181795121068902605861227855261137820944008926,
+            19066677689644738377698246183563772429336693972053703295610958340458742082029,
+            18382399103927718843559375435273026243156067647398564021675359801612095278180,
+            5324479202449903542726783395506214481928257762400643279780343368557297135718,
+            16208900380737693084919495127334387981393726419856888799917914180988844123039,
+            8941241848238582420466759817324047081148088512956452953208002715982955420483,
+            10338197737521362862238855242243140895517409139741313354160881284257516364953];
+    }
+    // a^(p^power), the coefficients of w^k are conjugated for an odd power and times gamma_k
+    function frobenius(uint256[12] memory a, uint256 power, uint256[12] memory r) internal pure {
+        uint256 fmp = freeMemory();
+        uint256[12] memory g = gammas(power);
+        for (uint256 k = 0; k < 6; k++) {
+            uint256 p = 6 * (k % 2) + 2 * (k / 2);
+            uint256 c1 = power % 2 == 1 ? neg(a[p + 1]) : a[p + 1];
+            (r[p], r[p + 1]) = f2mul(a[p], c1, g[2 * k], g[2 * k + 1]);
+        }
+        restoreMemory(fmp);
+    }
+    function pow(uint256[12] memory a, uint256 e, uint256[12] memory r) internal pure {
+        uint256 fmp = freeMemory();
+        uint256[12] memory t = one();
+        uint256 i = 256;
+        while (i > 0 && (e >> (i - 1)) == 0) {
+            i--;
+        }
+        for (; i > 0; i--) {
+            mul(t, t, t);
+            if ((e >> (i - 1)) & 1 == 1) {
+                mul(t, a, t);
+            }
+        }
+        copy(t, r);
+        restoreMemory(fmp);
+    }
+
+    function isZero(Pairing.G1Point memory p, Pairing.G2Point memory q) internal pure returns (bool) {
+        return (p.X == 0 && p.Y == 0) || (q.X[0] == 0 && q.X[1] == 0 && q.Y[0] == 0 && q.Y[1] == 0);
+    }
+    // 3 x^2 / 2 y at T = t[i..i + 4]
+    function tangentSlope(uint256[] memory t, uint256 i) internal view returns (uint256, uint256) {
+        (uint256 n0, uint256 n1) = f2mul(t[i], t[i + 1], t[i], t[i + 1]);
+        (uint256 d0, uint256 d1) = f2inverse(addmod(t[i + 2], t[i + 2], Q), addmod(t[i + 3], t[i + 3], Q));
+        return f2mul(mulmod(n0, 3, Q), mulmod(n1, 3, Q), d0, d1);
+    }
+    // the slope from T = t[i..i + 4] to (qx, qy)
+    function chordSlope(uint256[] memory t, uint256 i, uint256 qx0, uint256 qx1, uint256 qy0, uint256 qy1) internal view returns (uint256, uint256) {
+        (uint256 n0, uint256 n1) = f2sub(qy0, qy1, t[i + 2], t[i + 3]);
+        (uint256 d0, uint256 d1) = f2sub(qx0, qx1, t[i], t[i + 1]);
+        (d0, d1) = f2inverse(d0, d1);
+        return f2mul(n0, n1, d0, d1);
+    }
+    // the line through T with slope l at P, y_P - l x_P w + (l x_T - y_T) w^3
+    function evalLine(uint256[12] memory line, Pairing.G1Point memory p, uint256[] memory t, uint256 i, uint256 l0, uint256 l1) internal pure {
+        line[0] = p.Y;
+        line[6] = mulmod(neg(l0), p.X, Q);
+        line[7] = mulmod(neg(l1), p.X, Q);
+        (uint256 x0, uint256 x1) = f2mul(l0, l1, t[i], t[i + 1]);
+        (line[8], line[9]) = f2sub(x0, x1, t[i + 2], t[i + 3]);
+    }
+    // T = T + Q where l is the slope from T to Q
+    function moveT(uint256[] memory t, uint256 i, uint256 l0, uint256 l1, uint256 qx0, uint256 qx1) internal pure {
+        (uint256 x0, uint256 x1) = f2mul(l0, l1, l0, l1);
+        (x0, x1) = f2sub(x0, x1, t[i], t[i + 1]);
+        (x0, x1) = f2sub(x0, x1, qx0, qx1);
+        (uint256 y0, uint256 y1) = f2sub(t[i], t[i + 1], x0, x1);
+        (y0, y1) = f2mul(l0, l1, y0, y1);
+        (y0, y1) = f2sub(y0, y1, t[i + 2], t[i + 3]);
+        (t[i], t[i + 1], t[i + 2], t[i + 3]) = (x0, x1, y0, y1);
+    }
+    function lineStep(uint256[12] memory f, Pairing.G1Point memory p, uint256[] memory t, uint256 i, uint256 l0, uint256 l1, uint256 qx0, uint256 qx1) internal pure {
+        uint256 fmp = freeMemory();
+        uint256[12] memory line;
+        evalLine(line, p, t, i, l0, l1);
+        mul(f, line, f);
+        moveT(t, i, l0, l1, qx0, qx1);
+        restoreMemory(fmp);
+    }
+    // the image of q by the Frobenius map of the twist, power 1 or 2
+    function twistFrobenius(Pairing.G2Point memory q, uint256 power) internal pure returns (uint256[4] memory r) {
+        uint256[12] memory g = gammas(power);
+        if (power == 1) {
+            (r[0], r[1]) = f2mul(q.X[0], neg(q.X[1]), g[4], g[5]);
+            (r[2], r[3]) = f2mul(q.Y[0], neg(q.Y[1]), g[6], g[7]);
+        } else {
+            (r[0], r[1]) = f2mul(q.X[0], q.X[1], g[4], g[5]);
+            (r[2], r[3]) = f2mul(q.Y[0], q.Y[1], g[6], g[7]);
+            (r[2], r[3]) = (neg(r[2]), neg(r[3]));
+        }
+    }
+    // the optimal ate Miller loop of 6u + 2 over the pairs, with affine lines
+    function millerLoop(Pairing.G1Point[] memory p, Pairing.G2Point[] memory q, uint256[12] memory f) internal view {
+        uint256[] memory t = new uint256[](4 * p.length);
+        for (uint256 i = 0; i < p.length; i++) {
+            (t[4 * i], t[4 * i + 1], t[4 * i + 2], t[4 * i + 3]) = (q[i].X[0], q[i].X[1], q[i].Y[0], q[i].Y[1]);
+        }
+        f[0] = 1;
+        for (uint256 b = 64; b > 0; b--) {
+            mul(f, f, f);
+            for (uint256 i = 0; i < p.length; i++) {
+                if (isZero(p[i], q[i])) {
+                    continue;
+                }
+                (uint256 l0, uint256 l1) = tangentSlope(t, 4 * i);
+                lineStep(f, p[i], t, 4 * i, l0, l1, t[4 * i], t[4 * i + 1]);
+                if ((ATE_LOOP_COUNT >> (b - 1)) & 1 == 1) {
+                    (l0, l1) = chordSlope(t, 4 * i, q[i].X[0], q[i].X[1], q[i].Y[0], q[i].Y[1]);
+                    lineStep(f, p[i], t, 4 * i, l0, l1, q[i].X[0], q[i].X[1]);
+                }
+            }
+        }
+        for (uint256 i = 0; i < p.length; i++) {
+            if (isZero(p[i], q[i])) {
+                continue;
+            }
+            for (uint256 power = 1; power <= 2; power++) {
+                uint256[4] memory q1 = twistFrobenius(q[i], power);
+                (uint256 l0, uint256 l1) = chordSlope(t, 4 * i, q1[0], q1[1], q1[2], q1[3]);
+                lineStep(f, p[i], t, 4 * i, l0, l1, q1[0], q1[1]);
+            }
+        }
+    }
+    // f^((p^4 - p^2 + 1) / r), see Scott et al., On the final exponentiation for calculating
+    // pairings on ordinary elliptic curves
+    function hardPart(uint256[12] memory f) internal pure returns (uint256[12] memory) {
+        uint256[12][10] memory y;
+        // y7 = f^u, y8 = f^(u^2), y9 = f^(u^3)
+        pow(f, U, y[7]);
+        pow(y[7], U, y[8]);
+        pow(y[8], U, y[9]);
+        frobenius(f, 1, y[0]);
+        frobenius(f, 2, y[1]);
+        mul(y[0], y[1], y[0]);
+        frobenius(y[1], 1, y[1]);
+        mul(y[0], y[1], y[0]);
+        conjugate(f, y[1]);
+        frobenius(y[8], 2, y[2]);
+        frobenius(y[7], 1, y[3]);
+        conjugate(y[3], y[3]);
+        frobenius(y[8], 1, y[4]);
+        mul(y[4], y[7], y[4]);
+        conjugate(y[4], y[4]);
+        conjugate(y[8], y[5]);
+        frobenius(y[9], 1, y[6]);
+        mul(y[6], y[9], y[6]);
+        conjugate(y[6], y[6]);
+        uint256[12] memory t0 = y[7];
+        uint256[12] memory t1 = y[8];
+        mul(y[6], y[6], t0);
+        mul(t0, y[4], t0);
+        mul(t0, y[5], t0);
+        mul(y[3], y[5], t1);
+        mul(t1, t0, t1);
+        mul(t0, y[2], t0);
+        mul(t1, t1, t1);
+        mul(t1, t0, t1);
+        mul(t1, t1, t1);
+        mul(t1, y[1], t0);
+        mul(t1, y[0], t1);
+        mul(t0, t0, t0);
+        mul(t0, t1, t0);
+        return t0;
+    }
+    function finalExponentiation(uint256[12] memory f) internal view returns (uint256[12] memory) {
+        uint256[12] memory t;
+        uint256[12] memory s;
+        // f^((p^6 - 1) (p^2 + 1))
+        inverse(f, s);
+        conjugate(f, t);
+        mul(t, s, t);
+        frobenius(t, 2, s);
+        mul(s, t, t);
+        return hardPart(t);
+    }
+    /// @return the product e(p[0], q[0]) * ... * e(p[n], q[n]) in GT
+    function pairing(Pairing.G1Point[] memory p, Pairing.G2Point[] memory q) internal view returns (uint256[12] memory) {
+        require(p.length == q.length);
+        uint256[12] memory f;
+        millerLoop(p, q, f);
+        return finalExponentiation(f);
+    }
+}
+
+// The proof is the words of AggregateProof::to_words, in the order of the transcript: the
+// commitments T and U to A and B, to C, ip_ab, agg_c, 124 words for each round and 32 for the
+// final A, C, w, B, v and the openings. The input is the public inputs of the proofs one after
+// the other.
+contract AggregateVerifier {
+    using Pairing for *;
+    uint256 constant Q = 21888242871839275222246405745257275088696311157297823662689037894645226208583;
+    uint256 constant R = 21888242871839275222246405745257275088548364400416034343698204186575808495617;
+    struct VerifyingKey {
+        Pairing.G1Point alpha;
+        Pairing.G2Point beta;
+        Pairing.G2Point gamma;
+        Pairing.G2Point delta;
+        Pairing.G1Point[] gamma_abc;
+    }
+    struct AggregationKey {
+        Pairing.G1Point g;
+        Pairing.G2Point h;
+        Pairing.G1Point g_a;
+        Pairing.G1Point g_b;
+        Pairing.G2Point h_a;
+        Pairing.G2Point h_b;
+    }
+    struct Challenges {
+        uint256 r;
+        uint256 rInv;
+        uint256[] x;
+        uint256[] xInv;
+        uint256 z;
+        uint256 rho;
+    }
+    function verifyingKey() pure internal returns (VerifyingKey memory vk) {
+        vk.alpha = Pairing.G1Point(<%vk_alpha%>);
+        vk.beta = Pairing.G2Point(<%vk_beta%>);
+        vk.gamma = Pairing.G2Point(<%vk_gamma%>);
+        vk.delta = Pairing.G2Point(<%vk_delta%>);
+        vk.gamma_abc = new Pairing.G1Point[](<%vk_gamma_abc_length%>);
+        <%vk_gamma_abc_pts%>
+    }
+    function aggregationKey() pure internal returns (AggregationKey memory ak) {
+        ak.g = Pairing.G1Point(<%ak_g%>);
+        ak.h = Pairing.G2Point(<%ak_h%>);
+        ak.g_a = Pairing.G1Point(<%ak_g_a%>);
+        ak.g_b = Pairing.G1Point(<%ak_g_b%>);
+        ak.h_a = Pairing.G2Point(<%ak_h_a%>);
+        ak.h_b = Pairing.G2Point(<%ak_h_b%>);
+    }
+
+    function g1At(uint256[] calldata proof, uint256 i) internal pure returns (Pairing.G1Point memory) {
+        return Pairing.G1Point(proof[i], proof[i + 1]);
+    }
+    // the words of G2 are x.c1, x.c0, y.c1, y.c0
+    function g2At(uint256[] calldata proof, uint256 i) internal pure returns (Pairing.G2Point memory) {
+        return Pairing.G2Point([proof[i + 1], proof[i]], [proof[i + 3], proof[i + 2]]);
+    }
+    function gtAt(uint256[] calldata proof, uint256 i) internal pure returns (uint256[12] memory r) {
+        for (uint256 k = 0; k < 12; k++) {
+            r[k] = proof[i + k];
+        }
+    }
+    function rhoPowers(uint256 rho) internal pure returns (uint256[7] memory powers) {
+        powers[0] = 1;
+        for (uint256 i = 1; i < 7; i++) {
+            powers[i] = mulmod(powers[i - 1], rho, R);
+        }
+    }
+
+    function hash(uint256[] memory buf) internal pure returns (uint256 c) {
+        c = uint256(keccak256(abi.encodePacked(buf))) % R;
+        require(c != 0, "zero challenge");
+    }
+    // the hash of the previous challenge and proof[from..to]
+    function challenge(uint256 prev, uint256[] calldata proof, uint256 from, uint256 to) internal pure returns (uint256) {
+        uint256[] memory buf = new uint256[](1 + to - from);
+        buf[0] = prev;
+        for (uint256 i = from; i < to; i++) {
+            buf[1 + i - from] = proof[i];
+        }
+        return hash(buf);
+    }
+    // the hash of n, the public inputs padded to n proofs and the commitments
+    function firstChallenge(uint256[] calldata input, uint256 inputLength, uint256 n, uint256[] calldata proof) internal pure returns (uint256) {
+        uint256 count = inputLength == 0 ? n : input.length / inputLength;
+        uint256[] memory buf = new uint256[](1 + n * inputLength + 48);
+        buf[0] = n;
+        for (uint256 i = 0; i < n; i++) {
+            uint256 row = (i < count ? i : count - 1) * inputLength;
+            for (uint256 j = 0; j < inputLength; j++) {
+                buf[1 + i * inputLength + j] = input[row + j];
+            }
+        }
+        for (uint256 i = 0; i < 48; i++) {
+            buf[1 + n * inputLength + i] = proof[i];
+        }
+        return hash(buf);
+    }
+    function challenges(uint256[] calldata input, uint256 inputLength, uint256 n, uint256[] calldata proof) internal view returns (Challenges memory c) {
+        uint256 rounds = (proof.length - 94) / 124;
+        c.r = firstChallenge(input, inputLength, n, proof);
+        c.rInv = Gt.expMod(c.r, R - 2, R);
+        c.x = new uint256[](rounds);
+        c.xInv = new uint256[](rounds);
+        uint256 prev = c.r;
+        for (uint256 j = 0; j < rounds; j++) {
+            prev = challenge(prev, proof, j == 0 ? uint256(48) : 62 + 124 * j, 62 + 124 * (j + 1));
+            c.x[j] = prev;
+            c.xInv[j] = Gt.expMod(prev, R - 2, R);
+        }
+        uint256 f = 62 + 124 * rounds;
+        c.z = challenge(prev, proof, f, f + 20);
+        c.rho = challenge(c.z, proof, f + 20, f + 32);
+    }
+
+    // agg_c + sum x zc_l + x^-1 zc_r is C of the last round times the folded r^i
+    function checkAggregatedC(Challenges memory c, uint256[] calldata proof) internal view returns (bool) {
+        uint256 n = 1 << c.x.length;
+        Pairing.G1Point memory zc = g1At(proof, 60);
+        uint256 rFinal = 1;
+        for (uint256 j = 0; j < c.x.length; j++) {
+            uint256 at = 62 + 124 * j;
+            zc = Pairing.addition(zc, Pairing.scalar_mul(g1At(proof, at + 120), c.x[j]));
+            zc = Pairing.addition(zc, Pairing.scalar_mul(g1At(proof, at + 122), c.xInv[j]));
+            uint256 m = Gt.expMod(c.r, n >> (j + 1), R);
+            rFinal = mulmod(rFinal, addmod(1, mulmod(c.xInv[j], m, R), R), R);
+        }
+        Pairing.G1Point memory last = Pairing.scalar_mul(g1At(proof, 62 + 124 * c.x.length + 2), rFinal);
+        return zc.X == last.X && zc.Y == last.Y;
+    }
+    // f_v(z) = prod 1 + x^-1 z^m and g_w(z) = z^n prod 1 + x r^-m z^m, m = n / 2^(j + 1)
+    function foldingEvals(Challenges memory c) internal view returns (uint256 fvZ, uint256 gwZ) {
+        uint256 n = 1 << c.x.length;
+        fvZ = 1;
+        gwZ = Gt.expMod(c.z, n, R);
+        for (uint256 j = 0; j < c.x.length; j++) {
+            uint256 m = n >> (j + 1);
+            uint256 zm = Gt.expMod(c.z, m, R);
+            fvZ = mulmod(fvZ, addmod(1, mulmod(c.xInv[j], zm, R), R), R);
+            uint256 t = mulmod(mulmod(c.x[j], Gt.expMod(c.rInv, m, R), R), zm, R);
+            gwZ = mulmod(gwZ, addmod(1, t, R), R);
+        }
+    }
+    // w - g_w(z) g + z pi
+    function openingTerm(Pairing.G1Point memory g, uint256 gwZ, uint256 z, uint256[] calldata proof, uint256 w, uint256 pi) internal view returns (Pairing.G1Point memory) {
+        Pairing.G1Point memory t = Pairing.addition(g1At(proof, w), Pairing.scalar_mul(g, R - gwZ));
+        return Pairing.addition(t, Pairing.scalar_mul(g1At(proof, pi), z));
+    }
+    // -(1 + rho) f_v(z) g + rho^2 (w1 - g_w(z) g + z pi_w1) + rho^3 (w2 - g_w(z) g + z pi_w2)
+    function openingCommitment(Pairing.G1Point memory g, Challenges memory c, uint256[] calldata proof) internal view returns (Pairing.G1Point memory) {
+        uint256 f = 62 + 124 * c.x.length;
+        (uint256 fvZ, uint256 gwZ) = foldingEvals(c);
+        uint256 rho2 = mulmod(c.rho, c.rho, R);
+        Pairing.G1Point memory t = Pairing.scalar_mul(g, R - mulmod(addmod(1, c.rho, R), fvZ, R));
+        t = Pairing.addition(t, Pairing.scalar_mul(openingTerm(g, gwZ, c.z, proof, f + 4, f + 28), rho2));
+        return Pairing.addition(t, Pairing.scalar_mul(openingTerm(g, gwZ, c.z, proof, f + 6, f + 30), mulmod(rho2, c.rho, R)));
+    }
+    // s (z g - p)
+    function zMinus(Pairing.G1Point memory g, uint256 z, Pairing.G1Point memory p, uint256 s) internal view returns (Pairing.G1Point memory) {
+        return Pairing.scalar_mul(Pairing.addition(Pairing.scalar_mul(g, z), Pairing.negate(p)), s);
+    }
+    // the KZG openings of v at a and b and of w, with the powers of rho
+    function checkOpenings(Challenges memory c, uint256[] calldata proof) internal view returns (bool) {
+        AggregationKey memory ak = aggregationKey();
+        uint256 f = 62 + 124 * c.x.length;
+        uint256 rho2 = mulmod(c.rho, c.rho, R);
+        Pairing.G1Point[] memory p1 = new Pairing.G1Point[](8);
+        Pairing.G2Point[] memory p2 = new Pairing.G2Point[](8);
+        p1[0] = ak.g;
+        p2[0] = g2At(proof, f + 12);
+        p1[1] = Pairing.scalar_mul(ak.g, c.rho);
+        p2[1] = g2At(proof, f + 16);
+        p1[2] = openingCommitment(ak.g, c, proof);
+        p2[2] = ak.h;
+        p1[3] = zMinus(ak.g, c.z, ak.g_a, 1);
+        p2[3] = g2At(proof, f + 20);
+        p1[4] = zMinus(ak.g, c.z, ak.g_b, c.rho);
+        p2[4] = g2At(proof, f + 24);
+        p1[5] = Pairing.negate(Pairing.scalar_mul(g1At(proof, f + 28), rho2));
+        p2[5] = ak.h_a;
+        p1[6] = Pairing.negate(Pairing.scalar_mul(g1At(proof, f + 30), mulmod(rho2, c.rho, R)));
+        p2[6] = ak.h_b;
+        // e(0, B) is one, the precompile checks that B is in G2 as Gt.pairing does not
+        p1[7] = Pairing.G1Point(0, 0);
+        p2[7] = g2At(proof, f + 8);
+        return Pairing.pairing(p1, p2);
+    }
+
+    // the 10 elements of GT of the round at proof[at..], tab_l T, U, tab_r T, U, zab_l, zab_r,
+    // tc_l T, U, tc_r T, U, with the powers of rho of their equation and x or x^-1
+    function roundTerms(uint256[] calldata proof, uint256 at, uint256[7] memory rho, uint256 x, uint256 xInv, uint256[12][] memory bases, uint256[] memory exps, uint256 k) internal pure {
+        for (uint256 i = 0; i < 10; i++) {
+            bases[k + i] = gtAt(proof, at + 12 * i);
+        }
+        exps[k] = mulmod(rho[1], x, R);
+        exps[k + 1] = mulmod(rho[2], x, R);
+        exps[k + 2] = mulmod(rho[1], xInv, R);
+        exps[k + 3] = mulmod(rho[2], xInv, R);
+        exps[k + 4] = mulmod(rho[3], x, R);
+        exps[k + 5] = mulmod(rho[3], xInv, R);
+        exps[k + 6] = mulmod(rho[4], x, R);
+        exps[k + 7] = mulmod(rho[5], x, R);
+        exps[k + 8] = mulmod(rho[4], xInv, R);
+        exps[k + 9] = mulmod(rho[5], xInv, R);
+    }
+    function multiExp(uint256[12][] memory bases, uint256[] memory exps) internal pure returns (uint256[12] memory r) {
+        r[0] = 1;
+        for (uint256 b = 254; b > 0; b--) {
+            Gt.mul(r, r, r);
+            for (uint256 i = 0; i < bases.length; i++) {
+                if ((exps[i] >> (b - 1)) & 1 == 1) {
+                    Gt.mul(r, bases[i], r);
+                }
+            }
+        }
+    }
+    // T, U, Z and the commitments to C folded with the messages of the rounds
+    function gtLhs(Challenges memory c, uint256[] calldata proof) internal pure returns (uint256[12] memory) {
+        uint256 k = 5 + 10 * c.x.length;
+        uint256[12][] memory bases = new uint256[12][](k);
+        uint256[] memory exps = new uint256[](k);
+        uint256[7] memory rho = rhoPowers(c.rho);
+        for (uint256 i = 0; i < 5; i++) {
+            bases[i] = gtAt(proof, 12 * i);
+        }
+        exps[0] = rho[1];
+        exps[1] = rho[2];
+        exps[2] = rho[4];
+        exps[3] = rho[5];
+        exps[4] = addmod(rho[3], rho[6], R);
+        for (uint256 j = 0; j < c.x.length; j++) {
+            roundTerms(proof, 62 + 124 * j, rho, c.x[j], c.xInv[j], bases, exps, 5 + 10 * j);
+        }
+        return multiExp(bases, exps);
+    }
+    function proofPairs(Challenges memory c, uint256[] calldata proof, Pairing.G1Point[] memory p1, Pairing.G2Point[] memory p2) internal view {
+        uint256 f = 62 + 124 * c.x.length;
+        uint256[7] memory rho = rhoPowers(c.rho);
+        Pairing.G1Point memory a = g1At(proof, f);
+        Pairing.G1Point memory cc = g1At(proof, f + 2);
+        p1[0] = Pairing.addition(Pairing.scalar_mul(a, rho[1]), Pairing.scalar_mul(cc, rho[4]));
+        p2[0] = g2At(proof, f + 12);
+        p1[1] = Pairing.addition(Pairing.scalar_mul(a, rho[2]), Pairing.scalar_mul(cc, rho[5]));
+        p2[1] = g2At(proof, f + 16);
+        p1[2] = Pairing.addition(Pairing.scalar_mul(g1At(proof, f + 4), rho[1]), Pairing.scalar_mul(g1At(proof, f + 6), rho[2]));
+        p1[2] = Pairing.addition(p1[2], Pairing.scalar_mul(a, rho[3]));
+        p2[2] = g2At(proof, f + 8);
+        p1[5] = Pairing.scalar_mul(g1At(proof, 60), rho[6]);
+    }
+    // sum_i r^i (1, x_i) over the padded inputs
+    function inputCoefficients(uint256[] calldata input, uint256 inputLength, uint256 n, uint256 r) internal pure returns (uint256[] memory coefs) {
+        uint256 count = inputLength == 0 ? n : input.length / inputLength;
+        coefs = new uint256[](inputLength + 1);
+        uint256 ri = 1;
+        for (uint256 i = 0; i < n; i++) {
+            uint256 row = (i < count ? i : count - 1) * inputLength;
+            coefs[0] = addmod(coefs[0], ri, R);
+            for (uint256 j = 0; j < inputLength; j++) {
+                coefs[j + 1] = addmod(coefs[j + 1], mulmod(ri, input[row + j], R), R);
+            }
+            ri = mulmod(ri, r, R);
+        }
+    }
+    function keyPairs(VerifyingKey memory vk, Challenges memory c, uint256[] calldata input, Pairing.G1Point[] memory p1, Pairing.G2Point[] memory p2) internal view {
+        uint256[] memory coefs = inputCoefficients(input, vk.gamma_abc.length - 1, 1 << c.x.length, c.r);
+        uint256 rho6 = rhoPowers(c.rho)[6];
+        Pairing.G1Point memory ic = Pairing.G1Point(0, 0);
+        for (uint256 j = 0; j < coefs.length; j++) {
+            ic = Pairing.addition(ic, Pairing.scalar_mul(vk.gamma_abc[j], coefs[j]));
+        }
+        p1[3] = Pairing.scalar_mul(vk.alpha, mulmod(coefs[0], rho6, R));
+        p2[3] = vk.beta;
+        p1[4] = Pairing.scalar_mul(ic, rho6);
+        p2[4] = vk.gamma;
+        p2[5] = vk.delta;
+    }
+    // the pairings of the last round and of the groth16 equation of ip_ab
+    function gtRhs(VerifyingKey memory vk, Challenges memory c, uint256[] calldata input, uint256[] calldata proof) internal view returns (uint256[12] memory) {
+        Pairing.G1Point[] memory p1 = new Pairing.G1Point[](6);
+        Pairing.G2Point[] memory p2 = new Pairing.G2Point[](6);
+        proofPairs(c, proof, p1, p2);
+        keyPairs(vk, c, input, p1, p2);
+        return Gt.pairing(p1, p2);
+    }
+
+    function checkRanges(uint256[] calldata input, uint256[] calldata proof) internal pure {
+        for (uint256 i = 0; i < input.length; i++) {
+            require(input[i] < R, "input out of the scalar field");
+        }
+        for (uint256 i = 0; i < proof.length; i++) {
+            require(proof[i] < Q, "proof word out of the base field");
+        }
+    }
+    function verifyAggregate(uint256[] calldata input, uint256[] calldata proof) public view returns (bool) {
+        VerifyingKey memory vk = verifyingKey();
+        uint256 inputLength = vk.gamma_abc.length - 1;
+        require(proof.length > 94 && (proof.length - 94) % 124 == 0, "invalid proof length");
+        require(proof.length < 94 + 124 * 32, "invalid proof length");
+        uint256 n = 1 << ((proof.length - 94) / 124);
+        if (inputLength == 0) {
+            require(input.length == 0, "invalid input length");
+        } else {
+            require(input.length > 0 && input.length % inputLength == 0, "invalid input length");
+            uint256 count = input.length / inputLength;
+            // n is the number of proofs padded to a power of two, at least 2
+            require(count <= n && (n == 2 || 2 * count > n), "invalid input length");
+        }
+        checkRanges(input, proof);
+        Challenges memory c = challenges(input, inputLength, n, proof);
+        if (!checkAggregatedC(c, proof) || !checkOpenings(c, proof)) {
+            return false;
+        }
+        return Gt.equal(gtLhs(c, proof), gtRhs(vk, c, input, proof));
+    }
+}
+"#;
+
+// A groth16 verifier module for `no_std` Rust, e.g. a Substrate pallet, on the arkworks
+// curves. The key is embedded as big-endian coordinates, and the proof is the words of
+// `calldata::Calldata`: a.x, a.y, b.x[0], b.x[1], b.y[0], b.y[1], c.x, c.y.
+pub const RUST_VERIFIER_TEMPLATE: &str = r#"//! Groth16 verifier of a <%curve%> verification key, generated by zkit.
+//!
+//! The module only uses `core`, and the arkworks crates without default features:
+//!
+//! ```toml
+//! <%ark_curve_crate%> = { version = "0.4", default-features = false, features = ["curve"] }
+//! ark-ec = { version = "0.4", default-features = false }
+//! ark-ff = { version = "0.4", default-features = false }
+//! ```
+use <%ark_curve_module%>::{<%ark_curve%> as Curve, Fq, Fq2, Fr, G1Affine, G2Affine};
+use ark_ec::{pairing::Pairing, AffineRepr, CurveGroup};
+use ark_ff::{One, PrimeField};
+
+/// The bytes of a big-endian base field element.
+pub const FQ_BYTES: usize = <%fq_bytes%>;
+/// The bytes of a big-endian scalar field element.
+pub const FR_BYTES: usize = 32;
+/// The bytes of a proof, a.x, a.y, b.x[0], b.x[1], b.y[0], b.y[1], c.x, c.y.
+pub const PROOF_BYTES: usize = 8 * FQ_BYTES;
+pub const NUM_INPUTS: usize = <%num_inputs%>;
+
+type G1Bytes = [[u8; FQ_BYTES]; 2];
+type G2Bytes = [[u8; FQ_BYTES]; 4];
+
+<%vk_constants%>
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The proof is not `PROOF_BYTES` long.
+    InvalidProofLength,
+    /// There are not `NUM_INPUTS` public inputs.
+    InvalidInputLength,
+    /// A coordinate or a public input is not in its field.
+    InvalidFieldElement,
+    /// A point is not on the curve or not in the prime order subgroup.
+    InvalidPoint,
+}
+
+/// Verify `proof` of the big-endian public `inputs`.
+pub fn verify(proof: &[u8], inputs: &[[u8; FR_BYTES]]) -> Result<bool, Error> {
+    if proof.len() != PROOF_BYTES {
+        return Err(Error::InvalidProofLength);
+    }
+    if inputs.len() != NUM_INPUTS {
+        return Err(Error::InvalidInputLength);
+    }
+    let word = |i: usize| &proof[i * FQ_BYTES..(i + 1) * FQ_BYTES];
+    let a = g1(word(0), word(1))?;
+    let b = g2(word(2), word(3), word(4), word(5))?;
+    let c = g1(word(6), word(7))?;
+
+    let mut vk_x = g1(&IC[0][0], &IC[0][1])?.into_group();
+    for (input, ic) in inputs.iter().zip(IC[1..].iter()) {
+        let input: Fr = field(input)?;
+        vk_x += g1(&ic[0], &ic[1])?.mul_bigint(input.into_bigint());
+    }
+
+    // e(A, B) = e(alpha, beta) * e(vk_x, gamma) * e(C, delta)
+    let result = Curve::multi_pairing(
+        [a, -vk_x.into_affine(), -c, -g1_constant(&ALPHA_G1)?],
+        [
+            b,
+            g2_constant(&GAMMA_G2)?,
+            g2_constant(&DELTA_G2)?,
+            g2_constant(&BETA_G2)?,
+        ],
+    );
+    Ok(result.0.is_one())
+}
+
+fn field<F: PrimeField>(bytes: &[u8]) -> Result<F, Error> {
+    let mut repr = F::BigInt::default();
+    let limbs = repr.as_mut();
+    let n = limbs.len();
+    if bytes.len() != n * 8 {
+        return Err(Error::InvalidFieldElement);
+    }
+    for (i, limb) in limbs.iter_mut().enumerate() {
+        let start = (n - 1 - i) * 8;
+        let mut word = [0u8; 8];
+        word.copy_from_slice(&bytes[start..start + 8]);
+        *limb = u64::from_be_bytes(word);
+    }
+    F::from_bigint(repr).ok_or(Error::InvalidFieldElement)
+}
+
+fn g1(x: &[u8], y: &[u8]) -> Result<G1Affine, Error> {
+    let p = G1Affine::new_unchecked(field(x)?, field(y)?);
+    if !p.is_on_curve() || !p.is_in_correct_subgroup_assuming_on_curve() {
+        return Err(Error::InvalidPoint);
+    }
+    Ok(p)
+}
+
+fn g2(x0: &[u8], x1: &[u8], y0: &[u8], y1: &[u8]) -> Result<G2Affine, Error> {
+    let x = Fq2::new(field::<Fq>(x0)?, field::<Fq>(x1)?);
+    let y = Fq2::new(field::<Fq>(y0)?, field::<Fq>(y1)?);
+    let p = G2Affine::new_unchecked(x, y);
+    if !p.is_on_curve() || !p.is_in_correct_subgroup_assuming_on_curve() {
+        return Err(Error::InvalidPoint);
+    }
+    Ok(p)
+}
+
+fn g1_constant(p: &G1Bytes) -> Result<G1Affine, Error> {
+    g1(&p[0], &p[1])
+}
+
+fn g2_constant(p: &G2Bytes) -> Result<G2Affine, Error> {
+    g2(&p[0], &p[1], &p[2], &p[3])
+}
+"#;
+
+// The wasm-bindgen package around `RUST_VERIFIER_TEMPLATE`, written to src/verifier.rs. It
+// verifies the proof.json and public input json of `groth16_prove`.
+pub const WASM_CARGO_TEMPLATE: &str = r#"[package]
+name = "groth16-verifier"
+version = "0.1.0"
+edition = "2021"
+
+[lib]
+crate-type = ["cdylib", "rlib"]
+
+[dependencies]
+<%ark_curve_crate%> = { version = "0.4", default-features = false, features = ["curve"] }
+ark-ec = { version = "0.4", default-features = false }
+ark-ff = { version = "0.4", default-features = false }
+num-bigint = "0.4"
+num-traits = "0.2"
+serde = { version = "1.0", features = ["derive"] }
+serde_json = "1.0"
+wasm-bindgen = "0.2"
+"#;
+
+pub const WASM_LIB_TEMPLATE: &str = r#"//! wasm-bindgen groth16 verifier of a <%curve%> verification key, generated by zkit.
+//! Build it with `wasm-pack build --target web`.
+mod proof_json;
+mod verifier;
+
+use proof_json::{to_be_bytes, ProofFile};
+use wasm_bindgen::prelude::*;
+
+const CURVE: &str = "<%curve%>";
+
+/// The number of public inputs of the verification key.
+#[wasm_bindgen(js_name = numInputs)]
+pub fn num_inputs() -> usize {
+    verifier::NUM_INPUTS
+}
+
+/// Verify the proof.json and the public input json of zkit.
+#[wasm_bindgen]
+pub fn verify(proof_json: &str, public_input_json: &str) -> Result<bool, JsError> {
+    let proof: ProofFile = serde_json::from_str(proof_json)?;
+    if proof.protocol != "groth16" || proof.curve != CURVE {
+        return Err(JsError::new(&format!(
+            "expect a groth16 proof on {}, not {} on {}",
+            CURVE, proof.protocol, proof.curve
+        )));
+    }
+    let inputs: Vec<String> = serde_json::from_str(public_input_json)?;
+
+    let mut proof_bytes = Vec::with_capacity(verifier::PROOF_BYTES);
+    for w in proof.words() {
+        let bytes = to_be_bytes::<{ verifier::FQ_BYTES }>(w).map_err(|e| JsError::new(&e))?;
+        proof_bytes.extend_from_slice(&bytes);
+    }
+    let inputs = inputs
+        .iter()
+        .map(|s| to_be_bytes::<{ verifier::FR_BYTES }>(s))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| JsError::new(&e))?;
+    verifier::verify(&proof_bytes, &inputs)
+        .map_err(|e| JsError::new(&format!("invalid proof or inputs: {:?}", e)))
+}
+"#;
+
+// The proof.json of `groth16_prove`, shared with `json_utils::to_proof`, written to
+// src/proof_json.rs of the wasm-bindgen package.
+pub const WASM_PROOF_JSON: &str = include_str!("proof_json.rs");
@@ -0,0 +1,970 @@
+//! The snarkjs `.zkey` proving keys of groth16, see snarkjs/src/zkey_utils.js.
+//!
+//! A zkey is a binary file of sections: 1 the protocol, 2 the groth16 header and the
+//! verifying key, 3 IC, 4 the coefficients of A and B, 5 A, 6 B in G1, 7 B in G2, 8 C, the
+//! query of the private signals, 9 H and 10 the contributions of the ceremony. The field
+//! elements are little endian in Montgomery form, the coefficients twice.
+//!
+//! A, B and C are built over the Lagrange basis of the domain of snarkjs, whose root of unity
+//! is not the one of bellman but on small domains, and the basis is not in the key, so the
+//! points can't be moved to the rows of the bellman prover and a zkey is not converted to
+//! `Parameters<E>`. `ZKey::prove` is the prover of snarkjs instead, see
+//! snarkjs/src/groth16_prove.js, and H is the Lagrange basis of the odd points of the domain
+//! of size 2n over delta.
+use crate::aggregation::multiexp;
+use crate::bellman_ce::{
+    bls12_381::{self, Bls12},
+    bn256::{self, Bn256},
+    groth16::{Proof, VerifyingKey},
+    pairing::{CurveAffine, CurveProjective, Engine},
+};
+use crate::json_utils::Parser;
+use crate::{Field, LegendreSymbol, PrimeField, PrimeFieldRepr, SqrtField};
+use algebraic::circom_circuit::R1CS;
+use anyhow::{anyhow, bail, Result};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use rand_old::{Rand, Rng};
+use rayon::prelude::*;
+use std::collections::BTreeMap;
+use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+
+const PROTOCOL_GROTH16: u32 = 1;
+
+const HEADER: u32 = 1;
+const GROTH16_HEADER: u32 = 2;
+const IC: u32 = 3;
+const COEFS: u32 = 4;
+const POINTS_A: u32 = 5;
+const POINTS_B1: u32 = 6;
+const POINTS_B2: u32 = 7;
+const POINTS_C: u32 = 8;
+const POINTS_H: u32 = 9;
+const CONTRIBUTIONS: u32 = 10;
+
+/// The curves of a zkey, G2 is over the quadratic extension of Fq.
+pub trait ZKeyEngine: Engine + Parser {
+    fn to_fqe(c0: Self::Fq, c1: Self::Fq) -> Self::Fqe;
+    fn from_fqe(e: &Self::Fqe) -> (Self::Fq, Self::Fq);
+}
+
+impl ZKeyEngine for Bn256 {
+    fn to_fqe(c0: Self::Fq, c1: Self::Fq) -> Self::Fqe {
+        bn256::Fq2 { c0, c1 }
+    }
+    fn from_fqe(e: &Self::Fqe) -> (Self::Fq, Self::Fq) {
+        (e.c0, e.c1)
+    }
+}
+
+impl ZKeyEngine for Bls12 {
+    fn to_fqe(c0: Self::Fq, c1: Self::Fq) -> Self::Fqe {
+        bls12_381::Fq2 { c0, c1 }
+    }
+    fn from_fqe(e: &Self::Fqe) -> (Self::Fq, Self::Fq) {
+        (e.c0, e.c1)
+    }
+}
+
+/// One coefficient of the A or B matrix, `matrix` is 0 for A and 1 for B.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Coef<F> {
+    pub matrix: u32,
+    pub constraint: u32,
+    pub signal: u32,
+    pub value: F,
+}
+
+pub struct ZKey<E: ZKeyEngine> {
+    pub n_vars: usize,
+    pub n_public: usize,
+    pub domain_size: usize,
+    /// alpha, beta, gamma, delta and IC
+    pub vk: VerifyingKey<E>,
+    pub coefs: Vec<Coef<E::Fr>>,
+    /// A, B1 and B2 have a point per signal, the unused ones are zero
+    pub a: Vec<E::G1Affine>,
+    pub b_g1: Vec<E::G1Affine>,
+    pub b_g2: Vec<E::G2Affine>,
+    /// the private signals, `l` of bellman
+    pub c: Vec<E::G1Affine>,
+    /// L_{2i+1}(tau) / delta over the domain of size 2 * domain_size
+    pub h: Vec<E::G1Affine>,
+    /// the contributions section, kept as is
+    pub contributions: Vec<u8>,
+}
+
+/// The file starts with the magic of the zkeys, the bellman parameters never do.
+pub fn is_zkey(file_path: &str) -> Result<bool> {
+    let mut file =
+        std::fs::File::open(file_path).map_err(|e| anyhow!("Open {}, {:?}", file_path, e))?;
+    let mut magic = [0u8; 4];
+    Ok(file.read_exact(&mut magic).is_ok() && magic == *b"zkey")
+}
+
+pub(crate) fn n8<F: PrimeField>() -> usize {
+    F::Repr::default().as_ref().len() * 8
+}
+
+pub(crate) fn read_fq<F: PrimeField, R: Read>(reader: &mut R) -> Result<F> {
+    let mut repr = F::Repr::default();
+    repr.read_le(reader)?;
+    Ok(F::from_raw_repr(repr)?)
+}
+
+pub(crate) fn write_fq<F: PrimeField, W: Write>(writer: &mut W, f: &F) -> Result<()> {
+    f.into_raw_repr().write_le(writer)?;
+    Ok(())
+}
+
+// the coefficients are multiplied by R twice
+fn read_fr2<F: PrimeField, R: Read>(reader: &mut R) -> Result<F> {
+    let f: F = read_fq(reader)?;
+    Ok(F::from_raw_repr(f.into_repr())?)
+}
+
+fn write_fr2<F: PrimeField, W: Write>(writer: &mut W, f: &F) -> Result<()> {
+    write_fq(writer, &F::from_repr(f.into_raw_repr())?)
+}
+
+pub(crate) fn read_g1<E: ZKeyEngine, R: Read>(
+    reader: &mut R,
+    checked: bool,
+) -> Result<E::G1Affine> {
+    let x: E::Fq = read_fq(reader)?;
+    let y: E::Fq = read_fq(reader)?;
+    if x.is_zero() && y.is_zero() {
+        return Ok(E::G1Affine::zero());
+    }
+    if checked {
+        Ok(E::G1Affine::from_xy_checked(x, y)?)
+    } else {
+        Ok(E::G1Affine::from_xy_unchecked(x, y))
+    }
+}
+
+pub(crate) fn write_g1<E: ZKeyEngine, W: Write>(writer: &mut W, p: &E::G1Affine) -> Result<()> {
+    let (x, y) = if p.is_zero() {
+        (E::Fq::zero(), E::Fq::zero())
+    } else {
+        p.into_xy_unchecked()
+    };
+    write_fq(writer, &x)?;
+    write_fq(writer, &y)
+}
+
+pub(crate) fn read_g2<E: ZKeyEngine, R: Read>(
+    reader: &mut R,
+    checked: bool,
+) -> Result<E::G2Affine> {
+    let mut c = [E::Fq::zero(); 4];
+    for ci in c.iter_mut() {
+        *ci = read_fq(reader)?;
+    }
+    if c.iter().all(|ci| ci.is_zero()) {
+        return Ok(E::G2Affine::zero());
+    }
+    let x = E::to_fqe(c[0], c[1]);
+    let y = E::to_fqe(c[2], c[3]);
+    if checked {
+        Ok(E::G2Affine::from_xy_checked(x, y)?)
+    } else {
+        Ok(E::G2Affine::from_xy_unchecked(x, y))
+    }
+}
+
+pub(crate) fn write_g2<E: ZKeyEngine, W: Write>(writer: &mut W, p: &E::G2Affine) -> Result<()> {
+    let (x, y) = if p.is_zero() {
+        let zero = (E::Fq::zero(), E::Fq::zero());
+        (zero, zero)
+    } else {
+        let (x, y) = p.into_xy_unchecked();
+        (E::from_fqe(&x), E::from_fqe(&y))
+    };
+    for c in [x.0, x.1, y.0, y.1].iter() {
+        write_fq(writer, c)?;
+    }
+    Ok(())
+}
+
+pub(crate) fn read_g1s<E: ZKeyEngine, R: Read>(
+    reader: &mut R,
+    n: usize,
+    checked: bool,
+) -> Result<Vec<E::G1Affine>> {
+    (0..n).map(|_| read_g1::<E, _>(reader, checked)).collect()
+}
+
+pub(crate) fn prime_bytes<F: PrimeField>() -> Result<Vec<u8>> {
+    let mut bytes = vec![];
+    F::char().write_le(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// The sections of a binary file of snarkjs, type -> (file offset, size).
+pub(crate) fn read_sections<R: Read + Seek>(
+    reader: &mut R,
+    magic: &[u8; 4],
+    file_path: &str,
+) -> Result<BTreeMap<u32, (u64, u64)>> {
+    let mut file_magic = [0u8; 4];
+    reader.read_exact(&mut file_magic)?;
+    if file_magic != *magic {
+        bail!("{} is not a {}", file_path, String::from_utf8_lossy(magic));
+    }
+    let version = reader.read_u32::<LittleEndian>()?;
+    if version != 1 {
+        bail!(
+            "Unsupported {} version {}",
+            String::from_utf8_lossy(magic),
+            version
+        );
+    }
+    let n_sections = reader.read_u32::<LittleEndian>()?;
+    let mut sections = BTreeMap::new();
+    for _ in 0..n_sections {
+        let section_type = reader.read_u32::<LittleEndian>()?;
+        let size = reader.read_u64::<LittleEndian>()?;
+        sections.insert(section_type, (reader.stream_position()?, size));
+        reader.seek(SeekFrom::Current(size as i64))?;
+    }
+    Ok(sections)
+}
+
+/// Moves to the start of `section` and returns its size.
+pub(crate) fn seek_section<R: Seek>(
+    reader: &mut R,
+    sections: &BTreeMap<u32, (u64, u64)>,
+    section: u32,
+    kind: &str,
+) -> Result<u64> {
+    let (offset, size) = sections
+        .get(&section)
+        .ok_or_else(|| anyhow!("{} section {} is missing", kind, section))?;
+    reader.seek(SeekFrom::Start(*offset))?;
+    Ok(*size)
+}
+
+/// The 2^k-th root of unity of snarkjs, which squares down from nqr^t for the smallest
+/// quadratic non residue nqr, see wasmcurves/src/build_fft.js. It is not the one of bellman.
+pub fn snarkjs_root_of_unity<F: PrimeField + SqrtField>(k: u32) -> Result<F> {
+    if k > F::S {
+        bail!("no root of unity of order 2^{}", k);
+    }
+    let mut t = F::char();
+    t.shr(F::S);
+    let mut nqr = F::one();
+    nqr.double();
+    while nqr.legendre() != LegendreSymbol::QuadraticNonResidue {
+        nqr.add_assign(&F::one());
+    }
+    let mut w = nqr.pow(t.as_ref());
+    for _ in k..F::S {
+        w.square();
+    }
+    Ok(w)
+}
+
+// a[i] = sum_j a[j] * omega^(i*j), radix 2 over the field
+fn fft<F: PrimeField>(a: &mut [F], omega: &F) {
+    let n = a.len();
+    if n <= 1 {
+        return;
+    }
+    let log_n = n.trailing_zeros();
+    for k in 0..n {
+        let rk = k.reverse_bits() >> (usize::BITS - log_n);
+        if k < rk {
+            a.swap(k, rk);
+        }
+    }
+
+    let mut m = 1;
+    while m < n {
+        let w_m = omega.pow([(n / (2 * m)) as u64]);
+        let mut twiddles = Vec::with_capacity(m);
+        let mut w = F::one();
+        for _ in 0..m {
+            twiddles.push(w);
+            w.mul_assign(&w_m);
+        }
+        a.par_chunks_mut(2 * m).for_each(|chunk| {
+            let (lo, hi) = chunk.split_at_mut(m);
+            lo.par_iter_mut()
+                .zip(hi.par_iter_mut())
+                .zip(twiddles.par_iter())
+                .for_each(|((x, y), w)| {
+                    let mut t = *y;
+                    t.mul_assign(w);
+                    *y = *x;
+                    y.sub_assign(&t);
+                    x.add_assign(&t);
+                });
+        });
+        m *= 2;
+    }
+}
+
+/// The domain of snarkjs, with a row per constraint and per public signal.
+pub fn domain_size<E: ZKeyEngine>(r1cs: &R1CS<E>) -> usize {
+    (r1cs.constraints.len() + r1cs.num_inputs).next_power_of_two()
+}
+
+/// The coefficients of A and B of `r1cs` in the order of snarkjs, then the
+/// `signal * 0 = 0` rows of the public signals.
+pub fn r1cs_coefs<E: ZKeyEngine>(r1cs: &R1CS<E>) -> Vec<Coef<E::Fr>> {
+    let mut coefs = vec![];
+    for (i, (a, b, _)) in r1cs.constraints.iter().enumerate() {
+        for (matrix, lc) in [a, b].iter().enumerate() {
+            for (s, v) in lc.iter() {
+                coefs.push(Coef {
+                    matrix: matrix as u32,
+                    constraint: i as u32,
+                    signal: *s as u32,
+                    value: *v,
+                });
+            }
+        }
+    }
+    let n_constraints = r1cs.constraints.len();
+    for s in 0..r1cs.num_inputs {
+        coefs.push(Coef {
+            matrix: 0,
+            constraint: (n_constraints + s) as u32,
+            signal: s as u32,
+            value: E::Fr::one(),
+        });
+    }
+    coefs
+}
+
+impl<E: ZKeyEngine> ZKey<E> {
+    pub fn read(file_path: &str, checked: bool) -> Result<Self> {
+        let file =
+            std::fs::File::open(file_path).map_err(|e| anyhow!("Open {}, {:?}", file_path, e))?;
+        let mut reader = BufReader::new(file);
+
+        let sections = read_sections(&mut reader, b"zkey", file_path)?;
+        let seek = |reader: &mut BufReader<std::fs::File>, section: u32| -> Result<u64> {
+            seek_section(reader, &sections, section, "zkey")
+        };
+
+        seek(&mut reader, HEADER)?;
+        let protocol = reader.read_u32::<LittleEndian>()?;
+        if protocol != PROTOCOL_GROTH16 {
+            bail!("The zkey is not a groth16 key, protocol {}", protocol);
+        }
+
+        seek(&mut reader, GROTH16_HEADER)?;
+        let n8q = reader.read_u32::<LittleEndian>()? as usize;
+        let mut q = vec![0u8; n8q];
+        reader.read_exact(&mut q)?;
+        let n8r = reader.read_u32::<LittleEndian>()? as usize;
+        let mut r = vec![0u8; n8r];
+        reader.read_exact(&mut r)?;
+        if q != prime_bytes::<E::Fq>()? || r != prime_bytes::<E::Fr>()? {
+            bail!("The primes of the zkey are not the ones of the curve");
+        }
+        let n_vars = reader.read_u32::<LittleEndian>()? as usize;
+        let n_public = reader.read_u32::<LittleEndian>()? as usize;
+        let domain_size = reader.read_u32::<LittleEndian>()? as usize;
+        if !domain_size.is_power_of_two() {
+            bail!("The zkey domain size {} is not a power of 2", domain_size);
+        }
+        let alpha_g1 = read_g1::<E, _>(&mut reader, checked)?;
+        let beta_g1 = read_g1::<E, _>(&mut reader, checked)?;
+        let beta_g2 = read_g2::<E, _>(&mut reader, checked)?;
+        let gamma_g2 = read_g2::<E, _>(&mut reader, checked)?;
+        let delta_g1 = read_g1::<E, _>(&mut reader, checked)?;
+        let delta_g2 = read_g2::<E, _>(&mut reader, checked)?;
+
+        seek(&mut reader, IC)?;
+        let ic = read_g1s::<E, _>(&mut reader, n_public + 1, checked)?;
+
+        seek(&mut reader, COEFS)?;
+        let n_coefs = reader.read_u32::<LittleEndian>()?;
+        let mut coefs = Vec::with_capacity(n_coefs as usize);
+        for _ in 0..n_coefs {
+            coefs.push(Coef {
+                matrix: reader.read_u32::<LittleEndian>()?,
+                constraint: reader.read_u32::<LittleEndian>()?,
+                signal: reader.read_u32::<LittleEndian>()?,
+                value: read_fr2(&mut reader)?,
+            });
+        }
+
+        seek(&mut reader, POINTS_A)?;
+        let a = read_g1s::<E, _>(&mut reader, n_vars, checked)?;
+        seek(&mut reader, POINTS_B1)?;
+        let b_g1 = read_g1s::<E, _>(&mut reader, n_vars, checked)?;
+        seek(&mut reader, POINTS_B2)?;
+        let b_g2 = (0..n_vars)
+            .map(|_| read_g2::<E, _>(&mut reader, checked))
+            .collect::<Result<Vec<_>>>()?;
+        seek(&mut reader, POINTS_C)?;
+        let c = read_g1s::<E, _>(&mut reader, n_vars - n_public - 1, checked)?;
+        seek(&mut reader, POINTS_H)?;
+        let h = read_g1s::<E, _>(&mut reader, domain_size, checked)?;
+
+        let contributions = match sections.contains_key(&CONTRIBUTIONS) {
+            true => {
+                let size = seek(&mut reader, CONTRIBUTIONS)?;
+                let mut buf = vec![0u8; size as usize];
+                reader.read_exact(&mut buf)?;
+                buf
+            }
+            false => vec![],
+        };
+
+        Ok(ZKey {
+            n_vars,
+            n_public,
+            domain_size,
+            vk: VerifyingKey {
+                alpha_g1,
+                beta_g1,
+                beta_g2,
+                gamma_g2,
+                delta_g1,
+                delta_g2,
+                ic,
+            },
+            coefs,
+            a,
+            b_g1,
+            b_g2,
+            c,
+            h,
+            contributions,
+        })
+    }
+
+    pub fn write(&self, file_path: &str) -> Result<()> {
+        let file = std::fs::File::create(file_path)?;
+        let mut writer = BufWriter::new(file);
+        self.write_to(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<()> {
+        let mut sections: Vec<(u32, Vec<u8>)> = vec![];
+
+        let mut buf = vec![];
+        buf.write_u32::<LittleEndian>(PROTOCOL_GROTH16)?;
+        sections.push((HEADER, buf));
+
+        let mut buf = vec![];
+        buf.write_u32::<LittleEndian>(n8::<E::Fq>() as u32)?;
+        buf.write_all(&prime_bytes::<E::Fq>()?)?;
+        buf.write_u32::<LittleEndian>(n8::<E::Fr>() as u32)?;
+        buf.write_all(&prime_bytes::<E::Fr>()?)?;
+        buf.write_u32::<LittleEndian>(self.n_vars as u32)?;
+        buf.write_u32::<LittleEndian>(self.n_public as u32)?;
+        buf.write_u32::<LittleEndian>(self.domain_size as u32)?;
+        write_g1::<E, _>(&mut buf, &self.vk.alpha_g1)?;
+        write_g1::<E, _>(&mut buf, &self.vk.beta_g1)?;
+        write_g2::<E, _>(&mut buf, &self.vk.beta_g2)?;
+        write_g2::<E, _>(&mut buf, &self.vk.gamma_g2)?;
+        write_g1::<E, _>(&mut buf, &self.vk.delta_g1)?;
+        write_g2::<E, _>(&mut buf, &self.vk.delta_g2)?;
+        sections.push((GROTH16_HEADER, buf));
+
+        let g1s = |points: &[E::G1Affine]| -> Result<Vec<u8>> {
+            let mut buf = vec![];
+            for p in points.iter() {
+                write_g1::<E, _>(&mut buf, p)?;
+            }
+            Ok(buf)
+        };
+        sections.push((IC, g1s(&self.vk.ic)?));
+
+        let mut buf = vec![];
+        buf.write_u32::<LittleEndian>(self.coefs.len() as u32)?;
+        for coef in self.coefs.iter() {
+            buf.write_u32::<LittleEndian>(coef.matrix)?;
+            buf.write_u32::<LittleEndian>(coef.constraint)?;
+            buf.write_u32::<LittleEndian>(coef.signal)?;
+            write_fr2(&mut buf, &coef.value)?;
+        }
+        sections.push((COEFS, buf));
+
+        sections.push((POINTS_A, g1s(&self.a)?));
+        sections.push((POINTS_B1, g1s(&self.b_g1)?));
+        let mut buf = vec![];
+        for p in self.b_g2.iter() {
+            write_g2::<E, _>(&mut buf, p)?;
+        }
+        sections.push((POINTS_B2, buf));
+        sections.push((POINTS_C, g1s(&self.c)?));
+        sections.push((POINTS_H, g1s(&self.h)?));
+        sections.push((CONTRIBUTIONS, self.contributions.clone()));
+
+        writer.write_all(b"zkey")?;
+        writer.write_u32::<LittleEndian>(1)?;
+        writer.write_u32::<LittleEndian>(sections.len() as u32)?;
+        for (section_type, data) in sections.iter() {
+            writer.write_u32::<LittleEndian>(*section_type)?;
+            writer.write_u64::<LittleEndian>(data.len() as u64)?;
+            writer.write_all(data)?;
+        }
+        Ok(())
+    }
+
+    /// Checks that the key is the one of `r1cs`: the signals, the domain and every
+    /// coefficient of A and B, with the `signal * 0 = 0` rows of the public signals.
+    pub fn check_r1cs(&self, r1cs: &R1CS<E>) -> Result<()> {
+        if self.n_vars != r1cs.num_variables || self.n_public + 1 != r1cs.num_inputs {
+            bail!(
+                "The zkey has {} signals and {} public, the R1CS {} and {}",
+                self.n_vars,
+                self.n_public,
+                r1cs.num_variables,
+                r1cs.num_inputs - 1
+            );
+        }
+        let domain_size = domain_size(r1cs);
+        if self.domain_size != domain_size {
+            bail!(
+                "The zkey domain size is {}, the R1CS needs {}",
+                self.domain_size,
+                domain_size
+            );
+        }
+        if self.vk.ic.len() != self.n_public + 1
+            || self.a.len() != self.n_vars
+            || self.b_g1.len() != self.n_vars
+            || self.b_g2.len() != self.n_vars
+            || self.c.len() != self.n_vars - self.n_public - 1
+            || self.h.len() != self.domain_size
+        {
+            bail!("The zkey sections do not match its header");
+        }
+
+        type Key = (u32, u32, u32);
+        let add = |m: &mut BTreeMap<Key, E::Fr>, key: Key, v: &E::Fr| {
+            m.entry(key).or_insert_with(E::Fr::zero).add_assign(v);
+        };
+        let sum = |coefs: &[Coef<E::Fr>]| {
+            let mut m = BTreeMap::new();
+            for coef in coefs.iter() {
+                add(
+                    &mut m,
+                    (coef.matrix, coef.constraint, coef.signal),
+                    &coef.value,
+                );
+            }
+            m
+        };
+        let mut expected = sum(&r1cs_coefs(r1cs));
+        let mut actual = sum(&self.coefs);
+        expected.retain(|_, v| !v.is_zero());
+        actual.retain(|_, v| !v.is_zero());
+
+        let differs = expected
+            .iter()
+            .find(|(k, v)| actual.get(*k) != Some(*v))
+            .or_else(|| actual.iter().find(|(k, _)| !expected.contains_key(*k)));
+        if let Some(((matrix, constraint, signal), _)) = differs {
+            bail!(
+                "The zkey coefficient of {} at constraint {}, signal {} differs from the R1CS",
+                if *matrix == 0 { "A" } else { "B" },
+                constraint,
+                signal
+            );
+        }
+        Ok(())
+    }
+
+    /// The proof of `witness`, which must satisfy the R1CS of the key, as snarkjs computes it:
+    /// with A, B and C = A * B on the rows of the domain, H(tau) * Z(tau) / delta is the sum
+    /// of A * B - C on the odd points of the domain of size 2n times the points of H, since
+    /// A * B - C vanishes on the even ones.
+    pub fn prove<R: Rng>(&self, witness: &[E::Fr], rng: &mut R) -> Result<Proof<E>> {
+        if witness.len() != self.n_vars {
+            bail!(
+                "The witness has {} values, the zkey {} signals",
+                witness.len(),
+                self.n_vars
+            );
+        }
+        let n = self.domain_size;
+        let mut a = vec![E::Fr::zero(); n];
+        let mut b = vec![E::Fr::zero(); n];
+        for coef in self.coefs.iter() {
+            let (row, signal) = (coef.constraint as usize, coef.signal as usize);
+            if row >= n || signal >= self.n_vars || coef.matrix > 1 {
+                bail!(
+                    "The zkey coefficient at constraint {}, signal {} is out of the key",
+                    row,
+                    signal
+                );
+            }
+            let mut v = coef.value;
+            v.mul_assign(&witness[signal]);
+            let rows = if coef.matrix == 0 { &mut a } else { &mut b };
+            rows[row].add_assign(&v);
+        }
+        let mut c: Vec<E::Fr> = a
+            .par_iter()
+            .zip(b.par_iter())
+            .map(|(a, b)| {
+                let mut c = *a;
+                c.mul_assign(b);
+                c
+            })
+            .collect();
+
+        // the coefficients by the inverse FFT, times w^i to evaluate on w * w^(2k)
+        let omega = snarkjs_root_of_unity::<E::Fr>(n.trailing_zeros() + 1)?;
+        let mut omega_n = omega;
+        omega_n.square();
+        let omega_n_inv = omega_n.inverse().unwrap();
+        let mut shift = E::Fr::from_repr((n as u64).into())?.inverse().unwrap();
+        let mut shifts = Vec::with_capacity(n);
+        for _ in 0..n {
+            shifts.push(shift);
+            shift.mul_assign(&omega);
+        }
+        for p in [&mut a, &mut b, &mut c] {
+            fft(p, &omega_n_inv);
+            p.par_iter_mut()
+                .zip(shifts.par_iter())
+                .for_each(|(x, s)| x.mul_assign(s));
+            fft(p, &omega_n);
+        }
+        let h: Vec<E::Fr> = a
+            .par_iter()
+            .zip(b.par_iter())
+            .zip(c.par_iter())
+            .map(|((a, b), c)| {
+                let mut h = *a;
+                h.mul_assign(b);
+                h.sub_assign(c);
+                h
+            })
+            .collect();
+
+        let (r, s) = (E::Fr::rand(rng), E::Fr::rand(rng));
+        let vk = &self.vk;
+        let mut pi_a = multiexp(&self.a, witness);
+        pi_a.add_assign_mixed(&vk.alpha_g1);
+        pi_a.add_assign(&vk.delta_g1.mul(r));
+        let mut pi_b = multiexp(&self.b_g2, witness);
+        pi_b.add_assign_mixed(&vk.beta_g2);
+        pi_b.add_assign(&vk.delta_g2.mul(s));
+        let mut pi_b1 = multiexp(&self.b_g1, witness);
+        pi_b1.add_assign_mixed(&vk.beta_g1);
+        pi_b1.add_assign(&vk.delta_g1.mul(s));
+
+        // C + H + s * A + r * B1 - r * s * delta
+        let mut pi_c = multiexp(&self.c, &witness[self.n_public + 1..]);
+        pi_c.add_assign(&multiexp(&self.h, &h));
+        let mut sa = pi_a;
+        sa.mul_assign(s);
+        pi_c.add_assign(&sa);
+        pi_b1.mul_assign(r);
+        pi_c.add_assign(&pi_b1);
+        let mut rs = r;
+        rs.mul_assign(&s);
+        let mut rs_delta = vk.delta_g1.mul(rs);
+        rs_delta.negate();
+        pi_c.add_assign(&rs_delta);
+
+        Ok(Proof {
+            a: pi_a.into_affine(),
+            b: pi_b.into_affine(),
+            c: pi_c.into_affine(),
+        })
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::api::{
+        calculate_witness, groth16_import_zkey, groth16_prove, groth16_verify, WitnessGenerator,
+    };
+    use crate::bellman_ce::groth16::generate_parameters;
+    use crate::groth16::Groth16;
+    use algebraic::circom_circuit::CircomCircuit;
+    use algebraic::reader::{load_r1cs, load_witness_from_bin_file};
+
+    pub(crate) const CIRCUIT_FILE: &str =
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../test/multiplier.r1cs");
+    pub(crate) const WASM_FILE: &str =
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../test/multiplier.wasm");
+    pub(crate) const INPUT_FILE: &str =
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../test/multiplier.input.json");
+    pub(crate) const CIRCUIT_FILE_BLS12: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/test-vectors/mycircuit_bls12381.r1cs"
+    );
+    const WASM_FILE_BLS12: &str = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/test-vectors/mycircuit_bls12381.wasm"
+    );
+    // made by snarkjs, see test/snarkjs_zkey.sh
+    const SNARKJS_ZKEY_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/multiplier.zkey");
+
+    /// L_j(tau) = w^j (tau^m - 1) / (m (tau - w^j)) over the domain of size m of snarkjs.
+    pub(crate) fn lagrange<F: PrimeField + SqrtField>(m: usize, j: usize, tau: &F) -> F {
+        let w = snarkjs_root_of_unity::<F>(m.trailing_zeros())
+            .unwrap()
+            .pow([j as u64]);
+        let mut z = tau.pow([m as u64]);
+        z.sub_assign(&F::one());
+        let mut den = *tau;
+        den.sub_assign(&w);
+        den.mul_assign(&F::from_str(&m.to_string()).unwrap());
+        let mut l = w;
+        l.mul_assign(&z);
+        l.mul_assign(&den.inverse().unwrap());
+        l
+    }
+
+    /// The zkey of `r1cs` for the toxic waste `alpha`, `beta`, `gamma`, `delta` and `tau` as
+    /// snarkjs builds it: per signal, A and B are sum_j a_j * L_j(tau), and IC and C are
+    /// beta * A + alpha * B + C over gamma and delta.
+    pub(crate) fn zkey_for_setup<E: ZKeyEngine>(
+        r1cs: &R1CS<E>,
+        alpha: E::Fr,
+        beta: E::Fr,
+        gamma: E::Fr,
+        delta: E::Fr,
+        tau: E::Fr,
+    ) -> ZKey<E> {
+        let n = domain_size(r1cs);
+        let l: Vec<E::Fr> = (0..n).map(|j| lagrange(n, j, &tau)).collect();
+        let coefs = r1cs_coefs(r1cs);
+        // A, B and C at tau per signal
+        let mut at_tau = vec![[E::Fr::zero(); 3]; r1cs.num_variables];
+        let mut add = |matrix: usize, row: usize, signal: usize, v: &E::Fr| {
+            let mut t = l[row];
+            t.mul_assign(v);
+            at_tau[signal][matrix].add_assign(&t);
+        };
+        for coef in coefs.iter() {
+            add(
+                coef.matrix as usize,
+                coef.constraint as usize,
+                coef.signal as usize,
+                &coef.value,
+            );
+        }
+        for (i, (_, _, c)) in r1cs.constraints.iter().enumerate() {
+            for (s, v) in c.iter() {
+                add(2, i, *s, v);
+            }
+        }
+
+        let g1 = |s: E::Fr| E::G1Affine::one().mul(s).into_affine();
+        let g2 = |s: E::Fr| E::G2Affine::one().mul(s).into_affine();
+        let gamma_inv = gamma.inverse().unwrap();
+        let delta_inv = delta.inverse().unwrap();
+        let mut ic: Vec<E::G1Affine> = at_tau
+            .iter()
+            .enumerate()
+            .map(|(i, [a, b, c])| {
+                let mut t = *a;
+                t.mul_assign(&beta);
+                let mut alpha_b = *b;
+                alpha_b.mul_assign(&alpha);
+                t.add_assign(&alpha_b);
+                t.add_assign(c);
+                t.mul_assign(if i < r1cs.num_inputs {
+                    &gamma_inv
+                } else {
+                    &delta_inv
+                });
+                g1(t)
+            })
+            .collect();
+        let c = ic.split_off(r1cs.num_inputs);
+        let h = (0..n)
+            .map(|k| {
+                let mut l = lagrange(2 * n, 2 * k + 1, &tau);
+                l.mul_assign(&delta_inv);
+                g1(l)
+            })
+            .collect();
+
+        ZKey {
+            n_vars: r1cs.num_variables,
+            n_public: r1cs.num_inputs - 1,
+            domain_size: n,
+            vk: VerifyingKey {
+                alpha_g1: g1(alpha),
+                beta_g1: g1(beta),
+                beta_g2: g2(beta),
+                gamma_g2: g2(gamma),
+                delta_g1: g1(delta),
+                delta_g2: g2(delta),
+                ic,
+            },
+            coefs,
+            a: at_tau.iter().map(|p| g1(p[0])).collect(),
+            b_g1: at_tau.iter().map(|p| g1(p[1])).collect(),
+            b_g2: at_tau.iter().map(|p| g2(p[1])).collect(),
+            c,
+            h,
+            contributions: vec![],
+        }
+    }
+
+    pub(crate) fn random_zkey<E: ZKeyEngine>(r1cs: &R1CS<E>) -> ZKey<E> {
+        let rng = &mut rand_old::thread_rng();
+        zkey_for_setup(
+            r1cs,
+            E::Fr::rand(rng),
+            E::Fr::rand(rng),
+            E::Fr::rand(rng),
+            E::Fr::rand(rng),
+            E::Fr::rand(rng),
+        )
+    }
+
+    fn verify<E: ZKeyEngine>(zkey: &ZKey<E>, witness: &[E::Fr]) -> bool {
+        let proof = zkey.prove(witness, &mut rand_old::thread_rng()).unwrap();
+        Groth16::<E, CircomCircuit<E>>::verify_with_processed_vk(
+            &zkey.vk,
+            &witness[1..zkey.n_public + 1],
+            &proof,
+        )
+        .unwrap()
+    }
+
+    fn check_zkey<E: ZKeyEngine>(curve_type: &str, circuit_file: &str, wasm_file: &str) {
+        let prefix = format!("/tmp/zkey_{}", curve_type);
+        let zkey_file = format!("{}.zkey", prefix);
+        let witness_file = format!("{}.wtns", prefix);
+        let r1cs = load_r1cs::<E>(circuit_file);
+        let zkey = random_zkey(&r1cs);
+        zkey.write(&zkey_file).unwrap();
+        assert!(is_zkey(&zkey_file).unwrap());
+
+        let read = ZKey::<E>::read(&zkey_file, true).unwrap();
+        assert!(read.vk == zkey.vk);
+        assert_eq!(read.coefs, zkey.coefs);
+        assert_eq!(read.a, zkey.a);
+        assert_eq!(read.b_g1, zkey.b_g1);
+        assert_eq!(read.b_g2, zkey.b_g2);
+        assert_eq!(read.c, zkey.c);
+        assert_eq!(read.h, zkey.h);
+        read.check_r1cs(&r1cs).unwrap();
+
+        // the proofs of the witness verify, not the ones of another witness or another H
+        calculate_witness(wasm_file, WitnessGenerator::Wasm, INPUT_FILE, &witness_file).unwrap();
+        let mut witness = load_witness_from_bin_file::<E>(&witness_file);
+        assert!(verify(&read, &witness));
+        let mut wrong = ZKey::<E>::read(&zkey_file, true).unwrap();
+        wrong.h.reverse();
+        assert!(!verify(&wrong, &witness));
+        witness[1].add_assign(&E::Fr::one());
+        assert!(!verify(&read, &witness));
+
+        let mut wrong = read;
+        wrong.coefs[0].value.double();
+        assert!(wrong.check_r1cs(&r1cs).is_err());
+    }
+
+    #[test]
+    fn test_zkey_bn128() {
+        check_zkey::<Bn256>("BN128", CIRCUIT_FILE, WASM_FILE);
+        assert!(ZKey::<Bls12>::read("/tmp/zkey_BN128.zkey", false).is_err());
+    }
+
+    #[test]
+    fn test_zkey_bls12381() {
+        check_zkey::<Bls12>("BLS12381", CIRCUIT_FILE_BLS12, WASM_FILE_BLS12);
+    }
+
+    /// The zkey can't be a `Parameters<E>`, see above, but for the same circuit and toxic
+    /// waste the bellman prover and `ZKey::prove` both make proofs of the witness that verify.
+    fn check_both_provers<E: ZKeyEngine>(curve_type: &str, circuit_file: &str, wasm_file: &str) {
+        let witness_file = format!("/tmp/zkey_both_{}.wtns", curve_type);
+        let rng = &mut rand_old::thread_rng();
+        let [alpha, beta, gamma, delta, tau] = [(); 5].map(|_| E::Fr::rand(rng));
+        let r1cs = load_r1cs::<E>(circuit_file);
+        let zkey = zkey_for_setup(&r1cs, alpha, beta, gamma, delta, tau);
+        calculate_witness(wasm_file, WitnessGenerator::Wasm, INPUT_FILE, &witness_file).unwrap();
+        let witness = load_witness_from_bin_file::<E>(&witness_file);
+        let circuit = CircomCircuit {
+            r1cs,
+            witness: Some(witness.clone()),
+            wire_mapping: None,
+            aux_offset: 0,
+        };
+        let pk = generate_parameters::<E, _>(
+            circuit.clone(),
+            E::G1::one(),
+            E::G2::one(),
+            alpha,
+            beta,
+            gamma,
+            delta,
+            tau,
+        )
+        .unwrap();
+        // the same key but IC, which is over the domain of each prover
+        assert!(pk.vk.alpha_g1 == zkey.vk.alpha_g1);
+        assert!(pk.vk.beta_g2 == zkey.vk.beta_g2);
+        assert!(pk.vk.gamma_g2 == zkey.vk.gamma_g2);
+        assert!(pk.vk.delta_g2 == zkey.vk.delta_g2);
+        assert_eq!(pk.vk.ic.len(), zkey.vk.ic.len());
+
+        let proof = Groth16::prove(&pk, circuit, rng).unwrap();
+        let public = &witness[1..zkey.n_public + 1];
+        assert!(
+            Groth16::<E, CircomCircuit<E>>::verify_with_processed_vk(&pk.vk, public, &proof)
+                .unwrap()
+        );
+        assert!(verify(&zkey, &witness));
+    }
+
+    #[test]
+    fn test_both_provers_bn128() {
+        check_both_provers::<Bn256>("BN128", CIRCUIT_FILE, WASM_FILE);
+    }
+
+    #[test]
+    fn test_both_provers_bls12381() {
+        check_both_provers::<Bls12>("BLS12381", CIRCUIT_FILE_BLS12, WASM_FILE_BLS12);
+    }
+
+    fn check_zkey_prove(zkey_file: &str, prefix: &str) {
+        let pk_file = format!("{}.pk", prefix);
+        let vk_file = format!("{}.vk.json", prefix);
+        let public_input_file = format!("{}.public.json", prefix);
+        let proof_file = format!("{}.proof.json", prefix);
+        groth16_import_zkey("BN128", zkey_file, CIRCUIT_FILE, &pk_file, &vk_file, false).unwrap();
+        // the zkey directly, and the checked one
+        for pk_file in [zkey_file, pk_file.as_str()] {
+            groth16_prove(
+                "BN128",
+                CIRCUIT_FILE,
+                WASM_FILE,
+                WitnessGenerator::Wasm,
+                pk_file,
+                INPUT_FILE,
+                &public_input_file,
+                &proof_file,
+                false,
+            )
+            .unwrap();
+            groth16_verify("BN128", &vk_file, &public_input_file, &proof_file).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_zkey_prove() {
+        let r1cs = load_r1cs::<Bn256>(CIRCUIT_FILE);
+        random_zkey(&r1cs).write("/tmp/zkey_prove.zkey").unwrap();
+        check_zkey_prove("/tmp/zkey_prove.zkey", "/tmp/zkey_prove");
+    }
+
+    #[test]
+    #[ignore = "needs test/multiplier.zkey, made by test/snarkjs_zkey.sh"]
+    fn test_snarkjs_zkey_prove() {
+        check_zkey_prove(SNARKJS_ZKEY_FILE, "/tmp/snarkjs_zkey_prove");
+    }
+}
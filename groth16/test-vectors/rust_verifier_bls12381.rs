@@ -0,0 +1,146 @@
+//! Groth16 verifier of a BLS12381 verification key, generated by zkit.
+//!
+//! The module only uses `core`, and the arkworks crates without default features:
+//!
+//! ```toml
+//! ark-bls12-381 = { version = "0.4", default-features = false, features = ["curve"] }
+//! ark-ec = { version = "0.4", default-features = false }
+//! ark-ff = { version = "0.4", default-features = false }
+//! ```
+use ark_bls12_381::{Bls12_381 as Curve, Fq, Fq2, Fr, G1Affine, G2Affine};
+use ark_ec::{pairing::Pairing, AffineRepr, CurveGroup};
+use ark_ff::{One, PrimeField};
+
+/// The bytes of a big-endian base field element.
+pub const FQ_BYTES: usize = 48;
+/// The bytes of a big-endian scalar field element.
+pub const FR_BYTES: usize = 32;
+/// The bytes of a proof, a.x, a.y, b.x[0], b.x[1], b.y[0], b.y[1], c.x, c.y.
+pub const PROOF_BYTES: usize = 8 * FQ_BYTES;
+pub const NUM_INPUTS: usize = 2;
+
+type G1Bytes = [[u8; FQ_BYTES]; 2];
+type G2Bytes = [[u8; FQ_BYTES]; 4];
+
+pub const ALPHA_G1: G1Bytes = [
+    [0x0a, 0xd9, 0x47, 0x2e, 0x15, 0x26, 0x53, 0xb5, 0xf7, 0x16, 0x01, 0x76, 0xc3, 0x81, 0x79, 0x76, 0x3f, 0x9b, 0x52, 0x62, 0xed, 0xdc, 0xbb, 0x98, 0x59, 0xf7, 0x58, 0xd9, 0x10, 0x89, 0x33, 0x59, 0x11, 0x1b, 0x24, 0xba, 0x58, 0x1b, 0xdf, 0xfc, 0xeb, 0x63, 0x6f, 0x20, 0xe9, 0xa7, 0x8a, 0xe2],
+    [0x09, 0xd9, 0x30, 0x35, 0x60, 0xe6, 0x75, 0xf3, 0x48, 0x46, 0x89, 0x01, 0xb2, 0x52, 0xfd, 0xe9, 0x63, 0x6f, 0x42, 0x80, 0x1c, 0xfc, 0x6b, 0x86, 0x21, 0xb5, 0x49, 0x58, 0x3e, 0x20, 0xfa, 0x7c, 0x52, 0x42, 0x66, 0xcf, 0xf9, 0xbd, 0x78, 0x78, 0xad, 0x6e, 0x3c, 0x45, 0x52, 0x22, 0x1e, 0xdc],
+];
+pub const BETA_G2: G2Bytes = [
+    [0x18, 0x2f, 0xb0, 0xd4, 0x50, 0xd4, 0xfa, 0x15, 0xc2, 0xf4, 0x54, 0xaf, 0x7e, 0x87, 0x0e, 0x27, 0x34, 0xd5, 0xbf, 0xfb, 0x84, 0x65, 0x56, 0xa4, 0xf3, 0xfe, 0xc1, 0x90, 0x48, 0x2b, 0xe0, 0xc8, 0xd8, 0xcf, 0xe2, 0x19, 0xa0, 0xcb, 0x1d, 0x41, 0xf1, 0xa9, 0x71, 0x1c, 0xe7, 0x8d, 0xc3, 0xa3],
+    [0x0f, 0xa4, 0x62, 0x77, 0x44, 0x54, 0x60, 0x0c, 0xd1, 0xdb, 0xe0, 0x14, 0xd4, 0xed, 0x42, 0x50, 0x30, 0x4b, 0xeb, 0x6b, 0xe5, 0xc4, 0x48, 0x02, 0x21, 0x8f, 0x06, 0xce, 0x34, 0x5e, 0x91, 0xe4, 0xc5, 0x2f, 0x16, 0x7f, 0x4f, 0xf2, 0x0c, 0xb9, 0xb3, 0x42, 0x0a, 0x2d, 0x62, 0x61, 0xed, 0xa5],
+    [0x0d, 0xbc, 0x96, 0x19, 0x23, 0xb9, 0x6b, 0x9d, 0x28, 0xf5, 0xa4, 0x28, 0xd7, 0x6e, 0xe4, 0xc9, 0x71, 0x45, 0x89, 0xaf, 0xf0, 0xa2, 0x72, 0xfc, 0x0f, 0xe5, 0x9a, 0x0a, 0x46, 0x76, 0xa2, 0x2e, 0x93, 0xe1, 0xc5, 0x0a, 0x99, 0x63, 0x3f, 0xcd, 0xb3, 0x1b, 0xfd, 0xce, 0xc7, 0x92, 0x2e, 0x02],
+    [0x18, 0x05, 0xfd, 0xaa, 0xc9, 0x28, 0x38, 0x0a, 0xcb, 0x0d, 0x4b, 0xd5, 0x85, 0x3a, 0x2d, 0x11, 0x64, 0x21, 0xa0, 0xf4, 0xc3, 0x28, 0x66, 0x96, 0xcd, 0x26, 0xce, 0xd9, 0x93, 0x79, 0x30, 0x7b, 0x08, 0xc6, 0x5b, 0xbd, 0x3a, 0xf8, 0x27, 0x19, 0x68, 0x39, 0xa0, 0x95, 0x37, 0xfb, 0xdc, 0xef],
+];
+pub const GAMMA_G2: G2Bytes = [
+    [0x06, 0xd1, 0x10, 0x4d, 0x5f, 0xcc, 0x2c, 0x24, 0x1a, 0x60, 0xdb, 0x2b, 0xde, 0x51, 0x27, 0xa8, 0xeb, 0xda, 0xd1, 0x9d, 0xf7, 0xeb, 0xb9, 0xab, 0xb2, 0x65, 0x4b, 0xb7, 0x85, 0x4f, 0xa2, 0x77, 0xf8, 0xa2, 0x77, 0x4b, 0x95, 0x62, 0xd8, 0xfb, 0xc7, 0x79, 0x0e, 0x02, 0x36, 0xec, 0x21, 0xaa],
+    [0x15, 0xc5, 0x08, 0x24, 0x8b, 0xd0, 0x6d, 0x02, 0xa4, 0xc5, 0xde, 0x35, 0xe2, 0xa4, 0xcc, 0x0d, 0x1e, 0x22, 0x67, 0x85, 0xb3, 0x7e, 0x96, 0x92, 0xf2, 0x3b, 0x6e, 0xb1, 0x79, 0xe6, 0x84, 0x9d, 0xf4, 0x3c, 0x75, 0x61, 0x2e, 0xe0, 0xd3, 0x0e, 0x1d, 0xc6, 0x03, 0x20, 0x34, 0xea, 0x07, 0x31],
+    [0x12, 0xae, 0x76, 0x03, 0x9e, 0xaa, 0x07, 0xb1, 0xcc, 0xb3, 0xe8, 0xe3, 0x48, 0x03, 0xea, 0x90, 0x8d, 0x58, 0x44, 0x84, 0xf7, 0x83, 0x60, 0xbd, 0x0e, 0xc7, 0xb2, 0x5d, 0x21, 0xed, 0x4c, 0xe2, 0x14, 0xc8, 0x83, 0x93, 0x00, 0x81, 0x6a, 0x03, 0x41, 0x34, 0xb4, 0x27, 0xb6, 0x2b, 0x9e, 0x90],
+    [0x02, 0xc0, 0xbe, 0x03, 0x2d, 0x1d, 0x74, 0xc5, 0x2f, 0x37, 0x78, 0x2e, 0xc4, 0x1e, 0x06, 0x23, 0xbb, 0x45, 0x78, 0xc7, 0x65, 0x1c, 0x35, 0x4b, 0x23, 0xb9, 0x9e, 0x10, 0x5c, 0x48, 0x9b, 0xcd, 0x5e, 0x48, 0x4c, 0x08, 0xdc, 0xf6, 0xfd, 0x10, 0x64, 0xe3, 0xe8, 0x5e, 0x91, 0xd5, 0x99, 0x96],
+];
+pub const DELTA_G2: G2Bytes = [
+    [0x11, 0x17, 0xe1, 0x31, 0x6c, 0xc1, 0x6f, 0x16, 0x3d, 0xf6, 0x64, 0x16, 0xb6, 0x6c, 0x12, 0x7a, 0x52, 0x60, 0x66, 0x06, 0xb0, 0xea, 0xfa, 0xa7, 0x86, 0x8f, 0x6e, 0x5b, 0x79, 0x56, 0x2a, 0x05, 0x0e, 0x3b, 0xb1, 0x30, 0xf1, 0x11, 0x92, 0xe6, 0xa7, 0xd1, 0x25, 0x00, 0xc4, 0x8c, 0x8b, 0x19],
+    [0x00, 0x14, 0x0d, 0x77, 0xa9, 0x0f, 0x31, 0x8f, 0xb9, 0xe0, 0x50, 0x27, 0x86, 0x31, 0xda, 0x12, 0xeb, 0x6e, 0xf0, 0x6c, 0xeb, 0x94, 0x32, 0xb9, 0x17, 0x92, 0x04, 0xb1, 0x16, 0xa3, 0xb6, 0xac, 0xd1, 0x7f, 0xd7, 0x85, 0xd9, 0x35, 0x4e, 0x95, 0xc1, 0x86, 0x23, 0xcb, 0x13, 0x1f, 0xd2, 0xa7],
+    [0x15, 0x7e, 0x88, 0xe7, 0x77, 0x5d, 0x64, 0x07, 0x2e, 0xa0, 0xbc, 0x10, 0xde, 0x08, 0xbc, 0x6c, 0x65, 0x69, 0x5c, 0xb7, 0xb9, 0x6d, 0x8e, 0xd2, 0xa4, 0x85, 0x2a, 0x9b, 0x46, 0xa2, 0x3a, 0xed, 0xe5, 0xef, 0x9a, 0x86, 0xd0, 0x33, 0xb3, 0x16, 0x58, 0x7f, 0xc4, 0xc5, 0x70, 0xc3, 0xc5, 0x9d],
+    [0x08, 0xc6, 0x92, 0xf3, 0x7a, 0xfc, 0xb5, 0x6c, 0x13, 0x15, 0x24, 0xb6, 0x80, 0x4d, 0x15, 0x48, 0x98, 0xbb, 0x58, 0x25, 0xbb, 0xba, 0x25, 0xb1, 0x50, 0x2e, 0x4e, 0x3b, 0xe2, 0x28, 0xfe, 0x35, 0x15, 0xba, 0xb6, 0x84, 0xe8, 0xa4, 0x4c, 0x0f, 0xfa, 0x36, 0x83, 0x7d, 0x1b, 0x4c, 0x4a, 0x35],
+];
+pub const IC: [G1Bytes; NUM_INPUTS + 1] = [
+    [
+        [0x0b, 0xcf, 0xf6, 0x1c, 0x0f, 0xb9, 0xc7, 0x6d, 0x73, 0xfe, 0xb6, 0x17, 0xda, 0x40, 0x5d, 0x93, 0x0b, 0xfb, 0xcb, 0x97, 0xcc, 0xac, 0xe8, 0xb0, 0x18, 0x0e, 0xb6, 0x41, 0xb9, 0xad, 0xf6, 0xa9, 0x10, 0xab, 0x99, 0xef, 0x17, 0x36, 0x46, 0x26, 0x97, 0xd6, 0xb9, 0xe8, 0x7a, 0x10, 0xca, 0x83],
+        [0x19, 0xaa, 0xf4, 0x19, 0xdf, 0xd9, 0x1d, 0x25, 0x68, 0x4c, 0xdc, 0x40, 0x26, 0x02, 0xc3, 0xe9, 0x47, 0x94, 0x59, 0x7d, 0xe4, 0x75, 0x0b, 0xd1, 0x15, 0xbe, 0x26, 0x70, 0x51, 0x25, 0x05, 0x17, 0x27, 0xd0, 0x48, 0x84, 0xdc, 0x5c, 0x66, 0x21, 0x4e, 0x8c, 0xf9, 0xff, 0xe7, 0x36, 0xa7, 0x2a],
+    ],
+    [
+        [0x0a, 0xbe, 0x3a, 0x0d, 0x10, 0x8d, 0x23, 0x95, 0x26, 0x76, 0x0e, 0x57, 0x4f, 0x54, 0x87, 0x8f, 0xb5, 0x2b, 0x96, 0x7a, 0xf5, 0xc6, 0x7d, 0x5c, 0x07, 0xba, 0xcf, 0x79, 0x04, 0x2c, 0xb0, 0x05, 0xd2, 0x8b, 0xf4, 0x20, 0x87, 0xa2, 0xa2, 0x1f, 0x99, 0xc6, 0x6a, 0x53, 0x22, 0x1f, 0x5a, 0xea],
+        [0x06, 0xd2, 0x8d, 0xa9, 0xf5, 0xf6, 0x86, 0xd8, 0xaf, 0xbd, 0x0b, 0x6e, 0x62, 0xff, 0xad, 0x21, 0xdb, 0xed, 0x39, 0xf9, 0xb9, 0x29, 0xf7, 0xcb, 0x24, 0xe9, 0xb4, 0x56, 0x5c, 0xa7, 0xca, 0xe1, 0xed, 0x2e, 0x3c, 0x1c, 0x9d, 0xfe, 0xed, 0x38, 0xe6, 0x3d, 0x9f, 0x3b, 0xd3, 0xc8, 0x4f, 0x10],
+    ],
+    [
+        [0x08, 0x3e, 0x05, 0x85, 0xd4, 0x59, 0xbf, 0x3f, 0x35, 0x4e, 0xa5, 0x5b, 0xf3, 0x2f, 0xdb, 0xde, 0x3d, 0x3e, 0x84, 0x2f, 0x12, 0x85, 0x23, 0x87, 0xfb, 0xfd, 0x43, 0xc7, 0xbb, 0x0a, 0x95, 0x75, 0x1a, 0x87, 0xfb, 0xdb, 0xc1, 0xdc, 0x8b, 0x2d, 0xfb, 0xd9, 0xb3, 0xa9, 0xb9, 0xb5, 0x88, 0xac],
+        [0x09, 0x84, 0xf6, 0x13, 0xe2, 0xb4, 0xaa, 0xe8, 0xf7, 0x3b, 0x5d, 0xb3, 0xc6, 0x53, 0x48, 0xf7, 0xf5, 0xc8, 0x04, 0x32, 0x30, 0x9c, 0x70, 0x4c, 0x8a, 0x1d, 0x2d, 0xed, 0xf2, 0x68, 0xbf, 0xa8, 0x31, 0x65, 0x74, 0x12, 0x82, 0xe5, 0xee, 0xec, 0x49, 0x2c, 0x7b, 0xde, 0x1d, 0x77, 0x08, 0xe1],
+    ],
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The proof is not `PROOF_BYTES` long.
+    InvalidProofLength,
+    /// There are not `NUM_INPUTS` public inputs.
+    InvalidInputLength,
+    /// A coordinate or a public input is not in its field.
+    InvalidFieldElement,
+    /// A point is not on the curve or not in the prime order subgroup.
+    InvalidPoint,
+}
+
+/// Verify `proof` of the big-endian public `inputs`.
+pub fn verify(proof: &[u8], inputs: &[[u8; FR_BYTES]]) -> Result<bool, Error> {
+    if proof.len() != PROOF_BYTES {
+        return Err(Error::InvalidProofLength);
+    }
+    if inputs.len() != NUM_INPUTS {
+        return Err(Error::InvalidInputLength);
+    }
+    let word = |i: usize| &proof[i * FQ_BYTES..(i + 1) * FQ_BYTES];
+    let a = g1(word(0), word(1))?;
+    let b = g2(word(2), word(3), word(4), word(5))?;
+    let c = g1(word(6), word(7))?;
+
+    let mut vk_x = g1(&IC[0][0], &IC[0][1])?.into_group();
+    for (input, ic) in inputs.iter().zip(IC[1..].iter()) {
+        let input: Fr = field(input)?;
+        vk_x += g1(&ic[0], &ic[1])?.mul_bigint(input.into_bigint());
+    }
+
+    // e(A, B) = e(alpha, beta) * e(vk_x, gamma) * e(C, delta)
+    let result = Curve::multi_pairing(
+        [a, -vk_x.into_affine(), -c, -g1_constant(&ALPHA_G1)?],
+        [
+            b,
+            g2_constant(&GAMMA_G2)?,
+            g2_constant(&DELTA_G2)?,
+            g2_constant(&BETA_G2)?,
+        ],
+    );
+    Ok(result.0.is_one())
+}
+
+fn field<F: PrimeField>(bytes: &[u8]) -> Result<F, Error> {
+    let mut repr = F::BigInt::default();
+    let limbs = repr.as_mut();
+    let n = limbs.len();
+    if bytes.len() != n * 8 {
+        return Err(Error::InvalidFieldElement);
+    }
+    for (i, limb) in limbs.iter_mut().enumerate() {
+        let start = (n - 1 - i) * 8;
+        let mut word = [0u8; 8];
+        word.copy_from_slice(&bytes[start..start + 8]);
+        *limb = u64::from_be_bytes(word);
+    }
+    F::from_bigint(repr).ok_or(Error::InvalidFieldElement)
+}
+
+fn g1(x: &[u8], y: &[u8]) -> Result<G1Affine, Error> {
+    let p = G1Affine::new_unchecked(field(x)?, field(y)?);
+    if !p.is_on_curve() || !p.is_in_correct_subgroup_assuming_on_curve() {
+        return Err(Error::InvalidPoint);
+    }
+    Ok(p)
+}
+
+fn g2(x0: &[u8], x1: &[u8], y0: &[u8], y1: &[u8]) -> Result<G2Affine, Error> {
+    let x = Fq2::new(field::<Fq>(x0)?, field::<Fq>(x1)?);
+    let y = Fq2::new(field::<Fq>(y0)?, field::<Fq>(y1)?);
+    let p = G2Affine::new_unchecked(x, y);
+    if !p.is_on_curve() || !p.is_in_correct_subgroup_assuming_on_curve() {
+        return Err(Error::InvalidPoint);
+    }
+    Ok(p)
+}
+
+fn g1_constant(p: &G1Bytes) -> Result<G1Affine, Error> {
+    g1(&p[0], &p[1])
+}
+
+fn g2_constant(p: &G2Bytes) -> Result<G2Affine, Error> {
+    g2(&p[0], &p[1], &p[2], &p[3])
+}
@@ -0,0 +1,146 @@
+//! Groth16 verifier of a BN128 verification key, generated by zkit.
+//!
+//! The module only uses `core`, and the arkworks crates without default features:
+//!
+//! ```toml
+//! ark-bn254 = { version = "0.4", default-features = false, features = ["curve"] }
+//! ark-ec = { version = "0.4", default-features = false }
+//! ark-ff = { version = "0.4", default-features = false }
+//! ```
+use ark_bn254::{Bn254 as Curve, Fq, Fq2, Fr, G1Affine, G2Affine};
+use ark_ec::{pairing::Pairing, AffineRepr, CurveGroup};
+use ark_ff::{One, PrimeField};
+
+/// The bytes of a big-endian base field element.
+pub const FQ_BYTES: usize = 32;
+/// The bytes of a big-endian scalar field element.
+pub const FR_BYTES: usize = 32;
+/// The bytes of a proof, a.x, a.y, b.x[0], b.x[1], b.y[0], b.y[1], c.x, c.y.
+pub const PROOF_BYTES: usize = 8 * FQ_BYTES;
+pub const NUM_INPUTS: usize = 2;
+
+type G1Bytes = [[u8; FQ_BYTES]; 2];
+type G2Bytes = [[u8; FQ_BYTES]; 4];
+
+pub const ALPHA_G1: G1Bytes = [
+    [0x04, 0x3a, 0xb8, 0xc3, 0x30, 0x7e, 0xf6, 0x68, 0x49, 0xd1, 0x03, 0xd8, 0x5f, 0xa8, 0xb7, 0xa3, 0xc6, 0x50, 0x60, 0x50, 0x7f, 0xd0, 0x01, 0x08, 0xc4, 0x1c, 0x9f, 0xff, 0x6d, 0x48, 0x3e, 0x18],
+    [0x26, 0x1d, 0xd1, 0x44, 0xa8, 0xe7, 0x36, 0x4b, 0x95, 0xbc, 0x2e, 0x0e, 0x69, 0x08, 0x7e, 0xf6, 0xff, 0x04, 0x22, 0xba, 0x88, 0xd8, 0xd6, 0x72, 0xbe, 0x1a, 0x3b, 0xe0, 0x4c, 0x34, 0xe6, 0x16],
+];
+pub const BETA_G2: G2Bytes = [
+    [0x06, 0xe1, 0x56, 0x11, 0x0c, 0x9c, 0x86, 0xbe, 0x38, 0x5a, 0x01, 0x87, 0x54, 0x8b, 0x14, 0xe7, 0x34, 0xff, 0xb5, 0xdd, 0x96, 0x12, 0x3f, 0x4a, 0xd1, 0x60, 0x2a, 0x6e, 0x15, 0xef, 0x67, 0xab],
+    [0x1a, 0x3f, 0x6f, 0xe2, 0xe3, 0x6c, 0x60, 0xf4, 0x4b, 0x58, 0x51, 0xde, 0x14, 0xcd, 0x19, 0x24, 0xd7, 0xac, 0x25, 0x89, 0x73, 0x56, 0x01, 0xc2, 0xcb, 0x57, 0xc5, 0x1d, 0xf6, 0xe2, 0xd5, 0xdc],
+    [0x20, 0x54, 0x50, 0x6b, 0xb7, 0x7c, 0xad, 0xf9, 0xf7, 0xb8, 0xd6, 0xb8, 0x17, 0xcb, 0xe9, 0xa9, 0xde, 0xc9, 0x80, 0xce, 0x7f, 0x52, 0x1a, 0x48, 0x31, 0xb8, 0x0a, 0xec, 0xf6, 0x27, 0xd9, 0xeb],
+    [0x21, 0x6e, 0x4b, 0x2c, 0xfc, 0x28, 0xc3, 0xc3, 0xc3, 0x7e, 0xaa, 0x48, 0x5b, 0xdc, 0x75, 0x74, 0x71, 0x24, 0xc2, 0x72, 0xe6, 0xb8, 0xa1, 0xc4, 0x84, 0x03, 0x6e, 0x0a, 0x09, 0x17, 0x8d, 0x73],
+];
+pub const GAMMA_G2: G2Bytes = [
+    [0x0b, 0x56, 0x10, 0x4f, 0x0e, 0xf2, 0x83, 0x38, 0xcf, 0x9e, 0x4c, 0x72, 0x0d, 0xd9, 0xc3, 0x66, 0x0b, 0x96, 0xd4, 0x05, 0x94, 0x10, 0x43, 0xcb, 0xa5, 0x4b, 0xb2, 0xc2, 0x2f, 0x7d, 0xca, 0x88],
+    [0x05, 0x1d, 0x34, 0x5c, 0x29, 0xee, 0x02, 0xae, 0xc7, 0x0e, 0xbf, 0x9b, 0x45, 0xb3, 0xa5, 0x5e, 0x90, 0x5e, 0x39, 0x51, 0x67, 0xee, 0x54, 0x01, 0x90, 0x4b, 0x9e, 0xcb, 0x84, 0xe5, 0xc1, 0x37],
+    [0x26, 0x23, 0xb1, 0x47, 0x53, 0x43, 0x2e, 0xbf, 0x6e, 0x71, 0xd7, 0xd7, 0xce, 0xa9, 0xbb, 0x12, 0xb1, 0xa2, 0x40, 0x64, 0xb7, 0xb8, 0x3d, 0xd7, 0x2d, 0xf9, 0x1e, 0x89, 0x8c, 0x16, 0x6e, 0x56],
+    [0x18, 0xcd, 0x00, 0xf7, 0x1a, 0x08, 0x53, 0x94, 0xa3, 0xf5, 0xcf, 0x0f, 0x61, 0x7a, 0x17, 0xac, 0x1c, 0xd0, 0x75, 0xfb, 0x8b, 0x06, 0xf9, 0x54, 0xaf, 0x24, 0x6f, 0x09, 0x52, 0xdd, 0xc7, 0x6b],
+];
+pub const DELTA_G2: G2Bytes = [
+    [0x20, 0xe7, 0xbe, 0x21, 0x76, 0xde, 0x42, 0xf0, 0x29, 0x75, 0xab, 0xe5, 0xa1, 0xd0, 0x62, 0x22, 0x25, 0x5f, 0x63, 0xaa, 0xf9, 0x26, 0x5d, 0x54, 0x7e, 0x00, 0x3e, 0x36, 0xb0, 0x29, 0x4a, 0x23],
+    [0x19, 0x81, 0x41, 0xa3, 0xda, 0xd2, 0x83, 0xd2, 0x87, 0x23, 0xc2, 0x37, 0x2c, 0x2d, 0x33, 0xf3, 0x0f, 0x9a, 0x21, 0xcb, 0x93, 0x8b, 0x29, 0xc0, 0x1b, 0x29, 0xad, 0xc1, 0x1f, 0x25, 0xf3, 0xbb],
+    [0x02, 0x25, 0x13, 0x62, 0x86, 0x23, 0x6f, 0x33, 0xb3, 0x10, 0x66, 0x5c, 0xd1, 0x94, 0x49, 0x2f, 0x9b, 0x85, 0xcb, 0x14, 0x32, 0xa3, 0xe7, 0x12, 0x5c, 0x4b, 0x29, 0xde, 0x68, 0x47, 0x2c, 0xd7],
+    [0x0a, 0xc1, 0x77, 0x61, 0xf6, 0x0a, 0xc5, 0x90, 0xd9, 0x6f, 0xdf, 0xac, 0x14, 0xb1, 0xaf, 0xa2, 0x26, 0x25, 0xf3, 0xbd, 0xb1, 0x0a, 0xd7, 0xe8, 0x6d, 0xcf, 0xfe, 0x59, 0x58, 0x0f, 0xb7, 0xf7],
+];
+pub const IC: [G1Bytes; NUM_INPUTS + 1] = [
+    [
+        [0x12, 0x4d, 0x0d, 0xb8, 0x9e, 0xfe, 0x08, 0x25, 0xdd, 0xe5, 0xf6, 0x1b, 0xf6, 0x0c, 0xee, 0xfe, 0xae, 0x99, 0x0f, 0xa3, 0x9e, 0xe4, 0xe2, 0xb7, 0x89, 0x25, 0x6c, 0x98, 0x8d, 0x83, 0xb6, 0xaf],
+        [0x22, 0xe9, 0x24, 0x0e, 0x48, 0x08, 0x24, 0xac, 0x45, 0xe0, 0x34, 0xbc, 0xd8, 0x3d, 0x98, 0xb4, 0x61, 0x29, 0x71, 0x4a, 0xd1, 0x0a, 0xf0, 0xc4, 0xb0, 0x56, 0x52, 0x60, 0x98, 0x19, 0x50, 0x08],
+    ],
+    [
+        [0x1c, 0x77, 0xf6, 0x9a, 0x71, 0xae, 0x54, 0x79, 0xf9, 0x91, 0x36, 0x34, 0xc6, 0xc7, 0x0a, 0x52, 0x44, 0x91, 0x2b, 0xda, 0x90, 0x90, 0xf9, 0x54, 0x12, 0x8b, 0x9f, 0xcf, 0x04, 0x26, 0xc8, 0xd5],
+        [0x09, 0x0c, 0x42, 0xcd, 0x6e, 0x97, 0xc3, 0xfb, 0x2f, 0xac, 0x1b, 0x9c, 0xb5, 0xbc, 0x21, 0x33, 0xe2, 0x47, 0xa7, 0x61, 0x0f, 0xb3, 0x17, 0xe5, 0xbc, 0x8a, 0x4b, 0xa7, 0xbb, 0xaa, 0x49, 0x8b],
+    ],
+    [
+        [0x16, 0x28, 0x5f, 0x31, 0x4b, 0xae, 0xef, 0xc7, 0x76, 0x35, 0xba, 0x3a, 0x6a, 0xfb, 0x63, 0x25, 0x4e, 0xe8, 0xc5, 0x12, 0xb7, 0x6d, 0x4f, 0x60, 0x06, 0xef, 0xea, 0x14, 0x9c, 0xc2, 0xf6, 0xc5],
+        [0x11, 0x41, 0xae, 0x9c, 0xeb, 0x3a, 0x0d, 0x0b, 0x8d, 0xa2, 0x03, 0xfd, 0x71, 0x82, 0xb2, 0x2b, 0x25, 0x7d, 0xaf, 0xcb, 0x53, 0xdb, 0x8e, 0x52, 0xfd, 0x81, 0x55, 0xbb, 0x65, 0x0f, 0x2e, 0x09],
+    ],
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// The proof is not `PROOF_BYTES` long.
+    InvalidProofLength,
+    /// There are not `NUM_INPUTS` public inputs.
+    InvalidInputLength,
+    /// A coordinate or a public input is not in its field.
+    InvalidFieldElement,
+    /// A point is not on the curve or not in the prime order subgroup.
+    InvalidPoint,
+}
+
+/// Verify `proof` of the big-endian public `inputs`.
+pub fn verify(proof: &[u8], inputs: &[[u8; FR_BYTES]]) -> Result<bool, Error> {
+    if proof.len() != PROOF_BYTES {
+        return Err(Error::InvalidProofLength);
+    }
+    if inputs.len() != NUM_INPUTS {
+        return Err(Error::InvalidInputLength);
+    }
+    let word = |i: usize| &proof[i * FQ_BYTES..(i + 1) * FQ_BYTES];
+    let a = g1(word(0), word(1))?;
+    let b = g2(word(2), word(3), word(4), word(5))?;
+    let c = g1(word(6), word(7))?;
+
+    let mut vk_x = g1(&IC[0][0], &IC[0][1])?.into_group();
+    for (input, ic) in inputs.iter().zip(IC[1..].iter()) {
+        let input: Fr = field(input)?;
+        vk_x += g1(&ic[0], &ic[1])?.mul_bigint(input.into_bigint());
+    }
+
+    // e(A, B) = e(alpha, beta) * e(vk_x, gamma) * e(C, delta)
+    let result = Curve::multi_pairing(
+        [a, -vk_x.into_affine(), -c, -g1_constant(&ALPHA_G1)?],
+        [
+            b,
+            g2_constant(&GAMMA_G2)?,
+            g2_constant(&DELTA_G2)?,
+            g2_constant(&BETA_G2)?,
+        ],
+    );
+    Ok(result.0.is_one())
+}
+
+fn field<F: PrimeField>(bytes: &[u8]) -> Result<F, Error> {
+    let mut repr = F::BigInt::default();
+    let limbs = repr.as_mut();
+    let n = limbs.len();
+    if bytes.len() != n * 8 {
+        return Err(Error::InvalidFieldElement);
+    }
+    for (i, limb) in limbs.iter_mut().enumerate() {
+        let start = (n - 1 - i) * 8;
+        let mut word = [0u8; 8];
+        word.copy_from_slice(&bytes[start..start + 8]);
+        *limb = u64::from_be_bytes(word);
+    }
+    F::from_bigint(repr).ok_or(Error::InvalidFieldElement)
+}
+
+fn g1(x: &[u8], y: &[u8]) -> Result<G1Affine, Error> {
+    let p = G1Affine::new_unchecked(field(x)?, field(y)?);
+    if !p.is_on_curve() || !p.is_in_correct_subgroup_assuming_on_curve() {
+        return Err(Error::InvalidPoint);
+    }
+    Ok(p)
+}
+
+fn g2(x0: &[u8], x1: &[u8], y0: &[u8], y1: &[u8]) -> Result<G2Affine, Error> {
+    let x = Fq2::new(field::<Fq>(x0)?, field::<Fq>(x1)?);
+    let y = Fq2::new(field::<Fq>(y0)?, field::<Fq>(y1)?);
+    let p = G2Affine::new_unchecked(x, y);
+    if !p.is_on_curve() || !p.is_in_correct_subgroup_assuming_on_curve() {
+        return Err(Error::InvalidPoint);
+    }
+    Ok(p)
+}
+
+fn g1_constant(p: &G1Bytes) -> Result<G1Affine, Error> {
+    g1(&p[0], &p[1])
+}
+
+fn g2_constant(p: &G2Bytes) -> Result<G2Affine, Error> {
+    g2(&p[0], &p[1], &p[2], &p[3])
+}
@@ -0,0 +1,225 @@
+//! Recursive aggregation of Plonk proofs, with the circuit of zksync.
+//!
+//! The proofs to aggregate must be proved with the rescue transcript. The aggregation circuit
+//! checks them against a tree of verification keys, and its one public input commits to the
+//! tree root, the indexes of the keys, the inputs of the proofs and the limbs of the pairing
+//! points it aggregates. The pairing check of these points is left to the verifier of the
+//! aggregation proof, which gets their limbs, see `serialize_aggregation_proof`.
+use crate::bellman_ce::{
+    kate_commitment::{Crs, CrsForMonomialForm},
+    pairing::bn256::Bn256,
+    plonk::{
+        better_better_cs::{proof::Proof, setup::VerificationKey, verifier::verify},
+        commitments::transcript::keccak_transcript::RollingKeccakTranscript,
+    },
+    worker::Worker,
+    ScalarEngine,
+};
+use crate::plonk::{fe_to_string, g1_to_strings, PlonkProof, PlonkVerificationKey};
+use anyhow::{bail, Result};
+use recursive_aggregation_circuit::circuit::{
+    create_recursive_circuit_vk_and_setup, create_vks_tree, create_zksync_recursive_aggregate,
+    proof_recursive_aggregate_for_zksync, RecursiveAggreagationDataStorage,
+    RecursiveAggregationCircuitBn256,
+};
+
+pub const VK_TREE_DEPTH: usize = 3;
+/// The aggregation circuit indexes the proofs by a byte.
+pub const MAX_PROOFS_TO_CHECK: usize = 255;
+
+pub type AggregationProof = Proof<Bn256, RecursiveAggregationCircuitBn256<'static>>;
+pub type AggregationVerificationKey =
+    VerificationKey<Bn256, RecursiveAggregationCircuitBn256<'static>>;
+
+/// The verification key of the circuit aggregating `num_proofs_to_check` proofs of
+/// `num_inputs` public inputs each.
+pub fn export_vk(
+    num_proofs_to_check: usize,
+    num_inputs: usize,
+    big_crs: &Crs<Bn256, CrsForMonomialForm>,
+) -> Result<AggregationVerificationKey> {
+    check_sizes(num_proofs_to_check)?;
+    let (vk, _) = create_recursive_circuit_vk_and_setup(
+        num_proofs_to_check,
+        num_inputs,
+        VK_TREE_DEPTH,
+        big_crs,
+    )?;
+    Ok(vk)
+}
+
+pub fn prove(
+    big_crs: &Crs<Bn256, CrsForMonomialForm>,
+    old_proofs: &[PlonkProof],
+    old_vk: &PlonkVerificationKey,
+) -> Result<AggregationProof> {
+    let num_inputs = old_vk.num_inputs;
+    check_sizes(old_proofs.len())?;
+    if let Some(i) = old_proofs
+        .iter()
+        .position(|p| p.input_values.len() != num_inputs)
+    {
+        bail!(
+            "proof {} has {} public inputs, but the verification key {}",
+            i,
+            old_proofs[i].input_values.len(),
+            num_inputs
+        );
+    }
+    let (vk, setup) = create_recursive_circuit_vk_and_setup(
+        old_proofs.len(),
+        num_inputs,
+        VK_TREE_DEPTH,
+        big_crs,
+    )?;
+    let vk_indexes = vec![0; old_proofs.len()];
+    let proof = proof_recursive_aggregate_for_zksync(
+        VK_TREE_DEPTH,
+        num_inputs,
+        &[old_vk.clone()],
+        old_proofs,
+        &vk_indexes,
+        &vk,
+        &setup,
+        big_crs,
+        true,
+        &Worker::new(),
+    )?;
+    Ok(proof)
+}
+
+pub fn verify_proof(vk: &AggregationVerificationKey, proof: &AggregationProof) -> Result<bool> {
+    Ok(verify::<
+        _,
+        _,
+        RollingKeccakTranscript<<Bn256 as ScalarEngine>::Fr>,
+    >(vk, proof, None)?)
+}
+
+/// Check that `new_proof` aggregates `old_proofs` of `old_vk`: its public input must be the
+/// commitment recomputed from them. The proof itself is checked by `verify_proof`.
+pub fn check_aggregation(
+    old_proofs: &[PlonkProof],
+    old_vk: &PlonkVerificationKey,
+    new_proof: &AggregationProof,
+) -> Result<bool> {
+    let aggregate = aggregate_old_proofs(old_proofs, old_vk)?;
+    Ok(new_proof.inputs.len() == 1 && new_proof.inputs[0] == aggregate.expected_recursive_input)
+}
+
+/// The arguments of `verifyAggregatedProof` of the aggregation verifier contract: the public
+/// input, the proof, the key indexes, the inputs of the aggregated proofs and the limbs of the
+/// aggregated pairing points.
+pub fn serialize_aggregation_proof(
+    old_proofs: &[PlonkProof],
+    old_vk: &PlonkVerificationKey,
+    proof: &AggregationProof,
+) -> Result<serde_json::Value> {
+    let aggregate = aggregate_old_proofs(old_proofs, old_vk)?;
+    let inputs: Vec<String> = proof.inputs.iter().map(fe_to_string).collect();
+
+    let mut serialized_proof = vec![];
+    for c in proof.state_polys_commitments.iter() {
+        serialized_proof.extend(g1_to_strings(c));
+    }
+    serialized_proof.extend(g1_to_strings(
+        &proof.copy_permutation_grand_product_commitment,
+    ));
+    for c in proof.quotient_poly_parts_commitments.iter() {
+        serialized_proof.extend(g1_to_strings(c));
+    }
+    for c in proof.state_polys_openings_at_z.iter() {
+        serialized_proof.push(fe_to_string(c));
+    }
+    for (_, _, c) in proof.state_polys_openings_at_dilations.iter() {
+        serialized_proof.push(fe_to_string(c));
+    }
+    if !proof.gate_setup_openings_at_z.is_empty() {
+        bail!("the aggregation circuit opens no gate setup at z");
+    }
+    for (_, c) in proof.gate_selectors_openings_at_z.iter() {
+        serialized_proof.push(fe_to_string(c));
+    }
+    for c in proof.copy_permutation_polys_openings_at_z.iter() {
+        serialized_proof.push(fe_to_string(c));
+    }
+    serialized_proof.push(fe_to_string(
+        &proof.copy_permutation_grand_product_opening_at_z_omega,
+    ));
+    serialized_proof.push(fe_to_string(&proof.quotient_poly_opening_at_z));
+    serialized_proof.push(fe_to_string(&proof.linearization_poly_opening_at_z));
+    serialized_proof.extend(g1_to_strings(&proof.opening_proof_at_z));
+    serialized_proof.extend(g1_to_strings(&proof.opening_proof_at_z_omega));
+
+    let individual_vk_inputs: Vec<String> = old_proofs
+        .iter()
+        .flat_map(|p| p.input_values.iter().map(fe_to_string))
+        .collect();
+    let subproofs_limbs: Vec<String> = aggregate
+        .limbed_aggregated_g1_elements
+        .iter()
+        .map(fe_to_string)
+        .collect();
+    Ok(serde_json::json!([
+        inputs,
+        serialized_proof,
+        aggregate.indexes_of_used_proofs,
+        individual_vk_inputs,
+        subproofs_limbs,
+    ]))
+}
+
+/// Render the `KeysWithPlonkVerifier` contract, whose `verifyAggregatedProof` takes the
+/// arguments of `serialize_aggregation_proof`. It checks the aggregation proof by `new_vk`, and
+/// that the aggregated proofs, of `num_inputs` public inputs each, are of the tree of `old_vk`.
+pub fn generate_verifier(
+    old_vk: &PlonkVerificationKey,
+    new_vk: &AggregationVerificationKey,
+    num_inputs: usize,
+    sol: &str,
+) -> Result<()> {
+    if old_vk.num_inputs != num_inputs {
+        bail!(
+            "the verification key has {} public inputs, not {}",
+            old_vk.num_inputs,
+            num_inputs
+        );
+    }
+    let (_, (vks_tree, _)) = create_vks_tree(&[old_vk.clone()], VK_TREE_DEPTH)?;
+    bellman_vk_codegen::render_aggregation_verification_key_from_default_template(
+        new_vk,
+        &vks_tree.get_commitment(),
+        num_inputs,
+        VK_TREE_DEPTH,
+        &old_vk.g2_elements,
+        sol,
+    )?;
+    Ok(())
+}
+
+fn aggregate_old_proofs(
+    old_proofs: &[PlonkProof],
+    old_vk: &PlonkVerificationKey,
+) -> Result<RecursiveAggreagationDataStorage<Bn256>> {
+    check_sizes(old_proofs.len())?;
+    let vk_indexes = vec![0; old_proofs.len()];
+    Ok(create_zksync_recursive_aggregate(
+        VK_TREE_DEPTH,
+        old_vk.num_inputs,
+        &[old_vk.clone()],
+        old_proofs,
+        &vk_indexes,
+        &old_vk.g2_elements,
+    )?)
+}
+
+fn check_sizes(num_proofs_to_check: usize) -> Result<()> {
+    if num_proofs_to_check == 0 || num_proofs_to_check > MAX_PROOFS_TO_CHECK {
+        bail!(
+            "the number of proofs to aggregate must be in [1, {}], not {}",
+            MAX_PROOFS_TO_CHECK,
+            num_proofs_to_check
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,348 @@
+use crate::aggregation::{self, AggregationProof, AggregationVerificationKey};
+use crate::bellman_ce::pairing::bn256::Bn256;
+use crate::plonk::{self, PlonkProof, PlonkVerificationKey, SetupForProver};
+use algebraic::circom_circuit::CircomCircuit;
+use algebraic::reader;
+use anyhow::{anyhow, bail, Result};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter};
+use std::path::Path;
+
+/// Generate the monomial form SRS of 2^power points, see `plonk::gen_key_monomial_form`. Its
+/// toxic waste is known, so it must be asked for by `insecure`.
+pub fn setup(power: u32, srs_monomial_form: &str, insecure: bool) -> Result<()> {
+    if !insecure {
+        bail!(
+            "the generated SRS has the known toxic waste 42 and is only for tests, download \
+             setup_2^{}.key of a ceremony from https://universal-setup.ams3.digitaloceanspaces.com \
+             or pass --insecure",
+            power
+        );
+    }
+    if Path::new(srs_monomial_form).exists() {
+        bail!("the SRS {} already exists", srs_monomial_form);
+    }
+    let srs = plonk::gen_key_monomial_form(power)?;
+    srs.write(BufWriter::new(File::create(srs_monomial_form)?))?;
+    println!("srs_monomial_form saved to {}", srs_monomial_form);
+    Ok(())
+}
+
+pub fn export_verification_key(
+    srs_monomial_form: &str,
+    circuit_file: &str,
+    output_vk: &str,
+) -> Result<()> {
+    let circuit = create_circuit(circuit_file, None)?;
+    let setup = SetupForProver::prepare_setup_for_prover(
+        circuit,
+        reader::load_key_monomial_form(srs_monomial_form),
+    )?;
+    let vk = setup.make_verification_key()?;
+    vk.write(BufWriter::new(File::create(output_vk)?))?;
+    println!("Verification key saved to {}", output_vk);
+    Ok(())
+}
+
+/// Prove the circuit with its witness, a wtns or json file. The proof is written in binary
+/// for `verify` and `aggregation_prove`, and as the json arguments of the Solidity verifier.
+pub fn prove(
+    circuit_file: &str,
+    witness: &str,
+    srs_monomial_form: &str,
+    transcript: &str,
+    proof_bin: &str,
+    proof_json: &str,
+    public_json: &str,
+) -> Result<()> {
+    let circuit = create_circuit(circuit_file, Some(witness))?;
+    let setup = SetupForProver::prepare_setup_for_prover(
+        circuit.clone(),
+        reader::load_key_monomial_form(srs_monomial_form),
+    )?;
+    let proof = setup.prove(circuit, transcript)?;
+    proof.write(BufWriter::new(File::create(proof_bin)?))?;
+    println!("Proof saved to {}", proof_bin);
+
+    let (inputs, serialized_proof) = plonk::serialize_proof(&proof);
+    std::fs::write(proof_json, serde_json::to_string_pretty(&serialized_proof)?)?;
+    std::fs::write(public_json, serde_json::to_string_pretty(&inputs)?)?;
+    Ok(())
+}
+
+pub fn verify(vk_file: &str, proof_bin: &str, transcript: &str) -> Result<()> {
+    let vk = load_verification_key(vk_file)?;
+    let proof = load_proof(proof_bin)?;
+    if !plonk::verify(&vk, &proof, transcript)? {
+        bail!("Proof is invalid");
+    }
+    println!("Proof is valid");
+    Ok(())
+}
+
+pub fn generate_verifier(vk_file: &str, sol: &str) -> Result<()> {
+    let vk = load_verification_key(vk_file)?;
+    bellman_vk_codegen::render_verification_key_from_default_template(&vk, sol, true);
+    println!("Generate solidity verifier successfully!");
+    Ok(())
+}
+
+pub fn export_aggregation_verification_key(
+    num_proofs_to_check: usize,
+    num_inputs: usize,
+    srs_monomial_form: &str,
+    output_vk: &str,
+) -> Result<()> {
+    let big_crs = reader::load_key_monomial_form(srs_monomial_form);
+    let vk = aggregation::export_vk(num_proofs_to_check, num_inputs, &big_crs)?;
+    vk.write(BufWriter::new(File::create(output_vk)?))?;
+    println!("Aggregation verification key saved to {}", output_vk);
+    Ok(())
+}
+
+/// Aggregate the rescue transcript proofs listed in `old_proof_list`, one path per line, of
+/// the verification key `old_vk`.
+pub fn aggregation_prove(
+    srs_monomial_form: &str,
+    old_proof_list: &str,
+    old_vk: &str,
+    new_proof: &str,
+    proof_json: &str,
+) -> Result<()> {
+    let big_crs = reader::load_key_monomial_form(srs_monomial_form);
+    let old_proofs = load_proofs_from_list(old_proof_list)?;
+    let old_vk = load_verification_key(old_vk)?;
+    let proof = aggregation::prove(&big_crs, &old_proofs, &old_vk)?;
+    proof.write(BufWriter::new(File::create(new_proof)?))?;
+    println!("Aggregation proof saved to {}", new_proof);
+
+    let json = aggregation::serialize_aggregation_proof(&old_proofs, &old_vk, &proof)?;
+    std::fs::write(proof_json, serde_json::to_string_pretty(&json)?)?;
+    Ok(())
+}
+
+pub fn aggregation_verify(proof: &str, vk: &str) -> Result<()> {
+    let vk = load_aggregation_verification_key(vk)?;
+    let proof = load_aggregation_proof(proof)?;
+    if !aggregation::verify_proof(&vk, &proof)? {
+        bail!("Aggregation proof is invalid");
+    }
+    println!("Aggregation proof is valid");
+    Ok(())
+}
+
+pub fn aggregation_check(old_proof_list: &str, old_vk: &str, new_proof: &str) -> Result<()> {
+    let old_proofs = load_proofs_from_list(old_proof_list)?;
+    let old_vk = load_verification_key(old_vk)?;
+    let new_proof = load_aggregation_proof(new_proof)?;
+    if !aggregation::check_aggregation(&old_proofs, &old_vk, &new_proof)? {
+        bail!("the aggregation proof does not aggregate the proofs");
+    }
+    println!("Aggregation check passed");
+    Ok(())
+}
+
+/// Generate the Solidity verifier of the aggregation proofs of `aggregation_prove`, see
+/// `aggregation::generate_verifier`.
+pub fn generate_aggregation_verifier(
+    old_vk: &str,
+    new_vk: &str,
+    num_inputs: usize,
+    sol: &str,
+) -> Result<()> {
+    let old_vk = load_verification_key(old_vk)?;
+    let new_vk = load_aggregation_verification_key(new_vk)?;
+    aggregation::generate_verifier(&old_vk, &new_vk, num_inputs, sol)?;
+    println!("Generate solidity aggregation verifier successfully!");
+    Ok(())
+}
+
+fn create_circuit(circuit_file: &str, witness: Option<&str>) -> Result<CircomCircuit<Bn256>> {
+    let r1cs = reader::load_r1cs(circuit_file);
+    let witness = match witness {
+        Some(witness) => {
+            let file = File::open(witness).map_err(|e| anyhow!("Open {}, {:?}", witness, e))?;
+            let w = if witness.ends_with("json") {
+                reader::load_witness_from_json::<Bn256, _>(BufReader::new(file))?
+            } else {
+                reader::load_witness_from_bin_reader::<Bn256, _>(BufReader::new(file))?
+            };
+            if w.len() != r1cs.num_variables {
+                bail!(
+                    "The witness has {} values, but the R1CS has {} wires",
+                    w.len(),
+                    r1cs.num_variables
+                );
+            }
+            Some(w)
+        }
+        None => None,
+    };
+    Ok(CircomCircuit {
+        r1cs,
+        witness,
+        wire_mapping: None,
+        aux_offset: plonk::AUX_OFFSET,
+    })
+}
+
+fn load_verification_key(vk_file: &str) -> Result<PlonkVerificationKey> {
+    Ok(PlonkVerificationKey::read(BufReader::new(File::open(
+        vk_file,
+    )?))?)
+}
+
+fn load_proof(proof_bin: &str) -> Result<PlonkProof> {
+    Ok(PlonkProof::read(BufReader::new(File::open(proof_bin)?))?)
+}
+
+fn load_proofs_from_list(old_proof_list: &str) -> Result<Vec<PlonkProof>> {
+    let mut proofs = vec![];
+    for line in BufReader::new(File::open(old_proof_list)?).lines() {
+        let line = line?;
+        let path = line.trim();
+        if !path.is_empty() {
+            proofs.push(load_proof(path)?);
+        }
+    }
+    Ok(proofs)
+}
+
+fn load_aggregation_verification_key(vk_file: &str) -> Result<AggregationVerificationKey> {
+    Ok(AggregationVerificationKey::read(BufReader::new(
+        File::open(vk_file)?,
+    ))?)
+}
+
+fn load_aggregation_proof(proof: &str) -> Result<AggregationProof> {
+    Ok(AggregationProof::read(BufReader::new(File::open(proof)?))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CIRCUIT_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/multiplier.r1cs");
+    const WASM_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/multiplier.wasm");
+    const INPUT_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../test/multiplier.input.json");
+    const SRS_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/../keys/setup_2^10.key");
+
+    fn write_witness(witness: &str) {
+        use algebraic::witness::{load_input_for_witness, WitnessCalculator};
+        let mut wtns = WitnessCalculator::from_file(WASM_FILE).unwrap();
+        let w = wtns
+            .calculate_witness_bin(load_input_for_witness(INPUT_FILE), false)
+            .unwrap();
+        wtns.save_witness_to_bin_file::<Bn256>(witness, &w).unwrap();
+    }
+
+    #[test]
+    fn test_plonk_prove_and_verify() {
+        let witness = "/tmp/plonk.wtns";
+        let vk_file = "/tmp/plonk.vk.bin";
+        write_witness(witness);
+        export_verification_key(SRS_FILE, CIRCUIT_FILE, vk_file).unwrap();
+
+        for transcript in ["keccak", "rescue"] {
+            let proof_bin = format!("/tmp/plonk.{}.proof.bin", transcript);
+            let proof_json = format!("/tmp/plonk.{}.proof.json", transcript);
+            let public_json = format!("/tmp/plonk.{}.public.json", transcript);
+            prove(
+                CIRCUIT_FILE,
+                witness,
+                SRS_FILE,
+                transcript,
+                &proof_bin,
+                &proof_json,
+                &public_json,
+            )
+            .unwrap();
+            verify(vk_file, &proof_bin, transcript).unwrap();
+            let other = if transcript == "keccak" {
+                "rescue"
+            } else {
+                "keccak"
+            };
+            assert!(verify(vk_file, &proof_bin, other).is_err());
+        }
+        for bad_witness in ["/tmp/plonk.missing.wtns", vk_file, INPUT_FILE] {
+            assert!(prove(
+                CIRCUIT_FILE,
+                bad_witness,
+                SRS_FILE,
+                "keccak",
+                "/tmp/plonk.bad.proof.bin",
+                "/tmp/plonk.bad.proof.json",
+                "/tmp/plonk.bad.public.json",
+            )
+            .is_err());
+        }
+        assert!(setup(10, SRS_FILE, true).is_err());
+        assert!(setup(10, "/tmp/plonk.setup_2^10.key", false).is_err());
+        assert!(plonk::gen_key_monomial_form(plonk::SETUP_MIN_POW2 - 1).is_err());
+    }
+
+    #[test]
+    #[ignore = "slow, the aggregation circuit needs a setup of 2^23"]
+    fn test_aggregation_prove_and_verify() {
+        let prefix = "/tmp/plonk_aggregation";
+        let witness = format!("{}.wtns", prefix);
+        let vk_file = format!("{}.vk.bin", prefix);
+        let big_srs_file = format!("{}.setup_2^23.key", prefix);
+        let proof_list = format!("{}.proof_list.txt", prefix);
+        let aggregation_vk = format!("{}.aggregation_vk.bin", prefix);
+        let aggregation_proof = format!("{}.aggregation_proof.bin", prefix);
+        let aggregation_json = format!("{}.aggregation_proof.json", prefix);
+        let sol = format!("{}.verifier.sol", prefix);
+        write_witness(&witness);
+        export_verification_key(SRS_FILE, CIRCUIT_FILE, &vk_file).unwrap();
+
+        let mut proof_bins = vec![];
+        for i in 0..2 {
+            let proof_bin = format!("{}.proof{}.bin", prefix, i);
+            let proof_json = format!("{}.proof{}.json", prefix, i);
+            let public_json = format!("{}.public{}.json", prefix, i);
+            prove(
+                CIRCUIT_FILE,
+                &witness,
+                SRS_FILE,
+                "rescue",
+                &proof_bin,
+                &proof_json,
+                &public_json,
+            )
+            .unwrap();
+            proof_bins.push(proof_bin);
+        }
+        std::fs::write(&proof_list, proof_bins.join("\n")).unwrap();
+
+        if !Path::new(&big_srs_file).exists() {
+            setup(23, &big_srs_file, true).unwrap();
+        }
+        let num_inputs = load_verification_key(&vk_file).unwrap().num_inputs;
+        export_aggregation_verification_key(2, num_inputs, &big_srs_file, &aggregation_vk).unwrap();
+        aggregation_prove(
+            &big_srs_file,
+            &proof_list,
+            &vk_file,
+            &aggregation_proof,
+            &aggregation_json,
+        )
+        .unwrap();
+        aggregation_verify(&aggregation_proof, &aggregation_vk).unwrap();
+        aggregation_check(&proof_list, &vk_file, &aggregation_proof).unwrap();
+
+        // the aggregation proof is of the two proofs, not of the first one alone
+        std::fs::write(&proof_list, &proof_bins[0]).unwrap();
+        assert!(aggregation_check(&proof_list, &vk_file, &aggregation_proof).is_err());
+        assert!(aggregation_verify(&proof_bins[0], &aggregation_vk).is_err());
+
+        generate_aggregation_verifier(&vk_file, &aggregation_vk, num_inputs, &sol).unwrap();
+        assert!(std::fs::read_to_string(&sol)
+            .unwrap()
+            .contains("verifyAggregatedProof"));
+        assert!(
+            generate_aggregation_verifier(&vk_file, &aggregation_vk, num_inputs + 1, &sol).is_err()
+        );
+    }
+}
@@ -0,0 +1,5 @@
+pub mod aggregation;
+pub mod api;
+pub mod plonk;
+
+pub use franklin_crypto::bellman as bellman_ce;
@@ -0,0 +1,193 @@
+// The setup and proving follow the prover utils of zksync, on the width 4 Plonk of bellman.
+use crate::bellman_ce::{
+    kate_commitment::{Crs, CrsForMonomialForm},
+    pairing::{
+        bn256::{Bn256, Fq},
+        Engine,
+    },
+    plonk::{
+        better_cs::{
+            adaptor::TranspilationVariant,
+            cs::PlonkCsWidth4WithNextStepParams,
+            keys::{Proof, SetupPolynomials, VerificationKey},
+            verifier,
+        },
+        commitments::transcript::keccak_transcript::RollingKeccakTranscript,
+        is_satisfied_using_one_shot_check, prove_by_steps, setup, transpile,
+    },
+    worker::Worker,
+    Circuit, CurveAffine, PrimeField, ScalarEngine,
+};
+use algebraic::utils::repr_to_big;
+use anyhow::{anyhow, bail, Result};
+use franklin_crypto::plonk::circuit::bigint::field::RnsParameters;
+use franklin_crypto::rescue::bn256::Bn256RescueParams;
+use franklin_crypto::rescue::rescue_transcript::RescueTranscriptForRNS;
+
+pub const SETUP_MIN_POW2: u32 = 10;
+pub const SETUP_MAX_POW2: u32 = 26;
+
+/// The transpiler allocates the constant one as the first aux variable of the Plonk circuit,
+/// so the aux wires of a circom circuit start at 1.
+pub const AUX_OFFSET: usize = 1;
+
+pub type PlonkProof = Proof<Bn256, PlonkCsWidth4WithNextStepParams>;
+pub type PlonkVerificationKey = VerificationKey<Bn256, PlonkCsWidth4WithNextStepParams>;
+
+/// The monomial form SRS of 2^power points, from the known toxic waste 42. It is only for
+/// tests, a key of a public ceremony must be used in production.
+pub fn gen_key_monomial_form(power: u32) -> Result<Crs<Bn256, CrsForMonomialForm>> {
+    if !(SETUP_MIN_POW2..=SETUP_MAX_POW2).contains(&power) {
+        bail!(
+            "setup power {} is not in [{}, {}]",
+            power,
+            SETUP_MIN_POW2,
+            SETUP_MAX_POW2
+        );
+    }
+    Ok(Crs::<Bn256, CrsForMonomialForm>::crs_42(
+        1 << power,
+        &Worker::new(),
+    ))
+}
+
+/// The parameters of the rescue transcript, the one a recursive circuit can verify.
+pub fn rescue_transcript_params() -> (Bn256RescueParams, RnsParameters<Bn256, Fq>) {
+    let rns_params = RnsParameters::<Bn256, Fq>::new_for_field(68, 110, 4);
+    let rescue_params = Bn256RescueParams::new_checked_2_into_1();
+    (rescue_params, rns_params)
+}
+
+pub struct SetupForProver {
+    setup_polynomials: SetupPolynomials<Bn256, PlonkCsWidth4WithNextStepParams>,
+    hints: Vec<(usize, TranspilationVariant)>,
+    key_monomial_form: Crs<Bn256, CrsForMonomialForm>,
+}
+
+impl SetupForProver {
+    pub fn prepare_setup_for_prover<C: Circuit<Bn256> + Clone>(
+        circuit: C,
+        key_monomial_form: Crs<Bn256, CrsForMonomialForm>,
+    ) -> Result<Self> {
+        let hints = transpile(circuit.clone())?;
+        let setup_polynomials = setup(circuit, &hints)?;
+        let size = setup_polynomials.n.next_power_of_two();
+        if size > key_monomial_form.g1_bases.len() {
+            bail!(
+                "the circuit needs a setup of 2^{}, but the key only has {} points",
+                size.trailing_zeros(),
+                key_monomial_form.g1_bases.len()
+            );
+        }
+        Ok(SetupForProver {
+            setup_polynomials,
+            hints,
+            key_monomial_form,
+        })
+    }
+
+    pub fn make_verification_key(&self) -> Result<PlonkVerificationKey> {
+        Ok(VerificationKey::from_setup(
+            &self.setup_polynomials,
+            &Worker::new(),
+            &self.key_monomial_form,
+        )?)
+    }
+
+    pub fn validate_witness<C: Circuit<Bn256> + Clone>(&self, circuit: C) -> Result<()> {
+        is_satisfied_using_one_shot_check(circuit, &self.hints)
+            .map_err(|e| anyhow!("the witness does not satisfy the circuit: {:?}", e))
+    }
+
+    /// Prove with the keccak transcript, verifiable in the EVM, or the rescue one, verifiable
+    /// by the recursive aggregation circuit.
+    pub fn prove<C: Circuit<Bn256> + Clone>(
+        &self,
+        circuit: C,
+        transcript: &str,
+    ) -> Result<PlonkProof> {
+        self.validate_witness(circuit.clone())?;
+        let proof = match transcript {
+            "keccak" => {
+                prove_by_steps::<_, _, RollingKeccakTranscript<<Bn256 as ScalarEngine>::Fr>>(
+                    circuit,
+                    &self.hints,
+                    &self.setup_polynomials,
+                    None,
+                    &self.key_monomial_form,
+                    None,
+                )?
+            }
+            "rescue" => {
+                let (rescue_params, rns_params) = rescue_transcript_params();
+                prove_by_steps::<_, _, RescueTranscriptForRNS<Bn256>>(
+                    circuit,
+                    &self.hints,
+                    &self.setup_polynomials,
+                    None,
+                    &self.key_monomial_form,
+                    Some((&rescue_params, &rns_params)),
+                )?
+            }
+            _ => bail!("Unknown transcript: {}", transcript),
+        };
+        Ok(proof)
+    }
+}
+
+pub fn verify(vk: &PlonkVerificationKey, proof: &PlonkProof, transcript: &str) -> Result<bool> {
+    let valid = match transcript {
+        "keccak" => verifier::verify::<_, _, RollingKeccakTranscript<<Bn256 as ScalarEngine>::Fr>>(
+            vk, proof, None,
+        )?,
+        "rescue" => {
+            let (rescue_params, rns_params) = rescue_transcript_params();
+            verifier::verify::<_, _, RescueTranscriptForRNS<Bn256>>(
+                vk,
+                proof,
+                Some((&rescue_params, &rns_params)),
+            )?
+        }
+        _ => bail!("Unknown transcript: {}", transcript),
+    };
+    Ok(valid)
+}
+
+/// A field element as the decimal uint256 of the Solidity verifiers.
+pub fn fe_to_string<F: PrimeField>(fe: &F) -> String {
+    repr_to_big(fe.into_repr())
+}
+
+/// The coordinates of a point as the decimal uint256 of the Solidity verifiers.
+pub fn g1_to_strings(p: &<Bn256 as Engine>::G1Affine) -> [String; 2] {
+    let (x, y) = p.into_xy_unchecked();
+    [fe_to_string(&x), fe_to_string(&y)]
+}
+
+/// The public inputs and the proof as the arguments of the Solidity verifier.
+pub fn serialize_proof(proof: &PlonkProof) -> (Vec<String>, Vec<String>) {
+    let inputs = proof.input_values.iter().map(fe_to_string).collect();
+    let mut serialized_proof = vec![];
+    for c in proof.wire_commitments.iter() {
+        serialized_proof.extend(g1_to_strings(c));
+    }
+    serialized_proof.extend(g1_to_strings(&proof.grand_product_commitment));
+    for c in proof.quotient_poly_commitments.iter() {
+        serialized_proof.extend(g1_to_strings(c));
+    }
+    for c in proof.wire_values_at_z.iter() {
+        serialized_proof.push(fe_to_string(c));
+    }
+    for c in proof.wire_values_at_z_omega.iter() {
+        serialized_proof.push(fe_to_string(c));
+    }
+    serialized_proof.push(fe_to_string(&proof.grand_product_at_z_omega));
+    serialized_proof.push(fe_to_string(&proof.quotient_polynomial_at_z));
+    serialized_proof.push(fe_to_string(&proof.linearization_polynomial_at_z));
+    for c in proof.permutation_polynomials_at_z.iter() {
+        serialized_proof.push(fe_to_string(c));
+    }
+    serialized_proof.extend(g1_to_strings(&proof.opening_at_z_proof));
+    serialized_proof.extend(g1_to_strings(&proof.opening_at_z_omega_proof));
+    (inputs, serialized_proof)
+}
@@ -0,0 +1,458 @@
+use super::constants::CPOSEIDON;
+use algebraic::circom_circuit::{Constraint, CustomGates, CustomGatesUses, R1CS};
+use anyhow::{bail, Result};
+use fields::ff::Field;
+use fields::field_gl::Fr as FGL;
+use fields::field_gl::GL;
+use starky::f3g::F3G;
+use starky::traits::FieldExtension;
+use std::collections::BTreeMap;
+
+/// Index of a signal in the witness. Wire 0 is the constant one, as in circom.
+pub type Wire = usize;
+/// An element of GF(p^3) held in three wires.
+pub type ExtWire = [Wire; 3];
+
+// ids of the custom gates declared by `CircuitBuilder::build`
+const POSEIDON12_ID: u64 = 0;
+const CMULADD_ID: u64 = 1;
+
+// Rows used by one Poseidon12 gate: the input state and one row per round.
+const POSEIDON_ROWS: usize = 31;
+
+// Poseidon12 MDS matrix: circulant over MDS_CIRC plus MDS_DIAG0 on [0][0],
+// the same coefficients as the POSEIDON12 gate in compressor12_pil.
+const MDS_CIRC: [u64; 12] = [17, 15, 41, 16, 2, 28, 13, 13, 39, 18, 34, 20];
+const MDS_DIAG0: u64 = 8;
+
+/// Builds an `R1CS<GL>` for Compressor12 directly from Rust, computing the witness
+/// alongside the constraints.
+///
+/// Besides plain R1CS constraints it emits uses of the `Poseidon12` and `CMulAdd`
+/// custom gates, so the result can be fed to `compressor12_setup::setup_from_r1cs`
+/// and the witness to `compressor12_exec::exec_from_witness`, without circom.
+#[derive(Debug, Default)]
+pub struct CircuitBuilder {
+    values: Vec<FGL>,
+    n_publics: usize,
+    constraints: Vec<Constraint<GL>>,
+    custom_gates_uses: Vec<CustomGatesUses>,
+    constants: BTreeMap<u64, Wire>,
+}
+
+impl CircuitBuilder {
+    pub fn new() -> Self {
+        Self {
+            values: vec![FGL::ONE],
+            ..Default::default()
+        }
+    }
+
+    /// Allocates a public input. Publics must be allocated before any other wire.
+    pub fn public(&mut self, v: FGL) -> Wire {
+        assert_eq!(
+            self.values.len(),
+            1 + self.n_publics,
+            "publics must be allocated before other wires"
+        );
+        self.n_publics += 1;
+        self.alloc(v)
+    }
+
+    /// Allocates an unconstrained witness wire.
+    pub fn alloc(&mut self, v: FGL) -> Wire {
+        self.values.push(v);
+        self.values.len() - 1
+    }
+
+    pub fn alloc_ext(&mut self, v: &F3G) -> ExtWire {
+        [
+            self.alloc(v.cube[0]),
+            self.alloc(v.cube[1]),
+            self.alloc(v.cube[2]),
+        ]
+    }
+
+    pub fn value(&self, w: Wire) -> FGL {
+        self.values[w]
+    }
+
+    pub fn ext_value(&self, w: &ExtWire) -> F3G {
+        F3G::new(self.values[w[0]], self.values[w[1]], self.values[w[2]])
+    }
+
+    pub fn n_constraints(&self) -> usize {
+        self.constraints.len()
+    }
+
+    pub fn n_custom_gates_uses(&self) -> usize {
+        self.custom_gates_uses.len()
+    }
+
+    // merge repeated wires, r1cs2plonk expects each signal once per linear combination
+    fn lc(terms: &[(Wire, FGL)]) -> Vec<(usize, FGL)> {
+        let mut res: BTreeMap<usize, FGL> = BTreeMap::new();
+        for (w, k) in terms {
+            let e = res.entry(*w).or_insert(FGL::ZERO);
+            *e = *e + *k;
+        }
+        res.into_iter().filter(|(_, k)| *k != FGL::ZERO).collect()
+    }
+
+    fn push(&mut self, a: &[(Wire, FGL)], b: &[(Wire, FGL)], c: &[(Wire, FGL)]) {
+        self.constraints
+            .push((Self::lc(a), Self::lc(b), Self::lc(c)));
+    }
+
+    fn push_custom(&mut self, id: u64, signals: Vec<Wire>) {
+        // the exec phase maps signal 0 to zero, so the constant one can't feed a gate
+        assert!(
+            signals.iter().all(|s| *s != 0),
+            "custom gates can not use the constant wire"
+        );
+        self.custom_gates_uses.push(CustomGatesUses {
+            id,
+            signals: signals.into_iter().map(|s| s as u64).collect(),
+        });
+    }
+
+    /// Returns a wire constrained to `c`, shared by all uses of the same constant.
+    pub fn constant(&mut self, c: FGL) -> Wire {
+        if let Some(w) = self.constants.get(&c.as_int()) {
+            return *w;
+        }
+        let w = self.alloc(c);
+        self.push(&[], &[], &[(w, FGL::ONE), (0, -c)]);
+        self.constants.insert(c.as_int(), w);
+        w
+    }
+
+    /// out = sum(k_i * w_i) + k
+    pub fn lin(&mut self, terms: &[(Wire, FGL)], k: FGL) -> Wire {
+        let v = terms
+            .iter()
+            .fold(k, |acc, (w, c)| acc + self.values[*w] * *c);
+        let out = self.alloc(v);
+        let mut c = terms.to_vec();
+        c.push((0, k));
+        c.push((out, -FGL::ONE));
+        self.push(&[], &[], &c);
+        out
+    }
+
+    /// Constrains sum(k_i * w_i) + k = 0.
+    pub fn assert_lin_zero(&mut self, terms: &[(Wire, FGL)], k: FGL) {
+        let mut c = terms.to_vec();
+        c.push((0, k));
+        self.push(&[], &[], &c);
+    }
+
+    pub fn add(&mut self, a: Wire, b: Wire) -> Wire {
+        self.lin(&[(a, FGL::ONE), (b, FGL::ONE)], FGL::ZERO)
+    }
+
+    pub fn sub(&mut self, a: Wire, b: Wire) -> Wire {
+        self.lin(&[(a, FGL::ONE), (b, -FGL::ONE)], FGL::ZERO)
+    }
+
+    pub fn mul(&mut self, a: Wire, b: Wire) -> Wire {
+        let out = self.alloc(self.values[a] * self.values[b]);
+        self.push(&[(a, FGL::ONE)], &[(b, FGL::ONE)], &[(out, FGL::ONE)]);
+        out
+    }
+
+    /// Inverse of `a`. A zero input gets a zero witness, leaving the constraint unsatisfied.
+    pub fn inv(&mut self, a: Wire) -> Wire {
+        let out = self.alloc(self.values[a].inverse().unwrap_or(FGL::ZERO));
+        self.push(&[(a, FGL::ONE)], &[(out, FGL::ONE)], &[(0, FGL::ONE)]);
+        out
+    }
+
+    pub fn assert_equal(&mut self, a: Wire, b: Wire) {
+        self.assert_lin_zero(&[(a, FGL::ONE), (b, -FGL::ONE)], FGL::ZERO);
+    }
+
+    pub fn assert_bool(&mut self, b: Wire) {
+        self.push(&[(b, FGL::ONE)], &[(b, FGL::ONE)], &[(b, FGL::ONE)]);
+    }
+
+    /// Returns `a` when `bit` is 0 and `b` when it is 1.
+    pub fn select(&mut self, bit: Wire, a: Wire, b: Wire) -> Wire {
+        let (va, vb) = (self.values[a], self.values[b]);
+        let out = self.alloc(va + self.values[bit] * (vb - va));
+        self.push(
+            &[(bit, FGL::ONE)],
+            &[(b, FGL::ONE), (a, -FGL::ONE)],
+            &[(out, FGL::ONE), (a, -FGL::ONE)],
+        );
+        out
+    }
+
+    /// Decomposes `a` into `n` little-endian bits. With `n == 64` the decomposition is
+    /// also checked to be canonical, i.e. below the Goldilocks modulus.
+    pub fn to_bits(&mut self, a: Wire, n: usize) -> Vec<Wire> {
+        assert!(n <= 64);
+        let v = self.values[a].as_int();
+        let mut bits = Vec::with_capacity(n);
+        for i in 0..n {
+            let bit = self.alloc(FGL::from((v >> i) & 1));
+            self.assert_bool(bit);
+            bits.push(bit);
+        }
+        let mut terms = bits
+            .iter()
+            .enumerate()
+            .map(|(i, b)| (*b, FGL::from(1u64 << i)))
+            .collect::<Vec<_>>();
+        terms.push((a, -FGL::ONE));
+        self.assert_lin_zero(&terms, FGL::ZERO);
+
+        if n == 64 {
+            // p = 2^64 - 2^32 + 1: if the high 32 bits are all set, the low ones must be 0
+            let mut hi = bits[32];
+            for b in &bits[33..] {
+                hi = self.mul(hi, *b);
+            }
+            let lo = bits[..32]
+                .iter()
+                .enumerate()
+                .map(|(i, b)| (*b, FGL::from(1u64 << i)))
+                .collect::<Vec<_>>();
+            let lo = self.lin(&lo, FGL::ZERO);
+            self.push(&[(hi, FGL::ONE)], &[(lo, FGL::ONE)], &[]);
+        }
+        bits
+    }
+
+    pub fn ext_constant(&mut self, v: &F3G) -> ExtWire {
+        [
+            self.constant(v.cube[0]),
+            self.constant(v.cube[1]),
+            self.constant(v.cube[2]),
+        ]
+    }
+
+    pub fn ext_from_base(&mut self, a: Wire) -> ExtWire {
+        let zero = self.constant(FGL::ZERO);
+        [a, zero, zero]
+    }
+
+    pub fn ext_add(&mut self, a: &ExtWire, b: &ExtWire) -> ExtWire {
+        [
+            self.add(a[0], b[0]),
+            self.add(a[1], b[1]),
+            self.add(a[2], b[2]),
+        ]
+    }
+
+    pub fn ext_sub(&mut self, a: &ExtWire, b: &ExtWire) -> ExtWire {
+        [
+            self.sub(a[0], b[0]),
+            self.sub(a[1], b[1]),
+            self.sub(a[2], b[2]),
+        ]
+    }
+
+    /// Multiplies an extension element by a base field wire.
+    pub fn ext_scale(&mut self, a: &ExtWire, s: Wire) -> ExtWire {
+        [self.mul(a[0], s), self.mul(a[1], s), self.mul(a[2], s)]
+    }
+
+    /// a * b + c in GF(p^3), through one CMulAdd gate.
+    pub fn ext_mul_add(&mut self, a: &ExtWire, b: &ExtWire, c: &ExtWire) -> ExtWire {
+        let r = self.ext_value(a) * self.ext_value(b) + self.ext_value(c);
+        let out = self.alloc_ext(&r);
+        let signals = [*a, *b, *c, out].concat();
+        self.push_custom(CMULADD_ID, signals);
+        out
+    }
+
+    pub fn ext_mul(&mut self, a: &ExtWire, b: &ExtWire) -> ExtWire {
+        let zero = self.ext_constant(&F3G::ZEROS);
+        self.ext_mul_add(a, b, &zero)
+    }
+
+    /// Inverse of `a` in GF(p^3). A zero input gets a zero witness, leaving the
+    /// constraint unsatisfied.
+    pub fn ext_inv(&mut self, a: &ExtWire) -> ExtWire {
+        let v = self.ext_value(a);
+        let inv = if v.cube.iter().all(|e| *e == FGL::ZERO) {
+            F3G::ZEROS
+        } else {
+            F3G::inv(&v)
+        };
+        let out = self.alloc_ext(&inv);
+        let one = self.ext_mul(a, &out);
+        self.assert_lin_zero(&[(one[0], FGL::ONE)], -FGL::ONE);
+        self.assert_lin_zero(&[(one[1], FGL::ONE)], FGL::ZERO);
+        self.assert_lin_zero(&[(one[2], FGL::ONE)], FGL::ZERO);
+        out
+    }
+
+    pub fn ext_assert_equal(&mut self, a: &ExtWire, b: &ExtWire) {
+        for (x, y) in a.iter().zip(b.iter()) {
+            self.assert_equal(*x, *y);
+        }
+    }
+
+    /// Returns `a` when `bit` is 0 and `b` when it is 1.
+    pub fn ext_select(&mut self, bit: Wire, a: &ExtWire, b: &ExtWire) -> ExtWire {
+        [
+            self.select(bit, a[0], b[0]),
+            self.select(bit, a[1], b[1]),
+            self.select(bit, a[2], b[2]),
+        ]
+    }
+
+    /// Applies the Poseidon12 permutation to `state` through one custom gate use and
+    /// returns the whole output state.
+    pub fn poseidon(&mut self, state: &[Wire; 12]) -> [Wire; 12] {
+        let input = state.map(|w| self.values[w]);
+        let rows = poseidon_rows(&input);
+        let mut signals = state.to_vec();
+        let mut out = [0; 12];
+        for row in rows.iter().skip(1) {
+            for (k, v) in row.iter().enumerate() {
+                out[k] = self.alloc(*v);
+                signals.push(out[k]);
+            }
+        }
+        self.push_custom(POSEIDON12_ID, signals);
+        out
+    }
+
+    /// Evaluates every constraint and custom gate use against the witness.
+    pub fn check(&self) -> Result<()> {
+        let eval = |lc: &Vec<(usize, FGL)>| {
+            lc.iter()
+                .fold(FGL::ZERO, |acc, (w, k)| acc + self.values[*w] * *k)
+        };
+        for (i, (a, b, c)) in self.constraints.iter().enumerate() {
+            if eval(a) * eval(b) != eval(c) {
+                bail!("constraint {} is not satisfied", i);
+            }
+        }
+
+        for (i, cgu) in self.custom_gates_uses.iter().enumerate() {
+            let s = |k: usize| self.values[cgu.signals[k] as usize];
+            let ok = match cgu.id {
+                POSEIDON12_ID => {
+                    let input: [FGL; 12] = std::array::from_fn(s);
+                    let rows = poseidon_rows(&input);
+                    (1..POSEIDON_ROWS).all(|j| (0..12).all(|k| rows[j][k] == s(j * 12 + k)))
+                }
+                CMULADD_ID => {
+                    let r = F3G::new(s(0), s(1), s(2)) * F3G::new(s(3), s(4), s(5))
+                        + F3G::new(s(6), s(7), s(8));
+                    r.cube == [s(9), s(10), s(11)]
+                }
+                _ => bail!("unknown custom gate {}", cgu.id),
+            };
+            if !ok {
+                bail!("custom gate use {} is not satisfied", i);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the R1CS and its witness. The publics are wires `1..=n_publics`.
+    pub fn build(self) -> Result<(R1CS<GL>, Vec<FGL>)> {
+        if self.n_publics == 0 {
+            bail!("a Compressor12 circuit needs at least one public input");
+        }
+        let num_inputs = 1 + self.n_publics;
+        let num_variables = self.values.len();
+        let r1cs = R1CS {
+            num_inputs,
+            num_aux: num_variables - num_inputs,
+            num_variables,
+            num_outputs: 0,
+            constraints: self.constraints,
+            custom_gates: vec![
+                CustomGates {
+                    template_name: "Poseidon12".to_string(),
+                    parameters: vec![],
+                },
+                CustomGates {
+                    template_name: "CMulAdd".to_string(),
+                    parameters: vec![],
+                },
+            ],
+            custom_gates_uses: self.custom_gates_uses,
+        };
+        Ok((r1cs, self.values))
+    }
+}
+
+/// Rows of the Poseidon12 custom gate: row 0 is the input state and row `j + 1` is
+/// `MDS(sbox(row_j + C_j))`. The S-box is applied to every element in the first and
+/// last four rounds and only to the first one in the partial rounds.
+pub(crate) fn poseidon_rows(input: &[FGL; 12]) -> Vec<[FGL; 12]> {
+    let mut rows = vec![*input];
+    for j in 0..(POSEIDON_ROWS - 1) {
+        let prev = rows[j];
+        let s: [FGL; 12] = std::array::from_fn(|i| {
+            let t = prev[i] + FGL::from(CPOSEIDON[j * 12 + i]);
+            if i == 0 || !(4..26).contains(&j) {
+                t.exp(7)
+            } else {
+                t
+            }
+        });
+        let mut next = [FGL::ZERO; 12];
+        for (i, n) in next.iter_mut().enumerate() {
+            let mut acc = if i == 0 {
+                s[0] * FGL::from(MDS_DIAG0)
+            } else {
+                FGL::ZERO
+            };
+            for (k, sk) in s.iter().enumerate() {
+                acc = acc + FGL::from(MDS_CIRC[(k + 12 - i) % 12]) * *sk;
+            }
+            *n = acc;
+        }
+        rows.push(next);
+    }
+    rows
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use starky::poseidon_opt::Poseidon;
+
+    #[test]
+    fn test_poseidon_gate_matches_hash() {
+        let input: [FGL; 12] = std::array::from_fn(|i| FGL::from(i as u64 * 7 + 3));
+        let rows = poseidon_rows(&input);
+        let expected = Poseidon::new().hash(&input[..8], &input[8..], 12).unwrap();
+        assert_eq!(rows[POSEIDON_ROWS - 1].to_vec(), expected);
+    }
+
+    #[test]
+    fn test_builder_check() {
+        let mut b = CircuitBuilder::new();
+        let p = b.public(FGL::from(5u64));
+        let x = b.alloc(FGL::from(3u64));
+        let y = b.mul(x, x);
+        let z = b.add(y, p);
+        let fourteen = b.constant(FGL::from(14u64));
+        b.assert_equal(z, fourteen);
+        let bits = b.to_bits(z, 64);
+        assert_eq!(bits.len(), 64);
+
+        let e = b.alloc_ext(&F3G::new(FGL::from(2u64), FGL::from(3u64), FGL::from(4u64)));
+        let e_inv = b.ext_inv(&e);
+        let one = b.ext_mul(&e, &e_inv);
+        let one_c = b.ext_constant(&F3G::ONE);
+        b.ext_assert_equal(&one, &one_c);
+
+        let state: [Wire; 12] = std::array::from_fn(|_| fourteen);
+        b.poseidon(&state);
+        b.check().unwrap();
+
+        // a wrong witness is caught
+        b.values[y] = FGL::from(10u64);
+        assert!(b.check().is_err());
+    }
+}
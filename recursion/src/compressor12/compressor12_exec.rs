@@ -2,7 +2,7 @@ use crate::compressor12_pil::CompressorNameSpace::*;
 use crate::compressor12_pil::CompressorPolName::a;
 use crate::io_utils::read_vec_from_file;
 use crate::pilcom::compile_pil_from_path;
-use algebraic::witness::{load_input_for_witness, WitnessCalculator};
+use algebraic::witness::{calculate_witness_from_file, load_input_for_witness, WitnessGenerator};
 use anyhow::{anyhow, Result};
 use fields::ff::PrimeField;
 use fields::field_gl::Fr as FGL;
@@ -18,17 +18,44 @@ use std::path::Path;
 pub fn exec(
     input_file: &str,
     wasm_file: &str,
+    generator: WitnessGenerator,
     pil_file: &str,
     exec_file: &str,
     commit_file: &str,
 ) -> Result<()> {
-    // 0. load exec_file,
+    // 0. calculate witness. wasm or native generator+input->witness
+    let inputs = load_input_for_witness(input_file);
+    let w = calculate_witness_from_file(wasm_file, generator, inputs, false)?;
+    let w = w
+        .iter()
+        .map(|wi| {
+            if wi.is_zero() {
+                FGL::ZERO
+            } else {
+                assert!(wi.to_u64_digits().1.len() < 2);
+                FGL::from(wi.to_u64_digits().1[0])
+            }
+        })
+        .collect::<Vec<_>>();
 
+    exec_from_witness(w, pil_file, exec_file, commit_file)
+}
+
+// exec phase from a witness computed natively, e.g. by `stark_verifier`.
+// input files: .exec, .pil
+// output: .cm
+pub fn exec_from_witness(
+    mut w: Vec<FGL>,
+    pil_file: &str,
+    exec_file: &str,
+    commit_file: &str,
+) -> Result<()> {
+    // 1. load exec_file,
     let inputs_str = File::open(exec_file).map_err(|e| anyhow!("Read {}, {:?}", exec_file, e))?;
     let reader = BufReader::new(inputs_str);
     let (adds_len, s_map_column_len, adds, s_map) = read_exec_file(reader)?;
 
-    // 1. Compiles a .pil file to its json form , and save it.
+    // 2. Compiles a .pil file to its json form , and save it.
     // TODO: the pil_str has been compiled in plonk_setup#3
     let pil_json = compile_pil_from_path(pil_file);
     let mut file = File::create(Path::new(&format!("{pil_file}.json")))
@@ -36,25 +63,9 @@ pub fn exec(
     let input = serde_json::to_string(&pil_json)?;
     write!(file, "{}", input)?;
 
-    // 2. construct cmPol: .pil.json -> .cm
+    // 3. construct cmPol: .pil.json -> .cm
     let mut cm_pols = PolsArray::new(&pil_json, PolKind::Commit);
 
-    // 3. calculate witness. wasm+input->witness
-    let mut wtns = WitnessCalculator::from_file(wasm_file)?;
-    let inputs = load_input_for_witness(input_file);
-    let w = wtns.calculate_witness(inputs, false)?;
-    let mut w = w
-        .iter()
-        .map(|wi| {
-            if wi.is_zero() {
-                FGL::ZERO
-            } else {
-                assert!(wi.to_u64_digits().1.len() < 2);
-                FGL::from(wi.to_u64_digits().1[0])
-            }
-        })
-        .collect::<Vec<_>>();
-
     for i in 0..adds_len {
         let w2 = FGL::from_raw_repr(<FGL as PrimeField>::Repr::from(adds[i * 4 + 2]))?;
         let w3 = FGL::from_raw_repr(<FGL as PrimeField>::Repr::from(adds[i * 4 + 3]))?;
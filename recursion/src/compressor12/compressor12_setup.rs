@@ -2,6 +2,8 @@
 use crate::compressor12::plonk_setup::PlonkSetup;
 use crate::io_utils::write_vec_to_file;
 use crate::r1cs2plonk::PlonkAdd;
+use algebraic::circom_circuit::R1CS;
+use algebraic::r1cs_optimizer;
 use algebraic::reader::load_r1cs_from_bin;
 use anyhow::{anyhow, Result};
 use fields::field_gl::GL;
@@ -15,22 +17,48 @@ pub struct Options {
 // setup phase:
 // input: .r1cs
 // output: .pil, .const, .exec,
+// `optimize` simplifies the R1CS by `r1cs_optimizer::optimize` first, it keeps the wires, so
+// the exec phase takes the witness of the original R1CS.
 pub fn setup(
     r1cs_file: &str,
     pil_file: &str,
     const_file: &str,
     exec_file: &str,
     force_n_bits: usize,
+    optimize: bool,
 ) -> Result<()> {
     // 0. readR1cs
     let r1cs_reader = File::open(r1cs_file)?;
     let (r1cs, _) = load_r1cs_from_bin::<_, GL>(r1cs_reader);
+    if optimize {
+        let (optimized, stats) = r1cs_optimizer::optimize(&r1cs);
+        log::info!(
+            "optimize r1cs: {} -> {} constraints, {:?}",
+            r1cs.constraints.len(),
+            optimized.constraints.len(),
+            stats
+        );
+        setup_from_r1cs(&optimized, pil_file, const_file, exec_file, force_n_bits)
+    } else {
+        setup_from_r1cs(&r1cs, pil_file, const_file, exec_file, force_n_bits)
+    }
+}
+
+// setup phase from an in-memory R1CS, e.g. one built by `stark_verifier`.
+// output: .pil, .const, .exec,
+pub fn setup_from_r1cs(
+    r1cs: &R1CS<GL>,
+    pil_file: &str,
+    const_file: &str,
+    exec_file: &str,
+    force_n_bits: usize,
+) -> Result<()> {
     let opts = Options {
         force_bits: force_n_bits,
     };
 
     // 1. plonk setup: generate plonk circuit, the pil file.
-    let res = PlonkSetup::new(&r1cs, &opts);
+    let res = PlonkSetup::new(r1cs, &opts);
 
     // 2. And write it into pil_file.
     let mut file =
@@ -1,6 +1,8 @@
 #![allow(non_snake_case)]
+pub mod circuit_builder;
 pub mod compressor12_exec;
 pub(crate) mod compressor12_pil;
 pub mod compressor12_setup;
 pub(crate) mod constants;
 pub(crate) mod plonk_setup;
+pub mod stark_verifier;
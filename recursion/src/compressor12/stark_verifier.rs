@@ -0,0 +1,1011 @@
+use super::circuit_builder::{CircuitBuilder, ExtWire, Wire};
+use super::compressor12_exec::exec_from_witness;
+use super::compressor12_setup::setup_from_r1cs;
+use anyhow::{anyhow, bail, Result};
+use fields::field_gl::Fr as FGL;
+use starky::constant::{MG, SHIFT};
+use starky::f3g::F3G;
+use starky::fft::FFT;
+use starky::merklehash::MerkleTreeGL;
+use starky::pil2circom::StarkOption;
+use starky::polsarray::{PolKind, PolsArray};
+use starky::stark_gen::StarkProof;
+use starky::stark_setup::StarkSetup;
+use starky::starkinfo::{Program, StarkInfo};
+use starky::starkinfo_codegen::{Node, Section};
+use starky::traits::{FieldExtension, MTNodeType};
+use starky::types::{load_json, parse_pil_number, StarkStruct, PIL};
+use std::collections::HashMap;
+
+/// Values of a GL stark proof, laid out the way the verifier circuit reads them.
+#[derive(Debug, Clone)]
+pub struct ProofValues {
+    pub publics: Vec<F3G>,
+    pub roots: [[FGL; 4]; 4],
+    pub evals: Vec<F3G>,
+    /// `queries[si][q]` holds the (values, siblings) openings of query `q` at FRI step
+    /// `si`: trees 1 to 4 and the constant tree at step 0, the folded polynomial after.
+    pub queries: Vec<Vec<Vec<(Vec<FGL>, Vec<Vec<FGL>>)>>>,
+    /// Root of the tree committed for each FRI step, index 0 is unused.
+    pub fri_roots: Vec<[FGL; 4]>,
+    pub last: Vec<F3G>,
+    /// Root of the constant tree, read by the circuit with `verkey_input`.
+    pub root_c: Option<[FGL; 4]>,
+}
+
+fn digest<N: MTNodeType>(node: &N) -> [FGL; 4] {
+    let e = node.as_elements();
+    [e[0], e[1], e[2], e[3]]
+}
+
+impl ProofValues {
+    pub fn from_proof(proof: &StarkProof<MerkleTreeGL>) -> Result<Self> {
+        if proof.publics.iter().any(|p| p.dim != 1) {
+            bail!("the verifier circuit expects base field publics");
+        }
+        if proof.evals.iter().any(|e| e.dim != 3) {
+            bail!("the verifier circuit expects evaluations in the cubic extension");
+        }
+        let queries = &proof.fri_proof.queries;
+        Ok(Self {
+            publics: proof.publics.clone(),
+            roots: [
+                digest(&proof.root1),
+                digest(&proof.root2),
+                digest(&proof.root3),
+                digest(&proof.root4),
+            ],
+            evals: proof.evals.clone(),
+            queries: queries.iter().map(|q| q.pol_queries.clone()).collect(),
+            fri_roots: queries.iter().map(|q| digest(&q.root)).collect(),
+            last: proof.fri_proof.last.clone(),
+            root_c: proof.rootC.as_ref().map(digest),
+        })
+    }
+
+    /// A proof of the right shape filled with zeros. The circuit built from it has the
+    /// same constraints as the one built from any real proof, so it is enough for setup.
+    pub fn shape(starkinfo: &StarkInfo, stark_struct: &StarkStruct) -> Self {
+        let steps = &stark_struct.steps;
+        let opening =
+            |width: usize, depth: usize| (vec![FGL::ZERO; width], vec![vec![FGL::ZERO; 4]; depth]);
+        let s0 = [
+            starkinfo.map_sectionsN.cm1_2ns,
+            starkinfo.map_sectionsN.cm2_2ns,
+            starkinfo.map_sectionsN.cm3_2ns,
+            starkinfo.map_sectionsN.cm4_2ns,
+            starkinfo.n_constants,
+        ]
+        .iter()
+        .map(|w| opening(*w, stark_struct.nBitsExt))
+        .collect::<Vec<_>>();
+
+        let mut queries = vec![vec![s0; stark_struct.nQueries]];
+        for si in 1..steps.len() {
+            let width = 3 * (1 << (steps[si - 1].nBits - steps[si].nBits));
+            queries.push(vec![
+                vec![opening(width, steps[si].nBits)];
+                stark_struct.nQueries
+            ]);
+        }
+        Self {
+            publics: vec![F3G::ZERO; starkinfo.n_publics],
+            roots: [[FGL::ZERO; 4]; 4],
+            evals: vec![F3G::ZEROS; starkinfo.ev_map.len()],
+            queries,
+            fri_roots: vec![[FGL::ZERO; 4]; steps.len()],
+            last: vec![F3G::ZEROS; 1 << steps[steps.len() - 1].nBits],
+            root_c: Some([FGL::ZERO; 4]),
+        }
+    }
+}
+
+// In-circuit counterpart of `TranscriptGL`.
+struct TranscriptGadget {
+    state: [Wire; 4],
+    pending: Vec<Wire>,
+    out: Vec<Wire>,
+}
+
+impl TranscriptGadget {
+    fn new(b: &mut CircuitBuilder) -> Self {
+        let zero = b.constant(FGL::ZERO);
+        Self {
+            state: [zero; 4],
+            pending: vec![],
+            out: vec![],
+        }
+    }
+
+    fn update_state(&mut self, b: &mut CircuitBuilder) {
+        let zero = b.constant(FGL::ZERO);
+        while self.pending.len() < 8 {
+            self.pending.push(zero);
+        }
+        let mut inp = [zero; 12];
+        inp[..8].copy_from_slice(&self.pending);
+        inp[8..].copy_from_slice(&self.state);
+        let out = b.poseidon(&inp);
+        self.out = out.to_vec();
+        self.pending = vec![];
+        self.state.copy_from_slice(&out[..4]);
+    }
+
+    fn put(&mut self, b: &mut CircuitBuilder, es: &[Wire]) {
+        for e in es {
+            self.out = vec![];
+            self.pending.push(*e);
+            if self.pending.len() == 8 {
+                self.update_state(b);
+            }
+        }
+    }
+
+    fn get_fields1(&mut self, b: &mut CircuitBuilder) -> Wire {
+        if self.out.is_empty() {
+            self.update_state(b);
+        }
+        self.out.remove(0)
+    }
+
+    fn get_field(&mut self, b: &mut CircuitBuilder) -> ExtWire {
+        [
+            self.get_fields1(b),
+            self.get_fields1(b),
+            self.get_fields1(b),
+        ]
+    }
+
+    // the little-endian bits of each index, taken 63 bits per field like TranscriptGL
+    fn get_permutations(
+        &mut self,
+        b: &mut CircuitBuilder,
+        n: usize,
+        nbits: usize,
+    ) -> Vec<Vec<Wire>> {
+        let n_fields = (n * nbits - 1) / 63 + 1;
+        let mut bits = vec![];
+        for _ in 0..n_fields {
+            let f = self.get_fields1(b);
+            bits.extend_from_slice(&b.to_bits(f, 64)[..63]);
+        }
+        (0..n)
+            .map(|i| bits[i * nbits..(i + 1) * nbits].to_vec())
+            .collect()
+    }
+}
+
+fn hash_chunk(b: &mut CircuitBuilder, vals: &[Wire]) -> [Wire; 4] {
+    let zero = b.constant(FGL::ZERO);
+    let mut st = [zero; 4];
+    if vals.len() <= 4 {
+        st[..vals.len()].copy_from_slice(vals);
+        return st;
+    }
+    for chunk in vals.chunks(8) {
+        let mut inp = [zero; 12];
+        inp[..chunk.len()].copy_from_slice(chunk);
+        inp[8..].copy_from_slice(&st);
+        let out = b.poseidon(&inp);
+        st.copy_from_slice(&out[..4]);
+    }
+    st
+}
+
+// In-circuit counterpart of `LinearHash::hash(vals, 0)`.
+fn linear_hash(b: &mut CircuitBuilder, vals: &[Wire]) -> [Wire; 4] {
+    if vals.len() <= 4 {
+        return hash_chunk(b, vals);
+    }
+    let bs = std::cmp::max(8, (vals.len() + 3) / 4);
+    let mut hashes = vec![];
+    for chunk in vals.chunks(bs) {
+        hashes.extend_from_slice(&hash_chunk(b, chunk));
+    }
+    hash_chunk(b, &hashes)
+}
+
+// Root of a Merkle tree from a leaf hash and its siblings, `idx_bits` picks the side.
+fn merkle_root(
+    b: &mut CircuitBuilder,
+    leaf: [Wire; 4],
+    siblings: &[[Wire; 4]],
+    idx_bits: &[Wire],
+) -> [Wire; 4] {
+    let zero = b.constant(FGL::ZERO);
+    let mut value = leaf;
+    for (sib, bit) in siblings.iter().zip(idx_bits) {
+        let mut inp = [zero; 12];
+        for (k, (v, s)) in value.iter().zip(sib.iter()).enumerate() {
+            inp[k] = b.select(*bit, *v, *s);
+            inp[4 + k] = b.select(*bit, *s, *v);
+        }
+        let out = b.poseidon(&inp);
+        value.copy_from_slice(&out[..4]);
+    }
+    value
+}
+
+// Picks `items[idx]`, where `idx_bits` are the little-endian bits of idx.
+fn select_tree(b: &mut CircuitBuilder, idx_bits: &[Wire], items: &[ExtWire]) -> ExtWire {
+    assert_eq!(items.len(), 1 << idx_bits.len());
+    let mut level = items.to_vec();
+    for bit in idx_bits {
+        level = level
+            .chunks(2)
+            .map(|pair| b.ext_select(*bit, &pair[0], &pair[1]))
+            .collect();
+    }
+    level[0]
+}
+
+// `base^idx` from the little-endian bits of idx, as a product of `1 + bit * (base^(2^k) - 1)`.
+fn pow_bits(b: &mut CircuitBuilder, init: FGL, base: FGL, idx_bits: &[Wire]) -> Wire {
+    let mut acc = b.constant(init);
+    let mut p = base;
+    for bit in idx_bits {
+        let f = b.lin(&[(*bit, p - FGL::ONE)], FGL::ONE);
+        acc = b.mul(acc, f);
+        p = p * p;
+    }
+    acc
+}
+
+fn ext_pow2(b: &mut CircuitBuilder, x: &ExtWire, nbits: usize) -> ExtWire {
+    let mut acc = *x;
+    for _ in 0..nbits {
+        acc = b.ext_mul(&acc, &acc);
+    }
+    acc
+}
+
+// ifft_matrix(n)[j][k] is the weight of the evaluation k in the coefficient j.
+fn ifft_matrix(n: usize) -> Vec<Vec<FGL>> {
+    let mut fft = FFT::<F3G>::new();
+    let mut m = vec![vec![FGL::ZERO; n]; n];
+    for k in 0..n {
+        let mut e = vec![F3G::ZERO; n];
+        e[k] = F3G::ONE;
+        let col = fft.ifft(&e);
+        for (row, c) in m.iter_mut().zip(col.iter()) {
+            row[k] = c.cube[0];
+        }
+    }
+    m
+}
+
+// coefficient `j` of the polynomial interpolating `evals`, one linear combination per coordinate
+fn ifft_coef(b: &mut CircuitBuilder, m: &[Vec<FGL>], j: usize, evals: &[ExtWire]) -> ExtWire {
+    let mut out = [0; 3];
+    for (c, o) in out.iter_mut().enumerate() {
+        let terms = evals
+            .iter()
+            .zip(m[j].iter())
+            .map(|(e, k)| (e[c], *k))
+            .collect::<Vec<_>>();
+        *o = b.lin(&terms, FGL::ZERO);
+    }
+    out
+}
+
+#[derive(Clone, Copy)]
+enum Val {
+    Base(Wire),
+    Ext(ExtWire),
+}
+
+impl Val {
+    fn ext(self, b: &mut CircuitBuilder) -> ExtWire {
+        match self {
+            Val::Base(w) => b.ext_from_base(w),
+            Val::Ext(e) => e,
+        }
+    }
+}
+
+fn val_add(b: &mut CircuitBuilder, x: Val, y: Val) -> Val {
+    match (x, y) {
+        (Val::Base(x), Val::Base(y)) => Val::Base(b.add(x, y)),
+        _ => {
+            let (x, y) = (x.ext(b), y.ext(b));
+            Val::Ext(b.ext_add(&x, &y))
+        }
+    }
+}
+
+fn val_sub(b: &mut CircuitBuilder, x: Val, y: Val) -> Val {
+    match (x, y) {
+        (Val::Base(x), Val::Base(y)) => Val::Base(b.sub(x, y)),
+        _ => {
+            let (x, y) = (x.ext(b), y.ext(b));
+            Val::Ext(b.ext_sub(&x, &y))
+        }
+    }
+}
+
+fn val_mul(b: &mut CircuitBuilder, x: Val, y: Val) -> Val {
+    match (x, y) {
+        (Val::Base(x), Val::Base(y)) => Val::Base(b.mul(x, y)),
+        (Val::Base(s), Val::Ext(e)) | (Val::Ext(e), Val::Base(s)) => Val::Ext(b.ext_scale(&e, s)),
+        (Val::Ext(x), Val::Ext(y)) => Val::Ext(b.ext_mul(&x, &y)),
+    }
+}
+
+fn val_mul_add(b: &mut CircuitBuilder, x: Val, y: Val, z: Val) -> Val {
+    match (x, y) {
+        (Val::Ext(x), Val::Ext(y)) => {
+            let z = z.ext(b);
+            Val::Ext(b.ext_mul_add(&x, &y, &z))
+        }
+        _ => {
+            let xy = val_mul(b, x, y);
+            val_add(b, xy, z)
+        }
+    }
+}
+
+// What the verifier code can reference, mirrors `StarkContext` in stark_verify.
+struct CodeCtx<'a> {
+    evals: &'a [ExtWire],
+    publics: &'a [Wire],
+    challenges: &'a [ExtWire],
+    z: ExtWire,
+    zp: ExtWire,
+    trees: [&'a [Wire]; 4],
+    consts: &'a [Wire],
+    x_div_x_sub_xi: Option<ExtWire>,
+    x_div_x_sub_wxi: Option<ExtWire>,
+}
+
+// In-circuit counterpart of `execute_code` in stark_verify.
+fn execute_code(b: &mut CircuitBuilder, ctx: &CodeCtx, code: &[Section]) -> Result<Val> {
+    let mut tmp: HashMap<usize, Val> = HashMap::new();
+
+    let get_ref = |b: &mut CircuitBuilder, r: &Node, tmp: &HashMap<usize, Val>| -> Result<Val> {
+        let tree = |t: &[Wire]| match r.dim {
+            1 => Ok(Val::Base(t[r.tree_pos])),
+            3 => Ok(Val::Ext([
+                t[r.tree_pos],
+                t[r.tree_pos + 1],
+                t[r.tree_pos + 2],
+            ])),
+            _ => Err(anyhow!("Invalid dimension {}", r.dim)),
+        };
+        Ok(match r.type_.as_str() {
+            "tmp" => *tmp
+                .get(&r.id)
+                .ok_or_else(|| anyhow!("tmp {} used before set", r.id))?,
+            "tree1" => tree(ctx.trees[0])?,
+            "tree2" => tree(ctx.trees[1])?,
+            "tree3" => tree(ctx.trees[2])?,
+            "tree4" => tree(ctx.trees[3])?,
+            "const" => Val::Base(ctx.consts[r.id]),
+            "eval" => Val::Ext(ctx.evals[r.id]),
+            "number" => {
+                Val::Base(b.constant(FGL::from(parse_pil_number(r.value.as_ref().unwrap()))))
+            }
+            "public" => Val::Base(ctx.publics[r.id]),
+            "challenge" => Val::Ext(ctx.challenges[r.id]),
+            "xDivXSubXi" => Val::Ext(
+                ctx.x_div_x_sub_xi
+                    .ok_or_else(|| anyhow!("xDivXSubXi outside of a query"))?,
+            ),
+            "xDivXSubWXi" => Val::Ext(
+                ctx.x_div_x_sub_wxi
+                    .ok_or_else(|| anyhow!("xDivXSubWXi outside of a query"))?,
+            ),
+            "x" => Val::Ext(ctx.challenges[7]),
+            "Z" => Val::Ext(if r.prime { ctx.zp } else { ctx.z }),
+            _ => bail!("Invalid reference type, get: {}", r.type_),
+        })
+    };
+
+    for ci in code {
+        let mut src = vec![];
+        for s in ci.src.iter() {
+            src.push(get_ref(b, s, &tmp)?);
+        }
+        let res = match ci.op.as_str() {
+            "add" => val_add(b, src[0], src[1]),
+            "sub" => val_sub(b, src[0], src[1]),
+            "mul" => val_mul(b, src[0], src[1]),
+            "muladd" => val_mul_add(b, src[0], src[1], src[2]),
+            "copy" => src[0],
+            _ => bail!("Invalid op: {}", ci.op),
+        };
+        if ci.dest.type_ != "tmp" {
+            bail!("Invalid reference type set: {}", ci.dest.type_);
+        }
+        tmp.insert(ci.dest.id, res);
+    }
+    let dest = &code
+        .last()
+        .ok_or_else(|| anyhow!("empty verifier code"))?
+        .dest;
+    get_ref(b, dest, &tmp)
+}
+
+/// Builds the circuit verifying a GL stark proof of the machine described by
+/// `starkinfo` and `program`, the same checks as `stark_verify`.
+///
+/// The publics of the stark become the publics of the circuit and `const_root` is
+/// hardcoded. With `verkey_input` of `options`, the root of the constant tree is read from
+/// the proof instead, as 4 more publics after the ones of the stark, like the circom verifier
+/// of the normalization stage. The aggregation stage of two proofs is only generated by
+/// `pil2circom`. The constraints only depend on the stark setup, the values come from
+/// `proof` (see `ProofValues::shape` for setup without a proof).
+pub fn build_verifier_circuit(
+    starkinfo: &StarkInfo,
+    program: &Program,
+    stark_struct: &StarkStruct,
+    const_root: &[FGL; 4],
+    proof: &ProofValues,
+    options: &StarkOption,
+) -> Result<CircuitBuilder> {
+    if options.agg_stage {
+        bail!("the native verifier does not support the aggregation stage, use pil2circom");
+    }
+    let steps = &stark_struct.steps;
+    if stark_struct.verificationHashType != "GL" {
+        bail!(
+            "the native verifier only supports GL, got {}",
+            stark_struct.verificationHashType
+        );
+    }
+    if steps.is_empty() || steps[0].nBits != stark_struct.nBitsExt {
+        bail!("the first FRI step must have nBitsExt bits");
+    }
+    if proof.publics.len() != starkinfo.n_publics
+        || proof.evals.len() != starkinfo.ev_map.len()
+        || proof.queries.len() != steps.len()
+        || proof
+            .queries
+            .iter()
+            .any(|q| q.len() != stark_struct.nQueries)
+        || proof.last.len() != 1 << steps[steps.len() - 1].nBits
+    {
+        bail!("the proof does not match the stark setup");
+    }
+
+    let mut b = CircuitBuilder::new();
+    let publics = proof
+        .publics
+        .iter()
+        .map(|p| b.public(p.cube[0]))
+        .collect::<Vec<_>>();
+    let const_root = if options.verkey_input {
+        match proof.root_c {
+            Some(root_c) => root_c.map(|e| b.public(e)),
+            None => bail!("the proof has no rootC for verkey_input"),
+        }
+    } else {
+        const_root.map(|e| b.constant(e))
+    };
+    let roots = proof
+        .roots
+        .iter()
+        .map(|r| r.map(|e| b.alloc(e)))
+        .collect::<Vec<_>>();
+    let evals = proof
+        .evals
+        .iter()
+        .map(|e| b.alloc_ext(e))
+        .collect::<Vec<_>>();
+    let fri_roots = proof
+        .fri_roots
+        .iter()
+        .map(|r| r.map(|e| b.alloc(e)))
+        .collect::<Vec<_>>();
+    let last = proof
+        .last
+        .iter()
+        .map(|e| b.alloc_ext(e))
+        .collect::<Vec<_>>();
+
+    // challenges, in the order of stark_verify
+    let mut transcript = TranscriptGadget::new(&mut b);
+    let zero = b.ext_constant(&F3G::ZEROS);
+    let mut challenges = vec![zero; 8];
+    transcript.put(&mut b, &publics);
+    transcript.put(&mut b, &roots[0]);
+    challenges[0] = transcript.get_field(&mut b);
+    challenges[1] = transcript.get_field(&mut b);
+    transcript.put(&mut b, &roots[1]);
+    challenges[2] = transcript.get_field(&mut b);
+    challenges[3] = transcript.get_field(&mut b);
+    transcript.put(&mut b, &roots[2]);
+    challenges[4] = transcript.get_field(&mut b);
+    transcript.put(&mut b, &roots[3]);
+    challenges[7] = transcript.get_field(&mut b);
+    for e in evals.iter() {
+        transcript.put(&mut b, e);
+    }
+    challenges[5] = transcript.get_field(&mut b);
+    challenges[6] = transcript.get_field(&mut b);
+
+    // Z = xi^N - 1, Zp = (xi * w)^N - 1
+    let one = b.ext_constant(&F3G::ONE);
+    let xi = challenges[7];
+    let x_n = ext_pow2(&mut b, &xi, stark_struct.nBits);
+    let z = b.ext_sub(&x_n, &one);
+    let w = b.constant(MG.0[stark_struct.nBits]);
+    let xiw = b.ext_scale(&xi, w);
+    let xiw_n = ext_pow2(&mut b, &xiw, stark_struct.nBits);
+    let zp = b.ext_sub(&xiw_n, &one);
+
+    // check the evaluations: C(xi) == Q(xi) * Z(xi)
+    let ctx = CodeCtx {
+        evals: &evals,
+        publics: &publics,
+        challenges: &challenges,
+        z,
+        zp,
+        trees: [&[], &[], &[], &[]],
+        consts: &[],
+        x_div_x_sub_xi: None,
+        x_div_x_sub_wxi: None,
+    };
+    let res = execute_code(&mut b, &ctx, &program.verifier_code.first)?.ext(&mut b);
+    let mut x_acc = one;
+    let mut q = zero;
+    for (i, q_id) in starkinfo.qs.iter().take(starkinfo.q_deg).enumerate() {
+        let idx = *starkinfo
+            .ev_idx
+            .get("cm", 0, *q_id)
+            .ok_or_else(|| anyhow!("no evaluation of q{}", i))?;
+        q = b.ext_mul_add(&x_acc, &evals[idx], &q);
+        x_acc = b.ext_mul(&x_acc, &x_n);
+    }
+    let q_z = b.ext_mul(&q, &z);
+    b.ext_assert_equal(&res, &q_z);
+
+    // FRI
+    let mut special_x = vec![];
+    for si in 0..steps.len() {
+        special_x.push(transcript.get_field(&mut b));
+        if si < steps.len() - 1 {
+            transcript.put(&mut b, &fri_roots[si + 1]);
+        } else {
+            for e in last.iter() {
+                transcript.put(&mut b, e);
+            }
+        }
+    }
+    let ys = transcript.get_permutations(&mut b, stark_struct.nQueries, steps[0].nBits);
+
+    let widths = [
+        starkinfo.map_sectionsN.cm1_2ns,
+        starkinfo.map_sectionsN.cm2_2ns,
+        starkinfo.map_sectionsN.cm3_2ns,
+        starkinfo.map_sectionsN.cm4_2ns,
+        starkinfo.n_constants,
+    ];
+    let ifft_matrices = (1..steps.len())
+        .map(|si| ifft_matrix(1 << (steps[si - 1].nBits - steps[si].nBits)))
+        .collect::<Vec<_>>();
+    let x_w = MG.0[stark_struct.nBitsExt];
+    let xi_w = b.ext_scale(&xi, w);
+
+    for (q, bits) in ys.iter().enumerate() {
+        // open every tree of the query and check it against its root
+        let mut opened = vec![];
+        for (si, step) in proof.queries.iter().enumerate() {
+            let depth = steps[si].nBits;
+            let trees = &step[q];
+            let mut step_vals = vec![];
+            for (k, (vals, sibs)) in trees.iter().enumerate() {
+                let vals = vals.iter().map(|v| b.alloc(*v)).collect::<Vec<_>>();
+                let sibs = sibs
+                    .iter()
+                    .map(|s| [b.alloc(s[0]), b.alloc(s[1]), b.alloc(s[2]), b.alloc(s[3])])
+                    .collect::<Vec<_>>();
+                // like the circom verifier, empty stages are not opened
+                if si == 0 && widths[k] == 0 {
+                    step_vals.push(vals);
+                    continue;
+                }
+                if sibs.len() != depth {
+                    bail!("invalid merkle proof length at step {}", si);
+                }
+                let root = if si == 0 {
+                    if k == 4 {
+                        const_root
+                    } else {
+                        roots[k]
+                    }
+                } else {
+                    fri_roots[si]
+                };
+                let leaf = linear_hash(&mut b, &vals);
+                let c_root = merkle_root(&mut b, leaf, &sibs, &bits[..depth]);
+                for (c, r) in c_root.iter().zip(root.iter()) {
+                    b.assert_equal(*c, *r);
+                }
+                step_vals.push(vals);
+            }
+            opened.push(step_vals);
+        }
+
+        // the value of the FRI polynomial at the query point
+        let x = pow_bits(&mut b, *SHIFT, x_w, bits);
+        let x_ext = b.ext_from_base(x);
+        let d = b.ext_sub(&x_ext, &xi);
+        let d = b.ext_inv(&d);
+        let x_div_x_sub_xi = b.ext_scale(&d, x);
+        let d = b.ext_sub(&x_ext, &xi_w);
+        let d = b.ext_inv(&d);
+        let x_div_x_sub_wxi = b.ext_scale(&d, x);
+        let s0 = &opened[0];
+        let ctx = CodeCtx {
+            evals: &evals,
+            publics: &publics,
+            challenges: &challenges,
+            // the query context of stark_verify leaves Z unset
+            z: zero,
+            zp: zero,
+            trees: [&s0[0], &s0[1], &s0[2], &s0[3]],
+            consts: &s0[4],
+            x_div_x_sub_xi: Some(x_div_x_sub_xi),
+            x_div_x_sub_wxi: Some(x_div_x_sub_wxi),
+        };
+        let mut ev = execute_code(&mut b, &ctx, &program.verifier_query_code.first)?.ext(&mut b);
+
+        let mut pol_bits = stark_struct.nBitsExt;
+        let mut shift = *SHIFT;
+        for (si, step) in steps.iter().enumerate() {
+            let step_bits = step.nBits;
+            if si > 0 {
+                // fold the group opened at this step and evaluate it at special_x
+                let group = opened[si][0]
+                    .chunks(3)
+                    .map(|c| [c[0], c[1], c[2]])
+                    .collect::<Vec<_>>();
+                let m = &ifft_matrices[si - 1];
+                let den = pow_bits(&mut b, shift, MG.0[pol_bits], &bits[..step_bits]);
+                let sinv = b.inv(den);
+                let point = b.ext_scale(&special_x[si], sinv);
+                ev = ifft_coef(&mut b, m, group.len() - 1, &group);
+                for j in (0..group.len() - 1).rev() {
+                    let c = ifft_coef(&mut b, m, j, &group);
+                    ev = b.ext_mul_add(&ev, &point, &c);
+                }
+            }
+
+            let expected = if si < steps.len() - 1 {
+                let next_bits = steps[si + 1].nBits;
+                let next_group = opened[si + 1][0]
+                    .chunks(3)
+                    .map(|c| [c[0], c[1], c[2]])
+                    .collect::<Vec<_>>();
+                select_tree(&mut b, &bits[next_bits..step_bits], &next_group)
+            } else {
+                select_tree(&mut b, &bits[..step_bits], &last)
+            };
+            b.ext_assert_equal(&ev, &expected);
+
+            for _ in 0..(pol_bits - step_bits) {
+                shift = shift * shift;
+            }
+            pol_bits = step_bits;
+        }
+    }
+
+    // the last polynomial must have a low degree
+    let last_bits = steps[steps.len() - 1].nBits;
+    let max_deg = if last_bits < stark_struct.nBitsExt - stark_struct.nBits {
+        0
+    } else {
+        1 << (last_bits - (stark_struct.nBitsExt - stark_struct.nBits))
+    };
+    let m = ifft_matrix(last.len());
+    for row in m.iter().skip(max_deg + 1) {
+        for c in 0..3 {
+            let terms = last
+                .iter()
+                .zip(row.iter())
+                .map(|(e, k)| (e[c], *k))
+                .collect::<Vec<_>>();
+            b.assert_lin_zero(&terms, FGL::ZERO);
+        }
+    }
+
+    Ok(b)
+}
+
+fn load_setup(
+    stark_struct_file: &str,
+    pil_json_file: &str,
+    const_pols_file: &str,
+) -> Result<(StarkStruct, StarkSetup<MerkleTreeGL>)> {
+    let stark_struct = load_json::<StarkStruct>(stark_struct_file)?;
+    let mut pil = load_json::<PIL>(pil_json_file)?;
+    let mut const_pol = PolsArray::new(&pil, PolKind::Constant);
+    const_pol.load(const_pols_file)?;
+    let setup = StarkSetup::<MerkleTreeGL>::new(&const_pol, &mut pil, &stark_struct, None)?;
+    Ok((stark_struct, setup))
+}
+
+// the options of the stages, as `stark_prove` sets them for pil2circom
+fn stage_options(norm_stage: bool, agg_stage: bool) -> StarkOption {
+    StarkOption {
+        enable_input: false,
+        verkey_input: norm_stage,
+        skip_main: false,
+        agg_stage,
+    }
+}
+
+// setup phase without circom:
+// input: stark struct, .pil.json and .const of the machine to verify, the stage of stark_prove
+//        (the aggregation stage is rejected)
+// output: .pil, .const, .exec of its Compressor12 verifier
+#[allow(clippy::too_many_arguments)]
+pub fn setup(
+    stark_struct_file: &str,
+    pil_json_file: &str,
+    const_pols_file: &str,
+    norm_stage: bool,
+    agg_stage: bool,
+    pil_file: &str,
+    const_file: &str,
+    exec_file: &str,
+    force_n_bits: usize,
+) -> Result<()> {
+    let (stark_struct, setup) = load_setup(stark_struct_file, pil_json_file, const_pols_file)?;
+    let proof = ProofValues::shape(&setup.starkinfo, &stark_struct);
+    let circuit = build_verifier_circuit(
+        &setup.starkinfo,
+        &setup.program,
+        &stark_struct,
+        &digest(&setup.const_root),
+        &proof,
+        &stage_options(norm_stage, agg_stage),
+    )?;
+    let (r1cs, _) = circuit.build()?;
+    setup_from_r1cs(&r1cs, pil_file, const_file, exec_file, force_n_bits)
+}
+
+// exec phase without wasm:
+// input: stark struct, .pil.json and .const of the machine, the stage given to `setup`, its
+//        proof (zkin.json), and the .pil, .exec from `setup`
+// output: .cm
+#[allow(clippy::too_many_arguments)]
+pub fn exec(
+    stark_struct_file: &str,
+    pil_json_file: &str,
+    const_pols_file: &str,
+    norm_stage: bool,
+    agg_stage: bool,
+    zkin_file: &str,
+    pil_file: &str,
+    exec_file: &str,
+    commit_file: &str,
+) -> Result<()> {
+    let (stark_struct, setup) = load_setup(stark_struct_file, pil_json_file, const_pols_file)?;
+    let proof = load_json::<StarkProof<MerkleTreeGL>>(zkin_file)?;
+    let circuit = build_verifier_circuit(
+        &setup.starkinfo,
+        &setup.program,
+        &stark_struct,
+        &digest(&setup.const_root),
+        &ProofValues::from_proof(&proof)?,
+        &stage_options(norm_stage, agg_stage),
+    )?;
+    circuit
+        .check()
+        .map_err(|e| anyhow!("the stark proof does not verify: {}", e))?;
+    let (_, w) = circuit.build()?;
+    exec_from_witness(w, pil_file, exec_file, commit_file)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::pilcom::compile_pil_from_path;
+    use starky::stark_verify::stark_verify;
+    use starky::transcript::TranscriptGL;
+    use starky::types::Step;
+
+    fn fib_setup() -> (PIL, PolsArray, PolsArray, StarkStruct) {
+        let pil = load_json::<PIL>("../starky/data/fib.pil.json.gl").unwrap();
+        let mut const_pol = PolsArray::new(&pil, PolKind::Constant);
+        const_pol.load("../starky/data/fib.const.gl").unwrap();
+        let mut cm_pol = PolsArray::new(&pil, PolKind::Commit);
+        cm_pol.load("../starky/data/fib.cm.gl").unwrap();
+        let stark_struct = load_json::<StarkStruct>("../starky/data/starkStruct.json.gl").unwrap();
+        (pil, const_pol, cm_pol, stark_struct)
+    }
+
+    #[test]
+    fn test_verifier_circuit_fib() {
+        let (mut pil, const_pol, cm_pol, stark_struct) = fib_setup();
+        let setup =
+            StarkSetup::<MerkleTreeGL>::new(&const_pol, &mut pil, &stark_struct, None).unwrap();
+        let proof = StarkProof::<MerkleTreeGL>::stark_gen::<TranscriptGL>(
+            cm_pol,
+            const_pol,
+            &setup.const_tree,
+            &setup.starkinfo,
+            &setup.program,
+            &pil,
+            &stark_struct,
+            "",
+        )
+        .unwrap();
+        assert!(stark_verify::<MerkleTreeGL, TranscriptGL>(
+            &proof,
+            &setup.const_root,
+            &setup.starkinfo,
+            &stark_struct,
+            &setup.program,
+        )
+        .unwrap());
+
+        let const_root = digest(&setup.const_root);
+        let values = ProofValues::from_proof(&proof).unwrap();
+        let circuit = build_verifier_circuit(
+            &setup.starkinfo,
+            &setup.program,
+            &stark_struct,
+            &const_root,
+            &values,
+            &stage_options(false, false),
+        )
+        .unwrap();
+        circuit.check().unwrap();
+
+        // setup doesn't need a proof: the shape gives the same circuit
+        let shape = build_verifier_circuit(
+            &setup.starkinfo,
+            &setup.program,
+            &stark_struct,
+            &const_root,
+            &ProofValues::shape(&setup.starkinfo, &stark_struct),
+            &stage_options(false, false),
+        )
+        .unwrap();
+        assert!(shape.check().is_err());
+        let (r1cs, _) = circuit.build().unwrap();
+        let (shape_r1cs, _) = shape.build().unwrap();
+        assert_eq!(r1cs.num_variables, shape_r1cs.num_variables);
+        assert_eq!(r1cs.constraints, shape_r1cs.constraints);
+        for (a, b) in r1cs
+            .custom_gates_uses
+            .iter()
+            .zip(shape_r1cs.custom_gates_uses.iter())
+        {
+            assert_eq!((a.id, &a.signals), (b.id, &b.signals));
+        }
+
+        // a tampered evaluation is rejected
+        let mut bad = values.clone();
+        bad.evals[0] = bad.evals[0] + F3G::ONE;
+        let circuit = build_verifier_circuit(
+            &setup.starkinfo,
+            &setup.program,
+            &stark_struct,
+            &const_root,
+            &bad,
+            &stage_options(false, false),
+        )
+        .unwrap();
+        assert!(circuit.check().is_err());
+
+        // with verkey_input the constant root is read from the proof, as 4 more publics
+        let build = |values: &ProofValues, options: &StarkOption| {
+            build_verifier_circuit(
+                &setup.starkinfo,
+                &setup.program,
+                &stark_struct,
+                &[FGL::ZERO; 4],
+                values,
+                options,
+            )
+        };
+        let circuit = build(&values, &stage_options(true, false)).unwrap();
+        circuit.check().unwrap();
+        let (r1cs, w) = circuit.build().unwrap();
+        assert_eq!(r1cs.num_inputs, 1 + setup.starkinfo.n_publics + 4);
+        assert_eq!(
+            &w[1 + setup.starkinfo.n_publics..r1cs.num_inputs],
+            &const_root
+        );
+        let mut bad = values.clone();
+        bad.root_c = Some([FGL::ONE; 4]);
+        assert!(build(&bad, &stage_options(true, false))
+            .unwrap()
+            .check()
+            .is_err());
+        bad.root_c = None;
+        assert!(build(&bad, &stage_options(true, false)).is_err());
+        assert!(build(&values, &stage_options(false, true)).is_err());
+    }
+
+    #[test]
+    fn test_native_compressor12_fib() {
+        let (mut pil, const_pol, cm_pol, stark_struct) = fib_setup();
+        let setup =
+            StarkSetup::<MerkleTreeGL>::new(&const_pol, &mut pil, &stark_struct, None).unwrap();
+        let proof = StarkProof::<MerkleTreeGL>::stark_gen::<TranscriptGL>(
+            cm_pol,
+            const_pol,
+            &setup.const_tree,
+            &setup.starkinfo,
+            &setup.program,
+            &pil,
+            &stark_struct,
+            "",
+        )
+        .unwrap();
+        let dir = std::env::temp_dir().join(format!("fib_native_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = |name: &str| dir.join(name).to_str().unwrap().to_string();
+        let zkin = &path("fib.zkin.json");
+        std::fs::write(zkin, serde_json::to_string(&proof).unwrap()).unwrap();
+
+        let c12_pil = &path("fib.c12.pil");
+        let c12_const = &path("fib.c12.const");
+        let c12_exec = &path("fib.c12.exec");
+        let c12_cm = &path("fib.c12.cm");
+        let args = (
+            "../starky/data/starkStruct.json.gl",
+            "../starky/data/fib.pil.json.gl",
+            "../starky/data/fib.const.gl",
+        );
+        super::setup(
+            args.0, args.1, args.2, false, false, c12_pil, c12_const, c12_exec, 0,
+        )
+        .unwrap();
+        super::exec(
+            args.0, args.1, args.2, false, false, zkin, c12_pil, c12_exec, c12_cm,
+        )
+        .unwrap();
+        assert!(
+            super::setup(args.0, args.1, args.2, false, true, c12_pil, c12_const, c12_exec, 0)
+                .is_err()
+        );
+
+        // prove and verify the compressor machine itself
+        let mut c12 = compile_pil_from_path(c12_pil);
+        let mut c12_const_pol = PolsArray::new(&c12, PolKind::Constant);
+        c12_const_pol.load(c12_const).unwrap();
+        let mut c12_cm_pol = PolsArray::new(&c12, PolKind::Commit);
+        c12_cm_pol.load(c12_cm).unwrap();
+        let n_bits = starky::helper::log2_any(c12_cm_pol.n);
+        let c12_struct = StarkStruct {
+            nBits: n_bits,
+            nBitsExt: n_bits + 1,
+            nQueries: 8,
+            verificationHashType: "GL".to_string(),
+            steps: vec![
+                Step { nBits: n_bits + 1 },
+                Step { nBits: n_bits - 3 },
+                Step { nBits: 4 },
+            ],
+            ..Default::default()
+        };
+        let c12_setup =
+            StarkSetup::<MerkleTreeGL>::new(&c12_const_pol, &mut c12, &c12_struct, None).unwrap();
+        let c12_proof = StarkProof::<MerkleTreeGL>::stark_gen::<TranscriptGL>(
+            c12_cm_pol,
+            c12_const_pol,
+            &c12_setup.const_tree,
+            &c12_setup.starkinfo,
+            &c12_setup.program,
+            &c12,
+            &c12_struct,
+            "",
+        )
+        .unwrap();
+        assert_eq!(c12_proof.publics, proof.publics);
+        assert!(stark_verify::<MerkleTreeGL, TranscriptGL>(
+            &c12_proof,
+            &c12_setup.const_root,
+            &c12_setup.starkinfo,
+            &c12_struct,
+            &c12_setup.program,
+        )
+        .unwrap());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -2,29 +2,177 @@
 use std::rc::Rc;
 mod export;
 mod expression_counter;
+mod source;
 
 pub use export::export;
+pub use source::{Location, PilCompileError};
 
+use anyhow::{bail, Result};
 use powdr::number::GoldilocksField;
-use starky::types::PIL;
-use std::path::Path;
+use powdr_ast::analyzed::Analyzed;
+use serde_json::Value;
+use source::Source;
+use starky::types::{load_json, PIL};
+use std::fs::File;
+use std::io::Write;
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
 pub fn compile_pil_from_str(pil_str: &str) -> PIL {
     let analyze = powdr_pil_analyzer::analyze_string::<GoldilocksField>(pil_str);
     export(&Rc::new(analyze))
 }
 pub fn compile_pil_from_path(pil_path: &str) -> PIL {
-    let analyze = powdr_pil_analyzer::analyze_file::<GoldilocksField>(Path::new(pil_path));
-    export(&Rc::new(analyze))
+    compile_pil(pil_path, &[]).unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Compile the PIL file `pil_path` to the pil.json of pilcom. An `include` is searched in the
+/// directory of the including file, then in `include_paths`.
+pub fn compile_pil(pil_path: &str, include_paths: &[String]) -> Result<PIL, PilCompileError> {
+    let include_paths = include_paths.iter().map(PathBuf::from).collect::<Vec<_>>();
+    let source = Source::load(Path::new(pil_path), &include_paths)?;
+
+    // the parser reports where a syntax error is, the analyzer only panics
+    powdr_parser::parse(None, &source.text).map_err(|e| PilCompileError {
+        location: source.locate(e.source_ref().start),
+        message: e.message().to_string(),
+    })?;
+    let analyzed = with_silent_panics(|| {
+        analyze(&source.text).map_err(|message| match locate_analyzer_error(&source) {
+            Some(location) => PilCompileError {
+                location: Some(location),
+                message,
+            },
+            None => PilCompileError {
+                location: None,
+                message: format!("{}: {}", pil_path, message),
+            },
+        })
+    })?;
+    Ok(export(&Rc::new(analyzed)))
+}
+
+fn analyze(text: &str) -> std::result::Result<Analyzed<GoldilocksField>, String> {
+    std::panic::catch_unwind(|| powdr_pil_analyzer::analyze_string::<GoldilocksField>(text))
+        .map_err(|e| {
+            e.downcast_ref::<String>()
+                .cloned()
+                .or_else(|| e.downcast_ref::<&str>().map(|s| s.to_string()))
+                .unwrap_or_else(|| "unknown error".to_string())
+        })
+}
+
+// The analyzer panics without telling where. A statement only sees the statements before it,
+// so the text up to a statement fails to analyze iff the failing statement is in it: search
+// the first prefix of statements that fails.
+fn locate_analyzer_error(source: &Source) -> Option<Location> {
+    let statements = source::statements(&source.text);
+    let prefix = |n: usize| match n {
+        0 => "",
+        n => &source.text[..statements[n - 1].1],
+    };
+    // prefix(lo) is analyzed, prefix(hi) fails
+    let (mut lo, mut hi) = (0, statements.len());
+    if hi == 0 || analyze(prefix(hi)).is_ok() {
+        return None;
+    }
+    while hi - lo > 1 {
+        let mid = (lo + hi) / 2;
+        match analyze(prefix(mid)) {
+            Ok(_) => lo = mid,
+            Err(_) => hi = mid,
+        }
+    }
+    source.locate(statements[hi - 1].0)
+}
+
+// The panic hook is process global: the calls that silence it are serialized, so that the
+// hook saved by one is not the silent one of another.
+static PANIC_HOOK: Mutex<()> = Mutex::new(());
+
+fn with_silent_panics<T>(f: impl FnOnce() -> T) -> T {
+    let _guard = PANIC_HOOK.lock().unwrap_or_else(|e| e.into_inner());
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(|_| {}));
+    let res = std::panic::catch_unwind(AssertUnwindSafe(f));
+    std::panic::set_hook(hook);
+    res.unwrap_or_else(|e| std::panic::resume_unwind(e))
+}
+
+/// The differences of two pil.json, one `path: actual != expected` per leaf, the keys of the
+/// path are joined by `/` as the polynomial names have dots.
+pub fn diff_pil(actual: &PIL, expected: &PIL) -> Vec<String> {
+    let mut diffs = vec![];
+    diff_value(
+        "",
+        &serde_json::to_value(actual).unwrap(),
+        &serde_json::to_value(expected).unwrap(),
+        &mut diffs,
+    );
+    diffs
+}
+
+fn diff_value(path: &str, a: &Value, b: &Value, diffs: &mut Vec<String>) {
+    match (a, b) {
+        (Value::Object(x), Value::Object(y)) => {
+            for (k, v) in x.iter() {
+                match y.get(k) {
+                    Some(w) => diff_value(&format!("{}/{}", path, k), v, w, diffs),
+                    None => diffs.push(format!("{}/{}: unexpected", path, k)),
+                }
+            }
+            for k in y.keys().filter(|k| !x.contains_key(*k)) {
+                diffs.push(format!("{}/{}: missing", path, k));
+            }
+        }
+        (Value::Array(x), Value::Array(y)) => {
+            if x.len() != y.len() {
+                diffs.push(format!("{}: length {} != {}", path, x.len(), y.len()));
+            }
+            for (i, (v, w)) in x.iter().zip(y.iter()).enumerate() {
+                diff_value(&format!("{}/{}", path, i), v, w, diffs);
+            }
+        }
+        _ if a != b => diffs.push(format!("{}: {} != {}", path, a, b)),
+        _ => {}
+    }
+}
+
+/// zkit pil_compile: compile `pil_path` to `output`, and compare it with the pil.json
+/// `reference` if any.
+pub fn pil_compile(
+    pil_path: &str,
+    include_paths: &[String],
+    output: &str,
+    reference: Option<&str>,
+) -> Result<()> {
+    let pil = compile_pil(pil_path, include_paths)?;
+    let mut file = File::create(output)?;
+    write!(file, "{}", serde_json::to_string_pretty(&pil)?)?;
+
+    if let Some(reference) = reference {
+        let expected = load_json::<PIL>(reference)?;
+        let diffs = diff_pil(&pil, &expected);
+        for d in diffs.iter() {
+            println!("{}", d);
+        }
+        if !diffs.is_empty() {
+            bail!(
+                "{} differs from {} at {} places",
+                output,
+                reference,
+                diffs.len()
+            );
+        }
+    }
+    Ok(())
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
-    use starky::types::load_json;
     use std::fs;
-    use std::fs::File;
-    use std::io::Write;
 
     // This test is check whether the js_res the js version pilcom.compile equals to compile_pil_from_str.
     #[test]
@@ -46,4 +194,70 @@ mod test {
         let input = serde_json::to_string_pretty(&actual).unwrap();
         write!(file, "{}", input).unwrap();
     }
+
+    #[test]
+    fn test_compile_pil() {
+        let pil = compile_pil("../starkjs/connection/connection_main.pil", &[]).unwrap();
+        let expected = load_json::<PIL>("../starky/data/connection.pil.json").unwrap();
+        let mut refs = pil.references.keys().collect::<Vec<_>>();
+        refs.sort();
+        let mut expected_refs = expected.references.keys().collect::<Vec<_>>();
+        expected_refs.sort();
+        assert_eq!(refs, expected_refs);
+        for (k, r) in pil.references.iter() {
+            assert_eq!(r.id, expected.references[k].id);
+        }
+        assert!(diff_pil(&pil, &pil).is_empty());
+
+        let mut other = pil.clone();
+        other.nCommitments += 1;
+        other.references.remove("Connection.a");
+        let diffs = diff_pil(&pil, &other);
+        assert!(diffs.contains(&format!(
+            "/nCommitments: {} != {}",
+            pil.nCommitments,
+            pil.nCommitments + 1
+        )));
+        assert!(diffs.contains(&"/references/Connection.a: unexpected".to_string()));
+    }
+
+    #[test]
+    fn test_compile_pil_error() {
+        let dir = std::env::temp_dir().join("pilcom_error_test");
+        fs::create_dir_all(&dir).unwrap();
+        let pil = dir.join("bad.pil");
+        fs::write(
+            &pil,
+            "let N: int = 2**10;\nnamespace Main(N);\n    pol commit a b;\n",
+        )
+        .unwrap();
+        let err = compile_pil(pil.to_str().unwrap(), &[]).unwrap_err();
+        assert_eq!(err.location.unwrap().line, 3);
+
+        // an analyzer error, in an included file
+        fs::write(
+            dir.join("lib.pil"),
+            "namespace Lib(N);\n    pol commit x;\n    x = y;\n",
+        )
+        .unwrap();
+        fs::write(
+            &pil,
+            "let N: int = 2**10;\ninclude \"lib.pil\";\nnamespace Main(N);\n    pol commit a;\n",
+        )
+        .unwrap();
+        let err = compile_pil(pil.to_str().unwrap(), &[]).unwrap_err();
+        let location = err.location.unwrap();
+        assert!(location.file.ends_with("lib.pil"));
+        assert_eq!((location.line, location.col), (3, 5));
+
+        fs::write(
+            &pil,
+            "let N: int = 2**10;\nnamespace Main(N);\n    pol commit a;\n    a' = a * b;\n",
+        )
+        .unwrap();
+        let err = compile_pil(pil.to_str().unwrap(), &[]).unwrap_err();
+        let location = err.location.unwrap();
+        assert!(location.file.ends_with("bad.pil"));
+        assert_eq!((location.line, location.col), (4, 5));
+    }
 }
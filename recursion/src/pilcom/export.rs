@@ -396,7 +396,7 @@ impl<'a, T: FieldElement> Exporter<'a, T> {
     }
 }
 
-fn compute_line_starts(source: &str) -> Vec<usize> {
+pub(super) fn compute_line_starts(source: &str) -> Vec<usize> {
     std::iter::once(0)
         .chain(source.match_indices('\n').map(|(i, _)| i + 1))
         .collect::<Vec<_>>()
@@ -404,7 +404,7 @@ fn compute_line_starts(source: &str) -> Vec<usize> {
 
 /// Returns a tuple `(line, col)` given the file offset of line starts.
 /// `line` is 1 based and `col` is 0 based.
-fn offset_to_line_col(offset: usize, line_starts: &[usize]) -> (usize, usize) {
+pub(super) fn offset_to_line_col(offset: usize, line_starts: &[usize]) -> (usize, usize) {
     let line = match line_starts.binary_search(&offset) {
         Ok(line) => line + 1,
         Err(next_line) => next_line,
@@ -0,0 +1,308 @@
+//! Inlines the `include "file.pil";` statements of a PIL file, searching the directory of the
+//! including file first and then the include paths (pilcom's `-I`), and maps the offsets of
+//! the merged source back to the original files.
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use super::export::{compute_line_starts, offset_to_line_col};
+
+/// A position in a PIL file, `line` and `col` are 1 based.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    pub file: String,
+    pub line: usize,
+    pub col: usize,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.file, self.line, self.col)
+    }
+}
+
+#[derive(Debug)]
+pub struct PilCompileError {
+    pub location: Option<Location>,
+    pub message: String,
+}
+
+impl fmt::Display for PilCompileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.location {
+            Some(loc) => write!(f, "{}: {}", loc, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+impl std::error::Error for PilCompileError {}
+
+struct File {
+    path: PathBuf,
+    line_starts: Vec<usize>,
+}
+
+// merged[start..] comes from files[file] at file_start, up to the next segment.
+struct Segment {
+    start: usize,
+    file: usize,
+    file_start: usize,
+}
+
+pub struct Source {
+    pub text: String,
+    files: Vec<File>,
+    segments: Vec<Segment>,
+}
+
+impl Source {
+    pub fn load(path: &Path, include_paths: &[PathBuf]) -> Result<Self, PilCompileError> {
+        let mut source = Source {
+            text: String::new(),
+            files: vec![],
+            segments: vec![],
+        };
+        source.inline(path, include_paths, None)?;
+        Ok(source)
+    }
+
+    /// The file position of the offset `offset` of the merged text.
+    pub fn locate(&self, offset: usize) -> Option<Location> {
+        let i = self.segments.partition_point(|s| s.start <= offset);
+        let seg = self.segments.get(i.checked_sub(1)?)?;
+        let file = &self.files[seg.file];
+        let (line, col) =
+            offset_to_line_col(seg.file_start + offset - seg.start, &file.line_starts);
+        Some(Location {
+            file: file.path.display().to_string(),
+            line,
+            col: col + 1,
+        })
+    }
+
+    fn location(&self, file: usize, offset: usize) -> Location {
+        let file = &self.files[file];
+        let (line, col) = offset_to_line_col(offset, &file.line_starts);
+        Location {
+            file: file.path.display().to_string(),
+            line,
+            col: col + 1,
+        }
+    }
+
+    // Every file is included once, like pilcom, which also stops include cycles.
+    fn inline(
+        &mut self,
+        path: &Path,
+        include_paths: &[PathBuf],
+        from: Option<Location>,
+    ) -> Result<(), PilCompileError> {
+        let error = |message: String| PilCompileError {
+            location: from.clone(),
+            message,
+        };
+        let path = path
+            .canonicalize()
+            .map_err(|e| error(format!("{}: {}", path.display(), e)))?;
+        if self.files.iter().any(|f| f.path == path) {
+            return Ok(());
+        }
+        let contents = std::fs::read_to_string(&path)
+            .map_err(|e| error(format!("{}: {}", path.display(), e)))?;
+        let file = self.files.len();
+        self.files.push(File {
+            path: path.clone(),
+            line_starts: compute_line_starts(&contents),
+        });
+
+        let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+        let mut cursor = 0;
+        for (start, end, name) in find_includes(&contents) {
+            self.push(file, cursor, &contents[cursor..start]);
+            let at = self.location(file, start);
+            let included = std::iter::once(&dir)
+                .chain(include_paths.iter())
+                .map(|d| d.join(&name))
+                .find(|p| p.is_file())
+                .ok_or_else(|| PilCompileError {
+                    location: Some(at.clone()),
+                    message: format!("include file not found: {}", name),
+                })?;
+            self.inline(&included, include_paths, Some(at))?;
+            // keep the statements of the two files apart
+            self.push(file, start, "\n");
+            cursor = end;
+        }
+        self.push(file, cursor, &contents[cursor..]);
+        Ok(())
+    }
+
+    fn push(&mut self, file: usize, file_start: usize, text: &str) {
+        self.segments.push(Segment {
+            start: self.text.len(),
+            file,
+            file_start,
+        });
+        self.text.push_str(text);
+    }
+}
+
+/// The `include "name";` statements of `src` as (start, end, name), skipping the comments
+/// and the string literals.
+fn find_includes(src: &str) -> Vec<(usize, usize, String)> {
+    let b = src.as_bytes();
+    let skip_ws = |mut i: usize| {
+        while i < b.len() && b[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        i
+    };
+    // byte offset of `pat` from `i`, the sources may hold any utf8 in comments
+    let find = |i: usize, pat: &[u8]| b[i..].windows(pat.len()).position(|w| w == pat);
+    let mut res = vec![];
+    let mut i = 0;
+    while i < b.len() {
+        if b[i..].starts_with(b"//") {
+            i = find(i, b"\n").map_or(b.len(), |e| i + e);
+        } else if b[i..].starts_with(b"/*") {
+            i = find(i + 2, b"*/").map_or(b.len(), |e| i + e + 4);
+        } else if b[i] == b'"' {
+            i = find(i + 1, b"\"").map_or(b.len(), |e| i + e + 2);
+        } else if b[i].is_ascii_alphabetic() || b[i] == b'_' {
+            let start = i;
+            while i < b.len() && (b[i].is_ascii_alphanumeric() || b[i] == b'_') {
+                i += 1;
+            }
+            if &src[start..i] != "include" {
+                continue;
+            }
+            let q = skip_ws(i);
+            if q >= b.len() || b[q] != b'"' {
+                continue;
+            }
+            let Some(len) = find(q + 1, b"\"") else {
+                continue;
+            };
+            let name = src[q + 1..q + 1 + len].to_string();
+            let mut end = skip_ws(q + len + 2);
+            if end < b.len() && b[end] == b';' {
+                end += 1;
+            }
+            res.push((start, end, name));
+            i = end;
+        } else {
+            i += 1;
+        }
+    }
+    res
+}
+
+/// The statements of `src` as (start, end), from their first token to their `;`, skipping
+/// the comments and the string literals.
+pub fn statements(src: &str) -> Vec<(usize, usize)> {
+    let b = src.as_bytes();
+    let find = |i: usize, pat: &[u8]| b[i..].windows(pat.len()).position(|w| w == pat);
+    let mut res = vec![];
+    let mut start = None;
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i < b.len() {
+        if b[i..].starts_with(b"//") {
+            i = find(i, b"\n").map_or(b.len(), |e| i + e);
+            continue;
+        } else if b[i..].starts_with(b"/*") {
+            i = find(i + 2, b"*/").map_or(b.len(), |e| i + e + 4);
+            continue;
+        } else if b[i].is_ascii_whitespace() {
+            i += 1;
+            continue;
+        }
+        let token = i;
+        match b[i] {
+            b'"' => i = find(i + 1, b"\"").map_or(b.len(), |e| i + e + 2),
+            b'(' | b'[' | b'{' => {
+                depth += 1;
+                i += 1;
+            }
+            b')' | b']' | b'}' => {
+                depth = depth.saturating_sub(1);
+                i += 1;
+            }
+            b';' if depth == 0 => {
+                res.push((start.take().unwrap_or(token), i + 1));
+                i += 1;
+                continue;
+            }
+            _ => i += 1,
+        }
+        start.get_or_insert(token);
+    }
+    res
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_find_includes() {
+        let src = "// include \"a.pil\";\ninclude \"b.pil\";\n/* include \"c.pil\"; */ pol x = \"include\";\ninclude   \"d.pil\" ;";
+        let inc = find_includes(src);
+        assert_eq!(inc.len(), 2);
+        assert_eq!(&src[inc[0].0..inc[0].1], "include \"b.pil\";");
+        assert_eq!(inc[0].2, "b.pil");
+        assert_eq!(inc[1].2, "d.pil");
+        assert_eq!(inc[1].1, src.len());
+    }
+
+    #[test]
+    fn test_statements() {
+        let src = "namespace Main(N); // a; b\n  pol commit a, b;\n/* c; */ a = \"d;\" * { b; };\n";
+        let statements = statements(src)
+            .iter()
+            .map(|(s, e)| &src[*s..*e])
+            .collect::<Vec<_>>();
+        assert_eq!(
+            statements,
+            vec![
+                "namespace Main(N);",
+                "pol commit a, b;",
+                "a = \"d;\" * { b; };"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_source_load() {
+        let dir = std::env::temp_dir().join("pilcom_source_test");
+        let lib = dir.join("lib");
+        std::fs::create_dir_all(&lib).unwrap();
+        std::fs::write(
+            lib.join("global.pil"),
+            "namespace Global(%N);\n    pol constant L1;\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("main.pil"),
+            "constant %N = 2**10;\ninclude \"global.pil\";\ninclude \"global.pil\";\nnamespace Main(%N);\n    pol commit a;\n",
+        )
+        .unwrap();
+
+        let err = Source::load(&dir.join("main.pil"), &[]).err().unwrap();
+        let loc = err.location.unwrap();
+        assert_eq!((loc.line, loc.col), (2, 1));
+        assert!(err.message.contains("global.pil"));
+
+        let source = Source::load(&dir.join("main.pil"), &[lib]).unwrap();
+        // included once
+        assert_eq!(source.text.matches("namespace Global").count(), 1);
+        let offset = source.text.find("pol constant").unwrap();
+        let loc = source.locate(offset).unwrap();
+        assert!(loc.file.ends_with("global.pil"));
+        assert_eq!((loc.line, loc.col), (2, 5));
+        let offset = source.text.find("pol commit").unwrap();
+        let loc = source.locate(offset).unwrap();
+        assert!(loc.file.ends_with("main.pil"));
+        assert_eq!((loc.line, loc.col), (5, 5));
+    }
+}
@@ -0,0 +1,535 @@
+#![allow(non_snake_case)]
+//! Composite PIL machines.
+//!
+//! Several PILs (tables) are merged into one PIL so that they are committed in the same
+//! trees, share the Fiat-Shamir challenges of one transcript and are opened by a single FRI.
+//! Every table keeps its own degree as the degree of its namespaces, the committed
+//! polynomials of the tables smaller than the biggest one are merkelized at their degree and
+//! lifted to the biggest one in the constraints, see [`crate::starkinfo::namespace_degrees`].
+//!
+//! Cross-table plookup and permutation arguments are declared with [`TableLink`], the
+//! columns are referenced as `table::Namespace.pol` or `table::Namespace.pol[k]`.
+use crate::polsarray::{PolKind, PolsArray};
+use crate::starkinfo::namespace_degrees;
+use crate::types::{
+    Expression, PermutationIdentity, PlookupIdentity, Public, Reference, StarkStruct, PIL,
+};
+use anyhow::{anyhow, bail, Result};
+use std::collections::HashMap;
+
+/// The separator between the table name and the original namespace.
+pub const TABLE_SEPARATOR: &str = "::";
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum LinkKind {
+    Plookup,
+    Permutation,
+}
+
+/// A lookup or permutation argument between the columns of two tables.
+#[derive(Debug, Clone)]
+pub struct TableLink {
+    pub kind: LinkKind,
+    pub f: Vec<String>,
+    pub t: Vec<String>,
+    pub sel_f: Option<String>,
+    pub sel_t: Option<String>,
+}
+
+/// Where a table lives inside the composite PIL.
+#[derive(Debug, Clone, Default)]
+pub struct TableLayout {
+    pub name: String,
+    pub degree: usize,
+    pub cm_offset: usize,
+    pub n_commitments: usize,
+    pub const_offset: usize,
+    pub n_constants: usize,
+    pub exp_offset: usize,
+    pub public_offset: usize,
+    pub q_offset: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct CompositePil {
+    pub pil: PIL,
+    pub tables: Vec<TableLayout>,
+    /// the degree of the composite trace, the biggest degree of all the tables
+    pub n: usize,
+}
+
+fn shift_expression(e: &mut Expression, layout: &TableLayout) {
+    match e.op.as_str() {
+        "cm" => e.id = e.id.map(|id| id + layout.cm_offset),
+        "const" => e.id = e.id.map(|id| id + layout.const_offset),
+        "exp" => e.id = e.id.map(|id| id + layout.exp_offset),
+        "public" => e.id = e.id.map(|id| id + layout.public_offset),
+        _ => {}
+    }
+    e.idQ = e.idQ.map(|id| id + layout.q_offset);
+    if let Some(values) = e.values.as_mut() {
+        for v in values.iter_mut() {
+            shift_expression(v, layout);
+        }
+    }
+}
+
+fn shift_ids(ids: &Option<Vec<usize>>, offset: usize) -> Option<Vec<usize>> {
+    ids.as_ref()
+        .map(|ids| ids.iter().map(|id| id + offset).collect())
+}
+
+impl CompositePil {
+    pub fn new(tables: &[(String, PIL)], links: &[TableLink]) -> Result<Self> {
+        if tables.is_empty() {
+            bail!("A composite pil needs at least one table");
+        }
+
+        let mut layouts: Vec<TableLayout> = vec![];
+        let mut next = TableLayout::default();
+        for (name, pil) in tables.iter() {
+            if name.is_empty() || name.contains('.') || name.contains(TABLE_SEPARATOR) {
+                bail!("Invalid table name {:?}", name);
+            }
+            if layouts.iter().any(|l| &l.name == name) {
+                bail!("Duplicated table name {}", name);
+            }
+            let degree = namespace_degrees(pil)?.0;
+            let layout = TableLayout {
+                name: name.clone(),
+                degree,
+                n_commitments: pil.nCommitments,
+                n_constants: pil.nConstants,
+                ..next
+            };
+            next = TableLayout {
+                cm_offset: layout.cm_offset + pil.nCommitments,
+                const_offset: layout.const_offset + pil.nConstants,
+                exp_offset: layout.exp_offset + pil.expressions.len(),
+                public_offset: layout.public_offset + pil.publics.len(),
+                q_offset: layout.q_offset + pil.nQ,
+                ..Default::default()
+            };
+            layouts.push(layout);
+        }
+        let n = layouts.iter().map(|l| l.degree).max().unwrap();
+
+        let mut pil = PIL {
+            nCommitments: 0,
+            nQ: 0,
+            nIm: 0,
+            nConstants: 0,
+            publics: vec![],
+            references: HashMap::new(),
+            expressions: vec![],
+            polIdentities: vec![],
+            plookupIdentities: vec![],
+            permutationIdentities: Some(vec![]),
+            connectionIdentities: Some(vec![]),
+            cm_dims: vec![],
+            q2exp: vec![],
+        };
+
+        for ((_, table), layout) in tables.iter().zip(layouts.iter()) {
+            let connections = table.connectionIdentities.as_ref();
+            if layout.degree < n && connections.map_or(false, |c| !c.is_empty()) {
+                bail!(
+                    "Table {} has connection identities and can not be lifted from {} to {}",
+                    layout.name,
+                    layout.degree,
+                    n
+                );
+            }
+
+            pil.nCommitments += table.nCommitments;
+            pil.nConstants += table.nConstants;
+            pil.nQ += table.nQ;
+            pil.nIm += table.nIm;
+
+            for (ref_name, r) in table.references.iter() {
+                let id = match r.type_.as_str() {
+                    "cmP" => r.id + layout.cm_offset,
+                    "constP" => r.id + layout.const_offset,
+                    "imP" => r.id + layout.exp_offset,
+                    _ => r.id,
+                };
+                pil.references.insert(
+                    format!("{}{}{}", layout.name, TABLE_SEPARATOR, ref_name),
+                    Reference { id, ..r.clone() },
+                );
+            }
+
+            for p in table.publics.iter() {
+                let pol_id = match p.polType.as_str() {
+                    "cmP" => p.polId + layout.cm_offset,
+                    "imP" => p.polId + layout.exp_offset,
+                    _ => bail!("Invalid public type {}", p.polType),
+                };
+                pil.publics.push(Public {
+                    polType: p.polType.clone(),
+                    polId: pol_id,
+                    idx: p.idx,
+                    id: p.id + layout.public_offset,
+                    name: format!("{}{}{}", layout.name, TABLE_SEPARATOR, p.name),
+                });
+            }
+
+            for e in table.expressions.iter() {
+                let mut e = e.clone();
+                shift_expression(&mut e, layout);
+                pil.expressions.push(e);
+            }
+
+            for pi in table.polIdentities.iter() {
+                let mut pi = pi.clone();
+                pi.e += layout.exp_offset;
+                pil.polIdentities.push(pi);
+            }
+
+            for pu in table.plookupIdentities.iter() {
+                pil.plookupIdentities.push(PlookupIdentity {
+                    f: shift_ids(&pu.f, layout.exp_offset),
+                    t: shift_ids(&pu.t, layout.exp_offset),
+                    selF: pu.selF.map(|id| id + layout.exp_offset),
+                    selT: pu.selT.map(|id| id + layout.exp_offset),
+                    ..pu.clone()
+                });
+            }
+
+            for pe in table.permutationIdentities.iter().flatten() {
+                pil.permutationIdentities
+                    .as_mut()
+                    .unwrap()
+                    .push(PermutationIdentity {
+                        f: shift_ids(&pe.f, layout.exp_offset),
+                        t: shift_ids(&pe.t, layout.exp_offset),
+                        selF: pe.selF.map(|id| id + layout.exp_offset),
+                        selT: pe.selT.map(|id| id + layout.exp_offset),
+                        ..pe.clone()
+                    });
+            }
+
+            for ci in connections.into_iter().flatten() {
+                let mut ci = ci.clone();
+                ci.pols = shift_ids(&ci.pols, layout.exp_offset);
+                ci.connections = shift_ids(&ci.connections, layout.exp_offset);
+                pil.connectionIdentities.as_mut().unwrap().push(ci);
+            }
+        }
+
+        let mut composite = CompositePil {
+            pil,
+            tables: layouts,
+            n,
+        };
+        for link in links.iter() {
+            composite.add_link(link)?;
+        }
+        Ok(composite)
+    }
+
+    fn table(&self, name: &str) -> Result<&TableLayout> {
+        self.tables
+            .iter()
+            .find(|t| t.name == name)
+            .ok_or_else(|| anyhow!("Unknown table {}", name))
+    }
+
+    /// Resolve `table::Namespace.pol[k]` into the expression reading that column, and the
+    /// degree of the table it belongs to.
+    fn column(&self, name: &str) -> Result<(Expression, usize)> {
+        let (ref_name, k) = match name.strip_suffix(']') {
+            Some(s) => {
+                let (ref_name, k) = s
+                    .split_once('[')
+                    .ok_or_else(|| anyhow!("Invalid column {}", name))?;
+                (ref_name, k.parse::<usize>()?)
+            }
+            None => (name, 0),
+        };
+        let table = ref_name
+            .split_once(TABLE_SEPARATOR)
+            .ok_or_else(|| anyhow!("Column {} must be prefixed by its table", name))?
+            .0;
+        let degree = self.table(table)?.degree;
+
+        let r = self
+            .pil
+            .references
+            .get(ref_name)
+            .ok_or_else(|| anyhow!("Unknown column {}", name))?;
+        if k >= r.len.unwrap_or(1) {
+            bail!("Column index out of range: {}", name);
+        }
+        let op = match r.type_.as_str() {
+            "cmP" => "cm",
+            "constP" => "const",
+            _ => bail!("Column {} must be committed or constant", name),
+        };
+        let mut e = Expression::new(op.to_string(), 1, Some(r.id + k), None, None);
+        e.next = Some(false);
+        Ok((e, degree))
+    }
+
+    fn push_column(&mut self, name: &str) -> Result<(usize, usize)> {
+        let (e, degree) = self.column(name)?;
+        self.pil.expressions.push(e);
+        Ok((self.pil.expressions.len() - 1, degree))
+    }
+
+    fn add_link(&mut self, link: &TableLink) -> Result<()> {
+        if link.f.is_empty() || link.f.len() != link.t.len() {
+            bail!(
+                "Invalid link, f has {} columns and t has {}",
+                link.f.len(),
+                link.t.len()
+            );
+        }
+
+        let mut degrees = vec![];
+        let mut f = vec![];
+        for c in link.f.iter() {
+            let (id, deg) = self.push_column(c)?;
+            f.push(id);
+            degrees.push(deg);
+        }
+        let mut t = vec![];
+        for c in link.t.iter() {
+            let (id, deg) = self.push_column(c)?;
+            t.push(id);
+            degrees.push(deg);
+        }
+        let selF = match &link.sel_f {
+            Some(c) => Some(self.push_column(c)?.0),
+            None => None,
+        };
+        let selT = match &link.sel_t {
+            Some(c) => Some(self.push_column(c)?.0),
+            None => None,
+        };
+
+        let file_name = format!("{}{}link", link.f[0], TABLE_SEPARATOR);
+        match link.kind {
+            LinkKind::Plookup => self.pil.plookupIdentities.push(PlookupIdentity {
+                f: Some(f),
+                t: Some(t),
+                selF,
+                selT,
+                fileName: file_name,
+                line: 0,
+            }),
+            LinkKind::Permutation => {
+                // lifting repeats the rows of the smaller tables, so both sides of a
+                // permutation must come from tables of the same size.
+                if degrees.iter().any(|d| *d != degrees[0]) {
+                    bail!(
+                        "Permutation between tables of different degrees: {:?} and {:?}",
+                        link.f,
+                        link.t
+                    );
+                }
+                self.pil
+                    .permutationIdentities
+                    .as_mut()
+                    .unwrap()
+                    .push(PermutationIdentity {
+                        f: Some(f),
+                        t: Some(t),
+                        selF,
+                        selT,
+                        fileName: file_name,
+                        line: 0,
+                    })
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the StarkStruct is built for the composite trace.
+    pub fn check_stark_struct(&self, stark_struct: &StarkStruct) -> Result<()> {
+        if (1 << stark_struct.nBits) != self.n {
+            bail!(
+                "stark_struct.nBits is {}, but the composite degree is {}",
+                stark_struct.nBits,
+                self.n
+            );
+        }
+        Ok(())
+    }
+
+    /// Merge the polynomials of every table, in the order of the tables, into the
+    /// polynomials of the composite pil.
+    pub fn merge_pols(&self, pols: Vec<PolsArray>, kind: PolKind) -> Result<PolsArray> {
+        if pols.len() != self.tables.len() {
+            bail!(
+                "Expect polynomials of {} tables, got {}",
+                self.tables.len(),
+                pols.len()
+            );
+        }
+        let is_commit = kind == PolKind::Commit;
+        let mut out = PolsArray::new(&self.pil, kind);
+        for (layout, table_pols) in self.tables.iter().zip(pols) {
+            let (offset, n_pols) = if is_commit {
+                (layout.cm_offset, layout.n_commitments)
+            } else {
+                (layout.const_offset, layout.n_constants)
+            };
+            if table_pols.nPols != n_pols || table_pols.n != layout.degree {
+                bail!(
+                    "Table {} expects {} polynomials of degree {}, got {} of degree {}",
+                    layout.name,
+                    n_pols,
+                    layout.degree,
+                    table_pols.nPols,
+                    table_pols.n
+                );
+            }
+            for (j, col) in table_pols.array.into_iter().enumerate() {
+                out.array[offset + j] = col;
+            }
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merklehash::MerkleTreeGL;
+    use crate::stark_gen::StarkProof;
+    use crate::stark_setup::StarkSetup;
+    use crate::stark_verify::stark_verify;
+    use crate::transcript::TranscriptGL;
+    use crate::types::load_json;
+    use fields::field_gl::Fr as FGL;
+
+    // namespace X(2**bits); pol constant K; pol commit k; k = K;
+    fn copy_table(ns: &str, bits: usize) -> PIL {
+        serde_json::from_value(json!({
+            "nCommitments": 1,
+            "nQ": 0,
+            "nIm": 0,
+            "nConstants": 1,
+            "publics": [],
+            "references": {
+                format!("{ns}.K"): {"type": "constP", "id": 0, "polDeg": 1 << bits, "isArray": false},
+                format!("{ns}.k"): {"type": "cmP", "id": 0, "polDeg": 1 << bits, "isArray": false},
+            },
+            "expressions": [{
+                "op": "sub", "deg": 1, "values": [
+                    {"op": "cm", "deg": 1, "id": 0, "next": false},
+                    {"op": "const", "deg": 1, "id": 0, "next": false},
+                ]
+            }],
+            "polIdentities": [{"e": 0, "fileName": "composite.pil", "line": 1}],
+            "plookupIdentities": [],
+            "permutationIdentities": [],
+            "connectionIdentities": [],
+        }))
+        .unwrap()
+    }
+
+    fn copy_table_pols(pil: &PIL, ns: &str, values: &[u64]) -> (PolsArray, PolsArray) {
+        let mut const_pols = PolsArray::new(pil, PolKind::Constant);
+        let mut cm_pols = PolsArray::new(pil, PolKind::Commit);
+        for (i, v) in values.iter().enumerate() {
+            let v = FGL::from(*v);
+            const_pols.set_matrix(pil, &ns.to_string(), &"K".to_string(), 0, i, v);
+            cm_pols.set_matrix(pil, &ns.to_string(), &"k".to_string(), 0, i, v);
+        }
+        (const_pols, cm_pols)
+    }
+
+    #[test]
+    fn test_composite_lift_and_link() {
+        let fib = load_json::<PIL>("data/fib.pil.json.gl").unwrap();
+        let mut fib_const = PolsArray::new(&fib, PolKind::Constant);
+        fib_const.load("data/fib.const.gl").unwrap();
+        let mut fib_cm = PolsArray::new(&fib, PolKind::Commit);
+        fib_cm.load("data/fib.cm.gl").unwrap();
+
+        let range = copy_table("Range", 4);
+        let (range_const, range_cm) =
+            copy_table_pols(&range, "Range", &(0..16).collect::<Vec<u64>>());
+        let bytes = copy_table("Bytes", 6);
+        let (bytes_const, bytes_cm) = copy_table_pols(
+            &bytes,
+            "Bytes",
+            &(0..64).map(|i| i % 16).collect::<Vec<u64>>(),
+        );
+
+        let links = vec![TableLink {
+            kind: LinkKind::Plookup,
+            f: vec!["bytes::Bytes.k".to_string()],
+            t: vec!["range::Range.K".to_string()],
+            sel_f: None,
+            sel_t: None,
+        }];
+        let composite = CompositePil::new(
+            &[
+                ("fib".to_string(), fib),
+                ("range".to_string(), range),
+                ("bytes".to_string(), bytes),
+            ],
+            &links,
+        )
+        .unwrap();
+        assert_eq!(composite.n, 1024);
+        assert_eq!(composite.pil.nCommitments, 4);
+        assert_eq!(composite.pil.plookupIdentities.len(), 1);
+
+        let const_pols = composite
+            .merge_pols(vec![fib_const, range_const, bytes_const], PolKind::Constant)
+            .unwrap();
+        let cm_pols = composite
+            .merge_pols(vec![fib_cm, range_cm, bytes_cm], PolKind::Commit)
+            .unwrap();
+        let bytes_ns = "bytes::Bytes".to_string();
+        let value = cm_pols.get(&composite.pil, &bytes_ns, &"k".to_string(), 0, 100);
+        assert_eq!(value, FGL::from(100 % 16));
+
+        let stark_struct = load_json::<StarkStruct>("data/starkStruct.json.gl").unwrap();
+        composite.check_stark_struct(&stark_struct).unwrap();
+
+        let mut pil = composite.pil.clone();
+        let setup =
+            StarkSetup::<MerkleTreeGL>::new(&const_pols, &mut pil, &stark_struct, None).unwrap();
+        let starkproof = StarkProof::<MerkleTreeGL>::stark_gen::<TranscriptGL>(
+            cm_pols,
+            const_pols,
+            &setup.const_tree,
+            &setup.starkinfo,
+            &setup.program,
+            &pil,
+            &stark_struct,
+            "273030697313060285579891744179749754319274977764",
+        )
+        .unwrap();
+        let result = stark_verify::<MerkleTreeGL, TranscriptGL>(
+            &starkproof,
+            &setup.const_root,
+            &setup.starkinfo,
+            &stark_struct,
+            &setup.program,
+        )
+        .unwrap();
+        assert!(result);
+    }
+
+    #[test]
+    fn test_composite_reject_lifted_permutation() {
+        let links = vec![TableLink {
+            kind: LinkKind::Permutation,
+            f: vec!["bytes::Bytes.k".to_string()],
+            t: vec!["range::Range.k".to_string()],
+            sel_f: None,
+            sel_t: None,
+        }];
+        let tables = [
+            ("range".to_string(), copy_table("Range", 4)),
+            ("bytes".to_string(), copy_table("Bytes", 6)),
+        ];
+        assert!(CompositePil::new(&tables, &links).is_err());
+    }
+}
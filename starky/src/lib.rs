@@ -56,19 +56,22 @@ pub mod stark_setup;
 pub mod stark_verify;
 pub mod starkinfo;
 mod starkinfo_Z;
-mod starkinfo_codegen;
+pub mod starkinfo_codegen;
 mod starkinfo_cp_prover;
 mod starkinfo_cp_ver;
 mod starkinfo_fri_prover;
 mod starkinfo_fri_ver;
 mod starkinfo_map;
+pub mod starkinfo_stats;
 pub mod transcript;
 pub mod transcript_bls12381;
 pub mod transcript_bn128;
 
+pub mod composite;
 pub mod pil2circom;
 pub mod prove;
 pub mod serializer;
+pub mod state_machine;
 pub mod zkin_join;
 
 pub mod dev;
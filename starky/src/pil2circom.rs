@@ -2,7 +2,7 @@ use crate::digest::ElementDigest;
 use crate::starkinfo::Program;
 use crate::starkinfo::StarkInfo;
 use crate::types::{StarkStruct, PIL};
-use anyhow::Result;
+use anyhow::{bail, Result};
 use profiler_macro::time_profiler;
 
 /// The option to control the generation of recursive verifier
@@ -26,6 +26,9 @@ pub fn pil2circom<F: ff::PrimeField + Default>(
     program: &mut Program,
     options: &StarkOption,
 ) -> Result<String> {
+    if !starkinfo.ns_trees.is_empty() {
+        bail!("The circom verifier doesn't support namespaces smaller than the trace");
+    }
     starkinfo.set_code_dimensions_first(&mut program.verifier_code)?;
     starkinfo.set_code_dimensions_first(&mut program.verifier_query_code)?;
     let res = match stark_struct.verificationHashType.as_str() {
@@ -1,6 +1,6 @@
 #![allow(non_snake_case)]
 use crate::{traits::FieldExtension, types::PIL};
-use anyhow::Result;
+use anyhow::{bail, Result};
 use fields::field_gl::Fr as FGL;
 use profiler_macro::time_profiler;
 use rayon::prelude::*;
@@ -99,9 +99,12 @@ impl PolsArray {
             }
         }
 
+        // namespaces may have different degrees, the smaller ones are lifted to the biggest
+        // one by periodic repetition when the trace is saved or written to the buffer. The
+        // prover commits them at their own degree, see `StarkInfo::ns_trees`.
         PolsArray {
             nPols: defArray.len(),
-            n: defArray[0].polDeg,
+            n: defArray.iter().map(|p| p.polDeg).max().unwrap_or(0),
             defArray,
             array,
             def,
@@ -111,7 +114,7 @@ impl PolsArray {
     #[inline(always)]
     pub fn get(&self, pil: &PIL, ns: &String, np: &String, i: usize, j: usize) -> FGL {
         let ref_id = self.get_pol_id(pil, ns, np, i);
-        self.array[ref_id][j]
+        self.array[ref_id][j % self.array[ref_id].len()]
     }
 
     /// Set the ns.np[i][j] = value, where ns is the namespace, np is the state variable, i is
@@ -139,6 +142,8 @@ impl PolsArray {
         pol.id + k
     }
 
+    /// Load the trace saved by `save`. The rows of a column smaller than the trace must repeat
+    /// its first rows, as `save` writes them.
     #[time_profiler("load_cm_pols_array")]
     pub fn load(&mut self, fileName: &str) -> Result<()> {
         let mut f = File::open(fileName)?;
@@ -174,7 +179,20 @@ impl PolsArray {
             n = rs / 8;
 
             for l in 0..n {
-                self.array[i][j] = FGL::from(buff[l]);
+                let value = FGL::from(buff[l]);
+                let len = self.array[i].len();
+                if j < len {
+                    self.array[i][j] = value;
+                } else if self.array[i][j % len] != value {
+                    bail!(
+                        "{}: row {} of {} is out of its degree {} and doesn't repeat row {}",
+                        fileName,
+                        j,
+                        self.defArray[i].name,
+                        len,
+                        j % len
+                    );
+                }
                 i += 1;
                 if i == self.nPols {
                     i = 0;
@@ -195,7 +213,8 @@ impl PolsArray {
         let mut p = 0usize;
         for i in 0..self.n {
             for j in 0..self.nPols {
-                buff[p] = self.array[j][i].as_int() % 0xFFFFFFFF00000001; //u128
+                let col = &self.array[j];
+                buff[p] = col[i % col.len()].as_int() % 0xFFFFFFFF00000001; //u128
                 p += 1;
                 if p == buff.capacity() {
                     // copy to [u8]
@@ -228,7 +247,8 @@ impl PolsArray {
             .enumerate()
             .for_each(|(i, chunk)| {
                 for j in 0..self.nPols {
-                    chunk[j] = F::from(self.array[j][i]);
+                    let col = &self.array[j];
+                    chunk[j] = F::from(col[i % col.len()]);
                 }
             });
         buff
@@ -253,4 +273,48 @@ pub mod tests {
         let file = File::create("data/fib.exec.cp").unwrap();
         cmp.save(file).unwrap();
     }
+
+    #[test]
+    fn test_load_mixed_degrees() {
+        let pil: PIL = serde_json::from_value(serde_json::json!({
+            "nCommitments": 2,
+            "nQ": 0,
+            "nIm": 0,
+            "nConstants": 0,
+            "publics": [],
+            "references": {
+                "Main.a": {"type": "cmP", "id": 0, "polDeg": 8, "isArray": false},
+                "Rom.r": {"type": "cmP", "id": 1, "polDeg": 2, "isArray": false},
+            },
+            "expressions": [],
+            "polIdentities": [],
+            "plookupIdentities": [],
+            "permutationIdentities": [],
+            "connectionIdentities": [],
+        }))
+        .unwrap();
+        let mut cmp = PolsArray::new(&pil, PolKind::Commit);
+        for j in 0..8 {
+            cmp.array[0][j] = FGL::from(j as u64);
+        }
+        cmp.array[1] = vec![FGL::from(10u64), FGL::from(11u64)];
+
+        let dir = std::env::temp_dir().join(format!("polsarray_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("mixed.exec");
+        let path = path.to_str().unwrap();
+        cmp.save(File::create(path).unwrap()).unwrap();
+
+        let mut loaded = PolsArray::new(&pil, PolKind::Commit);
+        loaded.load(path).unwrap();
+        assert_eq!(loaded.array, cmp.array);
+
+        // a row of Rom.r past its degree that doesn't repeat it
+        let mut bytes = std::fs::read(path).unwrap();
+        let offset = (2 * 5 + 1) * 8;
+        bytes[offset..offset + 8].copy_from_slice(&12u64.to_le_bytes());
+        std::fs::write(path, bytes).unwrap();
+        assert!(loaded.load(path).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
 }
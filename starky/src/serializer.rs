@@ -140,7 +140,7 @@ impl<M: MerkleTree> Serialize for StarkProof<M> {
         S: Serializer,
     {
         // root, evals, friProof * 3, s0_val{1,2,3,4,C},  s0_siblings{1,2,3,4,C}, finalPol
-        let len = 16 + (self.fri_proof.queries.len() - 1) * 3;
+        let len = 16 + (self.fri_proof.queries.len() - 1) * 3 + self.ns_roots.len() * 2 + 1;
         let mut map = serializer.serialize_map(Some(len))?;
 
         if self.rootC.is_some() {
@@ -151,6 +151,9 @@ impl<M: MerkleTree> Serialize for StarkProof<M> {
         map.serialize_entry("root2", &self.root2)?;
         map.serialize_entry("root3", &self.root3)?;
         map.serialize_entry("root4", &self.root4)?;
+        if !self.ns_roots.is_empty() {
+            map.serialize_entry("nsRoots", &self.ns_roots)?;
+        }
         map.serialize_entry("evals", &self.evals)?;
 
         for i in 1..(self.fri_proof.queries.len()) {
@@ -274,6 +277,24 @@ impl<M: MerkleTree> Serialize for StarkProof<M> {
         }
         map.serialize_entry("s0_siblings4", &s0_siblings4)?;
         map.serialize_entry("s0_siblingsC", &s0_siblingsC)?;
+
+        // the trees of the smaller namespaces follow the constant tree in the queries
+        for k in 0..self.ns_roots.len() {
+            let mut vals: Vec<Vec<F3G>> = vec![];
+            let mut sibs: Vec<Vec<Vec<M::MTNode>>> = vec![];
+            for qe in self.fri_proof.queries[0].pol_queries.iter() {
+                vals.push(qe[5 + k].0.iter().map(|e| F3G::from(*e)).collect());
+                sibs.push(
+                    qe[5 + k]
+                        .1
+                        .iter()
+                        .map(|e| e.iter().map(|ee| M::from_basefield(ee)).collect())
+                        .collect(),
+                );
+            }
+            map.serialize_entry(&format!("s0_valsNs{}", k), &vals)?;
+            map.serialize_entry(&format!("s0_siblingsNs{}", k), &sibs)?;
+        }
         map.serialize_entry("finalPol", &self.fri_proof.last)?;
         map.serialize_entry("publics", &self.publics)?;
 
@@ -326,6 +347,10 @@ impl<'de, T: MerkleTree + Default> Deserialize<'de> for StarkProof<T> {
                     serde_json::from_value(map.get("root4").unwrap().clone()).unwrap();
                 sp.root4 = root;
 
+                if let Some(roots) = map.get("nsRoots") {
+                    sp.ns_roots = serde_json::from_value(roots.clone()).unwrap();
+                }
+
                 let root = map.get("rootC");
                 if root.is_some() {
                     let root: MT::MTNode = serde_json::from_value(root.unwrap().clone()).unwrap();
@@ -427,6 +452,21 @@ impl<'de, T: MerkleTree + Default> Deserialize<'de> for StarkProof<T> {
                             (s0_vals_all[k][i].clone(), node_to_bf);
                     }
                 }
+                for k in 0..sp.ns_roots.len() {
+                    let vals: Vec<Vec<F3G>> = serde_json::from_value(
+                        map.get(&format!("s0_valsNs{}", k)).unwrap().clone(),
+                    )
+                    .unwrap();
+                    let sibs: Vec<Vec<Vec<MT::MTNode>>> = serde_json::from_value(
+                        map.get(&format!("s0_siblingsNs{}", k)).unwrap().clone(),
+                    )
+                    .unwrap();
+                    for (i, (v, s)) in vals.iter().zip(sibs.iter()).enumerate() {
+                        let v = v.iter().map(|e| e.as_elements()[0]).collect();
+                        let node_to_bf = crate::traits::mt_node_to_basefield::<MT>(s);
+                        fri_proof.queries[0].pol_queries[i].push((v, node_to_bf));
+                    }
+                }
 
                 // handle query 1 to num_query
                 for i in 1..=num_query {
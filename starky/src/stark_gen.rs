@@ -177,6 +177,8 @@ impl<F: FieldExtension> StarkContext<F> {
 #[derive(Default, Debug, PartialEq)]
 pub struct StarkProof<M: MerkleTree> {
     pub root1: M::MTNode,
+    /// the roots of the trees of `StarkInfo::ns_trees`
+    pub ns_roots: Vec<M::MTNode>,
     pub root2: M::MTNode,
     pub root3: M::MTNode,
     pub root4: M::MTNode,
@@ -282,8 +284,7 @@ impl<'a, M: MerkleTree> StarkProof<M> {
         //Do pre-allocation
         let mut result = vec![M::ExtendField::ZERO; (1 << stark_struct.nBitsExt) * 8];
         log::trace!("Merkelizing 1....");
-        let tree1 = extend_and_merkelize::<M>(&mut ctx, starkinfo, "cm1_n", &mut result)?;
-        tree1.to_extend(&mut ctx.cm1_2ns);
+        let (tree1, ns_trees) = extend_and_merkelize_cm1::<M>(&mut ctx, starkinfo, &mut result)?;
 
         log::trace!(
             "tree1 root: {}",
@@ -291,6 +292,9 @@ impl<'a, M: MerkleTree> StarkProof<M> {
             tree1.root(),
         );
         transcript.put(&[tree1.root().as_elements().to_vec()])?;
+        for tree in ns_trees.iter() {
+            transcript.put(&[tree.root().as_elements().to_vec()])?;
+        }
         // 2.- Calculate plookups h1 and h2
         ctx.challenge[0] = transcript.get_field(); //u
         ctx.challenge[1] = transcript.get_field(); //defVal
@@ -546,13 +550,19 @@ impl<'a, M: MerkleTree> StarkProof<M> {
         });
 
         let query_pol = |idx: usize| -> Vec<(Vec<FGL>, Vec<Vec<M::BaseField>>)> {
-            vec![
+            let mut proofs = vec![
                 tree1.get_group_proof(idx).unwrap(),
                 tree2.get_group_proof(idx).unwrap(),
                 tree3.get_group_proof(idx).unwrap(),
                 tree4.get_group_proof(idx).unwrap(),
                 const_tree.get_group_proof(idx).unwrap(),
-            ]
+            ];
+            // a smaller tree is opened at the point of the lifted polynomial
+            for (tree, t) in ns_trees.iter().zip(starkinfo.ns_trees.iter()) {
+                let n_ext = 1 << (t.n_bits + extend_bits);
+                proofs.push(tree.get_group_proof(idx % n_ext).unwrap());
+            }
+            proofs
         };
         let mut fri = FRI::new(stark_struct);
         let friProof = fri.prove::<M::ExtendField, M, T>(&mut transcript, &fri_pol, query_pol)?;
@@ -560,6 +570,7 @@ impl<'a, M: MerkleTree> StarkProof<M> {
         Ok(StarkProof {
             rootC: Some(const_tree.root()),
             root1: tree1.root(),
+            ns_roots: ns_trees.iter().map(|t| t.root()).collect(),
             root2: tree2.root(),
             root3: tree3.root(),
             root4: tree4.root(),
@@ -750,6 +761,84 @@ pub fn extend_and_merkelize<M: MerkleTree>(
     Ok(tree)
 }
 
+/// Extend and merkelize the committed polynomials, the ones of the trace degree in tree1 and
+/// the ones of every `StarkInfo::ns_trees` at their own degree. A polynomial p of degree n is
+/// lifted to p(X^k), k = N/n, so its extension on the coset shift * <w_ext> is the one of p on
+/// the coset shift^k * <w_ext^k> of size n << extend_bits, repeated. cm1_2ns gets them all lifted.
+#[time_profiler("extend_and_merkelize_cm1")]
+pub fn extend_and_merkelize_cm1<M: MerkleTree>(
+    ctx: &mut StarkContext<M::ExtendField>,
+    starkinfo: &StarkInfo,
+    result: &mut Vec<M::ExtendField>,
+) -> Result<(M, Vec<M>)> {
+    if starkinfo.ns_trees.is_empty() {
+        let tree1 = extend_and_merkelize::<M>(ctx, starkinfo, "cm1_n", result)?;
+        tree1.to_extend(&mut ctx.cm1_2ns);
+        return Ok((tree1, vec![]));
+    }
+
+    let extend_bits = ctx.nbits_ext - ctx.nbits;
+    let n_cm1 = starkinfo.map_sectionsN.cm1_n;
+    let pos = |id: usize| starkinfo.var_pol_map[starkinfo.cm_n[id]].section_pos;
+    let mut groups = vec![(ctx.nbits, starkinfo.tree1_pols())];
+    groups.extend(
+        starkinfo
+            .ns_trees
+            .iter()
+            .map(|t| (t.n_bits, t.pols.clone())),
+    );
+
+    let mut trees = vec![];
+    for (n_bits, pols) in groups.iter() {
+        let n = 1 << n_bits;
+        let n_ext = n << extend_bits;
+        let n_pols = pols.len();
+        let mut buff = vec![M::ExtendField::ZERO; n * n_pols];
+        buff.par_chunks_mut(n_pols.max(1))
+            .enumerate()
+            .for_each(|(i, row)| {
+                for (k, id) in pols.iter().enumerate() {
+                    row[k] = ctx.cm1_n[i * n_cm1 + pos(*id)];
+                }
+            });
+
+        let mut ext = vec![M::ExtendField::ZERO; n_ext * n_pols];
+        if *n_bits == ctx.nbits {
+            interpolate(&buff, n_pols, ctx.nbits, &mut ext, ctx.nbits_ext);
+        } else {
+            let mut coefs = vec![M::ExtendField::ZERO; n * n_pols];
+            ifft(&buff, n_pols, *n_bits, &mut coefs);
+            let shift = M::ExtendField::from(*SHIFT).exp(ctx.N / n);
+            let mut cur_s = M::ExtendField::ONE;
+            let mut shifted = vec![M::ExtendField::ZERO; n_ext * n_pols];
+            for i in 0..n {
+                for k in 0..n_pols {
+                    shifted[i * n_pols + k] = coefs[i * n_pols + k] * cur_s;
+                }
+                cur_s *= shift;
+            }
+            fft(&shifted, n_pols, n_bits + extend_bits, &mut ext);
+        }
+
+        ctx.cm1_2ns
+            .par_chunks_mut(n_cm1)
+            .enumerate()
+            .for_each(|(j, row)| {
+                let l = j % n_ext;
+                for (k, id) in pols.iter().enumerate() {
+                    row[pos(*id)] = ext[l * n_pols + k];
+                }
+            });
+
+        let p_be = ext.par_iter().map(|e| e.to_be()).collect();
+        let mut tree = M::new();
+        tree.merkelize(p_be, n_pols, n_ext)?;
+        trees.push(tree);
+    }
+    let tree1 = trees.remove(0);
+    Ok((tree1, trees))
+}
+
 #[time_profiler("merkelize")]
 pub fn merkelize<M: MerkleTree>(
     ctx: &mut StarkContext<M::ExtendField>,
@@ -1095,12 +1184,14 @@ pub mod tests {
     use crate::stark_gen::StarkProof;
     use crate::stark_setup::StarkSetup;
     use crate::stark_verify::stark_verify;
+    use crate::starkinfo::NsTree;
     use crate::traits::MTNodeType;
     use crate::transcript::TranscriptGL;
     use crate::transcript_bn128::TranscriptBN128;
     use crate::types::load_json;
     use crate::types::{StarkStruct, PIL};
     use ark_std::{end_timer, start_timer};
+    use fields::field_gl::Fr as FGL;
 
     #[test]
     fn test_stark_gen() {
@@ -1269,6 +1360,113 @@ pub mod tests {
         assert!(result);
     }
 
+    #[test]
+    fn test_stark_mixed_degrees() {
+        // namespace Main(2**10); namespace Rom(2**4); Main.a = Main.A; Rom.r = Rom.R; Main.a in Rom.R;
+        let cm = |id: usize| json!({"op": "cm", "deg": 1, "id": id, "next": false});
+        let cst = |id: usize| json!({"op": "const", "deg": 1, "id": id, "next": false});
+        let mut pil: PIL = serde_json::from_value(json!({
+            "nCommitments": 2,
+            "nQ": 0,
+            "nIm": 0,
+            "nConstants": 2,
+            "publics": [],
+            "references": {
+                "Main.A": {"type": "constP", "id": 0, "polDeg": 1024, "isArray": false},
+                "Rom.R": {"type": "constP", "id": 1, "polDeg": 16, "isArray": false},
+                "Main.a": {"type": "cmP", "id": 0, "polDeg": 1024, "isArray": false},
+                "Rom.r": {"type": "cmP", "id": 1, "polDeg": 16, "isArray": false},
+            },
+            "expressions": [
+                {"op": "sub", "deg": 1, "values": [cm(0), cst(0)]},
+                {"op": "sub", "deg": 1, "values": [cm(1), cst(1)]},
+                cm(0),
+                cst(1),
+            ],
+            "polIdentities": [
+                {"e": 0, "fileName": "mixed.pil", "line": 1},
+                {"e": 1, "fileName": "mixed.pil", "line": 2},
+            ],
+            "plookupIdentities": [
+                {"f": [2], "t": [3], "selF": null, "selT": null, "fileName": "mixed.pil", "line": 3},
+            ],
+            "permutationIdentities": [],
+            "connectionIdentities": [],
+        }))
+        .unwrap();
+
+        let mut const_pol = PolsArray::new(&pil, PolKind::Constant);
+        let mut cm_pol = PolsArray::new(&pil, PolKind::Commit);
+        assert_eq!(const_pol.n, 1024);
+        assert_eq!(cm_pol.array[1].len(), 16);
+        let (main, rom) = ("Main".to_string(), "Rom".to_string());
+        for i in 0..1024 {
+            let v = FGL::from((i % 16) as u64);
+            const_pol.set_matrix(&pil, &main, &"A".to_string(), 0, i, v);
+            cm_pol.set_matrix(&pil, &main, &"a".to_string(), 0, i, v);
+            if i < 16 {
+                const_pol.set_matrix(&pil, &rom, &"R".to_string(), 0, i, v);
+                cm_pol.set_matrix(&pil, &rom, &"r".to_string(), 0, i, v);
+            }
+        }
+
+        let stark_struct = load_json::<StarkStruct>("data/starkStruct.json.gl").unwrap();
+        let setup =
+            StarkSetup::<MerkleTreeGL>::new(&const_pol, &mut pil, &stark_struct, None).unwrap();
+        assert_eq!(setup.starkinfo.namespace_degrees["Rom"], 16);
+        assert_eq!(
+            setup.starkinfo.ns_trees,
+            vec![NsTree {
+                n_bits: 4,
+                pols: vec![1]
+            }]
+        );
+        assert_eq!(setup.starkinfo.tree1_pols(), vec![0]);
+
+        let starkproof = StarkProof::<MerkleTreeGL>::stark_gen::<TranscriptGL>(
+            cm_pol,
+            const_pol,
+            &setup.const_tree,
+            &setup.starkinfo,
+            &setup.program,
+            &pil,
+            &stark_struct,
+            "273030697313060285579891744179749754319274977764",
+        )
+        .unwrap();
+        // Rom.r is committed in a tree of 2^4 rows extended, 6 levels less than tree1
+        assert_eq!(starkproof.ns_roots.len(), 1);
+        let query = &starkproof.fri_proof.queries[0].pol_queries[0];
+        assert_eq!(query.len(), 6);
+        assert_eq!(query[0].0.len(), 1);
+        assert_eq!(query[5].0.len(), 1);
+        assert_eq!(query[5].1.len() + 6, query[0].1.len());
+
+        let serialized = serde_json::to_string(&starkproof).unwrap();
+        let starkproof: StarkProof<MerkleTreeGL> = serde_json::from_str(&serialized).unwrap();
+        let result = stark_verify::<MerkleTreeGL, TranscriptGL>(
+            &starkproof,
+            &setup.const_root,
+            &setup.starkinfo,
+            &stark_struct,
+            &setup.program,
+        )
+        .unwrap();
+        assert!(result);
+
+        // the root of the tree of Rom is bound to the proof
+        let mut wrong = starkproof;
+        wrong.ns_roots[0] = wrong.root1;
+        let result = stark_verify::<MerkleTreeGL, TranscriptGL>(
+            &wrong,
+            &setup.const_root,
+            &setup.starkinfo,
+            &stark_struct,
+            &setup.program,
+        );
+        assert!(!matches!(result, Ok(true)));
+    }
+
     #[test]
     fn test_stark_plookup_gl() {
         let mut pil = load_json::<PIL>("data/plookup.pil.json.gl").unwrap();
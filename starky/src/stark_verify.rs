@@ -43,7 +43,17 @@ pub fn stark_verify<M: MerkleTree, T: Transcript>(
         transcript.put(&b[..])?;
     }
 
+    if proof.ns_roots.len() != starkinfo.ns_trees.len() {
+        bail!(
+            "The proof has {} namespace trees, expect {}",
+            proof.ns_roots.len(),
+            starkinfo.ns_trees.len()
+        );
+    }
     transcript.put(&[proof.root1.as_elements().to_vec()])?;
+    for root in proof.ns_roots.iter() {
+        transcript.put(&[root.as_elements().to_vec()])?;
+    }
     ctx.challenge[0] = transcript.get_field(); // u
     ctx.challenge[1] = transcript.get_field(); // defVal
     transcript.put(&[proof.root2.as_elements().to_vec()])?;
@@ -116,8 +126,36 @@ pub fn stark_verify<M: MerkleTree, T: Transcript>(
         if !res {
             bail!("FRIVerifierFailed");
         }
+        if query.len() != 5 + proof.ns_roots.len() {
+            bail!("FRIVerifierFailed");
+        }
+        // the row of cm1 with the values of the smaller trees at their lifted point
+        let mut tree1 = query[0].0.clone();
+        if !starkinfo.ns_trees.is_empty() {
+            let pos = |id: usize| starkinfo.var_pol_map[starkinfo.cm_n[id]].section_pos;
+            let tree1_pols = starkinfo.tree1_pols();
+            if query[0].0.len() != tree1_pols.len() {
+                bail!("FRIVerifierFailed");
+            }
+            tree1 = vec![FGL::ZERO; starkinfo.n_cm1];
+            for (k, id) in tree1_pols.into_iter().enumerate() {
+                tree1[pos(id)] = query[0].0[k];
+            }
+            for (i, (t, root)) in starkinfo.ns_trees.iter().zip(&proof.ns_roots).enumerate() {
+                let (vals, path) = &query[5 + i];
+                let n_ext = 1 << (t.n_bits + extend_bits);
+                if !tree.verify_group_proof(root, path, idx % n_ext, vals)?
+                    || vals.len() != t.pols.len()
+                {
+                    bail!("FRIVerifierFailed");
+                }
+                for (k, id) in t.pols.iter().enumerate() {
+                    tree1[pos(*id)] = vals[k];
+                }
+            }
+        }
         let mut ctx_query = StarkContext::<<M as traits::MerkleTree>::ExtendField> {
-            tree1: query[0].0.clone(),
+            tree1,
             tree2: query[1].0.clone(),
             tree3: query[2].0.clone(),
             tree4: query[3].0.clone(),
@@ -8,7 +8,7 @@ use crate::starkinfo_codegen::{
 use crate::types::{Expression, Public, StarkStruct, PIL};
 use anyhow::{bail, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 
 #[derive(Default, Debug, Serialize, Deserialize)]
@@ -24,6 +24,54 @@ pub struct PCCTX {
     pub den_id: usize,
 }
 
+/// The committed polynomials of the namespaces of one degree smaller than the trace. They are
+/// extended and merkelized at that degree, out of tree1, see `stark_gen::extend_and_merkelize_cm1`.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NsTree {
+    pub n_bits: usize,
+    /// the ids of the polynomials in cm1
+    pub pols: Vec<usize>,
+}
+
+/// The intermediate polynomials chosen by the search mode, see `StarkStruct::imPolsSearch`.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ImPolsSearch {
+    /// every subset of the candidates was tried
+    pub exhaustive: bool,
+    pub candidates: usize,
+    /// (q_deg, the intermediate polynomials, the base field columns) of the cheapest choice
+    /// found for each q_deg
+    pub tradeoffs: Vec<(usize, usize, usize)>,
+    pub im_exps: Vec<usize>,
+    pub q_deg: usize,
+}
+
+impl fmt::Display for ImPolsSearch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "im pols search ({}, {} candidates):",
+            if self.exhaustive {
+                "exhaustive"
+            } else {
+                "heuristic"
+            },
+            self.candidates
+        )?;
+        for (q_deg, n_im, columns) in self.tradeoffs.iter() {
+            writeln!(
+                f,
+                "  q_deg {}: {} im pols, {} columns{}",
+                q_deg,
+                n_im,
+                columns,
+                if *q_deg == self.q_deg { " <-" } else { "" }
+            )?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Default, Serialize, Deserialize)]
 pub struct Program {
     pub publics_code: Vec<Segment>,
@@ -93,6 +141,16 @@ pub struct StarkInfo {
 
     pub publics: Vec<Public>,
     pub ev_idx: EVIdx,
+
+    /// the degree of each namespace, see `namespace_degrees`
+    #[serde(default)]
+    pub namespace_degrees: HashMap<String, usize>,
+    /// the trees of the committed polynomials smaller than the trace, by decreasing degree
+    #[serde(default)]
+    pub ns_trees: Vec<NsTree>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub im_pols_search: Option<ImPolsSearch>,
 }
 
 impl fmt::Display for StarkInfo {
@@ -212,17 +270,147 @@ impl fmt::Display for StarkInfo {
             "publics: {}",
             serde_json::to_string_pretty(&obj).unwrap()
         )?;
-        writeln!(f, "ev_idx: {:?}", self.ev_idx)
+        writeln!(f, "ev_idx: {:?}", self.ev_idx)?;
+        let obj = json!(self.namespace_degrees);
+        writeln!(
+            f,
+            "namespace_degrees: {}",
+            serde_json::to_string_pretty(&obj).unwrap()
+        )?;
+        let obj = json!(self.ns_trees);
+        writeln!(
+            f,
+            "ns_trees: {}",
+            serde_json::to_string_pretty(&obj).unwrap()
+        )
     }
 }
 
+// the degree of every committed and constant polynomial, and of every namespace
+fn pol_degrees(pil: &PIL) -> Result<(Vec<usize>, Vec<usize>, HashMap<String, usize>)> {
+    let mut cm_degs = vec![0; pil.nCommitments];
+    let mut const_degs = vec![0; pil.nConstants];
+    let mut ns_degs: HashMap<String, usize> = HashMap::new();
+    for (name, r) in pil.references.iter() {
+        let degs = match r.type_.as_str() {
+            "cmP" => &mut cm_degs,
+            "constP" => &mut const_degs,
+            _ => continue,
+        };
+        if !r.polDeg.is_power_of_two() {
+            bail!(
+                "The degree of {} must be a power of 2, got {}",
+                name,
+                r.polDeg
+            );
+        }
+        for i in 0..r.len.unwrap_or(1) {
+            degs[r.id + i] = r.polDeg;
+        }
+        let ns = name.split('.').next().unwrap().to_string();
+        if let Some(deg) = ns_degs.insert(ns.clone(), r.polDeg) {
+            if deg != r.polDeg {
+                bail!(
+                    "Namespace {} has polynomials of degree {} and {}",
+                    ns,
+                    deg,
+                    r.polDeg
+                );
+            }
+        }
+    }
+    Ok((cm_degs, const_degs, ns_degs))
+}
+
+/// Return the degree of the trace and the degree of every namespace.
+///
+/// The committed polynomials of a namespace smaller than the trace are extended and merkelized
+/// at their own degree, see [`NsTree`]. A polynomial p of degree n is lifted to the trace of
+/// degree N as p(X^(N/n)), i.e. by periodic repetition of its rows, when the constraints are
+/// evaluated, which keeps its polynomial identities and its plookups valid. The constant
+/// polynomials are in the constant tree of the setup, so they are lifted there. Connections and
+/// permutations can't see through the repetition, so they must only involve polynomials of one
+/// degree, and connections that degree must be the trace one.
+pub fn namespace_degrees(pil: &PIL) -> Result<(usize, HashMap<String, usize>)> {
+    let (cm_degs, const_degs, ns_degs) = pol_degrees(pil)?;
+    let pil_deg = match ns_degs.values().max() {
+        Some(deg) => *deg,
+        None => bail!("The pil has no committed or constant polynomials"),
+    };
+
+    fn collect(
+        pil: &PIL,
+        e: &Expression,
+        cm_degs: &[usize],
+        const_degs: &[usize],
+        out: &mut HashSet<usize>,
+    ) {
+        match e.op.as_str() {
+            "cm" => {
+                out.insert(cm_degs[e.id.unwrap()]);
+            }
+            "const" => {
+                out.insert(const_degs[e.id.unwrap()]);
+            }
+            "exp" => collect(
+                pil,
+                &pil.expressions[e.id.unwrap()],
+                cm_degs,
+                const_degs,
+                out,
+            ),
+            _ => {}
+        }
+        for v in e.values.iter().flatten() {
+            collect(pil, v, cm_degs, const_degs, out);
+        }
+    }
+    let degrees_of = |ids: &[usize]| -> HashSet<usize> {
+        let mut out = HashSet::new();
+        for id in ids {
+            collect(pil, &pil.expressions[*id], &cm_degs, &const_degs, &mut out);
+        }
+        out
+    };
+
+    for pe in pil.permutationIdentities.iter().flatten() {
+        let mut ids: Vec<usize> = pe.f.iter().chain(pe.t.iter()).flatten().cloned().collect();
+        ids.extend(pe.selF.iter().chain(pe.selT.iter()));
+        if degrees_of(&ids).len() > 1 {
+            bail!(
+                "Permutation at {}:{} mixes polynomials of different degrees",
+                pe.fileName,
+                pe.line
+            );
+        }
+    }
+    for ci in pil.connectionIdentities.iter().flatten() {
+        let ids: Vec<usize> = ci
+            .pols
+            .iter()
+            .chain(ci.connections.iter())
+            .flatten()
+            .cloned()
+            .collect();
+        if degrees_of(&ids).iter().any(|deg| *deg != pil_deg) {
+            bail!(
+                "Connection at {}:{} must only use polynomials of degree {}",
+                ci.fileName,
+                ci.line,
+                pil_deg
+            );
+        }
+    }
+    Ok((pil_deg, ns_degs))
+}
+
 impl StarkInfo {
     pub fn new(
         pil: &mut PIL,
         stark_struct: &StarkStruct,
         global_l1: Option<String>,
     ) -> Result<(StarkInfo, Program)> {
-        let pil_deg = pil.references.values().next().unwrap().polDeg;
+        let (pil_deg, namespace_degrees) = namespace_degrees(pil)?;
 
         let stark_deg = 2usize.pow(stark_struct.nBits as u32);
 
@@ -273,6 +461,9 @@ impl StarkInfo {
             map_total_n: 0,
             publics: Vec::new(),
             ev_idx: EVIdx::new(),
+            namespace_degrees,
+            ns_trees: Vec::new(),
+            im_pols_search: None,
         };
 
         let mut program = Program {
@@ -288,6 +479,21 @@ impl StarkInfo {
 
         info.generate_public_calculators(pil, &mut program)?;
         info.n_cm1 = pil.nCommitments;
+        let (cm_degs, _, _) = pol_degrees(pil)?;
+        for (id, deg) in cm_degs.iter().enumerate() {
+            if *deg == stark_deg {
+                continue;
+            }
+            let n_bits = deg.trailing_zeros() as usize;
+            match info.ns_trees.iter_mut().find(|t| t.n_bits == n_bits) {
+                Some(t) => t.pols.push(id),
+                None => info.ns_trees.push(NsTree {
+                    n_bits,
+                    pols: vec![id],
+                }),
+            }
+        }
+        info.ns_trees.sort_by(|a, b| b.n_bits.cmp(&a.n_bits));
 
         let mut ctx = Context {
             tmp_used: 0,
@@ -350,6 +556,13 @@ impl StarkInfo {
         Ok((info, program))
     }
 
+    /// The ids of the committed polynomials of the trace degree, the ones of tree1.
+    pub fn tree1_pols(&self) -> Vec<usize> {
+        (0..self.n_cm1)
+            .filter(|id| !self.ns_trees.iter().any(|t| t.pols.contains(id)))
+            .collect()
+    }
+
     pub fn generate_public_calculators(
         &mut self,
         pil: &mut PIL,
@@ -1,11 +1,11 @@
 use crate::expressionops::ExpressionOps as E;
-use crate::starkinfo::{Program, StarkInfo};
+use crate::starkinfo::{ImPolsSearch, Program, StarkInfo};
 use crate::starkinfo_codegen::Section;
 use crate::starkinfo_codegen::{build_code, pil_code_gen, Context, Node};
 use crate::types::Expression;
 use crate::types::{StarkStruct, PIL};
-use anyhow::Result;
-use std::collections::HashMap;
+use anyhow::{bail, Result};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 impl StarkInfo {
     #[allow(clippy::unnecessary_unwrap)]
@@ -35,15 +35,33 @@ impl StarkInfo {
         }
         self.q_deg = 0;
         let max_deg = (1 << (stark_struct.nBitsExt - stark_struct.nBits)) + 1;
-        for d in 2..=max_deg {
-            let (im_exps, q_deg) = calculate_im_pols(pil, &c_exp, d)?;
-            if im_exps.is_some()
-                && (self.q_deg == 0
-                    || (im_exps.as_ref().unwrap().len() + (q_deg as usize)
-                        < self.im_exps.len() + self.q_deg))
-            {
-                self.q_deg = q_deg as usize;
-                self.im_exps = im_exps.unwrap();
+        if stark_struct.imPolsSearch {
+            // the dimensions of the columns committed so far, as `map` sets them
+            pil.cm_dims = vec![1; self.n_cm1];
+            for pu in self.pu_ctx.iter() {
+                let dim = std::cmp::max(
+                    StarkInfo::get_exp_dim(pil, &pil.expressions[pu.f_exp_id]),
+                    StarkInfo::get_exp_dim(pil, &pil.expressions[pu.t_exp_id]),
+                );
+                pil.cm_dims.extend([dim, dim]);
+            }
+            pil.cm_dims.resize(pil.nCommitments, 3);
+            let search = search_im_pols(pil, &c_exp, max_deg)?;
+            log::info!("{}", search);
+            self.q_deg = search.q_deg;
+            self.im_exps = search.im_exps.iter().map(|id| (*id, true)).collect();
+            self.im_pols_search = Some(search);
+        } else {
+            for d in 2..=max_deg {
+                let (im_exps, q_deg) = calculate_im_pols(pil, &c_exp, d)?;
+                if im_exps.is_some()
+                    && (self.q_deg == 0
+                        || (im_exps.as_ref().unwrap().len() + (q_deg as usize)
+                            < self.im_exps.len() + self.q_deg))
+                {
+                    self.q_deg = q_deg as usize;
+                    self.im_exps = im_exps.unwrap();
+                }
             }
         }
 
@@ -303,3 +321,256 @@ pub fn calculate_im_pols(
     //);
     Ok((re, std::cmp::max(rd, abs_max_d) - 1))
 }
+
+// The candidates to commit: the expressions `exp` refers to, whose degree is above 1.
+fn im_pols_candidates(pil: &PIL, exp: &Expression, seen: &mut BTreeSet<usize>) {
+    if exp.op == "exp" {
+        let id = exp.id.unwrap();
+        if seen.insert(id) {
+            im_pols_candidates(pil, &pil.expressions[id], seen);
+        }
+    } else if let Some(values) = exp.values.as_ref() {
+        for v in values.iter() {
+            im_pols_candidates(pil, v, seen);
+        }
+    }
+}
+
+// `get_exp_dim` where the expressions of `im` are committed, so of degree 1.
+fn im_exp_dim(
+    pil: &PIL,
+    exp: &Expression,
+    im: &BTreeSet<usize>,
+    memo: &mut HashMap<usize, i32>,
+) -> i32 {
+    let values = exp.values.as_deref().unwrap_or_default();
+    let mut dim = |e: &Expression| im_exp_dim(pil, e, im, memo);
+    match exp.op.as_str() {
+        "add" | "sub" | "addc" | "mulc" | "neg" => values.iter().map(dim).fold(1, i32::max),
+        "mul" => dim(&values[0]) + dim(&values[1]),
+        "muladd" => std::cmp::max(dim(&values[0]) + dim(&values[1]), dim(&values[2])),
+        "exp" => {
+            let id = exp.id.unwrap();
+            if im.contains(&id) {
+                return 1;
+            }
+            if let Some(d) = memo.get(&id) {
+                return *d;
+            }
+            let d = im_exp_dim(pil, &pil.expressions[id], im, memo);
+            memo.insert(id, d);
+            d
+        }
+        _ => get_exp_dim(pil, exp),
+    }
+}
+
+// The q_deg of `exp` when `im` is committed, None if a constraint exceeds `max_deg`.
+fn im_pols_q_deg(
+    pil: &PIL,
+    exp: &Expression,
+    im: &BTreeSet<usize>,
+    max_deg: usize,
+) -> Option<usize> {
+    let mut memo = HashMap::new();
+    let mut d = im_exp_dim(pil, exp, im, &mut memo);
+    // each intermediate polynomial adds the constraint `exp - cm`
+    for id in im.iter() {
+        d = d.max(im_exp_dim(pil, &pil.expressions[*id], im, &mut memo));
+    }
+    if d > max_deg as i32 {
+        None
+    } else {
+        Some((d - 1).max(0) as usize)
+    }
+}
+
+const EXHAUSTIVE_CANDIDATES: usize = 12;
+
+/// Choose the intermediate polynomials of `exp` minimizing the committed columns: the
+/// intermediate polynomials plus the quotient chunks, so that no constraint exceeds
+/// `max_deg`. A column of the extension field counts as 3 base field columns, `pil.cm_dims`
+/// must hold the dimensions of the columns `exp` refers to. Every subset is tried if there
+/// are few candidates, otherwise a local search starts from the greedy choices of
+/// `calculate_im_pols`.
+pub fn search_im_pols(pil: &mut PIL, exp: &Expression, max_deg: usize) -> Result<ImPolsSearch> {
+    let mut seen = BTreeSet::new();
+    im_pols_candidates(pil, exp, &mut seen);
+    let candidates: Vec<usize> = seen
+        .into_iter()
+        .filter(|id| get_exp_dim(pil, &pil.expressions[*id]) > 1)
+        .collect();
+
+    let im_dims: HashMap<usize, usize> = candidates
+        .iter()
+        .map(|id| (*id, StarkInfo::get_exp_dim(pil, &pil.expressions[*id])))
+        .collect();
+    let exp_dim = StarkInfo::get_exp_dim(pil, exp);
+    // the base field columns of `im` and of the quotient chunks. The intermediate
+    // polynomials are added to the constraint with the challenge vc, so q is then in the
+    // extension field.
+    let columns = |im: &BTreeSet<usize>, q_deg: usize| -> usize {
+        let q_dim = if im.is_empty() { exp_dim } else { 3 };
+        q_deg * q_dim + im.iter().map(|id| im_dims[id]).sum::<usize>()
+    };
+
+    // the cheapest intermediate polynomials for each q_deg, and their columns
+    let mut best: BTreeMap<usize, (usize, BTreeSet<usize>)> = BTreeMap::new();
+    let mut try_set = |im: &BTreeSet<usize>, pil: &PIL| -> Option<usize> {
+        let q_deg = im_pols_q_deg(pil, exp, im, max_deg)?;
+        let cost = columns(im, q_deg);
+        let entry = best.entry(q_deg).or_insert_with(|| (cost, im.clone()));
+        if (cost, im.len(), im) < (entry.0, entry.1.len(), &entry.1) {
+            *entry = (cost, im.clone());
+        }
+        Some(cost)
+    };
+
+    let exhaustive = candidates.len() <= EXHAUSTIVE_CANDIDATES;
+    if exhaustive {
+        for mask in 0..(1usize << candidates.len()) {
+            let im = candidates
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| mask & (1 << i) != 0)
+                .map(|(_, id)| *id)
+                .collect();
+            try_set(&im, pil);
+        }
+    } else {
+        let mut starts = vec![BTreeSet::new(), candidates.iter().cloned().collect()];
+        for d in 2..=max_deg {
+            if let (Some(im), _) = calculate_im_pols(pil, exp, d)? {
+                starts.push(im.into_keys().collect());
+            }
+        }
+        for mut im in starts {
+            let Some(mut cost) = try_set(&im, pil) else {
+                continue;
+            };
+            // toggle the candidate improving the most, until none does
+            loop {
+                let mut next = None;
+                for id in candidates.iter() {
+                    let mut t = im.clone();
+                    if !t.remove(id) {
+                        t.insert(*id);
+                    }
+                    if let Some(c) = try_set(&t, pil) {
+                        if c < cost {
+                            cost = c;
+                            next = Some(t);
+                        }
+                    }
+                }
+                match next {
+                    Some(t) => im = t,
+                    None => break,
+                }
+            }
+        }
+    }
+
+    let Some((q_deg, (_, im))) = best
+        .iter()
+        .min_by_key(|(q_deg, (cost, im))| (*cost, im.len(), **q_deg))
+    else {
+        bail!(
+            "no intermediate polynomials keep the constraints under degree {}",
+            max_deg
+        );
+    };
+    Ok(ImPolsSearch {
+        exhaustive,
+        candidates: candidates.len(),
+        tradeoffs: best
+            .iter()
+            .map(|(q, (cost, im))| (*q, im.len(), *cost))
+            .collect(),
+        im_exps: im.iter().cloned().collect(),
+        q_deg: *q_deg,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{load_json, StarkStruct};
+
+    fn constraint(pil: &PIL) -> Expression {
+        let vc = E::challenge("vc".to_string());
+        let mut c_exp = E::nop();
+        for pi in pil.polIdentities.iter() {
+            let e = E::exp(pi.e, None);
+            if !E::is_nop(&c_exp) {
+                c_exp = E::add(&E::mul(&vc, &c_exp), &e);
+            } else {
+                c_exp = e;
+            }
+        }
+        c_exp
+    }
+
+    #[test]
+    fn test_search_im_pols() {
+        let mut pil = load_json::<PIL>("data/fib.pil.json").unwrap();
+        pil.cm_dims = vec![1; pil.nCommitments];
+        let c_exp = constraint(&pil);
+        for max_deg in [2, 3, 5] {
+            let search = search_im_pols(&mut pil, &c_exp, max_deg).unwrap();
+            assert!(search.exhaustive);
+            let im: BTreeSet<usize> = search.im_exps.iter().cloned().collect();
+            assert_eq!(
+                im_pols_q_deg(&pil, &c_exp, &im, max_deg),
+                Some(search.q_deg)
+            );
+
+            // never worse than the greedy choice, all the columns are in the base field
+            let mut greedy = usize::MAX;
+            for d in 2..=max_deg {
+                if let (Some(im), q_deg) = calculate_im_pols(&mut pil, &c_exp, d).unwrap() {
+                    greedy = greedy.min(im.len() + q_deg as usize);
+                }
+            }
+            let columns = search.q_deg + search.im_exps.len();
+            assert!(columns <= greedy);
+            assert!(search
+                .tradeoffs
+                .contains(&(search.q_deg, search.im_exps.len(), columns)));
+        }
+    }
+
+    #[test]
+    fn test_search_im_pols_columns() {
+        // the columns counted by the search are the ones committed in stage 3 and 4
+        let stark_struct = StarkStruct {
+            imPolsSearch: true,
+            ..load_json::<StarkStruct>("data/starkStruct.json").unwrap()
+        };
+        for pil_file in [
+            "data/plookup.pil.json",
+            "data/pe.pil.json",
+            "data/connection.pil.json",
+        ] {
+            let mut pil = load_json::<PIL>(pil_file).unwrap();
+            let (info, _) = StarkInfo::new(&mut pil, &stark_struct, None).unwrap();
+            let search = info.im_pols_search.as_ref().unwrap();
+            let im_columns: usize = info
+                .im_exps_list
+                .iter()
+                .map(|id| pil.cm_dims[info.im_exp2cm[id]])
+                .sum();
+            let (_, _, columns) = search
+                .tradeoffs
+                .iter()
+                .find(|t| t.0 == search.q_deg)
+                .unwrap();
+            assert_eq!(
+                *columns,
+                im_columns + info.q_deg * info.q_dim,
+                "{}",
+                pil_file
+            );
+        }
+    }
+}
@@ -0,0 +1,257 @@
+//! What `StarkInfo::new` decides for a PIL and a StarkStruct, before proving: the committed
+//! columns of each stage, the intermediate polynomials, the quotient, and an estimate of the
+//! proof size and of the prover memory.
+use crate::f3g::F3G;
+use crate::starkinfo::{ImPolsSearch, StarkInfo};
+use crate::types::{load_json, StarkStruct, PIL};
+use anyhow::{bail, Result};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+const SECTIONS: [&str; 11] = [
+    "cm1_n", "cm1_2ns", "cm2_n", "cm2_2ns", "cm3_n", "cm3_2ns", "cm4_n", "cm4_2ns", "tmpexp_n",
+    "q_2ns", "f_2ns",
+];
+
+// a digest is 4 goldilocks elements or one field element of the snark curve.
+const DIGEST_BYTES: usize = 32;
+const FGL_BYTES: usize = 8;
+
+#[derive(Debug, Serialize)]
+pub struct StarkInfoStats {
+    pub n_bits: usize,
+    pub n_bits_ext: usize,
+    pub hash_type: String,
+    pub n_constants: usize,
+    pub n_publics: usize,
+    pub n_cm1: usize,
+    pub n_cm2: usize,
+    pub n_cm3: usize,
+    pub n_cm4: usize,
+    pub n_q: usize,
+    /// the expressions committed as intermediate polynomials
+    pub im_exps: Vec<usize>,
+    pub q_deg: usize,
+    pub q_dim: usize,
+    pub n_evals: usize,
+    /// the width of each section, in base field elements
+    pub map_sections: BTreeMap<String, usize>,
+    /// the degree of each namespace, the smaller ones are lifted to the trace degree
+    pub namespace_degrees: BTreeMap<String, usize>,
+    pub proof_bytes: usize,
+    pub prover_memory_bytes: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub im_pols_search: Option<ImPolsSearch>,
+}
+
+// (arity, levels) of a merkle tree over 2^n_bits leaves
+fn merkle_shape(hash_type: &str, n_bits: usize) -> Result<(usize, usize)> {
+    match hash_type {
+        "GL" => Ok((2, n_bits)),
+        // 16-ary trees
+        "BN128" | "BLS12381" => Ok((16, n_bits.div_ceil(4))),
+        _ => bail!("unknown verificationHashType {}", hash_type),
+    }
+}
+
+fn merkle_path_bytes(hash_type: &str, n_bits: usize) -> Result<usize> {
+    let (arity, levels) = merkle_shape(hash_type, n_bits)?;
+    Ok(levels * (arity - 1) * DIGEST_BYTES)
+}
+
+fn merkle_nodes_bytes(hash_type: &str, n_bits: usize) -> Result<usize> {
+    let (arity, levels) = merkle_shape(hash_type, n_bits)?;
+    let mut n = 1usize << n_bits;
+    let mut nodes = n;
+    for _ in 0..levels {
+        n = n.div_ceil(arity);
+        nodes += n;
+    }
+    Ok(nodes * DIGEST_BYTES)
+}
+
+impl StarkInfoStats {
+    pub fn new(info: &StarkInfo, stark_struct: &StarkStruct) -> Result<Self> {
+        let hash_type = stark_struct.verificationHashType.as_str();
+        let sections: BTreeMap<String, usize> = SECTIONS
+            .iter()
+            .map(|s| (s.to_string(), info.map_sectionsN.get(s)))
+            .collect();
+        let n = 1usize << stark_struct.nBits;
+        let n_ext = 1usize << stark_struct.nBitsExt;
+
+        // the roots, the evaluations, and the fri proof
+        let mut proof_bytes = 5 * DIGEST_BYTES + info.ev_map.len() * 3 * FGL_BYTES;
+        let tree_widths = [
+            info.n_constants,
+            sections["cm1_2ns"],
+            sections["cm2_2ns"],
+            sections["cm3_2ns"],
+            sections["cm4_2ns"],
+        ];
+        let mut query_bytes = 0;
+        for (i, step) in stark_struct.steps.iter().enumerate() {
+            if i == 0 {
+                for width in tree_widths.iter().filter(|w| **w > 0) {
+                    query_bytes +=
+                        width * FGL_BYTES + merkle_path_bytes(hash_type, stark_struct.nBitsExt)?;
+                }
+            } else {
+                let group = 1 << (stark_struct.steps[i - 1].nBits - step.nBits);
+                proof_bytes += DIGEST_BYTES;
+                query_bytes += group * 3 * FGL_BYTES + merkle_path_bytes(hash_type, step.nBits)?;
+            }
+        }
+        proof_bytes += stark_struct.nQueries * query_bytes;
+        let last_bits = stark_struct.steps.last().map_or(0, |s| s.nBits);
+        proof_bytes += (1 << last_bits) * 3 * FGL_BYTES;
+
+        // the buffers of the prover context, see `StarkProof::stark_gen`, and the trees
+        let elements = n * (sections["cm1_n"] + sections["cm2_n"] + sections["cm3_n"])
+            + n * sections["tmpexp_n"]
+            + n_ext
+                * (sections["cm1_n"]
+                    + sections["cm2_n"]
+                    + sections["cm3_n"]
+                    + sections["cm4_n"]
+                    + info.n_constants
+                    + info.q_dim
+                    + 3)
+            + n
+            + n_ext;
+        let tree_bytes = tree_widths.iter().filter(|w| **w > 0).count()
+            * merkle_nodes_bytes(hash_type, stark_struct.nBitsExt)?
+            + tree_widths.iter().sum::<usize>() * n_ext * FGL_BYTES;
+        let prover_memory_bytes = elements * std::mem::size_of::<F3G>() + tree_bytes;
+
+        let mut im_exps = info.im_exps_list.clone();
+        im_exps.sort();
+        Ok(StarkInfoStats {
+            n_bits: stark_struct.nBits,
+            n_bits_ext: stark_struct.nBitsExt,
+            hash_type: hash_type.to_string(),
+            n_constants: info.n_constants,
+            n_publics: info.n_publics,
+            n_cm1: info.n_cm1,
+            n_cm2: info.n_cm2,
+            n_cm3: info.n_cm3,
+            n_cm4: info.n_cm4,
+            n_q: info.n_q,
+            im_exps,
+            q_deg: info.q_deg,
+            q_dim: info.q_dim,
+            n_evals: info.ev_map.len(),
+            map_sections: sections,
+            namespace_degrees: info
+                .namespace_degrees
+                .iter()
+                .map(|(ns, deg)| (ns.clone(), *deg))
+                .collect(),
+            proof_bytes,
+            prover_memory_bytes,
+            im_pols_search: info.im_pols_search.clone(),
+        })
+    }
+
+    pub fn table(&self) -> String {
+        let mib = |b: usize| format!("{:.2} MiB", b as f64 / (1 << 20) as f64);
+        let mut rows = vec![
+            ("N".to_string(), format!("2^{}", self.n_bits)),
+            ("N ext".to_string(), format!("2^{}", self.n_bits_ext)),
+            ("hash".to_string(), self.hash_type.clone()),
+            ("constants".to_string(), self.n_constants.to_string()),
+            ("publics".to_string(), self.n_publics.to_string()),
+            ("cm1".to_string(), self.n_cm1.to_string()),
+            ("cm2".to_string(), self.n_cm2.to_string()),
+            ("cm3".to_string(), self.n_cm3.to_string()),
+            ("cm4".to_string(), self.n_cm4.to_string()),
+            ("im pols".to_string(), format!("{:?}", self.im_exps)),
+            ("q_deg".to_string(), self.q_deg.to_string()),
+            ("q_dim".to_string(), self.q_dim.to_string()),
+            ("evals".to_string(), self.n_evals.to_string()),
+        ];
+        for (s, w) in self.map_sections.iter() {
+            rows.push((format!("section {}", s), w.to_string()));
+        }
+        for (ns, deg) in self.namespace_degrees.iter() {
+            let mut v = format!("2^{}", deg.trailing_zeros());
+            if deg.trailing_zeros() as usize != self.n_bits {
+                v.push_str(&format!(" (lifted to 2^{})", self.n_bits));
+            }
+            rows.push((format!("namespace {}", ns), v));
+        }
+        rows.push(("proof size (est.)".to_string(), mib(self.proof_bytes)));
+        rows.push((
+            "prover memory (est.)".to_string(),
+            mib(self.prover_memory_bytes),
+        ));
+
+        let width = rows.iter().map(|(k, _)| k.len()).max().unwrap_or(0);
+        let mut table: String = rows
+            .iter()
+            .map(|(k, v)| format!("{:<width$}  {}\n", k, v, width = width))
+            .collect();
+        if let Some(search) = self.im_pols_search.as_ref() {
+            table.push_str(&search.to_string());
+        }
+        table
+    }
+}
+
+/// zkit stark_info: build the StarkInfo of `pil_file` and print its statistics.
+/// `im_pols_search` turns on the search of the intermediate polynomials.
+pub fn stark_info(
+    stark_struct: &str,
+    pil_file: &str,
+    im_pols_search: bool,
+    json: bool,
+) -> Result<()> {
+    let mut pil = load_json::<PIL>(pil_file)?;
+    let mut stark_struct = load_json::<StarkStruct>(stark_struct)?;
+    stark_struct.imPolsSearch |= im_pols_search;
+    let (info, _) = StarkInfo::new(&mut pil, &stark_struct, None)?;
+    let stats = StarkInfoStats::new(&info, &stark_struct)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+    } else {
+        print!("{}", stats.table());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_starkinfo_stats() {
+        let mut pil = load_json::<PIL>("data/plookup.pil.json").unwrap();
+        let stark_struct = load_json::<StarkStruct>("data/starkStruct.json.gl").unwrap();
+        let (info, _) = StarkInfo::new(&mut pil, &stark_struct, None).unwrap();
+        let stats = StarkInfoStats::new(&info, &stark_struct).unwrap();
+        assert_eq!(stats.n_cm1, pil.nCommitments);
+        assert_eq!(stats.map_sections["cm1_n"], pil.nCommitments);
+        assert_eq!(stats.q_dim, info.q_dim);
+        assert!(stats.proof_bytes > 0);
+        assert!(stats.prover_memory_bytes > stats.proof_bytes);
+        assert!(stats.table().contains("q_deg"));
+        assert_eq!(stats.namespace_degrees.len(), info.namespace_degrees.len());
+        assert!(!stats.table().contains("lifted"));
+
+        let mut bn128 = stark_struct.clone();
+        bn128.verificationHashType = "BN128".to_string();
+        let stats_bn128 = StarkInfoStats::new(&info, &bn128).unwrap();
+        // 15 siblings per level of the 16-ary trees
+        assert!(stats_bn128.proof_bytes > stats.proof_bytes);
+        bn128.verificationHashType = "SHA256".to_string();
+        assert!(StarkInfoStats::new(&info, &bn128).is_err());
+
+        let mut pil = load_json::<PIL>("data/plookup.pil.json").unwrap();
+        let mut search = stark_struct.clone();
+        search.imPolsSearch = true;
+        let (info, _) = StarkInfo::new(&mut pil, &search, None).unwrap();
+        let searched = StarkInfoStats::new(&info, &search).unwrap();
+        assert!(searched.n_cm3 + searched.n_cm4 <= stats.n_cm3 + stats.n_cm4);
+        assert!(searched.table().contains("im pols search"));
+    }
+}
@@ -0,0 +1,102 @@
+//! starkjs/connection/connection.js
+use super::{build_constants_global, Pols, StateMachine};
+use crate::constant::MG;
+use crate::helper::{get_ks, log2_any};
+use anyhow::Result;
+use fields::field_gl::Fr as FGL;
+
+/// The connection machine of starkjs, `c` is `b` and `b` is `a` reordered by the
+/// permutation checks of S1, S2 and S3.
+pub struct Connection {
+    pub namespace: String,
+}
+
+impl Default for Connection {
+    fn default() -> Self {
+        Self {
+            namespace: "Connection".to_string(),
+        }
+    }
+}
+
+impl StateMachine for Connection {
+    type Input = ();
+
+    fn build_constants(&self, pols: &mut Pols) -> Result<()> {
+        build_constants_global(pols)?;
+        let ns = self.namespace.as_str();
+        let s1 = pols.id(ns, "S1", 0)?;
+        let s2 = pols.id(ns, "S2", 0)?;
+        let s3 = pols.id(ns, "S3", 0)?;
+        let n = pols.n(s1);
+        let pow = log2_any(n);
+
+        let ks = get_ks(2);
+        let mut w = FGL::ONE;
+        for i in 0..n {
+            pols.col(s1)[i] = w;
+            pols.col(s2)[i] = w * ks[0];
+            pols.col(s3)[i] = w * ks[1];
+            w *= MG.0[pow];
+        }
+
+        let mut connect = |p1: usize, i1: usize, p2: usize, i2: usize| {
+            let tmp = pols.col(p1)[i1];
+            pols.col(p1)[i1] = pols.col(p2)[i2];
+            pols.col(p2)[i2] = tmp;
+        };
+        for i in 0..n {
+            let j = if i % 2 == 0 {
+                i / 2
+            } else {
+                n / 2 + (i - 1) / 2
+            };
+            connect(s1, i, s2, j);
+            connect(s2, i, s3, j);
+        }
+        Ok(())
+    }
+
+    fn execute(&self, pols: &mut Pols, _input: &()) -> Result<()> {
+        let ns = self.namespace.as_str();
+        let a = pols.id(ns, "a", 0)?;
+        let b = pols.id(ns, "b", 0)?;
+        let c = pols.id(ns, "c", 0)?;
+        let n = pols.n(a);
+        // the even rows first, then the odd ones
+        let interleave = |i: usize| {
+            if i < n / 2 {
+                i * 2
+            } else {
+                (i - n / 2) * 2 + 1
+            }
+        };
+        for i in 0..n {
+            pols.col(a)[i] = FGL::from(i as u64);
+        }
+        for i in 0..n {
+            pols.col(b)[i] = pols.col(a)[interleave(i)];
+        }
+        for i in 0..n {
+            pols.col(c)[i] = pols.col(b)[interleave(i)];
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state_machine::tests::check_against_js;
+
+    #[test]
+    fn test_connection() {
+        check_against_js(
+            &Connection::default(),
+            &(),
+            "data/connection.pil.json",
+            "data/connection.const",
+            "data/connection.cm",
+        );
+    }
+}
@@ -0,0 +1,53 @@
+//! starkjs/fibonacci/fibonacci.js
+use super::{Pols, StateMachine};
+use anyhow::Result;
+use fields::field_gl::Fr as FGL;
+
+pub struct Fibonacci;
+
+impl StateMachine for Fibonacci {
+    /// l2[0], l1[0]
+    type Input = [u64; 2];
+
+    fn build_constants(&self, pols: &mut Pols) -> Result<()> {
+        let l1 = pols.id("Fibonacci", "L1", 0)?;
+        let llast = pols.id("Fibonacci", "LLAST", 0)?;
+        let n = pols.n(l1);
+        for i in 0..n {
+            pols.col(l1)[i] = if i == 0 { FGL::ONE } else { FGL::ZERO };
+            pols.col(llast)[i] = if i == n - 1 { FGL::ONE } else { FGL::ZERO };
+        }
+        Ok(())
+    }
+
+    fn execute(&self, pols: &mut Pols, input: &[u64; 2]) -> Result<()> {
+        let l1 = pols.id("Fibonacci", "l1", 0)?;
+        let l2 = pols.id("Fibonacci", "l2", 0)?;
+        let n = pols.n(l1);
+        pols.col(l2)[0] = FGL::from(input[0]);
+        pols.col(l1)[0] = FGL::from(input[1]);
+        for i in 1..n {
+            let (a, b) = (pols.col(l1)[i - 1], pols.col(l2)[i - 1]);
+            pols.col(l2)[i] = a;
+            pols.col(l1)[i] = b * b + a * a;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state_machine::tests::check_against_js;
+
+    #[test]
+    fn test_fibonacci() {
+        check_against_js(
+            &Fibonacci,
+            &[1, 2],
+            "data/fib.pil.json",
+            "data/fib.const",
+            "data/fib.cm",
+        );
+    }
+}
@@ -0,0 +1,215 @@
+//! State machine executors: the Rust side of the `buildConstants`/`execute` classes of
+//! starkjs, filling the constant and committed polynomials of a PIL by namespace and name.
+use crate::polsarray::{PolKind, PolsArray};
+use crate::types::PIL;
+use anyhow::{anyhow, bail, Result};
+use fields::field_gl::Fr as FGL;
+
+pub mod connection;
+pub mod fibonacci;
+pub mod permutation;
+pub mod plookup;
+pub mod poseidon;
+mod poseidon_constants;
+pub mod simple_vm;
+
+pub use poseidon_constants::CPOSEIDON;
+
+/// Named access to the polynomials of a `PolsArray`.
+pub struct Pols<'a> {
+    pil: &'a PIL,
+    array: &'a mut PolsArray,
+}
+
+impl<'a> Pols<'a> {
+    pub fn new(pil: &'a PIL, array: &'a mut PolsArray) -> Self {
+        Self { pil, array }
+    }
+
+    /// The id of `ns.np[i]`, an error if the array doesn't hold it (unknown name, index out
+    /// of range, or a committed polynomial in the constant array and vice versa).
+    pub fn id(&self, ns: &str, np: &str, i: usize) -> Result<usize> {
+        let name = format!("{}.{}", ns, np);
+        let ref_ = self
+            .pil
+            .references
+            .get(&name)
+            .ok_or_else(|| anyhow!("Unknown polynomial {}", name))?;
+        let len = if ref_.isArray {
+            ref_.len.unwrap_or(0)
+        } else {
+            1
+        };
+        if i >= len {
+            bail!("{}[{}] out of range, the length is {}", name, i, len);
+        }
+        let id = ref_.id + i;
+        match self.array.defArray.get(id) {
+            Some(pol) if pol.name == name => Ok(id),
+            _ => bail!("{} is not a {} polynomial", name, ref_.type_),
+        }
+    }
+
+    /// The number of rows of the polynomial `id`.
+    pub fn n(&self, id: usize) -> usize {
+        self.array.array[id].len()
+    }
+
+    /// The rows of the polynomial `id`, to fill a whole column at once.
+    pub fn col(&mut self, id: usize) -> &mut [FGL] {
+        &mut self.array.array[id]
+    }
+
+    pub fn get(&self, ns: &str, np: &str, j: usize) -> Result<FGL> {
+        self.get_matrix(ns, np, 0, j)
+    }
+
+    pub fn get_matrix(&self, ns: &str, np: &str, i: usize, j: usize) -> Result<FGL> {
+        self.id(ns, np, i)?;
+        Ok(self
+            .array
+            .get(self.pil, &ns.to_string(), &np.to_string(), i, j))
+    }
+
+    pub fn set(&mut self, ns: &str, np: &str, j: usize, value: FGL) -> Result<()> {
+        self.set_matrix(ns, np, 0, j, value)
+    }
+
+    /// ns.np[i][j] = value, as `PolsArray::set_matrix` but checked.
+    pub fn set_matrix(&mut self, ns: &str, np: &str, i: usize, j: usize, value: FGL) -> Result<()> {
+        let id = self.id(ns, np, i)?;
+        if j >= self.n(id) {
+            bail!("{}.{}[{}] has no row {}", ns, np, i, j);
+        }
+        self.array
+            .set_matrix(self.pil, &ns.to_string(), &np.to_string(), i, j, value);
+        Ok(())
+    }
+}
+
+/// A state machine executor, like the classes passed to `pil_verifier.generate` in starkjs.
+pub trait StateMachine {
+    type Input;
+
+    /// Fills the constant polynomials.
+    fn build_constants(&self, pols: &mut Pols) -> Result<()>;
+
+    /// Fills the committed polynomials for `input`.
+    fn execute(&self, pols: &mut Pols, input: &Self::Input) -> Result<()>;
+}
+
+/// Runs `sm` over `pil`, returns the constant and the committed polynomials.
+pub fn generate<SM: StateMachine>(
+    sm: &SM,
+    pil: &PIL,
+    input: &SM::Input,
+) -> Result<(PolsArray, PolsArray)> {
+    let mut const_pols = PolsArray::new(pil, PolKind::Constant);
+    sm.build_constants(&mut Pols::new(pil, &mut const_pols))?;
+    let mut cm_pols = PolsArray::new(pil, PolKind::Commit);
+    sm.execute(&mut Pols::new(pil, &mut cm_pols), input)?;
+    Ok((const_pols, cm_pols))
+}
+
+/// Global.L1 = [1, 0, 0, ...], `utils.buildConstantsGlobal` of starkjs.
+pub fn build_constants_global(pols: &mut Pols) -> Result<()> {
+    let l1 = pols.id("Global", "L1", 0)?;
+    for (i, v) in pols.col(l1).iter_mut().enumerate() {
+        *v = if i == 0 { FGL::ONE } else { FGL::ZERO };
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::types::{load_json, Reference};
+
+    /// A PIL with only the references, for machines without a pil.json in data/.
+    pub(crate) fn references_pil(n: usize, consts: &[(&str, usize)], cms: &[(&str, usize)]) -> PIL {
+        let mut pil: PIL = serde_json::from_value(serde_json::json!({
+            "nCommitments": 0,
+            "nQ": 0,
+            "nIm": 0,
+            "nConstants": 0,
+            "publics": [],
+            "references": {},
+            "expressions": [],
+            "polIdentities": [],
+            "plookupIdentities": [],
+        }))
+        .unwrap();
+        for (type_, pols) in [("constP", consts), ("cmP", cms)] {
+            let mut id = 0;
+            for (name, len) in pols.iter() {
+                pil.references.insert(
+                    name.to_string(),
+                    Reference {
+                        polType: None,
+                        type_: type_.to_string(),
+                        id,
+                        polDeg: n,
+                        isArray: *len > 1,
+                        elementType: None,
+                        len: if *len > 1 { Some(*len) } else { None },
+                    },
+                );
+                id += len;
+            }
+            if type_ == "constP" {
+                pil.nConstants = id;
+            } else {
+                pil.nCommitments = id;
+            }
+        }
+        pil
+    }
+
+    /// Runs `sm` and compares the result with the polynomials generated by starkjs.
+    pub(crate) fn check_against_js<SM: StateMachine>(
+        sm: &SM,
+        input: &SM::Input,
+        pil_file: &str,
+        const_file: &str,
+        cm_file: &str,
+    ) {
+        let pil = load_json::<PIL>(pil_file).unwrap();
+        let (const_pols, cm_pols) = generate(sm, &pil, input).unwrap();
+        let mut expected = PolsArray::new(&pil, PolKind::Constant);
+        expected.load(const_file).unwrap();
+        assert_eq!(const_pols.array, expected.array);
+        let mut expected = PolsArray::new(&pil, PolKind::Commit);
+        expected.load(cm_file).unwrap();
+        assert_eq!(cm_pols.array, expected.array);
+    }
+
+    struct Constants;
+
+    impl StateMachine for Constants {
+        type Input = ();
+
+        fn build_constants(&self, pols: &mut Pols) -> Result<()> {
+            build_constants_global(pols)
+        }
+
+        fn execute(&self, pols: &mut Pols, _input: &()) -> Result<()> {
+            // a constant polynomial can't be set in the committed array
+            assert!(pols.set("Global", "L1", 0, FGL::ONE).is_err());
+            assert!(pols.set("Global", "LX", 0, FGL::ONE).is_err());
+            pols.set_matrix("Main", "a", 1, 3, FGL::from(7u64))?;
+            assert!(pols.set_matrix("Main", "a", 2, 3, FGL::ONE).is_err());
+            assert!(pols.set_matrix("Main", "a", 1, 8, FGL::ONE).is_err());
+            assert_eq!(pols.get_matrix("Main", "a", 1, 3)?, FGL::from(7u64));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_pols_checked_access() {
+        let pil = references_pil(8, &[("Global.L1", 1)], &[("Main.a", 2)]);
+        let (const_pols, cm_pols) = generate(&Constants, &pil, &()).unwrap();
+        assert_eq!(const_pols.array[0][0], FGL::ONE);
+        assert_eq!(const_pols.array[0][1], FGL::ZERO);
+        assert_eq!(cm_pols.array[1][3], FGL::from(7u64));
+    }
+}
@@ -0,0 +1,55 @@
+//! starkjs/permutation/permutation.js
+use super::{build_constants_global, Pols, StateMachine};
+use anyhow::Result;
+use fields::field_gl::Fr as FGL;
+
+pub struct Permutation;
+
+impl StateMachine for Permutation {
+    type Input = ();
+
+    fn build_constants(&self, pols: &mut Pols) -> Result<()> {
+        build_constants_global(pols)
+    }
+
+    fn execute(&self, pols: &mut Pols, _input: &()) -> Result<()> {
+        let [a, b, c, d, sel_c, sel_d] =
+            ["a", "b", "c", "d", "selC", "selD"].map(|np| pols.id("Permutation", np, 0));
+        let (a, b, c, d, sel_c, sel_d) = (a?, b?, c?, d?, sel_c?, sel_d?);
+        let n = pols.n(c);
+        for i in 0..n {
+            let v = FGL::from((i * i + i + 1) as u64);
+            pols.col(a)[i] = v;
+            pols.col(b)[n - i - 1] = v;
+            if i % 2 == 0 {
+                pols.col(sel_c)[i] = FGL::ONE;
+                pols.col(c)[i] = v;
+                pols.col(sel_d)[i / 2] = FGL::ONE;
+                pols.col(d)[i / 2] = v;
+            } else {
+                pols.col(sel_c)[i] = FGL::ZERO;
+                pols.col(c)[i] = FGL::from(44u64);
+                pols.col(sel_d)[n / 2 + (i - 1) / 2] = FGL::ZERO;
+                pols.col(d)[n / 2 + (i - 1) / 2] = FGL::from(55u64);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state_machine::tests::check_against_js;
+
+    #[test]
+    fn test_permutation() {
+        check_against_js(
+            &Permutation,
+            &(),
+            "data/pe.pil.json",
+            "data/pe.const",
+            "data/pe.cm",
+        );
+    }
+}
@@ -0,0 +1,79 @@
+//! starkjs/plookup/plookup.js
+use super::{build_constants_global, Pols, StateMachine};
+use anyhow::Result;
+use fields::field_gl::Fr as FGL;
+
+pub struct Plookup;
+
+impl StateMachine for Plookup {
+    type Input = ();
+
+    fn build_constants(&self, pols: &mut Pols) -> Result<()> {
+        build_constants_global(pols)?;
+        let a = pols.id("Plookup", "A", 0)?;
+        let b = pols.id("Plookup", "B", 0)?;
+        let sel = pols.id("Plookup", "SEL", 0)?;
+        let n = pols.n(a);
+        // the 16x16 table, then zeros
+        for p in 0..n {
+            let (va, vb, vsel) = if p < 256 {
+                (
+                    FGL::from((p / 16) as u64),
+                    FGL::from((p % 16) as u64),
+                    FGL::ONE,
+                )
+            } else {
+                (FGL::ZERO, FGL::ZERO, FGL::ZERO)
+            };
+            pols.col(a)[p] = va;
+            pols.col(b)[p] = vb;
+            pols.col(sel)[p] = vsel;
+        }
+        Ok(())
+    }
+
+    fn execute(&self, pols: &mut Pols, _input: &()) -> Result<()> {
+        let cc = pols.id("Plookup", "cc", 0)?;
+        let sel = pols.id("Plookup", "sel", 0)?;
+        let a = pols.id("Plookup", "a", 0)?;
+        let b = pols.id("Plookup", "b", 0)?;
+        let n = pols.n(cc);
+        for p in 0..n {
+            pols.col(cc)[p] = if p < 256 {
+                FGL::from(((p / 16) * (p % 16)) as u64)
+            } else {
+                FGL::from(p as u64)
+            };
+        }
+
+        for p in 0..n {
+            let (vsel, va, vb) = match p {
+                0 => (1, 0, 55),
+                1..=9 => (1, p as u64, p as u64 + 3),
+                10 => (0, 55, 10),
+                _ => (0, 55, 55),
+            };
+            pols.col(sel)[p] = FGL::from(vsel);
+            pols.col(a)[p] = FGL::from(va);
+            pols.col(b)[p] = FGL::from(vb);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state_machine::tests::check_against_js;
+
+    #[test]
+    fn test_plookup() {
+        check_against_js(
+            &Plookup,
+            &(),
+            "data/plookup.pil.json",
+            "data/plookup.const",
+            "data/plookup.cm",
+        );
+    }
+}
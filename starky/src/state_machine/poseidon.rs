@@ -0,0 +1,219 @@
+//! starkjs/poseidon/sm_poseidong.js
+use super::{Pols, StateMachine, CPOSEIDON};
+use anyhow::{bail, Result};
+use fields::field_gl::Fr as FGL;
+
+const T: usize = 12;
+const N_ROUNDS_F: usize = 8;
+const N_ROUNDS_P: usize = 22;
+/// The rows of one permutation: the input and the state after each round.
+pub const BLOCK_ROWS: usize = N_ROUNDS_F + N_ROUNDS_P + 1;
+
+const MCIRC: [u64; T] = [17, 15, 41, 16, 2, 28, 13, 13, 39, 18, 34, 20];
+const MDIAG: [u64; T] = [8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+
+const STATE_POLS: [&str; T] = [
+    "in0", "in1", "in2", "in3", "in4", "in5", "in6", "in7", "hashType", "cap1", "cap2", "cap3",
+];
+const HASH_POLS: [&str; 4] = ["hash0", "hash1", "hash2", "hash3"];
+const RESULT_POLS: [&str; 3] = ["result1", "result2", "result3"];
+
+fn pow7(a: FGL) -> FGL {
+    let a2 = a * a;
+    let a4 = a2 * a2;
+    a * a2 * a4
+}
+
+/// The states of the permutation of `input`, before the first round and after each round.
+pub fn permutation_rows(input: &[FGL; T]) -> Vec<[FGL; T]> {
+    let mut rows = Vec::with_capacity(BLOCK_ROWS);
+    rows.push(*input);
+    for r in 0..(N_ROUNDS_F + N_ROUNDS_P) {
+        let prev = rows.last().unwrap();
+        let mut state: [FGL; T] =
+            std::array::from_fn(|i| prev[i] + FGL::from(CPOSEIDON[r * T + i]));
+        if r < N_ROUNDS_F / 2 || r >= N_ROUNDS_F / 2 + N_ROUNDS_P {
+            state = state.map(pow7);
+        } else {
+            state[0] = pow7(state[0]);
+        }
+        rows.push(std::array::from_fn(|i| {
+            state.iter().enumerate().fold(FGL::ZERO, |acc, (j, a)| {
+                acc + FGL::from(MCIRC[(T + j - i) % T] + if i == j { MDIAG[i] } else { 0 }) * *a
+            })
+        }));
+    }
+    rows
+}
+
+/// One permutation to prove: `state` is (in0..in7, hashType, cap1..cap3), `result` selects
+/// which of result1..result3 is set on its first row, 0 for none.
+#[derive(Debug, Clone)]
+pub struct PoseidonGInput {
+    pub state: [FGL; T],
+    pub result: usize,
+}
+
+/// The PoseidonG machine, each permutation takes `BLOCK_ROWS` rows, the unused blocks hash
+/// the zero state.
+pub struct PoseidonG;
+
+impl StateMachine for PoseidonG {
+    type Input = Vec<PoseidonGInput>;
+
+    fn build_constants(&self, pols: &mut Pols) -> Result<()> {
+        let ns = "PoseidonG";
+        let linput = pols.id(ns, "LINPUT", 0)?;
+        let loutput = pols.id(ns, "LOUTPUT", 0)?;
+        let last = pols.id(ns, "LAST", 0)?;
+        let latch = pols.id(ns, "LATCH", 0)?;
+        let lastblock = pols.id(ns, "LASTBLOCK", 0)?;
+        let partial = pols.id(ns, "PARTIAL", 0)?;
+        let c = (0..T)
+            .map(|j| pols.id(ns, "C", j))
+            .collect::<Result<Vec<_>>>()?;
+        let n = pols.n(last);
+        let max_hashes = n / BLOCK_ROWS;
+
+        let bool_ = |b: bool| if b { FGL::ONE } else { FGL::ZERO };
+        for i in 0..n {
+            let (ih, r) = (i / BLOCK_ROWS, i % BLOCK_ROWS);
+            pols.col(linput)[i] = bool_(i == 0);
+            pols.col(loutput)[i] = bool_(i == n - 1);
+            pols.col(last)[i] = bool_(i == n - 1 || r == BLOCK_ROWS - 1);
+            pols.col(latch)[i] = bool_(ih < max_hashes && r == 0);
+            pols.col(lastblock)[i] = bool_(r == BLOCK_ROWS - 1);
+            for (j, cj) in c.iter().enumerate() {
+                pols.col(*cj)[i] = FGL::from(CPOSEIDON[T * r + j]);
+            }
+            pols.col(partial)[i] = bool_(r >= N_ROUNDS_F / 2 && r < N_ROUNDS_F / 2 + N_ROUNDS_P);
+        }
+        Ok(())
+    }
+
+    fn execute(&self, pols: &mut Pols, input: &Self::Input) -> Result<()> {
+        let ns = "PoseidonG";
+        let ids = |names: &[&str]| {
+            names
+                .iter()
+                .map(|np| pols.id(ns, np, 0))
+                .collect::<Result<Vec<_>>>()
+        };
+        let (state_ids, hash_ids, result_ids) =
+            (ids(&STATE_POLS)?, ids(&HASH_POLS)?, ids(&RESULT_POLS)?);
+        let n = pols.n(state_ids[0]);
+        if input.len() > n / BLOCK_ROWS {
+            bail!("Not enough Poseidon slots");
+        }
+
+        let mut fill = |p: usize, state: &[FGL; T], hash: &[FGL], result: usize| {
+            for (id, v) in state_ids.iter().zip(state.iter()) {
+                pols.col(*id)[p] = *v;
+            }
+            for (id, v) in hash_ids.iter().zip(hash.iter()) {
+                pols.col(*id)[p] = *v;
+            }
+            for (k, id) in result_ids.iter().enumerate() {
+                pols.col(*id)[p] = if result == k + 1 { FGL::ONE } else { FGL::ZERO };
+            }
+        };
+
+        let mut p = 0;
+        for inp in input.iter() {
+            let rows = permutation_rows(&inp.state);
+            let hash = &rows[BLOCK_ROWS - 1][..4];
+            for (r, state) in rows.iter().enumerate() {
+                fill(p, state, hash, if r == 0 { inp.result } else { 0 });
+                p += 1;
+            }
+        }
+
+        let st0 = permutation_rows(&[FGL::ZERO; T]);
+        let hash = &st0[BLOCK_ROWS - 1][..4];
+        while p < n {
+            fill(p, &st0[p % BLOCK_ROWS], hash, 0);
+            p += 1;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poseidon_opt::Poseidon;
+    use crate::state_machine::generate;
+    use crate::state_machine::tests::references_pil;
+
+    #[test]
+    fn test_poseidon_g() {
+        let state: [FGL; T] = std::array::from_fn(|i| FGL::from(i as u64));
+        let rows = permutation_rows(&state);
+        let expected = Poseidon::new().hash(&state[..8], &state[8..], 4).unwrap();
+        assert_eq!(rows[BLOCK_ROWS - 1][..4], expected[..]);
+        // main_poseidon.js
+        let js = [
+            0xd64e1e3efc5b8e9e,
+            0x53666633020aaa47,
+            0xd40285597c6a8825,
+            0x613a4f81e81231d2,
+        ]
+        .map(FGL::from);
+        assert_eq!(rows[BLOCK_ROWS - 1][..4], js);
+
+        let consts = [
+            "LAST",
+            "LATCH",
+            "LASTBLOCK",
+            "PARTIAL",
+            "C",
+            "LINPUT",
+            "LOUTPUT",
+        ]
+        .map(|np| (format!("PoseidonG.{}", np), if np == "C" { T } else { 1 }));
+        let cms = STATE_POLS
+            .iter()
+            .chain(HASH_POLS.iter())
+            .chain(RESULT_POLS.iter())
+            .map(|np| (format!("PoseidonG.{}", np), 1))
+            .collect::<Vec<_>>();
+        let pil = references_pil(
+            1024,
+            &consts
+                .iter()
+                .map(|(s, l)| (s.as_str(), *l))
+                .collect::<Vec<_>>(),
+            &cms.iter()
+                .map(|(s, l)| (s.as_str(), *l))
+                .collect::<Vec<_>>(),
+        );
+
+        let input = vec![PoseidonGInput { state, result: 2 }];
+        let (const_pols, cm_pols) = generate(&PoseidonG, &pil, &input).unwrap();
+        // LATCH is set on the first row of the 33 blocks
+        let latch = &const_pols.array[1];
+        assert_eq!(
+            latch.iter().filter(|v| **v == FGL::ONE).count(),
+            1024 / BLOCK_ROWS
+        );
+        assert_eq!(latch[BLOCK_ROWS], FGL::ONE);
+        assert_eq!(latch[BLOCK_ROWS * (1024 / BLOCK_ROWS)], FGL::ZERO);
+
+        let st0 = permutation_rows(&[FGL::ZERO; T]);
+        for (p, row) in rows.iter().chain(st0.iter().cycle()).take(1024).enumerate() {
+            for (i, v) in row.iter().enumerate() {
+                assert_eq!(cm_pols.array[i][p], *v);
+            }
+            let hash = if p < BLOCK_ROWS {
+                &js[..]
+            } else {
+                &st0[BLOCK_ROWS - 1][..4]
+            };
+            for (i, v) in hash.iter().enumerate() {
+                assert_eq!(cm_pols.array[T + i][p], *v);
+            }
+        }
+        assert_eq!(cm_pols.array[T + 4 + 1][0], FGL::ONE);
+        assert_eq!(cm_pols.array[T + 4 + 1][1], FGL::ZERO);
+    }
+}
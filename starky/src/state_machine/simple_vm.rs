@@ -0,0 +1,60 @@
+//! starkjs/simple_vm/simple_vm.js, which is the connection machine over the `SimpleVM`
+//! namespace.
+use super::connection::Connection;
+use super::{Pols, StateMachine};
+use anyhow::Result;
+
+pub struct SimpleVM(Connection);
+
+impl Default for SimpleVM {
+    fn default() -> Self {
+        Self(Connection {
+            namespace: "SimpleVM".to_string(),
+        })
+    }
+}
+
+impl StateMachine for SimpleVM {
+    type Input = ();
+
+    fn build_constants(&self, pols: &mut Pols) -> Result<()> {
+        self.0.build_constants(pols)
+    }
+
+    fn execute(&self, pols: &mut Pols, input: &()) -> Result<()> {
+        self.0.execute(pols, input)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::polsarray::{PolKind, PolsArray};
+    use crate::state_machine::generate;
+    use crate::state_machine::tests::references_pil;
+    use crate::types::{load_json, PIL};
+
+    #[test]
+    fn test_simple_vm() {
+        let pil = references_pil(
+            1024,
+            &[
+                ("Global.L1", 1),
+                ("SimpleVM.S1", 1),
+                ("SimpleVM.S2", 1),
+                ("SimpleVM.S3", 1),
+            ],
+            &[("SimpleVM.a", 1), ("SimpleVM.b", 1), ("SimpleVM.c", 1)],
+        );
+        let (const_pols, cm_pols) = generate(&SimpleVM::default(), &pil, &()).unwrap();
+
+        // same layout as the connection machine
+        let connection = load_json::<PIL>("data/connection.pil.json").unwrap();
+        let mut expected = PolsArray::new(&connection, PolKind::Constant);
+        expected.load("data/connection.const").unwrap();
+        assert_eq!(const_pols.array, expected.array);
+        let mut expected = PolsArray::new(&connection, PolKind::Commit);
+        expected.load("data/connection.cm").unwrap();
+        assert_eq!(cm_pols.array, expected.array);
+    }
+}
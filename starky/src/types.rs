@@ -198,6 +198,10 @@ pub struct StarkStruct {
     pub nQueries: usize,
     pub verificationHashType: String,
     pub steps: Vec<Step>,
+    /// Search the intermediate polynomials minimizing the committed columns, instead of the
+    /// greedy choice of pil-stark
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub imPolsSearch: bool,
 }
 
 pub fn load_json<T>(filename: &str) -> Result<T>
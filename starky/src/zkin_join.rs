@@ -1,51 +1,162 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
 use serde_json::Value;
 use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::Write;
 
-/// Combine the `input1.zkin.json` and `input1.zkin.json` into one(`out.zkin.json`)
-// ../../target/release/eigen-zkit join_zkin --zkin1 0/fibonacci.recursive1/input.zkin.json  --zkin2 1/fibonacci.recursive1/input.zkin.json  --zkinout 0/fibonacci.recursive1/r1_input-rs.zkin.json
-pub fn join_zkin(
-    // stark_setup_file: &String,
-    zkin1: &String,
-    zkin2: &String,
-    zkout: &String,
-) -> Result<()> {
-    // 1. load files.
-    let inputs_str = std::fs::read_to_string(zkin1)?;
-    let zkin1_map: BTreeMap<String, serde_json::Value> = serde_json::from_str(&inputs_str)?;
+/// The last four publics of a recursive proof are the root of the constant tree it verifies.
+const ROOT_C_PUBLICS: usize = 4;
 
-    let inputs_str = std::fs::read_to_string(zkin2)?;
-    let zkin2_map: BTreeMap<String, serde_json::Value> = serde_json::from_str(&inputs_str)?;
+/// The prefixes used by the aggregation circuits: `a_`, `b_`, `c_`, ...
+pub fn default_prefixes(n: usize) -> Vec<String> {
+    (0..n)
+        .map(|i| {
+            let mut s = String::new();
+            let mut i = i;
+            loop {
+                s.insert(0, (b'a' + (i % 26) as u8) as char);
+                if i < 26 {
+                    break;
+                }
+                i = i / 26 - 1;
+            }
+            format!("{s}_")
+        })
+        .collect()
+}
 
-    // 2. construct zkout
-    let mut zkout_map = BTreeMap::new();
+// The dimensions of a json array, following the first element of each level.
+fn shape(v: &Value) -> Vec<usize> {
+    let mut dims = vec![];
+    let mut cur = v;
+    while let Value::Array(arr) = cur {
+        dims.push(arr.len());
+        match arr.first() {
+            Some(e) => cur = e,
+            None => break,
+        }
+    }
+    dims
+}
 
-    for (k, v) in zkin1_map {
-        // TODO: remove this
-        zkout_map.insert(format!("a_{k}"), v.clone());
-        if k == "publics" {
-            if let Value::Array(ref arr) = v {
-                if arr.len() >= 4 {
-                    let exclude_last_four = &arr[..(arr.len() - 4)];
-                    zkout_map.insert(
-                        "publics".to_string(),
-                        Value::Array(exclude_last_four.to_vec()),
-                    );
-                } else {
-                    zkout_map.insert("publics".to_string(), v.clone());
-                }
+/// Combine the zkin of several proofs of the same circuit into the input of an aggregation
+/// circuit: every key of `inputs[i]` is renamed with `prefixes[i]`, and the joined proof
+/// gets the `publics` of the first input without its root, and the common `rootC`.
+///
+/// All inputs must come from the same stark setup, so `rootC`, the set of keys and the
+/// shape of every field (fixed by the StarkStruct) have to agree.
+pub fn join_zkin_values(
+    inputs: &[BTreeMap<String, Value>],
+    prefixes: &[String],
+) -> Result<BTreeMap<String, Value>> {
+    if inputs.len() < 2 {
+        bail!("join_zkin needs at least 2 inputs, got {}", inputs.len());
+    }
+    if prefixes.len() != inputs.len() {
+        bail!(
+            "join_zkin got {} prefixes for {} inputs",
+            prefixes.len(),
+            inputs.len()
+        );
+    }
+    for (i, p) in prefixes.iter().enumerate() {
+        if p.is_empty() {
+            bail!("prefix {} is empty", i);
+        }
+        if prefixes[..i].contains(p) {
+            bail!("prefix {} is used twice", p);
+        }
+    }
+
+    let first = &inputs[0];
+    let publics = match first.get("publics") {
+        Some(Value::Array(arr)) => arr,
+        _ => bail!("input 0 has no publics"),
+    };
+    if publics.len() < ROOT_C_PUBLICS {
+        bail!(
+            "input 0 has {} publics, expect at least {}",
+            publics.len(),
+            ROOT_C_PUBLICS
+        );
+    }
+    let root_c = match first.get("rootC") {
+        Some(v) => v,
+        None => bail!("input 0 has no rootC"),
+    };
+
+    for (i, input) in inputs.iter().enumerate().skip(1) {
+        match input.get("publics") {
+            Some(Value::Array(arr)) if arr.len() == publics.len() => {}
+            Some(Value::Array(arr)) => bail!(
+                "the publics layout differs: input 0 has {} publics, input {} has {}",
+                publics.len(),
+                i,
+                arr.len()
+            ),
+            _ => bail!("input {} has no publics", i),
+        }
+        if input.get("rootC") != Some(root_c) {
+            bail!(
+                "rootC of input {} differs from input 0, the proofs are not from the same setup",
+                i
+            );
+        }
+        if !input.keys().eq(first.keys()) {
+            let diff = first
+                .keys()
+                .filter(|k| !input.contains_key(*k))
+                .chain(input.keys().filter(|k| !first.contains_key(*k)))
+                .cloned()
+                .collect::<Vec<_>>();
+            bail!("the fields of input {} differ from input 0: {:?}", i, diff);
+        }
+        for (k, v) in first.iter() {
+            let (s0, si) = (shape(v), shape(&input[k]));
+            if s0 != si {
+                bail!(
+                    "{} of input {} has shape {:?}, input 0 has {:?}",
+                    k,
+                    i,
+                    si,
+                    s0
+                );
             }
         }
-        if k == "rootC" {
-            zkout_map.insert(k.to_string(), v.clone());
+    }
+
+    let mut zkout_map = BTreeMap::new();
+    zkout_map.insert(
+        "publics".to_string(),
+        Value::Array(publics[..(publics.len() - ROOT_C_PUBLICS)].to_vec()),
+    );
+    zkout_map.insert("rootC".to_string(), root_c.clone());
+    for (input, prefix) in inputs.iter().zip(prefixes.iter()) {
+        for (k, v) in input {
+            zkout_map.insert(format!("{prefix}{k}"), v.clone());
         }
     }
-    for (k, v) in zkin2_map {
-        zkout_map.insert(format!("b_{k}"), v);
+    Ok(zkout_map)
+}
+
+/// Combine the zkin files `zkins` into one(`out.zkin.json`), see `join_zkin_values`.
+/// The default prefixes are `a_`, `b_`, ...
+pub fn join_zkin_n(zkins: &[String], prefixes: Option<&[String]>, zkout: &str) -> Result<()> {
+    // 1. load files.
+    let mut inputs = vec![];
+    for zkin in zkins {
+        let inputs_str = std::fs::read_to_string(zkin)?;
+        let zkin_map: BTreeMap<String, Value> = serde_json::from_str(&inputs_str)?;
+        inputs.push(zkin_map);
     }
 
+    // 2. construct zkout
+    let prefixes = match prefixes {
+        Some(p) => p.to_vec(),
+        None => default_prefixes(zkins.len()),
+    };
+    let zkout_map = join_zkin_values(&inputs, &prefixes)?;
+
     // 3. save zkout to file
     let input = serde_json::to_string(&zkout_map)?;
     let mut file = File::create(zkout)?;
@@ -53,3 +164,75 @@ pub fn join_zkin(
     log::trace!("zkout file Generated Correctly");
     Ok(())
 }
+
+/// Combine the `input1.zkin.json` and `input2.zkin.json` into one(`out.zkin.json`)
+// ../../target/release/eigen-zkit join_zkin --zkin1 0/fibonacci.recursive1/input.zkin.json  --zkin2 1/fibonacci.recursive1/input.zkin.json  --zkinout 0/fibonacci.recursive1/r1_input-rs.zkin.json
+pub fn join_zkin(zkin1: &String, zkin2: &String, zkout: &String) -> Result<()> {
+    join_zkin_n(&[zkin1.clone(), zkin2.clone()], None, zkout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn zkin(publics: Value, root_c: Value) -> BTreeMap<String, Value> {
+        serde_json::from_value(json!({
+            "publics": publics,
+            "rootC": root_c,
+            "root1": ["1", "2", "3", "4"],
+            "evals": [["1", "0", "0"], ["2", "0", "0"]],
+            "finalPol": [["1", "0", "0"], ["2", "0", "0"]],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_default_prefixes() {
+        let p = default_prefixes(28);
+        assert_eq!(p[0], "a_");
+        assert_eq!(p[1], "b_");
+        assert_eq!(p[25], "z_");
+        assert_eq!(p[26], "aa_");
+        assert_eq!(p[27], "ab_");
+    }
+
+    #[test]
+    fn test_join_zkin_values() {
+        let root_c = json!(["5", "6", "7", "8"]);
+        let inputs = (0..4)
+            .map(|i| zkin(json!([i.to_string(), "1", "2", "3", "4"]), root_c.clone()))
+            .collect::<Vec<_>>();
+        let out = join_zkin_values(&inputs, &default_prefixes(4)).unwrap();
+        assert_eq!(out["publics"], json!(["0"]));
+        assert_eq!(out["rootC"], root_c);
+        assert_eq!(out["d_publics"][0], json!("3"));
+        assert_eq!(out.len(), 2 + 4 * 5);
+
+        let prefixes = vec!["x_".to_string(), "y_".to_string()];
+        let out = join_zkin_values(&inputs[..2], &prefixes).unwrap();
+        assert!(out.contains_key("y_finalPol"));
+
+        // different setups
+        let mut bad = inputs.clone();
+        bad[2] = zkin(
+            json!(["0", "1", "2", "3", "4"]),
+            json!(["0", "6", "7", "8"]),
+        );
+        assert!(join_zkin_values(&bad, &default_prefixes(4)).is_err());
+
+        // different publics layouts
+        let mut bad = inputs.clone();
+        bad[1] = zkin(json!(["0", "1", "2", "3"]), root_c.clone());
+        let err = join_zkin_values(&bad, &default_prefixes(4)).unwrap_err();
+        assert!(err.to_string().contains("publics layout differs"));
+
+        // different shapes
+        let mut bad = inputs.clone();
+        bad[3].insert("finalPol".to_string(), json!([["1", "0", "0"]]));
+        assert!(join_zkin_values(&bad, &default_prefixes(4)).is_err());
+
+        assert!(join_zkin_values(&inputs[..1], &default_prefixes(1)).is_err());
+        assert!(join_zkin_values(&inputs, &default_prefixes(3)).is_err());
+    }
+}
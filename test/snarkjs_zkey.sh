@@ -0,0 +1,48 @@
+#!/bin/bash
+set -ex
+
+## make test/multiplier.zkey by snarkjs, prove with it by zkit, and verify the proof by both
+
+cargo build --release
+
+CUR_DIR=$(cd $(dirname $0);pwd)
+ZKIT="${CUR_DIR}/../target/release/eigen-zkit"
+SNARKJS=${CUR_DIR}/aggregation/node_modules/snarkjs/build/cli.cjs
+if [ ! -d "${CUR_DIR}/aggregation/node_modules/snarkjs" ]; then
+    cd ${CUR_DIR}/aggregation && npm install
+fi
+
+WORKSPACE=/tmp/snarkjs_zkey
+rm -rf $WORKSPACE && mkdir -p $WORKSPACE
+ZKEY=${CUR_DIR}/multiplier.zkey
+
+echo "1. setup by snarkjs"
+$SNARKJS powersoftau new bn128 4 $WORKSPACE/pot_0.ptau
+$SNARKJS powersoftau contribute $WORKSPACE/pot_0.ptau $WORKSPACE/pot_1.ptau --name="first" -e="zkit"
+$SNARKJS powersoftau prepare phase2 $WORKSPACE/pot_1.ptau $WORKSPACE/pot.ptau
+$SNARKJS groth16 setup ${CUR_DIR}/multiplier.r1cs $WORKSPACE/pot.ptau $WORKSPACE/multiplier_0.zkey
+$SNARKJS zkey contribute $WORKSPACE/multiplier_0.zkey $ZKEY --name="first" -e="zkit"
+$SNARKJS zkey export verificationkey $ZKEY $WORKSPACE/vk.snarkjs.json
+
+echo "2. prove by zkit"
+$ZKIT groth16_import_zkey -c BN128 --zkey $ZKEY --r1cs ${CUR_DIR}/multiplier.r1cs -p $WORKSPACE/g16.zkey -v $WORKSPACE/vk.json
+$ZKIT groth16_prove -c BN128 --r1cs ${CUR_DIR}/multiplier.r1cs -w ${CUR_DIR}/multiplier.wasm -p $ZKEY -i ${CUR_DIR}/multiplier.input.json --public-input $WORKSPACE/public.json --proof $WORKSPACE/proof.json
+
+echo "3. verify by zkit and snarkjs"
+$ZKIT groth16_verify -c BN128 -v $WORKSPACE/vk.json --public-input $WORKSPACE/public.json --proof $WORKSPACE/proof.json
+# the points of snarkjs are projective
+node -e '
+const fs = require("fs");
+const p = JSON.parse(fs.readFileSync(process.argv[1]));
+fs.writeFileSync(process.argv[2], JSON.stringify({
+    pi_a: [p.pi_a.x, p.pi_a.y, "1"],
+    pi_b: [p.pi_b.x, p.pi_b.y, ["1", "0"]],
+    pi_c: [p.pi_c.x, p.pi_c.y, "1"],
+    protocol: "groth16",
+    curve: "bn128",
+}));
+' $WORKSPACE/proof.json $WORKSPACE/proof.snarkjs.json
+$SNARKJS groth16 verify $WORKSPACE/vk.snarkjs.json $WORKSPACE/public.json $WORKSPACE/proof.snarkjs.json
+
+echo "4. the zkey test of groth16"
+cd ${CUR_DIR}/../groth16 && cargo test --release test_snarkjs_zkey_prove -- --ignored
@@ -24,12 +24,12 @@ BIG_SRS=${CUR_DIR}/../keys/setup_2^${BIG_POWER}.key
 
 if [ ! -f $SRS ]; then
 #   curl https://universal-setup.ams3.digitaloceanspaces.com/setup_2^${POWER}.key -o $SRS
-    ${ZKIT} setup -p ${POWER} -s ${SRS}
+    ${ZKIT} setup -p ${POWER} -s ${SRS} --insecure
 fi
 
 if [ ! -f $BIG_SRS ]; then
 #   curl https://universal-setup.ams3.digitaloceanspaces.com/setup_2^${BIG_POWER}.key -o $BIG_SRS
-    ${ZKIT} setup -p ${BIG_POWER} -s ${BIG_SRS}
+    ${ZKIT} setup -p ${BIG_POWER} -s ${BIG_SRS} --insecure
 fi
 
 echo "1. compile circuit"
@@ -22,7 +22,7 @@ rm -rf $WORKSPACE && mkdir -p $WORKSPACE
 
 SRS=${CUR_DIR}/../keys/setup_2^${POWER}.key
 if [ ! -f $SRS ]; then
-    ${ZKIT} setup -p ${POWER} -s ${SRS}
+    ${ZKIT} setup -p ${POWER} -s ${SRS} --insecure
 fi
 
 cd $CUR_DIR
@@ -65,7 +65,7 @@ ${ZKIT} verify -p $WORKSPACE/proof.bin -v $WORKSPACE/vk.bin
 
 echo "6. Generate verifier"
 mkdir -p ${WORKSPACE}/contracts
-${ZKIT} generate_verifier -v $WORKSPACE/vk.bin -s single/contracts/verifier.sol
+${ZKIT} generate_verifier -p plonk -v $WORKSPACE/vk.bin -s single/contracts/verifier.sol
 
 echo "7. run verifier test"
 cd $CUR_DIR/single && npm i && npx hardhat test --grep "Test Plonk verifier"
@@ -0,0 +1,602 @@
+//! Aggregate stark proofs into one groth16 proof, in process and driven by a manifest.
+//!
+//! The stages are the ones of `test/stark_aggregation.sh`:
+//! stark_prove -> compressor12 -> recursive1 -> join_zkin -> recursive2 -> final -> groth16.
+//! Every stage records the parameters it was run with in a `.stamp` file next to its first
+//! output. Setup stages (compiled circuits, compressor12 setups, groth16 keys) are skipped when
+//! the stamp also matches a hash of the content of their inputs, the proving stages, whose
+//! inputs are big polynomial files, when their outputs are newer than their inputs.
+//! `--force` runs every stage again.
+use anyhow::{bail, Result};
+use dsl_compile::{circom_compiler, Stats};
+use groth16::api::{groth16_prove, groth16_setup, groth16_verify, WitnessGenerator};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use starky::prove::stark_prove;
+use starky::zkin_join::join_zkin_n;
+use std::fs::File;
+use std::path::Path;
+use std::time::{Instant, SystemTime};
+
+fn default_curve() -> String {
+    "BN128".to_string()
+}
+
+fn default_force_n_bits() -> usize {
+    18
+}
+
+fn default_prover_addr() -> String {
+    "273030697313060285579891744179749754319274977764".to_string()
+}
+
+/// The polynomials of one proof to aggregate.
+#[derive(Debug, Deserialize)]
+pub struct ProofInput {
+    pub pil_json: String,
+    pub const_pols: String,
+    pub cm_pols: String,
+}
+
+/// The aggregation manifest, a json file like:
+/// ```json
+/// {
+///   "name": "fibonacci",
+///   "workspace": "/tmp/aggregation_fibonacci",
+///   "curve": "BN128",
+///   "stark_struct": "starky/data/starkStruct.json.gl",
+///   "c12_stark_struct": "starky/data/c12.starkStruct.json",
+///   "r1_stark_struct": "starky/data/r1.starkStruct.json",
+///   "final_stark_struct": "starky/data/final.starkStruct.bn128.json",
+///   "gl_link_directories": ["starkjs/node_modules/pil-stark/circuits.gl"],
+///   "snark_link_directories": ["starkjs/node_modules/pil-stark/circuits.bn128"],
+///   "proofs": [
+///     { "pil_json": "0/fibonacci.pil.json", "const_pols": "0/fibonacci.const", "cm_pols": "0/fibonacci.cm" },
+///     { "pil_json": "1/fibonacci.pil.json", "const_pols": "1/fibonacci.const", "cm_pols": "1/fibonacci.cm" }
+///   ]
+/// }
+/// ```
+/// All the proofs must be of the same PIL with the same constant polynomials.
+#[derive(Debug, Deserialize)]
+pub struct Manifest {
+    pub name: String,
+    pub workspace: String,
+    #[serde(default = "default_curve")]
+    pub curve: String,
+    pub stark_struct: String,
+    pub c12_stark_struct: String,
+    pub r1_stark_struct: String,
+    pub final_stark_struct: String,
+    /// circom libraries of the goldilocks circuits
+    #[serde(default)]
+    pub gl_link_directories: Vec<String>,
+    /// circom libraries of the final snark circuit
+    #[serde(default)]
+    pub snark_link_directories: Vec<String>,
+    /// degree of the recursive1 and recursive2 compressor12 machines
+    #[serde(default = "default_force_n_bits")]
+    pub recursive_force_n_bits: usize,
+    #[serde(default = "default_prover_addr")]
+    pub prover_addr: String,
+    pub proofs: Vec<ProofInput>,
+}
+
+fn modified(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+// `outputs` are up to date when they all exist and none of `inputs` is newer.
+fn is_fresh(outputs: &[&str], inputs: &[&str]) -> bool {
+    let oldest = match outputs
+        .iter()
+        .map(|o| modified(o))
+        .collect::<Option<Vec<_>>>()
+    {
+        Some(times) => times.into_iter().min(),
+        None => return false,
+    };
+    match oldest {
+        Some(oldest) => inputs
+            .iter()
+            .all(|i| modified(i).map_or(false, |t| t <= oldest)),
+        None => false,
+    }
+}
+
+// the hash of the parameters of a stage and of the content of `inputs`
+fn stage_key(params: &str, inputs: &[&str]) -> Result<String> {
+    let mut hasher = Sha256::new();
+    hasher.update(params.as_bytes());
+    for input in inputs {
+        hasher.update(input.as_bytes());
+        let mut file =
+            File::open(input).map_err(|e| anyhow::anyhow!("open {} failed: {}", input, e))?;
+        std::io::copy(&mut file, &mut hasher)?;
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn stamp_file(outputs: &[&str]) -> String {
+    format!("{}.stamp", outputs[0])
+}
+
+// the outputs all exist and were made by a stage of the same key
+fn is_stamped(outputs: &[&str], key: &str) -> bool {
+    outputs.iter().all(|o| Path::new(o).exists())
+        && std::fs::read_to_string(stamp_file(outputs)).map_or(false, |k| k == key)
+}
+
+struct Stages {
+    force: bool,
+}
+
+impl Stages {
+    // runs a proving stage unless its outputs are newer than its inputs and were made with
+    // the same parameters
+    fn run<F>(
+        &self,
+        name: &str,
+        inputs: &[&str],
+        params: &str,
+        outputs: &[&str],
+        f: F,
+    ) -> Result<()>
+    where
+        F: FnOnce() -> Result<()>,
+    {
+        let key = stage_key(params, &[])?;
+        if !self.force && is_fresh(outputs, inputs) && is_stamped(outputs, &key) {
+            log::info!("{}: cached", name);
+            return Ok(());
+        }
+        self.exec(name, outputs, &key, f)
+    }
+
+    // runs a setup stage unless its outputs were made from the same inputs and parameters
+    fn setup<F>(
+        &self,
+        name: &str,
+        inputs: &[&str],
+        params: &str,
+        outputs: &[&str],
+        f: F,
+    ) -> Result<()>
+    where
+        F: FnOnce() -> Result<()>,
+    {
+        let key = stage_key(params, inputs)?;
+        if !self.force && is_stamped(outputs, &key) {
+            log::info!("{}: cached", name);
+            return Ok(());
+        }
+        self.exec(name, outputs, &key, f)
+    }
+
+    fn exec<F>(&self, name: &str, outputs: &[&str], key: &str, f: F) -> Result<()>
+    where
+        F: FnOnce() -> Result<()>,
+    {
+        log::info!("{}: running", name);
+        let start = Instant::now();
+        // a stage that fails half way must not look up to date
+        let stamp = stamp_file(outputs);
+        let _ = std::fs::remove_file(&stamp);
+        f().map_err(|e| anyhow::anyhow!("{} failed: {}", name, e))?;
+        std::fs::write(&stamp, key)?;
+        log::info!("{}: done in {:.1}s", name, start.elapsed().as_secs_f64());
+        Ok(())
+    }
+}
+
+// The artifacts of a circuit compiled into `dir`.
+struct Circuit {
+    name: String,
+    dir: String,
+}
+
+impl Circuit {
+    fn new(dir: &str, name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            dir: dir.to_string(),
+        }
+    }
+
+    fn file(&self, ext: &str) -> String {
+        format!("{}/{}.{}", self.dir, self.name, ext)
+    }
+
+    fn r1cs(&self) -> String {
+        self.file("r1cs")
+    }
+
+    fn wasm(&self) -> String {
+        format!("{}/{}_js/{}.wasm", self.dir, self.name, self.name)
+    }
+
+    // compile `circom_file` once, into `dir`
+    fn compile(
+        &self,
+        stages: &Stages,
+        circom_file: &str,
+        prime: &str,
+        link_directories: &[String],
+    ) -> Result<()> {
+        stages.setup(
+            &format!("compile {}", self.name),
+            &[circom_file],
+            &format!("{} {:?}", prime, link_directories),
+            &[&self.r1cs(), &self.wasm()],
+            || {
+                circom_compiler(
+                    circom_file.to_string(),
+                    prime.to_string(),
+                    "full".to_string(),
+                    link_directories.to_vec(),
+                    self.dir.clone(),
+                    false,
+                    false,
+                    false,
+                    Stats::Skip,
+                )
+                .map(|_| ())
+            },
+        )
+    }
+
+    // convert the compiled circuit to a compressor12 machine
+    fn c12_setup(&self, stages: &Stages, force_n_bits: usize) -> Result<()> {
+        stages.setup(
+            &format!("compressor12_setup {}", self.name),
+            &[&self.r1cs()],
+            &force_n_bits.to_string(),
+            &[&self.file("pil"), &self.file("const"), &self.file("exec")],
+            || {
+                recursion::compressor12_setup::setup(
+                    &self.r1cs(),
+                    &self.file("pil"),
+                    &self.file("const"),
+                    &self.file("exec"),
+                    force_n_bits,
+                    false,
+                )
+            },
+        )
+    }
+
+    // the commit polynomials of the compressor12 machine for `zkin`
+    fn c12_exec(&self, stages: &Stages, zkin: &str, commit_file: &str) -> Result<()> {
+        let (wasm, pil, exec) = (self.wasm(), self.file("pil"), self.file("exec"));
+        stages.run(
+            &format!("compressor12_exec {}", commit_file),
+            &[zkin, &wasm, &pil, &exec],
+            "",
+            &[commit_file, &self.file("pil.json")],
+            || {
+                recursion::compressor12_exec::exec(
+                    zkin,
+                    &wasm,
+                    algebraic::witness::WitnessGenerator::Wasm,
+                    &pil,
+                    &exec,
+                    commit_file,
+                )
+            },
+        )
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn prove(
+    stages: &Stages,
+    stark_struct: &str,
+    pil_json: &str,
+    const_pols: &str,
+    cm_pols: &str,
+    (norm_stage, skip_main, agg_stage): (bool, bool, bool),
+    circom_file: &str,
+    zkin: &str,
+    prover_addr: &str,
+) -> Result<()> {
+    stages.run(
+        &format!("stark_prove {}", zkin),
+        &[stark_struct, pil_json, const_pols, cm_pols],
+        &format!("{} {} {} {}", norm_stage, skip_main, agg_stage, prover_addr),
+        &[circom_file, zkin],
+        || {
+            stark_prove(
+                stark_struct,
+                pil_json,
+                norm_stage,
+                skip_main,
+                agg_stage,
+                const_pols,
+                cm_pols,
+                circom_file,
+                zkin,
+                prover_addr,
+            )
+        },
+    )
+}
+
+/// Runs the whole aggregation of `manifest_file`, the groth16 proof and its public input
+/// are written to `{workspace}/aggregation/proof.json` and `public_input.json`.
+pub fn aggregate(manifest_file: &str, force: bool) -> Result<()> {
+    let manifest: Manifest = serde_json::from_str(&std::fs::read_to_string(manifest_file)?)?;
+    if manifest.proofs.is_empty() {
+        bail!("the manifest {} has no proof to aggregate", manifest_file);
+    }
+    if manifest.curve != "BN128" && manifest.curve != "BLS12381" {
+        bail!("Unknown curve type: {}", manifest.curve);
+    }
+    let m = &manifest;
+    let stages = Stages { force };
+    let ws = &m.workspace;
+    let setup_dir = format!("{ws}/setup");
+    let agg_dir = format!("{ws}/aggregation");
+    std::fs::create_dir_all(&setup_dir)?;
+    std::fs::create_dir_all(&agg_dir)?;
+
+    let verifier = Circuit::new(&setup_dir, &format!("{}.verifier", m.name));
+    let recursive1 = Circuit::new(&setup_dir, &format!("{}.recursive1", m.name));
+    let recursive2 = Circuit::new(&setup_dir, &format!("{}.recursive2", m.name));
+    let fin = Circuit::new(&setup_dir, &format!("{}.final", m.name));
+    let final_verifier = Circuit::new(&setup_dir, &format!("{}.final.verifier", m.name));
+    let gl_links = &m.gl_link_directories;
+
+    // 1. prove every input and compress it to a recursive1 proof
+    let mut r1_zkins = vec![];
+    for (i, p) in m.proofs.iter().enumerate() {
+        let dir = format!("{ws}/{i}");
+        std::fs::create_dir_all(&dir)?;
+        let verifier_circom = format!("{dir}/{}.circom", verifier.name);
+        let verifier_zkin = format!("{dir}/{}.zkin.json", verifier.name);
+        prove(
+            &stages,
+            &m.stark_struct,
+            &p.pil_json,
+            &p.const_pols,
+            &p.cm_pols,
+            (false, true, false),
+            &verifier_circom,
+            &verifier_zkin,
+            &m.prover_addr,
+        )?;
+        verifier.compile(&stages, &verifier_circom, "goldilocks", gl_links)?;
+        verifier.c12_setup(&stages, 0)?;
+        let c12_cm = format!("{dir}/{}.c12.cm", m.name);
+        verifier.c12_exec(&stages, &verifier_zkin, &c12_cm)?;
+
+        let r1_circom = format!("{dir}/{}.circom", recursive1.name);
+        let c12_zkin = format!("{dir}/{}.c12.zkin.json", m.name);
+        prove(
+            &stages,
+            &m.c12_stark_struct,
+            &verifier.file("pil.json"),
+            &verifier.file("const"),
+            &c12_cm,
+            (false, false, false),
+            &r1_circom,
+            &c12_zkin,
+            &m.prover_addr,
+        )?;
+        recursive1.compile(&stages, &r1_circom, "goldilocks", gl_links)?;
+        recursive1.c12_setup(&stages, m.recursive_force_n_bits)?;
+        let r1_cm = format!("{dir}/{}.cm", recursive1.name);
+        recursive1.c12_exec(&stages, &c12_zkin, &r1_cm)?;
+
+        let r2_circom = format!("{dir}/{}.circom", recursive2.name);
+        let r1_zkin = format!("{dir}/{}.zkin.json", recursive1.name);
+        prove(
+            &stages,
+            &m.r1_stark_struct,
+            &recursive1.file("pil.json"),
+            &recursive1.file("const"),
+            &r1_cm,
+            (true, false, true),
+            &r2_circom,
+            &r1_zkin,
+            &m.prover_addr,
+        )?;
+        recursive2.compile(&stages, &r2_circom, "goldilocks", gl_links)?;
+        r1_zkins.push(r1_zkin);
+    }
+
+    // 2. join the proofs two by two. A join must take proofs of the same circuit, so the
+    // recursive1 proofs are paired first, an odd one with itself, then recursive2 ones.
+    recursive2.c12_setup(&stages, m.recursive_force_n_bits)?;
+    let final_circom = format!("{agg_dir}/{}.circom", fin.name);
+    let join = |level: usize, j: usize, a: &str, b: &str| -> Result<String> {
+        let input = format!("{agg_dir}/{}_{level}_{j}.input.zkin.json", recursive2.name);
+        let cm = format!("{agg_dir}/{}_{level}_{j}.cm", recursive2.name);
+        let zkin = format!("{agg_dir}/{}_{level}_{j}.zkin.json", recursive2.name);
+        stages.run(
+            &format!("join_zkin {}", input),
+            &[a, b],
+            "",
+            &[&input],
+            || join_zkin_n(&[a.to_string(), b.to_string()], None, &input),
+        )?;
+        recursive2.c12_exec(&stages, &input, &cm)?;
+        prove(
+            &stages,
+            &m.r1_stark_struct,
+            &recursive2.file("pil.json"),
+            &recursive2.file("const"),
+            &cm,
+            (true, false, false),
+            &final_circom,
+            &zkin,
+            &m.prover_addr,
+        )?;
+        Ok(zkin)
+    };
+    let mut level_zkins = vec![];
+    for (j, pair) in r1_zkins.chunks(2).enumerate() {
+        level_zkins.push(join(0, j, &pair[0], &pair[pair.len() - 1])?);
+    }
+    let mut level = 1;
+    while level_zkins.len() > 1 {
+        let mut next = vec![];
+        for (j, pair) in level_zkins.chunks(2).enumerate() {
+            if pair.len() == 2 {
+                next.push(join(level, j, &pair[0], &pair[1])?);
+            } else {
+                next.push(pair[0].clone());
+            }
+        }
+        level_zkins = next;
+        level += 1;
+    }
+    let r2_zkin = &level_zkins[0];
+
+    // 3. the final stark proof, hashed with the snark curve
+    fin.compile(&stages, &final_circom, "goldilocks", gl_links)?;
+    fin.c12_setup(&stages, 0)?;
+    let final_cm = format!("{agg_dir}/{}.cm", fin.name);
+    fin.c12_exec(&stages, r2_zkin, &final_cm)?;
+    let final_verifier_circom = format!("{agg_dir}/{}.circom", final_verifier.name);
+    let final_zkin = format!("{agg_dir}/{}.zkin.json", fin.name);
+    prove(
+        &stages,
+        &m.final_stark_struct,
+        &fin.file("pil.json"),
+        &fin.file("const"),
+        &final_cm,
+        (false, false, false),
+        &final_verifier_circom,
+        &final_zkin,
+        &m.prover_addr,
+    )?;
+
+    // 4. groth16
+    final_verifier.compile(
+        &stages,
+        &final_verifier_circom,
+        &m.curve,
+        &m.snark_link_directories,
+    )?;
+    let pk = final_verifier.file("zkey");
+    let vk = format!("{setup_dir}/verification_key.json");
+    let r1cs = final_verifier.r1cs();
+    stages.setup("groth16_setup", &[&r1cs], &m.curve, &[&pk, &vk], || {
+        groth16_setup(&m.curve, &final_verifier.r1cs(), &pk, &vk, false)
+    })?;
+    let public_input = format!("{agg_dir}/public_input.json");
+    let proof = format!("{agg_dir}/proof.json");
+    stages.run(
+        "groth16_prove",
+        &[&final_zkin, &pk],
+        &m.curve,
+        &[&public_input, &proof],
+        || {
+            groth16_prove(
+                &m.curve,
+                &final_verifier.r1cs(),
+                &final_verifier.wasm(),
+                WitnessGenerator::Wasm,
+                &pk,
+                &final_zkin,
+                &public_input,
+                &proof,
+                false,
+            )
+        },
+    )?;
+    groth16_verify(&m.curve, &vk, &public_input, &proof)?;
+    log::info!("aggregated {} proofs into {}", m.proofs.len(), proof);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_defaults() {
+        let manifest: Manifest = serde_json::from_str(
+            r#"{
+                "name": "fibonacci",
+                "workspace": "/tmp/aggregation_fibonacci",
+                "stark_struct": "starkStruct.json.gl",
+                "c12_stark_struct": "c12.starkStruct.json",
+                "r1_stark_struct": "r1.starkStruct.json",
+                "final_stark_struct": "final.starkStruct.bn128.json",
+                "proofs": [{"pil_json": "a.pil.json", "const_pols": "a.const", "cm_pols": "a.cm"}]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(manifest.curve, "BN128");
+        assert_eq!(manifest.recursive_force_n_bits, 18);
+        assert_eq!(manifest.proofs.len(), 1);
+        assert!(manifest.gl_link_directories.is_empty());
+    }
+
+    #[test]
+    fn test_is_fresh() {
+        let dir =
+            std::env::temp_dir().join(format!("zkit_aggregate_is_fresh_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("input").to_str().unwrap().to_string();
+        let output = dir.join("output").to_str().unwrap().to_string();
+        let _ = std::fs::remove_file(&output);
+        std::fs::write(&input, "in").unwrap();
+        assert!(!is_fresh(&[&output], &[&input]));
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        std::fs::write(&output, "out").unwrap();
+        assert!(is_fresh(&[&output], &[&input]));
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        std::fs::write(&input, "in2").unwrap();
+        assert!(!is_fresh(&[&output], &[&input]));
+        assert!(!is_fresh(&[&output], &[&format!("{input}.missing")]));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_stages_stamp() {
+        let dir = std::env::temp_dir().join(format!("zkit_aggregate_stamp_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("input").to_str().unwrap().to_string();
+        let output = dir.join("output").to_str().unwrap().to_string();
+        std::fs::write(&input, "in").unwrap();
+
+        let stages = Stages { force: false };
+        let runs = std::cell::Cell::new(0);
+        let setup = |params: &str| {
+            stages
+                .setup("setup", &[&input], params, &[&output], || {
+                    runs.set(runs.get() + 1);
+                    std::fs::write(&output, "out")?;
+                    Ok(())
+                })
+                .unwrap();
+            runs.get()
+        };
+        assert_eq!(setup("a"), 1);
+        assert_eq!(setup("a"), 1);
+        // the parameters change, then the content of the input
+        assert_eq!(setup("b"), 2);
+        std::fs::write(&input, "in2").unwrap();
+        assert_eq!(setup("b"), 3);
+        assert_eq!(setup("b"), 3);
+        std::fs::remove_file(&output).unwrap();
+        assert_eq!(setup("b"), 4);
+
+        let run = |params: &str| {
+            stages
+                .run("run", &[&input], params, &[&output], || {
+                    runs.set(runs.get() + 1);
+                    std::thread::sleep(std::time::Duration::from_millis(20));
+                    std::fs::write(&output, "out")?;
+                    Ok(())
+                })
+                .unwrap();
+            runs.get()
+        };
+        assert_eq!(run("a"), 5);
+        assert_eq!(run("a"), 5);
+        assert_eq!(run("b"), 6);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,10 +1,12 @@
 extern crate clap;
-use clap::{command, Parser};
-use dsl_compile::circom_compiler;
+use clap::{builder::TypedValueParser, command, Parser};
+use dsl_compile::{circom_compiler, format_stats, Stats, StatsFormat};
 use groth16::api::*;
 use starky::prove::stark_prove;
 use std::time::Instant;
 
+mod aggregate;
+
 /// Trust setup for Plonk
 #[derive(Parser, Debug)]
 pub struct SetupOpt {
@@ -12,6 +14,41 @@ pub struct SetupOpt {
     power: u32,
     #[arg(short, required = true)]
     srs_monomial_form: String,
+    /// Generate the SRS of the known toxic waste 42, for tests only
+    #[arg(long = "insecure", action= clap::ArgAction::SetTrue)]
+    insecure: bool,
+}
+
+/// Prove by Plonk
+#[derive(Parser, Debug)]
+pub struct ProveOpt {
+    #[arg(short, required = true)]
+    srs_monomial_form: String,
+    #[arg(short, default_value = "circuit.r1cs")]
+    circuit_file: String,
+    /// the witness, a wtns or json file
+    #[arg(short, default_value = "witness.wtns")]
+    witness: String,
+    /// keccak to verify in the EVM, rescue to aggregate
+    #[arg(short, default_value = "keccak")]
+    transcript: String,
+    #[arg(long = "b", default_value = "proof.bin")]
+    proof_bin: String,
+    #[arg(long = "j", default_value = "proof.json")]
+    proof_json: String,
+    #[arg(long = "p", default_value = "public.json")]
+    public_json: String,
+}
+
+/// Verify the Plonk proof
+#[derive(Parser, Debug)]
+pub struct VerifyOpt {
+    #[arg(short, default_value = "proof.bin")]
+    proof_bin: String,
+    #[arg(short, default_value = "vk.bin")]
+    vk_file: String,
+    #[arg(short, default_value = "keccak")]
+    transcript: String,
 }
 
 #[derive(Debug, Parser)]
@@ -42,15 +79,26 @@ pub struct CompilierOpt {
     /// setup the library path
     #[arg(short)]
     link_directories: Vec<String>,
+
+    /// also build the native witness generator <output>/<name>_cpp/<name>, it needs g++, nasm,
+    /// GMP and nlohmann-json
+    #[arg(long = "c", action= clap::ArgAction::SetTrue)]
+    c_generator: bool,
+
+    /// print the instances, signals, wires and constraints of each template, as a table or json
+    #[arg(long = "stats", num_args = 0..=1, default_missing_value = "table", value_parser = clap::builder::PossibleValuesParser::new(["table", "json"]).map(|s| s.parse::<StatsFormat>().unwrap()))]
+    stats: Option<StatsFormat>,
 }
 
 /// Calculate witness and save to output file
 #[derive(Debug, Parser)]
-
 struct CalculateWitnessOpt {
-    /// wasm circuit
+    /// wasm circuit, or the native witness generator built by `compile --c` with --native
     #[arg(short, required = true)]
     wasm_file: String,
+    /// The witness generator is the native one built by `compile --c`, not a wasm
+    #[arg(long = "native", action= clap::ArgAction::SetTrue)]
+    native: bool,
     /// [input] input json
     #[arg(short, required = true)]
     input_json: String,
@@ -59,15 +107,45 @@ struct CalculateWitnessOpt {
     output: String,
 }
 
-/// Generate solidity verifier
+/// Check that a witness satisfies the R1CS, and report the unsatisfied constraints by signal names
+#[derive(Debug, Parser)]
+struct R1csCheckOpt {
+    #[arg(short, required = true, default_value = "BN128")]
+    curve_type: String,
+    #[arg(long = "r1cs", required = true)]
+    circuit_file: String,
+    #[arg(short, required_unless_present = "witness_file")]
+    wasm_file: Option<String>,
+    #[arg(short, required_unless_present = "witness_file")]
+    input_file: Option<String>,
+    /// Check this wtns or json witness instead of calculating it from the wasm and input
+    #[arg(long = "witness", conflicts_with_all = ["wasm_file", "input_file"])]
+    witness_file: Option<String>,
+    /// The signal names, by default the .sym next to the .r1cs
+    #[arg(long = "sym")]
+    sym_file: Option<String>,
+    /// Report at most this many unsatisfied constraints
+    #[arg(long = "max", default_value = "10")]
+    max_reports: usize,
+}
+
+/// Generate solidity, rust or wasm verifier
 #[derive(Debug, Parser)]
 struct GenerateVerifierOpt {
     #[arg(short, default_value = "vk.bin")]
     vk_file: String,
+    /// groth16 or plonk
     #[arg(short, default_value = "groth16")]
     protocal: String,
     #[arg(short, default_value = "verifier.sol")]
     sol: String,
+    /// Generate the gas optimized groth16 verifier in inline assembly, with the snarkjs ABI
+    #[arg(long = "assembly", action= clap::ArgAction::SetTrue)]
+    assembly: bool,
+    /// solidity, rust (a no_std groth16 module written to SOL) or wasm (a wasm-bindgen groth16
+    /// package in the directory SOL)
+    #[arg(long = "target", default_value = "solidity")]
+    target: String,
 }
 
 /// Export proof's verification key
@@ -135,6 +213,45 @@ struct AggregationCheckOpt {
     new_proof: String,
 }
 
+/// A subcommand for generating a Solidity aggregation verifier smart contract
+#[derive(Parser, Debug)]
+struct GenerateAggregationVerifierOpt {
+    #[arg(short, default_value = "vk.bin")]
+    old_vk: String,
+    #[arg(long = "n", default_value = "aggregation_vk.bin")]
+    new_vk: String,
+    /// the number of public inputs of each aggregated proof
+    #[arg(long = "num_inputs", default_value = "1")]
+    num_inputs: usize,
+    #[arg(short, default_value = "aggregation_verifier.sol")]
+    sol: String,
+}
+
+/// Proof aggregation for plonk
+#[derive(Parser, Debug)]
+struct AggregationProveOpt {
+    #[arg(short)]
+    srs_monomial_form: String,
+    /// the file listing the proofs to aggregate, one per line
+    #[arg(long = "f")]
+    old_proof_list: String,
+    #[arg(long = "v", default_value = "vk.bin")]
+    old_vk: String,
+    #[arg(long = "n", default_value = "aggregation_proof.bin")]
+    new_proof: String,
+    #[arg(long = "j", default_value = "aggregation_proof.json")]
+    proof_json: String,
+}
+
+/// Verify aggregation proof
+#[derive(Parser, Debug)]
+struct AggregationVerifyOpt {
+    #[arg(long = "p", default_value = "aggregation_proof.bin")]
+    proof: String,
+    #[arg(long = "v", default_value = "aggregation_vk.bin")]
+    vk: String,
+}
+
 /// Setup compressor12 for converting R1CS to PIL
 #[derive(Parser, Debug)]
 struct Compressor12SetupOpt {
@@ -148,6 +265,9 @@ struct Compressor12SetupOpt {
     exec_file: String, // File required to execute
     #[arg(long, default_value = "0")]
     force_n_bits: usize,
+    /// Simplify the R1CS before converting it to Plonk, the witness of the exec is unchanged
+    #[arg(long = "optimize", action= clap::ArgAction::SetTrue)]
+    optimize: bool,
 }
 
 /// Exec compressor12 for converting R1CS to PIL
@@ -156,8 +276,12 @@ struct Compressor12ExecOpt {
     // input files :  $C12_VERIFIER.r1cs  $C12_VERIFIER.const  $C12_VERIFIER.pil
     #[arg(long = "i", default_value = "mycircuit.proof.zkin.json")]
     input_file: String,
+    /// the wasm, or the native witness generator built by `compile --c` with --native
     #[arg(long = "w", default_value = "mycircuit.verifier.wasm")]
     wasm_file: String,
+    /// The witness generator is the native one built by `compile --c`, not a wasm
+    #[arg(long = "native", action= clap::ArgAction::SetTrue)]
+    native: bool,
     #[arg(long = "p", default_value = "mycircuit.c12.pil")]
     pil_file: String,
     // output files :  $C12_VERIFIER.exec
@@ -167,15 +291,112 @@ struct Compressor12ExecOpt {
     commit_file: String,
 }
 
-/// generate the input1.zkin.json and input2.zkin.json into out.zkin.json
+/// Setup the compressor12 verifier of a stark directly, without circom
+#[derive(Parser, Debug)]
+struct Compressor12NativeSetupOpt {
+    // input files : the stark struct, pil.json and constant polynomials of the proved machine
+    #[arg(short, long = "stark_struct", default_value = "stark_struct.json")]
+    stark_struct: String,
+    #[arg(long = "piljson", default_value = "pil.json")]
+    piljson: String,
+    #[arg(long = "o", default_value = "pols.const")]
+    const_pols: String,
+    /// the proof is of the normalization stage of stark_prove, its rootC becomes a public
+    #[arg(long = "norm_stage", action= clap::ArgAction::SetTrue)]
+    norm_stage: bool,
+    /// the aggregation stage of stark_prove, not supported natively, use stark_prove
+    #[arg(long = "agg_stage", action= clap::ArgAction::SetTrue)]
+    agg_stage: bool,
+    // output files : $C12_VERIFIER.const  $C12_VERIFIER.pil  $C12_VERIFIER.exec
+    #[arg(long = "c", default_value = "mycircuit.c12.const")]
+    const_file: String,
+    #[arg(long = "p", default_value = "mycircuit.c12.pil")]
+    pil_file: String,
+    #[arg(long = "e", default_value = "mycircuit.c12.exec")]
+    exec_file: String,
+    #[arg(long, default_value = "0")]
+    force_n_bits: usize,
+}
+
+/// Exec the compressor12 verifier of a stark proof directly, without wasm
+#[derive(Parser, Debug)]
+struct Compressor12NativeExecOpt {
+    #[arg(short, long = "stark_struct", default_value = "stark_struct.json")]
+    stark_struct: String,
+    #[arg(long = "piljson", default_value = "pil.json")]
+    piljson: String,
+    #[arg(long = "o", default_value = "pols.const")]
+    const_pols: String,
+    /// the proof is of the normalization stage of stark_prove, its rootC becomes a public
+    #[arg(long = "norm_stage", action= clap::ArgAction::SetTrue)]
+    norm_stage: bool,
+    /// the aggregation stage of stark_prove, not supported natively, use stark_prove
+    #[arg(long = "agg_stage", action= clap::ArgAction::SetTrue)]
+    agg_stage: bool,
+    #[arg(long = "i", default_value = "mycircuit.proof.zkin.json")]
+    input_file: String,
+    #[arg(long = "p", default_value = "mycircuit.c12.pil")]
+    pil_file: String,
+    #[arg(long = "e", default_value = "mycircuit.c12.exec")]
+    exec_file: String,
+    // output files :  $C12_VERIFIER.cm
+    #[arg(long = "m", default_value = "mycircuit.c12.cm")]
+    commit_file: String,
+}
+
+/// Print the columns, quotient and estimated proof size and memory of a PIL and StarkStruct
+#[derive(Parser, Debug)]
+struct StarkInfoOpt {
+    #[arg(short, long = "stark_struct", default_value = "stark_struct.json")]
+    stark_struct: String,
+    #[arg(short, long = "piljson", default_value = "pil.json")]
+    piljson: String,
+    /// Search the intermediate polynomials with the fewest committed columns
+    #[arg(long = "im_pols_search", action= clap::ArgAction::SetTrue)]
+    im_pols_search: bool,
+    /// Print JSON instead of a table
+    #[arg(long = "json", action= clap::ArgAction::SetTrue)]
+    json: bool,
+}
+
+/// Compile a PIL file to pil.json
+#[derive(Parser, Debug)]
+struct PilCompileOpt {
+    #[arg(short, long = "pil")]
+    pil_file: String,
+    /// Directories to search the included files in, after the directory of the including file
+    #[arg(short = 'I', long = "include")]
+    include_paths: Vec<String>,
+    #[arg(short, long = "output", default_value = "pil.json")]
+    output: String,
+    /// Compare the output with a reference pil.json, e.g. compiled by pilcom
+    #[arg(long = "diff")]
+    diff: Option<String>,
+}
+
+/// Aggregate the stark proofs of a manifest into one groth16 proof
+#[derive(Parser, Debug)]
+struct AggregateOpt {
+    #[arg(short, long = "manifest", default_value = "aggregation.json")]
+    manifest: String,
+    /// Rerun every stage, even the cached ones
+    #[arg(long = "force", action= clap::ArgAction::SetTrue)]
+    force: bool,
+}
+
+/// generate the input1.zkin.json, input2.zkin.json, ... into out.zkin.json
 #[derive(Parser, Debug)]
 struct JoinZkinExecOpt {
-    // #[arg(long = "starksetup", default_value = "starksetup.json")]
-    // starksetup: String,
     #[arg(long = "zkin1", default_value = "input1.zkin.json")]
     zkin1: String,
     #[arg(long = "zkin2", default_value = "input2.zkin.json")]
     zkin2: String,
+    /// More inputs to join after zkin1 and zkin2
+    #[arg(long = "zkin")]
+    zkin: Vec<String>,
+    /// Key prefix of each input, a_, b_, ... by default
+    #[arg(long = "prefix")]
+    prefix: Vec<String>,
     #[arg(long = "zkinout", default_value = "out.zkin.json")]
     zkinout: String,
 }
@@ -202,12 +423,19 @@ pub struct Groth16ProveOpt {
     curve_type: String,
     #[arg(long = "r1cs", required = true)]
     circuit_file: String,
-    #[arg(short, required = true)]
-    wasm_file: String,
+    /// the wasm, or the native witness generator built by `compile --c` with --native
+    #[arg(short, required_unless_present = "witness_file")]
+    wasm_file: Option<String>,
+    /// The witness generator is the native one built by `compile --c`, not a wasm
+    #[arg(long = "native", action= clap::ArgAction::SetTrue, conflicts_with = "witness_file")]
+    native: bool,
     #[arg(short, required = true, default_value = "g16.zkey")]
     pk_file: String,
-    #[arg(short, required = true)]
-    input_file: String,
+    #[arg(short, required_unless_present = "witness_file")]
+    input_file: Option<String>,
+    /// Prove with this wtns witness instead of calculating it from the wasm and input
+    #[arg(long = "witness", conflicts_with_all = ["wasm_file", "input_file"])]
+    witness_file: Option<String>,
     #[arg(
         long = "public-input",
         required = true,
@@ -237,20 +465,251 @@ pub struct Groth16VerifyOpt {
     proof_file: String,
 }
 
+/// Export a groth16 proof as the calldata, Solidity arguments and Foundry test of the solidity verifier
+#[derive(Parser, Debug)]
+pub struct Groth16CalldataOpt {
+    #[arg(short, required = true, default_value = "verification_key.json")]
+    vk_file: String,
+    #[arg(
+        long = "public-input",
+        required = true,
+        default_value = "public_input.json"
+    )]
+    public_input_file: String,
+    #[arg(long = "proof", required = true, default_value = "proof.json")]
+    proof_file: String,
+    /// [output] the ABI encoded call of verifyTx, or of verifyProof with --assembly
+    #[arg(long = "calldata", default_value = "calldata.txt")]
+    calldata_file: String,
+    /// [output] the Solidity declarations of the proof and public input
+    #[arg(long = "solidity", default_value = "calldata.sol")]
+    solidity_file: String,
+    /// [output] the Foundry test of the verifier
+    #[arg(long = "test", default_value = "Verifier.t.sol")]
+    test_file: String,
+    /// The path of the verifier imported by the Foundry test
+    #[arg(long = "verifier", default_value = "verifier.sol")]
+    verifier_path: String,
+    /// Export the arguments of verifyProof of the assembly verifier, with the snarkjs ABI
+    #[arg(long = "assembly", action= clap::ArgAction::SetTrue)]
+    assembly: bool,
+}
+
+/// Verify many groth16 proofs of one verification key with a single multi pairing
+#[derive(Parser, Debug)]
+pub struct Groth16BatchVerifyOpt {
+    #[arg(short, required = true, default_value = "BN128")]
+    curve_type: String,
+    #[arg(short, required = true, default_value = "verification_key.json")]
+    vk_file: String,
+    /// The public input of each proof, in the order of the proofs
+    #[arg(long = "public_input", required = true)]
+    public_input_files: Vec<String>,
+    #[arg(long = "proof", required = true)]
+    proof_files: Vec<String>,
+}
+
+/// Setup the key of groth16 proof aggregation
+#[derive(Parser, Debug)]
+pub struct Groth16AggregateSetupOpt {
+    #[arg(short, required = true, default_value = "BN128")]
+    curve_type: String,
+    /// The largest number of proofs the key can aggregate, rounded up to a power of two
+    #[arg(short = 'n', long, default_value = "16")]
+    max_proofs: usize,
+    /// The ptau of a, whose powers of tau give g^{a^i} and h^{a^i}
+    #[arg(long = "ptau-a", required = true)]
+    ptau_a: String,
+    /// The ptau of b, from a ceremony independent of the ptau of a
+    #[arg(long = "ptau-b", required = true)]
+    ptau_b: String,
+    #[arg(
+        short,
+        long = "key",
+        required = true,
+        default_value = "aggregation_key.json"
+    )]
+    key_file: String,
+}
+
+/// Aggregate groth16 proofs of one verification key into one proof
+#[derive(Parser, Debug)]
+pub struct Groth16AggregateOpt {
+    #[arg(short, required = true, default_value = "BN128")]
+    curve_type: String,
+    #[arg(
+        short,
+        long = "key",
+        required = true,
+        default_value = "aggregation_key.json"
+    )]
+    key_file: String,
+    /// The public input of each proof, in the order of the proofs
+    #[arg(long = "public_input", required = true)]
+    public_input_files: Vec<String>,
+    #[arg(long = "proof", required = true)]
+    proof_files: Vec<String>,
+    #[arg(long = "aggregate_proof", default_value = "aggregate_proof.json")]
+    aggregate_proof_file: String,
+}
+
+/// Verify an aggregate groth16 proof
+#[derive(Parser, Debug)]
+pub struct Groth16AggregateVerifyOpt {
+    #[arg(short, required = true, default_value = "BN128")]
+    curve_type: String,
+    #[arg(
+        short,
+        long = "key",
+        required = true,
+        default_value = "aggregation_key.json"
+    )]
+    key_file: String,
+    #[arg(short, required = true, default_value = "verification_key.json")]
+    vk_file: String,
+    /// The public input of each aggregated proof, in the order of the proofs
+    #[arg(long = "public_input", required = true)]
+    public_input_files: Vec<String>,
+    #[arg(long = "aggregate_proof", default_value = "aggregate_proof.json")]
+    aggregate_proof_file: String,
+}
+
+/// Generate the solidity verifier of aggregate groth16 proofs
+#[derive(Parser, Debug)]
+pub struct Groth16AggregateVerifierOpt {
+    #[arg(short, long = "key", default_value = "aggregation_key.json")]
+    key_file: String,
+    #[arg(short, default_value = "verification_key.json")]
+    vk_file: String,
+    #[arg(short, default_value = "aggregate_verifier.sol")]
+    sol: String,
+}
+
+/// Check a snarkjs zkey against a circuit as the groth16 proving key, and export its verification key
+#[derive(Parser, Debug)]
+pub struct Groth16ImportZkeyOpt {
+    #[arg(short, required = true, default_value = "BN128")]
+    curve_type: String,
+    #[arg(short, long = "zkey", required = true)]
+    zkey_file: String,
+    #[arg(long = "r1cs", required = true)]
+    circuit_file: String,
+    #[arg(short, required = true, default_value = "g16.zkey")]
+    pk_file: String,
+    #[arg(short, required = true, default_value = "verification_key.json")]
+    vk_file: String,
+    #[arg(short, action= clap::ArgAction::SetTrue)]
+    to_hex: bool,
+}
+
+/// Start the groth16 phase 2 ceremony of a circuit from a prepared snarkjs ptau
+#[derive(Parser, Debug)]
+pub struct Groth16ZkeyNewOpt {
+    #[arg(short, required = true, default_value = "BN128")]
+    curve_type: String,
+    #[arg(long = "r1cs", required = true)]
+    circuit_file: String,
+    #[arg(long = "ptau", required = true)]
+    ptau_file: String,
+    #[arg(short, long = "zkey", required = true, default_value = "g16_0000.zkey")]
+    zkey_file: String,
+}
+
+/// Contribute to the groth16 phase 2 ceremony, not compatible with snarkjs contributions
+#[derive(Parser, Debug)]
+pub struct Groth16ContributeOpt {
+    #[arg(short, required = true, default_value = "BN128")]
+    curve_type: String,
+    #[arg(short, long = "zkey", required = true)]
+    zkey_file: String,
+    #[arg(short, long = "output", required = true)]
+    zkey_out_file: String,
+    #[arg(short, long, default_value = "")]
+    name: String,
+    /// random text mixed with the system randomness
+    #[arg(short, long, default_value = "")]
+    entropy: String,
+}
+
+/// Finalize the groth16 phase 2 ceremony with a random beacon
+#[derive(Parser, Debug)]
+pub struct Groth16BeaconOpt {
+    #[arg(short, required = true, default_value = "BN128")]
+    curve_type: String,
+    #[arg(short, long = "zkey", required = true)]
+    zkey_file: String,
+    #[arg(short, long = "output", required = true)]
+    zkey_out_file: String,
+    #[arg(short, long, default_value = "")]
+    name: String,
+    /// the beacon value in hex
+    #[arg(short, long = "beacon", required = true)]
+    beacon_hash: String,
+    /// the beacon is hashed 2^iterations_exp times
+    #[arg(short, long, default_value = "10")]
+    iterations_exp: u32,
+}
+
+/// Verify the contributions of a groth16 zkey against its R1CS and ptau, not snarkjs ones
+#[derive(Parser, Debug)]
+pub struct Groth16VerifyContributionsOpt {
+    #[arg(short, required = true, default_value = "BN128")]
+    curve_type: String,
+    #[arg(long = "r1cs", required = true)]
+    circuit_file: String,
+    #[arg(long = "ptau", required = true)]
+    ptau_file: String,
+    #[arg(short, long = "zkey", required = true)]
+    zkey_file: String,
+}
+
 #[derive(Parser, Debug)]
 enum Command {
+    #[command(name = "setup")]
+    Setup(SetupOpt),
+    #[command(name = "prove")]
+    Prove(ProveOpt),
+    #[command(name = "verify")]
+    Verify(VerifyOpt),
+    #[command(name = "export_verification_key")]
+    ExportVerificationKey(ExportVerificationKeyOpt),
+    #[command(name = "export_aggregation_verification_key")]
+    ExportAggregationVerificationKey(ExportAggregationVerificationKeyOpt),
+    #[command(name = "aggregation_prove")]
+    AggregationProve(AggregationProveOpt),
+    #[command(name = "aggregation_verify")]
+    AggregationVerify(AggregationVerifyOpt),
+    #[command(name = "aggregation_check")]
+    AggregationCheck(AggregationCheckOpt),
+    #[command(name = "generate_aggregation_verifier")]
+    GenerateAggregationVerifier(GenerateAggregationVerifierOpt),
+
     /// Compile circom circuits to r1cs, and generate witness
     #[command(name = "compile")]
     Compile(CompilierOpt),
+    #[command(name = "calculate_witness")]
+    CalculateWitness(CalculateWitnessOpt),
+    #[command(name = "r1cs_check")]
+    R1csCheck(R1csCheckOpt),
     #[command(name = "generate_verifier")]
     GenerateVerifier(GenerateVerifierOpt),
 
+    #[command(name = "pil_compile")]
+    PilCompile(PilCompileOpt),
     #[command(name = "stark_prove")]
     StarkProve(StarkProveOpt),
+    #[command(name = "stark_info")]
+    StarkInfo(StarkInfoOpt),
     #[command(name = "compressor12_setup")]
     Compressor12Setup(Compressor12SetupOpt),
     #[command(name = "compressor12_exec")]
     Compressor12Exec(Compressor12ExecOpt),
+    #[command(name = "compressor12_native_setup")]
+    Compressor12NativeSetup(Compressor12NativeSetupOpt),
+    #[command(name = "compressor12_native_exec")]
+    Compressor12NativeExec(Compressor12NativeExecOpt),
+    #[command(name = "aggregate")]
+    Aggregate(AggregateOpt),
     #[command(name = "join_zkin")]
     JoinZkin(JoinZkinExecOpt),
 
@@ -260,6 +719,28 @@ enum Command {
     Groth16Prove(Groth16ProveOpt),
     #[command(name = "groth16_verify")]
     Groth16Verify(Groth16VerifyOpt),
+    #[command(name = "groth16_calldata")]
+    Groth16Calldata(Groth16CalldataOpt),
+    #[command(name = "groth16_batch_verify")]
+    Groth16BatchVerify(Groth16BatchVerifyOpt),
+    #[command(name = "groth16_aggregate_setup")]
+    Groth16AggregateSetup(Groth16AggregateSetupOpt),
+    #[command(name = "groth16_aggregate")]
+    Groth16Aggregate(Groth16AggregateOpt),
+    #[command(name = "groth16_aggregate_verify")]
+    Groth16AggregateVerify(Groth16AggregateVerifyOpt),
+    #[command(name = "groth16_aggregate_verifier")]
+    Groth16AggregateVerifier(Groth16AggregateVerifierOpt),
+    #[command(name = "groth16_import_zkey")]
+    Groth16ImportZkey(Groth16ImportZkeyOpt),
+    #[command(name = "groth16_zkey_new")]
+    Groth16ZkeyNew(Groth16ZkeyNewOpt),
+    #[command(name = "groth16_contribute")]
+    Groth16Contribute(Groth16ContributeOpt),
+    #[command(name = "groth16_beacon")]
+    Groth16Beacon(Groth16BeaconOpt),
+    #[command(name = "groth16_verify_contributions")]
+    Groth16VerifyContributions(Groth16VerifyContributionsOpt),
 }
 
 #[derive(Parser, Debug)]
@@ -269,11 +750,64 @@ struct Cli {
     command: Command,
 }
 
+/// The witness generator of groth16 by the --native flag
+fn witness_generator(native: bool) -> WitnessGenerator {
+    match native {
+        true => WitnessGenerator::Native,
+        false => WitnessGenerator::Wasm,
+    }
+}
+
 fn main() {
     let args = Cli::parse();
     env_logger::init();
     let start = Instant::now();
     let exec_result = match args.command {
+        Command::Setup(args) => {
+            plonky::api::setup(args.power, &args.srs_monomial_form, args.insecure)
+        }
+        Command::Prove(args) => plonky::api::prove(
+            &args.circuit_file,
+            &args.witness,
+            &args.srs_monomial_form,
+            &args.transcript,
+            &args.proof_bin,
+            &args.proof_json,
+            &args.public_json,
+        ),
+        Command::Verify(args) => {
+            plonky::api::verify(&args.vk_file, &args.proof_bin, &args.transcript)
+        }
+        Command::ExportVerificationKey(args) => plonky::api::export_verification_key(
+            &args.srs_monomial_form,
+            &args.circuit_file,
+            &args.output_vk,
+        ),
+        Command::ExportAggregationVerificationKey(args) => {
+            plonky::api::export_aggregation_verification_key(
+                args.num_proofs_to_check,
+                args.num_inputs,
+                &args.srs_monomial_form,
+                &args.output_vk,
+            )
+        }
+        Command::AggregationProve(args) => plonky::api::aggregation_prove(
+            &args.srs_monomial_form,
+            &args.old_proof_list,
+            &args.old_vk,
+            &args.new_proof,
+            &args.proof_json,
+        ),
+        Command::AggregationVerify(args) => plonky::api::aggregation_verify(&args.proof, &args.vk),
+        Command::AggregationCheck(args) => {
+            plonky::api::aggregation_check(&args.old_proof_list, &args.old_vk, &args.new_proof)
+        }
+        Command::GenerateAggregationVerifier(args) => plonky::api::generate_aggregation_verifier(
+            &args.old_vk,
+            &args.new_vk,
+            args.num_inputs,
+            &args.sol,
+        ),
         Command::Compile(args) => circom_compiler(
             args.input,
             args.prime.to_lowercase(),
@@ -282,14 +816,53 @@ fn main() {
             args.output,
             args.no_simplification,
             args.reduced_simplification,
+            args.c_generator,
+            match args.stats {
+                Some(_) => Stats::Collect,
+                None => Stats::Skip,
+            },
+        )
+        .and_then(|stats| match args.stats {
+            Some(format) => {
+                println!("{}", format_stats(&stats, format)?);
+                Ok(())
+            }
+            None => Ok(()),
+        }),
+        Command::CalculateWitness(args) => calculate_witness(
+            &args.wasm_file,
+            witness_generator(args.native),
+            &args.input_json,
+            &args.output,
         ),
-        Command::GenerateVerifier(args) => match args.protocal.as_str() {
-            "groth16" => groth16::api::generate_verifier(&args.vk_file, &args.sol),
+        Command::R1csCheck(args) => algebraic::r1cs_check::r1cs_check(
+            &args.curve_type,
+            &args.circuit_file,
+            args.witness_file.as_deref(),
+            args.wasm_file.as_deref(),
+            args.input_file.as_deref(),
+            args.sym_file.as_deref(),
+            args.max_reports,
+        ),
+        Command::GenerateVerifier(args) => match (args.protocal.as_str(), args.target.as_str()) {
+            ("groth16", "solidity") if args.assembly => {
+                groth16::api::generate_assembly_verifier(&args.vk_file, &args.sol)
+            }
+            ("groth16", "solidity") => groth16::api::generate_verifier(&args.vk_file, &args.sol),
+            ("groth16", "rust") => groth16::api::generate_rust_verifier(&args.vk_file, &args.sol),
+            ("groth16", "wasm") => groth16::api::generate_wasm_verifier(&args.vk_file, &args.sol),
+            ("plonk", "solidity") => plonky::api::generate_verifier(&args.vk_file, &args.sol),
             _ => {
-                panic!("unknown protocol")
+                panic!("unknown protocol or target")
             }
         },
 
+        Command::PilCompile(args) => recursion::pilcom::pil_compile(
+            &args.pil_file,
+            &args.include_paths,
+            &args.output,
+            args.diff.as_deref(),
+        ),
         Command::StarkProve(args) => stark_prove(
             &args.stark_struct,
             &args.piljson,
@@ -302,22 +875,63 @@ fn main() {
             &args.zkin,
             &args.prover_addr,
         ),
+        Command::StarkInfo(args) => starky::starkinfo_stats::stark_info(
+            &args.stark_struct,
+            &args.piljson,
+            args.im_pols_search,
+            args.json,
+        ),
         Command::Compressor12Setup(args) => recursion::compressor12_setup::setup(
             &args.r1cs_file,
             &args.pil_file,
             &args.const_file,
             &args.exec_file,
             args.force_n_bits,
+            args.optimize,
         ),
         Command::Compressor12Exec(args) => recursion::compressor12_exec::exec(
             &args.input_file,
             &args.wasm_file,
+            match args.native {
+                true => algebraic::witness::WitnessGenerator::Native,
+                false => algebraic::witness::WitnessGenerator::Wasm,
+            },
             &args.pil_file,
             &args.exec_file,
             &args.commit_file,
         ),
+        Command::Compressor12NativeSetup(args) => recursion::stark_verifier::setup(
+            &args.stark_struct,
+            &args.piljson,
+            &args.const_pols,
+            args.norm_stage,
+            args.agg_stage,
+            &args.pil_file,
+            &args.const_file,
+            &args.exec_file,
+            args.force_n_bits,
+        ),
+        Command::Compressor12NativeExec(args) => recursion::stark_verifier::exec(
+            &args.stark_struct,
+            &args.piljson,
+            &args.const_pols,
+            args.norm_stage,
+            args.agg_stage,
+            &args.input_file,
+            &args.pil_file,
+            &args.exec_file,
+            &args.commit_file,
+        ),
+        Command::Aggregate(args) => aggregate::aggregate(&args.manifest, args.force),
         Command::JoinZkin(args) => {
-            starky::zkin_join::join_zkin(&args.zkin1, &args.zkin2, &args.zkinout)
+            let mut zkins = vec![args.zkin1, args.zkin2];
+            zkins.extend(args.zkin);
+            let prefixes = if args.prefix.is_empty() {
+                None
+            } else {
+                Some(&args.prefix[..])
+            };
+            starky::zkin_join::join_zkin_n(&zkins, prefixes, &args.zkinout)
         }
         Command::Groth16Setup(args) => groth16_setup(
             &args.curve_type,
@@ -326,21 +940,108 @@ fn main() {
             &args.vk_file,
             args.to_hex,
         ),
-        Command::Groth16Prove(args) => groth16_prove(
+        Command::Groth16Prove(args) => match &args.witness_file {
+            Some(witness_file) => groth16_prove_with_witness(
+                &args.curve_type,
+                &args.circuit_file,
+                witness_file,
+                &args.pk_file,
+                &args.public_input_file,
+                &args.proof_file,
+                args.to_hex,
+            ),
+            None => groth16_prove(
+                &args.curve_type,
+                &args.circuit_file,
+                args.wasm_file.as_deref().unwrap(),
+                witness_generator(args.native),
+                &args.pk_file,
+                args.input_file.as_deref().unwrap(),
+                &args.public_input_file,
+                &args.proof_file,
+                args.to_hex,
+            ),
+        },
+        Command::Groth16Verify(args) => groth16_verify(
             &args.curve_type,
-            &args.circuit_file,
-            &args.wasm_file,
-            &args.pk_file,
-            &args.input_file,
+            &args.vk_file,
             &args.public_input_file,
             &args.proof_file,
-            args.to_hex,
         ),
-        Command::Groth16Verify(args) => groth16_verify(
-            &args.curve_type,
+        Command::Groth16Calldata(args) => groth16_calldata(
             &args.vk_file,
             &args.public_input_file,
             &args.proof_file,
+            &args.calldata_file,
+            &args.solidity_file,
+            &args.test_file,
+            &args.verifier_path,
+            args.assembly,
+        ),
+        Command::Groth16BatchVerify(args) => groth16_batch_verify(
+            &args.curve_type,
+            &args.vk_file,
+            &args.public_input_files,
+            &args.proof_files,
+        ),
+        Command::Groth16AggregateSetup(args) => groth16_aggregate_setup(
+            &args.curve_type,
+            args.max_proofs,
+            &args.ptau_a,
+            &args.ptau_b,
+            &args.key_file,
+        ),
+        Command::Groth16Aggregate(args) => groth16_aggregate(
+            &args.curve_type,
+            &args.key_file,
+            &args.public_input_files,
+            &args.proof_files,
+            &args.aggregate_proof_file,
+        ),
+        Command::Groth16AggregateVerify(args) => groth16_aggregate_verify(
+            &args.curve_type,
+            &args.key_file,
+            &args.vk_file,
+            &args.public_input_files,
+            &args.aggregate_proof_file,
+        ),
+        Command::Groth16AggregateVerifier(args) => {
+            generate_aggregate_verifier(&args.key_file, &args.vk_file, &args.sol)
+        }
+        Command::Groth16ImportZkey(args) => groth16_import_zkey(
+            &args.curve_type,
+            &args.zkey_file,
+            &args.circuit_file,
+            &args.pk_file,
+            &args.vk_file,
+            args.to_hex,
+        ),
+        Command::Groth16ZkeyNew(args) => groth16_zkey_new(
+            &args.curve_type,
+            &args.circuit_file,
+            &args.ptau_file,
+            &args.zkey_file,
+        ),
+        Command::Groth16Contribute(args) => groth16_contribute(
+            &args.curve_type,
+            &args.zkey_file,
+            &args.zkey_out_file,
+            &args.name,
+            &args.entropy,
+        ),
+        Command::Groth16Beacon(args) => groth16_beacon(
+            &args.curve_type,
+            &args.zkey_file,
+            &args.zkey_out_file,
+            &args.name,
+            &args.beacon_hash,
+            args.iterations_exp,
+        ),
+        Command::Groth16VerifyContributions(args) => groth16_verify_contributions(
+            &args.curve_type,
+            &args.circuit_file,
+            &args.ptau_file,
+            &args.zkey_file,
         ),
     };
     match exec_result {
@@ -152,6 +152,7 @@ fn generate_verifier<F: FieldElement>(
             nQueries: 2,
             verificationHashType: "GL".to_owned(),
             steps,
+            ..Default::default()
         };
 
         // generate circom